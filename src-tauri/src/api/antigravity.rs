@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use axum::response::sse::Event;
 use futures::{Stream, StreamExt};
 use serde_json::{json, Value};
@@ -8,7 +9,15 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
-use super::{gemini, schema_cleaner};
+use super::claude::ClaudeImageHandling;
+use super::collector;
+use super::handlers::{self, ModelInfo};
+use super::mappers::builtin_tools;
+use super::provider::{
+    self, ChatContext, ChatProvider, EventStream, MessagesMapping, ProviderCapabilities,
+    ProviderError,
+};
+use super::{gemini, http_client, schema_cleaner, sse};
 
 const ANTIGRAVITY_BASE_URL_DAILY: &str = "https://daily-cloudcode-pa.googleapis.com";
 const ANTIGRAVITY_BASE_URL_SANDBOX: &str = "https://daily-cloudcode-pa.sandbox.googleapis.com";
@@ -29,7 +38,7 @@ impl AntigravityClient {
     pub fn new(access_token: String) -> Self {
         Self {
             access_token,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::client_for("antigravity"),
         }
     }
 
@@ -353,37 +362,20 @@ pub fn antigravity_stream_to_openai_chunks(
             active_function_args: String::new(),
             active_function_index: 0,
         };
-        let mut buffer = String::new();
-        let mut stream = response.bytes_stream();
+        let mut events = sse::data_stream(http_client::byte_stream(response));
 
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(data) => data,
                 Err(_) => break,
             };
-            let text = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&text);
-
-            while let Some(pos) = buffer.find('\n') {
-                let mut line = buffer[..pos].to_string();
-                buffer = buffer[pos + 1..].to_string();
-                line = line.trim_end_matches('\r').to_string();
-
-                if !line.starts_with("data:") {
-                    continue;
-                }
-                let data = line[5..].trim();
-                if data.is_empty() {
-                    continue;
-                }
-                if data == "[DONE]" {
-                    yield "[DONE]".to_string();
-                    return;
-                }
+            if data == "[DONE]" {
+                yield "[DONE]".to_string();
+                return;
+            }
 
-                for chunk in convert_antigravity_stream_chunk(data, &mut state) {
-                    yield chunk;
-                }
+            for chunk in convert_antigravity_stream_chunk(&data, &mut state) {
+                yield chunk;
             }
         }
 
@@ -398,30 +390,16 @@ pub fn antigravity_stream_to_openai_events(
 }
 
 pub async fn collect_antigravity_stream(response: reqwest::Response) -> Result<Value> {
-    let mut buffer = String::new();
-    let mut stream = response.bytes_stream();
+    let mut events = sse::data_stream(http_client::byte_stream(response));
     let mut payloads: Vec<Value> = Vec::new();
 
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk?;
-        let text = String::from_utf8_lossy(&bytes);
-        buffer.push_str(&text);
-
-        while let Some(pos) = buffer.find('\n') {
-            let mut line = buffer[..pos].to_string();
-            buffer = buffer[pos + 1..].to_string();
-            line = line.trim_end_matches('\r').to_string();
-
-            if !line.starts_with("data:") {
-                continue;
-            }
-            let data = line[5..].trim();
-            if data.is_empty() || data == "[DONE]" {
-                continue;
-            }
-            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
-                payloads.push(parsed);
-            }
+    while let Some(event) = events.next().await {
+        let data = event?;
+        if data == "[DONE]" {
+            continue;
+        }
+        if let Ok(parsed) = serde_json::from_str::<Value>(&data) {
+            payloads.push(parsed);
         }
     }
 
@@ -431,6 +409,11 @@ pub async fn collect_antigravity_stream(response: reqwest::Response) -> Result<V
     Ok(convert_stream_payloads_to_non_stream(&payloads))
 }
 
+/// Collect a streamed reply into an OpenAI chat completion
+pub async fn collect_openai_response(response: reqwest::Response, model: &str) -> Result<Value> {
+    collector::collect_openai_chunks(antigravity_stream_to_openai_chunks(response), model).await
+}
+
 struct AntigravityStreamState {
     unix_timestamp: i64,
     function_index: i32,
@@ -535,6 +518,18 @@ fn convert_antigravity_stream_chunk(data: &str, state: &mut AntigravityStreamSta
         .and_then(|v| v.as_array())
     {
         for part in parts {
+            if let Some(item) = builtin_tools::code_execution_part(part) {
+                if !template["choices"][0]["delta"]["code_execution"].is_array() {
+                    template["choices"][0]["delta"]["code_execution"] = json!([]);
+                }
+                if let Some(arr) = template["choices"][0]["delta"]["code_execution"].as_array_mut()
+                {
+                    arr.push(item);
+                }
+                template["choices"][0]["delta"]["role"] = json!("assistant");
+                continue;
+            }
+
             let part_text = part.get("text").and_then(|v| v.as_str());
             let function_call = part.get("functionCall");
             let thought_sig = part
@@ -939,3 +934,149 @@ fn normalize_part(part: &Value) -> Value {
     }
     part.clone()
 }
+
+/// Antigravity (Cloud Code daily/sandbox) behind the common provider interface
+pub struct AntigravityProvider;
+
+impl AntigravityProvider {
+    fn build_request(
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<(AntigravityClient, Value), ProviderError> {
+        let (access_token, project_id) = ctx.credentials.access_token()?;
+        let antigravity_request = openai_to_antigravity_request(request, ctx.model, project_id);
+        Ok((AntigravityClient::new(access_token), antigravity_request))
+    }
+}
+
+#[async_trait]
+impl ChatProvider for AntigravityProvider {
+    fn id(&self) -> &'static str {
+        "antigravity"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Antigravity"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            thinking: true,
+            count_tokens: false,
+        }
+    }
+
+    fn ignored_params(&self) -> &'static [&'static str] {
+        // Gemini has no counterpart for these in generationConfig
+        &[
+            "max_completion_tokens",
+            "seed",
+            "frequency_penalty",
+            "presence_penalty",
+            "logit_bias",
+            "logprobs",
+            "top_logprobs",
+        ]
+    }
+
+    fn messages_mapping(&self, model: &str) -> MessagesMapping {
+        // Only thinking variants and Claude models return thinking blocks
+        let model = model.to_lowercase();
+        let supports_thinking = model.contains("-thinking") || model.starts_with("claude-");
+        MessagesMapping::OpenAiChat {
+            image_handling: ClaudeImageHandling::Base64TypeOnly,
+            guard_thinking: true,
+            reasoning_as_text: !supports_thinking,
+        }
+    }
+
+    fn list_models(&self) -> Vec<ModelInfo> {
+        let base = handlers::get_antigravity_models();
+        let mut models = handlers::build_prefixed_models(self.id(), &base);
+        models.extend(handlers::build_antigravity_models_with_reasoning(&base));
+        models
+    }
+
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError> {
+        let (client, antigravity_request) = Self::build_request(ctx, request)?;
+        if should_use_stream_for_non_stream(ctx.model) {
+            let response = client
+                .stream_generate_content(&antigravity_request, None)
+                .await?;
+            return Ok(collect_openai_response(response, ctx.model).await?);
+        }
+        let payload = client.generate_content(&antigravity_request, None).await?;
+        Ok(gemini::gemini_to_openai_response(
+            &payload,
+            ctx.model,
+            ctx.request_id,
+        ))
+    }
+
+    async fn chat_stream(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<EventStream, ProviderError> {
+        let (client, antigravity_request) = Self::build_request(ctx, request)?;
+        let response = client
+            .stream_generate_content(&antigravity_request, None)
+            .await?;
+        Ok(provider::chunk_events(
+            self.id(),
+            antigravity_stream_to_openai_chunks(response),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_execution_parts_reach_streamed_and_collected_replies() {
+        let mut state = AntigravityStreamState {
+            unix_timestamp: 0,
+            function_index: 0,
+            active_function_name: None,
+            active_function_id: None,
+            active_function_args: String::new(),
+            active_function_index: 0,
+        };
+        let payloads = [
+            json!({"response": {"candidates": [{"content": {"role": "model", "parts": [
+                {"executableCode": {"language": "PYTHON", "code": "print(1 + 1)"}}
+            ]}}]}}),
+            json!({"response": {"candidates": [{"content": {"role": "model", "parts": [
+                {"codeExecutionResult": {"outcome": "OUTCOME_OK", "output": "2\n"}},
+                {"text": "The answer is 2."}
+            ]}, "finishReason": "STOP"}]}}),
+        ];
+        let chunks: Vec<String> = payloads
+            .iter()
+            .flat_map(|payload| convert_antigravity_stream_chunk(&payload.to_string(), &mut state))
+            .collect();
+
+        let first: Value = serde_json::from_str(&chunks[0]).unwrap();
+        assert_eq!(
+            first["choices"][0]["delta"]["code_execution"],
+            json!([{"type": "executable_code", "language": "PYTHON", "code": "print(1 + 1)"}])
+        );
+
+        let mut collector = collector::ChunkCollector::new();
+        for chunk in &chunks {
+            collector.push_str(chunk);
+        }
+        let response = collector.finish("gemini-3-pro").unwrap();
+        let message = &response["choices"][0]["message"];
+        assert_eq!(message["content"], "The answer is 2.");
+        assert_eq!(message["code_execution"][0]["type"], "executable_code");
+        assert_eq!(
+            message["code_execution"][1],
+            json!({"type": "code_execution_result", "outcome": "OUTCOME_OK", "output": "2\n"})
+        );
+    }
+}
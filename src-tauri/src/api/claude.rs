@@ -1,11 +1,31 @@
 // Claude API client for proxying requests
 
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::time::Duration;
 use uuid::Uuid;
 
+use super::handlers::{self, ModelInfo};
+use super::http_client;
+use super::provider::{
+    ChatContext, ChatProvider, EventStream, MessagesMapping, Overloaded, ProviderCapabilities,
+    ProviderError,
+};
+
 const CLAUDE_API_BASE: &str = "https://api.anthropic.com/v1";
+pub const KIMI_ANTHROPIC_BASE: &str = "https://api.kimi.com/coding/v1";
+pub const GLM_ANTHROPIC_BASE: &str = "https://open.bigmodel.cn/api/anthropic/v1";
+
+/// Status Anthropic uses when its backend is overloaded, distinct from rate limiting (429)
+pub const OVERLOADED_STATUS: u16 = 529;
+/// Retries on the same account while the backend is overloaded
+const OVERLOAD_RETRIES: u32 = 2;
+/// First retry delay, doubled for each further retry
+const OVERLOAD_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound for retry delays, including the upstream's retry-after
+const OVERLOAD_MAX_DELAY: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub struct ClaudeClient {
@@ -66,7 +86,7 @@ impl ClaudeClient {
         Self {
             access_token,
             base_url: CLAUDE_API_BASE.to_string(),
-            http_client: reqwest::Client::new(),
+            http_client: http_client::client_for("claude"),
         }
     }
 
@@ -78,42 +98,300 @@ impl ClaudeClient {
         Self {
             access_token,
             base_url,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::client_for("claude"),
         }
     }
 
     pub async fn create_message(&self, request: ClaudeRequest) -> Result<ClaudeResponse> {
         let url = format!("{}/messages", self.base_url);
 
+        let send = || {
+            self.http_client
+                .post(&url)
+                .header("x-api-key", &self.access_token)
+                .header("anthropic-version", "2023-06-01")
+                .header("content-type", "application/json")
+                .json(&request)
+        };
+        let response = match send_messages(send, &self.base_url).await {
+            Ok(response) => response,
+            Err(MessagesError::Transport(e)) => return Err(e.into()),
+            Err(MessagesError::Status {
+                body,
+                overloaded: true,
+                ..
+            }) => return Err(Overloaded(error_message(&body)).into()),
+            Err(MessagesError::Status { body, .. }) => {
+                let body: Value = serde_json::from_slice(&body)
+                    .unwrap_or_else(|_| json!(String::from_utf8_lossy(&body)));
+                if let Some(error) = body.get("error") {
+                    return Ok(ClaudeResponse {
+                        id: None,
+                        content: None,
+                        model: None,
+                        stop_reason: None,
+                        usage: None,
+                        error: serde_json::from_value(error.clone()).ok(),
+                    });
+                }
+                return Err(anyhow::anyhow!("Claude API error: {}", body));
+            }
+        };
+
+        let body: Value = response.json().await?;
+        let claude_response: ClaudeResponse = serde_json::from_value(body)?;
+        Ok(claude_response)
+    }
+
+    /// Count input tokens for a native Messages API payload
+    pub async fn count_tokens(&self, payload: &Value) -> Result<Value> {
+        let url = format!("{}/messages/count_tokens", self.base_url);
+
         let response = self
             .http_client
             .post(&url)
             .header("x-api-key", &self.access_token)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&request)
+            .json(payload)
             .send()
             .await?;
 
         let status = response.status();
-        let body: Value = response.json().await?;
-
+        let body = http_client::read_body(response).await?;
         if !status.is_success() {
-            if let Some(error) = body.get("error") {
-                return Ok(ClaudeResponse {
-                    id: None,
-                    content: None,
-                    model: None,
-                    stop_reason: None,
-                    usage: None,
-                    error: serde_json::from_value(error.clone()).ok(),
-                });
-            }
-            return Err(anyhow::anyhow!("Claude API error: {}", body));
+            return Err(anyhow::anyhow!(
+                "HTTP {}: {}",
+                status,
+                String::from_utf8_lossy(&body)
+            ));
         }
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
 
-        let claude_response: ClaudeResponse = serde_json::from_value(body)?;
-        Ok(claude_response)
+/// Failed Messages API call
+pub enum MessagesError {
+    /// The request could not be sent
+    Transport(reqwest::Error),
+    /// The backend answered with an error status
+    Status {
+        status: reqwest::StatusCode,
+        body: Vec<u8>,
+        overloaded: bool,
+    },
+}
+
+/// Whether an error response means the backend is overloaded: status 529, or an
+/// `overloaded_error` body, which some compatible backends send with 500 or 503
+pub fn is_overloaded(status: u16, body: &[u8]) -> bool {
+    status == OVERLOADED_STATUS
+        || serde_json::from_slice::<Value>(body)
+            .ok()
+            .and_then(|body| {
+                body.pointer("/error/type")
+                    .and_then(|t| t.as_str())
+                    .map(|t| t == "overloaded_error")
+            })
+            .unwrap_or(false)
+}
+
+/// Message of an Anthropic error body, or the body itself
+pub fn error_message(body: &[u8]) -> String {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| {
+            body.pointer("/error/message")
+                .and_then(|m| m.as_str())
+                .map(|m| m.to_string())
+        })
+        .unwrap_or_else(|| String::from_utf8_lossy(body).to_string())
+}
+
+/// Delay before overload retry `attempt` (0-based), preferring the upstream's retry-after
+fn overload_delay(attempt: u32, retry_after: Option<&str>) -> Duration {
+    retry_after
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or(OVERLOAD_BASE_DELAY * 2u32.pow(attempt))
+        .min(OVERLOAD_MAX_DELAY)
+}
+
+/// Send a Messages API request, retrying with backoff while the backend is overloaded; a
+/// successful response is returned unread so it can be streamed
+pub async fn send_messages<F>(build: F, label: &str) -> Result<reqwest::Response, MessagesError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        let response = build().send().await.map_err(MessagesError::Transport)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = http_client::read_body(response).await.unwrap_or_default();
+        let overloaded = is_overloaded(status.as_u16(), &body);
+        if overloaded && attempt < OVERLOAD_RETRIES {
+            let delay = overload_delay(attempt, retry_after.as_deref());
+            tracing::warn!(
+                "{} is overloaded, retrying in {} ms",
+                label,
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+        return Err(MessagesError::Status {
+            status,
+            body,
+            overloaded,
+        });
+    }
+}
+
+/// Anthropic Messages API and compatible backends behind the common provider interface
+pub struct ClaudeProvider {
+    id: &'static str,
+    display_name: &'static str,
+    base_url: &'static str,
+    models: fn() -> Vec<ModelInfo>,
+}
+
+impl ClaudeProvider {
+    pub fn anthropic() -> Self {
+        Self {
+            id: "claude",
+            display_name: "Claude",
+            base_url: CLAUDE_API_BASE,
+            models: handlers::get_claude_models,
+        }
+    }
+
+    pub fn kimi() -> Self {
+        Self {
+            id: "kimi",
+            display_name: "Kimi",
+            base_url: KIMI_ANTHROPIC_BASE,
+            models: handlers::get_kimi_models,
+        }
+    }
+
+    pub fn glm() -> Self {
+        Self {
+            id: "glm",
+            display_name: "GLM",
+            base_url: GLM_ANTHROPIC_BASE,
+            models: handlers::get_glm_models,
+        }
+    }
+
+    fn client(&self, ctx: &ChatContext<'_>) -> Result<ClaudeClient, ProviderError> {
+        let (access_token, _) = ctx.credentials.access_token()?;
+        Ok(ClaudeClient::new_with_base_url(access_token, self.base_url))
+    }
+}
+
+#[async_trait]
+impl ChatProvider for ClaudeProvider {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn display_name(&self) -> &'static str {
+        self.display_name
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        // Chat goes through the simple text-only Messages mapping without streaming
+        ProviderCapabilities {
+            streaming: false,
+            tools: false,
+            vision: false,
+            thinking: false,
+            count_tokens: self.base_url == CLAUDE_API_BASE,
+        }
+    }
+
+    fn ignored_params(&self) -> &'static [&'static str] {
+        // Only model, messages, max_tokens and temperature are mapped
+        &[
+            "top_p",
+            "max_completion_tokens",
+            "stop",
+            "n",
+            "response_format",
+            "seed",
+            "frequency_penalty",
+            "presence_penalty",
+            "logit_bias",
+            "logprobs",
+            "top_logprobs",
+        ]
+    }
+
+    fn messages_mapping(&self, _model: &str) -> MessagesMapping {
+        MessagesMapping::Native {
+            base_url: self.base_url,
+        }
+    }
+
+    fn list_models(&self) -> Vec<ModelInfo> {
+        handlers::build_prefixed_models(self.id, &(self.models)())
+    }
+
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError> {
+        let parsed: handlers::ChatCompletionRequest = serde_json::from_value(request.clone())
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid request: {}", e)))?;
+        let (messages, system) = openai_to_claude_messages(&parsed.messages);
+        let claude_request = ClaudeRequest {
+            model: ctx.model.to_string(),
+            messages,
+            max_tokens: parsed.max_tokens.unwrap_or(4096),
+            temperature: parsed.temperature,
+            system,
+        };
+
+        let response = self.client(ctx)?.create_message(claude_request).await?;
+        Ok(claude_to_openai_response(
+            &response,
+            ctx.model,
+            ctx.request_id,
+        ))
+    }
+
+    async fn chat_stream(
+        &self,
+        _ctx: &ChatContext<'_>,
+        _request: &Value,
+    ) -> Result<EventStream, ProviderError> {
+        Err(ProviderError::InvalidRequest(format!(
+            "{} does not support streaming chat completions",
+            self.display_name
+        )))
+    }
+
+    async fn count_tokens(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<Value, ProviderError> {
+        if !self.capabilities().count_tokens {
+            return Err(ProviderError::InvalidRequest(format!(
+                "{} does not support token counting",
+                self.display_name
+            )));
+        }
+        let mut payload = request.clone();
+        payload["model"] = json!(ctx.model);
+        Ok(self.client(ctx)?.count_tokens(&payload).await?)
     }
 }
 
@@ -953,3 +1231,25 @@ fn convert_openai_tool_call(tool_call: &Value) -> Option<Value> {
         "input": input
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_overload_apart_from_rate_limits() {
+        let overloaded =
+            br#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let rate_limited =
+            br#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#;
+        assert!(is_overloaded(529, b""));
+        assert!(is_overloaded(503, overloaded));
+        assert!(!is_overloaded(429, rate_limited));
+        assert_eq!(error_message(overloaded), "Overloaded");
+
+        assert_eq!(overload_delay(0, None), Duration::from_secs(1));
+        assert_eq!(overload_delay(1, None), Duration::from_secs(2));
+        assert_eq!(overload_delay(0, Some("3")), Duration::from_secs(3));
+        assert_eq!(overload_delay(0, Some("120")), OVERLOAD_MAX_DELAY);
+    }
+}
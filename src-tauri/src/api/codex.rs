@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use axum::response::sse::Event;
 use futures::{Stream, StreamExt};
 use serde_json::{json, Value};
@@ -6,6 +7,16 @@ use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use uuid::Uuid;
 
+use super::claude::ClaudeImageHandling;
+use super::collector;
+use super::handlers::{self, ModelInfo};
+use super::http_client;
+use super::provider::{
+    self, ChatContext, ChatProvider, EventStream, MessagesMapping, ProviderCapabilities,
+    ProviderError,
+};
+use super::sse;
+
 const CODEX_BASE_URL: &str = "https://chatgpt.com/backend-api/codex";
 const DEFAULT_USER_AGENT: &str = "codex_cli_rs/0.101.0 (Mac OS 26.0.1; arm64) Apple_Terminal/464";
 
@@ -19,7 +30,7 @@ impl CodexClient {
     pub fn new(access_token: String) -> Self {
         Self {
             access_token,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::client_for("codex"),
         }
     }
 
@@ -566,7 +577,12 @@ pub fn openai_responses_to_codex_request(raw: &Value, model: &str) -> Value {
             "context_management",
             "user",
         ] {
-            obj.remove(key);
+            if obj.remove(key).is_some_and(|value| !value.is_null()) {
+                crate::api::compat_warnings::warn(format!(
+                    "{} is not supported by Codex and was ignored",
+                    key
+                ));
+            }
         }
     }
 
@@ -589,35 +605,19 @@ pub fn codex_stream_to_openai_chunks(
             has_tool_call_announced: false,
             reverse_tool_names: reverse_map,
         };
-        let mut buffer = String::new();
-        let mut stream = response.bytes_stream();
+        let mut events = sse::data_stream(http_client::byte_stream(response));
 
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(data) => data,
                 Err(_) => break,
             };
-            let text = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&text);
-
-            while let Some(pos) = buffer.find('\n') {
-                let mut line = buffer[..pos].to_string();
-                buffer = buffer[pos + 1..].to_string();
-                line = line.trim_end_matches('\r').to_string();
-                if !line.starts_with("data:") {
-                    continue;
-                }
-                let data = line[5..].trim();
-                if data.is_empty() {
-                    continue;
-                }
-                if data == "[DONE]" {
-                    yield "[DONE]".to_string();
-                    return;
-                }
-                for chunk in convert_codex_stream_chunk(data, &mut state) {
-                    yield chunk;
-                }
+            if data == "[DONE]" {
+                yield "[DONE]".to_string();
+                return;
+            }
+            for chunk in convert_codex_stream_chunk(&data, &mut state) {
+                yield chunk;
             }
         }
 
@@ -637,105 +637,42 @@ pub fn codex_stream_to_openai_responses_events(
     response: reqwest::Response,
 ) -> impl Stream<Item = Result<Event, Infallible>> {
     async_stream::stream! {
-        let mut buffer = String::new();
-        let mut stream = response.bytes_stream();
+        let mut events = sse::data_stream(http_client::byte_stream(response));
 
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(data) => data,
                 Err(_) => break,
             };
-            let text = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&text);
-
-            while let Some(pos) = buffer.find('\n') {
-                let mut line = buffer[..pos].to_string();
-                buffer = buffer[pos + 1..].to_string();
-                line = line.trim_end_matches('\r').to_string();
-                if !line.starts_with("data:") {
-                    continue;
-                }
-                let data = line[5..].trim();
-                if data.is_empty() {
-                    continue;
-                }
-                yield Ok(Event::default().data(data.to_string()));
-            }
+            yield Ok(Event::default().data(data));
         }
     }
 }
 
+/// Collect a streamed Responses API reply into an OpenAI chat completion
 pub async fn collect_non_stream_response(
     response: reqwest::Response,
     original_request: &Value,
+    model: &str,
 ) -> Result<Value> {
-    let mut buffer = String::new();
-    let mut stream = response.bytes_stream();
-    let mut completed: Option<Value> = None;
-
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk?;
-        let text = String::from_utf8_lossy(&bytes);
-        buffer.push_str(&text);
-
-        while let Some(pos) = buffer.find('\n') {
-            let mut line = buffer[..pos].to_string();
-            buffer = buffer[pos + 1..].to_string();
-            line = line.trim_end_matches('\r').to_string();
-            if !line.starts_with("data:") {
-                continue;
-            }
-            let data = line[5..].trim();
-            if data.is_empty() || data == "[DONE]" {
-                continue;
-            }
-            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
-                if parsed.get("type").and_then(|v| v.as_str()) == Some("response.completed") {
-                    completed = Some(parsed);
-                    break;
-                }
-            }
-        }
-        if completed.is_some() {
-            break;
-        }
-    }
-
-    let completed = completed.ok_or_else(|| anyhow!("stream closed before response.completed"))?;
-    codex_completed_event_to_openai(&completed, original_request)
-        .ok_or_else(|| anyhow!("invalid response.completed payload"))
+    let chunks = codex_stream_to_openai_chunks(response, original_request.clone());
+    collector::collect_openai_chunks(chunks, model).await
 }
 
 pub async fn collect_non_stream_responses_response(response: reqwest::Response) -> Result<Value> {
-    let mut buffer = String::new();
-    let mut stream = response.bytes_stream();
+    let mut events = sse::data_stream(http_client::byte_stream(response));
     let mut completed: Option<Value> = None;
 
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk?;
-        let text = String::from_utf8_lossy(&bytes);
-        buffer.push_str(&text);
-
-        while let Some(pos) = buffer.find('\n') {
-            let mut line = buffer[..pos].to_string();
-            buffer = buffer[pos + 1..].to_string();
-            line = line.trim_end_matches('\r').to_string();
-            if !line.starts_with("data:") {
-                continue;
-            }
-            let data = line[5..].trim();
-            if data.is_empty() || data == "[DONE]" {
-                continue;
-            }
-            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
-                if parsed.get("type").and_then(|v| v.as_str()) == Some("response.completed") {
-                    completed = Some(parsed);
-                    break;
-                }
-            }
+    while let Some(event) = events.next().await {
+        let data = event?;
+        if data == "[DONE]" {
+            continue;
         }
-        if completed.is_some() {
-            break;
+        if let Ok(parsed) = serde_json::from_str::<Value>(&data) {
+            if parsed.get("type").and_then(|v| v.as_str()) == Some("response.completed") {
+                completed = Some(parsed);
+                break;
+            }
         }
     }
 
@@ -1242,6 +1179,89 @@ fn set_text_format(out: &mut Value, format: Value) {
     }
 }
 
+/// ChatGPT Codex Responses API behind the common provider interface
+pub struct CodexProvider;
+
+#[async_trait]
+impl ChatProvider for CodexProvider {
+    fn id(&self) -> &'static str {
+        "codex"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Codex"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            thinking: true,
+            count_tokens: false,
+        }
+    }
+
+    fn ignored_params(&self) -> &'static [&'static str] {
+        // The Codex backend fixes sampling and output length itself
+        &[
+            "temperature",
+            "top_p",
+            "max_tokens",
+            "max_completion_tokens",
+            "stop",
+            "n",
+            "seed",
+            "frequency_penalty",
+            "presence_penalty",
+            "logit_bias",
+            "logprobs",
+            "top_logprobs",
+        ]
+    }
+
+    fn messages_mapping(&self, _model: &str) -> MessagesMapping {
+        MessagesMapping::OpenAiChat {
+            image_handling: ClaudeImageHandling::Base64Any,
+            guard_thinking: false,
+            reasoning_as_text: false,
+        }
+    }
+
+    fn list_models(&self) -> Vec<ModelInfo> {
+        let base = handlers::get_available_codex_models(&crate::config::resolve_auth_dir());
+        let mut models = handlers::build_prefixed_models(self.id(), &base);
+        models.extend(handlers::build_codex_models_with_reasoning(&base));
+        models
+    }
+
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError> {
+        let (access_token, _) = ctx.credentials.access_token()?;
+        let codex_request = openai_to_codex_request(request, ctx.model, true);
+        // The Codex backend only streams, non-stream responses are collected from the stream
+        let response = CodexClient::new(access_token)
+            .stream_responses(&codex_request, true)
+            .await?;
+        Ok(collect_non_stream_response(response, request, ctx.model).await?)
+    }
+
+    async fn chat_stream(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<EventStream, ProviderError> {
+        let (access_token, _) = ctx.credentials.access_token()?;
+        let codex_request = openai_to_codex_request(request, ctx.model, true);
+        let response = CodexClient::new(access_token)
+            .stream_responses(&codex_request, true)
+            .await?;
+        Ok(provider::chunk_events(
+            self.id(),
+            codex_stream_to_openai_chunks(response, request.clone()),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,274 @@
+// Non-stream responses from streaming backends
+// Codex, Antigravity and Kiro only stream, and each already translates its upstream events
+// into OpenAI `chat.completion.chunk` payloads. Non-stream OpenAI and Anthropic requests fold
+// those chunks into one `chat.completion` here, so content, reasoning, tool calls, usage and
+// finish reasons are collected the same way for every backend.
+
+use anyhow::{anyhow, Result};
+use futures::{Stream, StreamExt};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+struct ToolCallParts {
+    id: String,
+    call_type: String,
+    name: String,
+    arguments: String,
+}
+
+/// Accumulates OpenAI chat completion chunks into a single response
+#[derive(Debug, Default)]
+pub struct ChunkCollector {
+    id: Option<String>,
+    created: Option<i64>,
+    content: String,
+    reasoning: String,
+    tool_calls: BTreeMap<u64, ToolCallParts>,
+    /// Code the backend ran and its results, in the order they arrived
+    code_execution: Vec<Value>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+    chunks: usize,
+}
+
+impl ChunkCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one chunk as emitted by a stream converter, either bare JSON or an SSE `data:` line
+    pub fn push_str(&mut self, chunk: &str) {
+        for line in chunk.lines() {
+            let line = line.trim();
+            let data = line.strip_prefix("data:").unwrap_or(line).trim();
+            if data.is_empty() || data == "[DONE]" {
+                continue;
+            }
+            if let Ok(value) = serde_json::from_str::<Value>(data) {
+                self.push(&value);
+            }
+        }
+    }
+
+    pub fn push(&mut self, chunk: &Value) {
+        self.chunks += 1;
+
+        if let Some(id) = chunk.get("id").and_then(|v| v.as_str()) {
+            if !id.is_empty() {
+                self.id = Some(id.to_string());
+            }
+        }
+        if let Some(created) = chunk.get("created").and_then(|v| v.as_i64()) {
+            if created > 0 {
+                self.created = Some(created);
+            }
+        }
+        if let Some(usage) = chunk.get("usage").filter(|v| v.is_object()) {
+            self.usage = Some(usage.clone());
+        }
+
+        let Some(choice) = chunk
+            .get("choices")
+            .and_then(|v| v.as_array())
+            .and_then(|choices| choices.first())
+        else {
+            return;
+        };
+
+        if let Some(delta) = choice.get("delta") {
+            if let Some(content) = delta.get("content").and_then(|v| v.as_str()) {
+                self.content.push_str(content);
+            }
+            if let Some(reasoning) = delta.get("reasoning_content").and_then(|v| v.as_str()) {
+                self.reasoning.push_str(reasoning);
+            }
+            if let Some(calls) = delta.get("tool_calls").and_then(|v| v.as_array()) {
+                for call in calls {
+                    self.push_tool_call(call);
+                }
+            }
+            if let Some(items) = delta.get("code_execution").and_then(|v| v.as_array()) {
+                self.code_execution.extend(items.iter().cloned());
+            }
+        }
+
+        if let Some(reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
+            if !reason.is_empty() {
+                self.finish_reason = Some(reason.to_string());
+            }
+        }
+    }
+
+    fn push_tool_call(&mut self, call: &Value) {
+        let index = call
+            .get("index")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(self.tool_calls.len() as u64);
+        let entry = self.tool_calls.entry(index).or_default();
+
+        if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
+            if !id.is_empty() {
+                entry.id = id.to_string();
+            }
+        }
+        if let Some(call_type) = call.get("type").and_then(|v| v.as_str()) {
+            if !call_type.is_empty() {
+                entry.call_type = call_type.to_string();
+            }
+        }
+        if let Some(function) = call.get("function") {
+            if let Some(name) = function.get("name").and_then(|v| v.as_str()) {
+                if !name.is_empty() {
+                    entry.name = name.to_string();
+                }
+            }
+            if let Some(arguments) = function.get("arguments").and_then(|v| v.as_str()) {
+                entry.arguments.push_str(arguments);
+            }
+        }
+    }
+
+    /// Build the `chat.completion` object, reported under the requested model name
+    pub fn finish(self, model: &str) -> Result<Value> {
+        if self.chunks == 0 {
+            return Err(anyhow!("stream closed before response payload"));
+        }
+        let Some(finish_reason) = self.finish_reason else {
+            return Err(anyhow!("stream closed before the response finished"));
+        };
+
+        let mut message = Map::new();
+        message.insert("role".to_string(), json!("assistant"));
+        message.insert("content".to_string(), json!(self.content));
+        let reasoning = self.reasoning.trim_end();
+        if !reasoning.is_empty() {
+            message.insert("reasoning_content".to_string(), json!(reasoning));
+        }
+        if !self.code_execution.is_empty() {
+            message.insert(
+                "code_execution".to_string(),
+                Value::Array(self.code_execution),
+            );
+        }
+
+        let has_tool_calls = !self.tool_calls.is_empty();
+        if has_tool_calls {
+            let calls: Vec<Value> = self
+                .tool_calls
+                .into_values()
+                .map(|call| {
+                    json!({
+                        "id": call.id,
+                        "type": if call.call_type.is_empty() { "function".to_string() } else { call.call_type },
+                        "function": {
+                            "name": call.name,
+                            "arguments": if call.arguments.is_empty() { "{}".to_string() } else { call.arguments }
+                        }
+                    })
+                })
+                .collect();
+            message.insert("tool_calls".to_string(), Value::Array(calls));
+        }
+
+        let usage = self.usage.unwrap_or_else(
+            || json!({"prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0}),
+        );
+
+        Ok(json!({
+            "id": self
+                .id
+                .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4().simple())),
+            "object": "chat.completion",
+            "created": self.created.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": Value::Object(message),
+                "finish_reason": normalize_finish_reason(&finish_reason, has_tool_calls)
+            }],
+            "usage": usage
+        }))
+    }
+}
+
+/// Map backend finish reasons onto the OpenAI set
+fn normalize_finish_reason(reason: &str, has_tool_calls: bool) -> &'static str {
+    match reason.to_ascii_lowercase().as_str() {
+        _ if has_tool_calls => "tool_calls",
+        "length" | "max_tokens" => "length",
+        "content_filter" | "safety" | "recitation" | "blocklist" | "prohibited_content" => {
+            "content_filter"
+        }
+        "tool_calls" | "function_call" => "tool_calls",
+        _ => "stop",
+    }
+}
+
+/// Collect a stream of OpenAI chunk payloads into a `chat.completion`
+pub async fn collect_openai_chunks<S>(chunks: S, model: &str) -> Result<Value>
+where
+    S: Stream<Item = String>,
+{
+    futures::pin_mut!(chunks);
+    let mut collector = ChunkCollector::new();
+    while let Some(chunk) = chunks.next().await {
+        collector.push_str(&chunk);
+    }
+    collector.finish(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn merges_content_reasoning_tool_calls_and_usage() {
+        let chunks = vec![
+            r#"{"id":"chatcmpl-1","created":10,"choices":[{"index":0,"delta":{"role":"assistant","reasoning_content":"think"}}]}"#.to_string(),
+            r#"data: {"choices":[{"index":0,"delta":{"content":"Hel"}}]}"#.to_string(),
+            r#"{"choices":[{"index":0,"delta":{"content":"lo"}}]}"#.to_string(),
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":""}}]}}]}"#.to_string(),
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":"}}]}}]}"#.to_string(),
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"Paris\"}"}}]}}]}"#.to_string(),
+            r#"{"choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":7,"total_tokens":12}}"#.to_string(),
+            "[DONE]".to_string(),
+        ];
+
+        let response = collect_openai_chunks(futures::stream::iter(chunks), "gpt-5")
+            .await
+            .unwrap();
+
+        assert_eq!(response["id"], "chatcmpl-1");
+        assert_eq!(response["model"], "gpt-5");
+        let choice = &response["choices"][0];
+        assert_eq!(choice["message"]["content"], "Hello");
+        assert_eq!(choice["message"]["reasoning_content"], "think");
+        assert_eq!(choice["message"]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            r#"{"city":"Paris"}"#
+        );
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(response["usage"]["total_tokens"], 12);
+    }
+
+    #[tokio::test]
+    async fn rejects_streams_that_end_early() {
+        let empty = collect_openai_chunks(futures::stream::iter(Vec::<String>::new()), "m").await;
+        assert!(empty.is_err());
+
+        let truncated =
+            vec![r#"{"choices":[{"index":0,"delta":{"content":"partial"}}]}"#.to_string()];
+        let truncated = collect_openai_chunks(futures::stream::iter(truncated), "m").await;
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn normalizes_backend_finish_reasons() {
+        assert_eq!(normalize_finish_reason("MAX_TOKENS", false), "length");
+        assert_eq!(normalize_finish_reason("safety", false), "content_filter");
+        assert_eq!(normalize_finish_reason("stop", true), "tool_calls");
+        assert_eq!(normalize_finish_reason("STOP", false), "stop");
+    }
+}
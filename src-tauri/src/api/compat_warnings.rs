@@ -0,0 +1,182 @@
+// Translation warnings
+// Provider mappings only cover part of the OpenAI chat surface: parameters a backend has no
+// equivalent for are dropped, tools and images are lost on text-only mappings and streaming
+// falls back to a complete response. Such changes are collected while a request is served,
+// logged, and returned in the x-oneproxy-warnings header, so clients can tell why a provider
+// behaves differently instead of getting a silently degraded answer.
+
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use std::cell::RefCell;
+
+use super::provider::{ChatProvider, ProviderCapabilities};
+
+tokio::task_local! {
+    static WARNINGS: RefCell<Vec<String>>;
+}
+
+/// Response header listing what the provider mapping dropped or changed, `; ` separated
+pub const X_ONEPROXY_WARNINGS: &str = "x-oneproxy-warnings";
+
+/// Record a warning for the request being served; repeated warnings are kept once
+pub fn warn(message: String) {
+    let _ = WARNINGS.try_with(|warnings| {
+        let mut warnings = warnings.borrow_mut();
+        if !warnings.contains(&message) {
+            tracing::warn!("[Compat] {}", message);
+            warnings.push(message);
+        }
+    });
+}
+
+fn is_set(request: &Value, key: &str) -> bool {
+    request.get(key).is_some_and(|value| !value.is_null())
+}
+
+fn has_images(request: &Value) -> bool {
+    request
+        .get("messages")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message.get("content").and_then(|v| v.as_array()))
+        .flatten()
+        .any(|part| {
+            matches!(
+                part.get("type").and_then(|v| v.as_str()),
+                Some("image_url" | "input_image" | "image")
+            )
+        })
+}
+
+/// What a provider mapping drops or changes in an OpenAI chat request
+fn request_warnings(
+    label: &str,
+    capabilities: ProviderCapabilities,
+    ignored_params: &[&str],
+    request: &Value,
+) -> Vec<String> {
+    let mut warnings: Vec<String> = ignored_params
+        .iter()
+        .filter(|param| is_set(request, param))
+        .map(|param| format!("{} is not supported by {} and was ignored", param, label))
+        .collect();
+
+    let has_tools = request
+        .get("tools")
+        .and_then(|v| v.as_array())
+        .is_some_and(|tools| !tools.is_empty());
+    if has_tools && !capabilities.tools {
+        warnings.push(format!(
+            "tools are not supported by {} and were removed",
+            label
+        ));
+    }
+    if !capabilities.vision && has_images(request) {
+        warnings.push(format!("images are not supported by {}", label));
+    }
+    if !capabilities.thinking {
+        for param in ["reasoning_effort", "thinking"] {
+            if is_set(request, param) {
+                warnings.push(format!(
+                    "{} is not supported by {} and was ignored",
+                    param, label
+                ));
+            }
+        }
+    }
+    if request.get("stream").and_then(|v| v.as_bool()) == Some(true) && !capabilities.streaming {
+        warnings.push(format!(
+            "{} does not stream chat completions, the response was returned in one piece",
+            label
+        ));
+    }
+    warnings
+}
+
+/// Record what the provider will drop or change in an OpenAI chat request
+pub fn check_request(provider: &dyn ChatProvider, request: &Value) {
+    for warning in request_warnings(
+        provider.display_name(),
+        provider.capabilities(),
+        provider.ignored_params(),
+        request,
+    ) {
+        warn(warning);
+    }
+}
+
+/// Collect the warnings of a request and return them in the x-oneproxy-warnings header
+pub async fn warnings_middleware(request: Request<Body>, next: Next) -> Response {
+    let (mut response, warnings) = WARNINGS
+        .scope(RefCell::new(Vec::new()), async {
+            let response = next.run(request).await;
+            (response, WARNINGS.with(|warnings| warnings.take()))
+        })
+        .await;
+    if !warnings.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&warnings.join("; ")) {
+            response.headers_mut().insert(X_ONEPROXY_WARNINGS, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn reports_dropped_parameters_once() {
+        let text_only = ProviderCapabilities {
+            streaming: false,
+            tools: false,
+            vision: false,
+            thinking: false,
+            count_tokens: false,
+        };
+        let request = json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "frequency_penalty": 0.5,
+            "seed": null,
+            "tools": [{"type": "function", "function": {"name": "f"}}],
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+            ]}]
+        });
+        let warnings = request_warnings(
+            "Claude",
+            text_only,
+            &["frequency_penalty", "seed"],
+            &request,
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "frequency_penalty is not supported by Claude and was ignored",
+                "tools are not supported by Claude and were removed",
+                "images are not supported by Claude",
+                "Claude does not stream chat completions, the response was returned in one piece",
+            ]
+        );
+
+        let collected = WARNINGS
+            .scope(RefCell::new(Vec::new()), async {
+                warn("seed is not supported by Kiro and was ignored".to_string());
+                warn("seed is not supported by Kiro and was ignored".to_string());
+                WARNINGS.with(|warnings| warnings.take())
+            })
+            .await;
+        assert_eq!(collected.len(), 1);
+        // Outside of a request there is nothing to collect into
+        warn("ignored".to_string());
+    }
+}
@@ -0,0 +1,299 @@
+// Context-size aware model upgrades
+// When `model-routing.context-upgrade` is enabled and a prompt is estimated to exceed the
+// requested model's context window, the request is moved to a larger-context variant on
+// the same provider (a "-1m" style variant, or the next tier such as flash -> pro).
+// Clients are told about the substitution through the x-oneproxy-context-upgrade header.
+
+use axum::http::HeaderValue;
+use axum::response::Response;
+use serde_json::{json, Value};
+
+use super::handlers::parse_provider_prefix;
+use super::kiro;
+use super::model_router::{resolve_model, ResolvedModel};
+use super::provider;
+
+/// Response header describing a context upgrade, e.g. "gemini-2.5-flash -> gemini-2.5-pro"
+pub const X_ONEPROXY_CONTEXT_UPGRADE: &str = "x-oneproxy-context-upgrade";
+
+/// Rough characters per token used for prompt estimates
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Input context windows by model name prefix and suffix; the first matching entry wins, so
+/// larger-context variants come before the generic prefix of their family
+const CONTEXT_WINDOWS: &[(&str, &str, i64)] = &[
+    ("claude-", "-1m", 1_000_000),
+    ("claude-", "[1m]", 1_000_000),
+    ("claude-", "", 200_000),
+    ("gemini-2.0-flash", "", 1_048_576),
+    ("gemini-2.5-flash-lite", "", 1_048_576),
+    ("gemini-2.5-flash", "", 1_048_576),
+    ("gemini-2.5-pro", "", 1_048_576),
+    ("gemini-3", "", 1_048_576),
+    ("gpt-5", "", 272_000),
+    ("kimi-k2", "", 256_000),
+    ("glm-4.5", "", 128_000),
+    ("glm-4.6", "", 200_000),
+];
+
+/// Name substitutions that move a model up one tier within its family
+const TIER_UPGRADES: &[(&str, &str)] = &[
+    ("-flash-lite", "-flash"),
+    ("-flash", "-pro"),
+    ("-mini", ""),
+    ("haiku", "sonnet"),
+    ("sonnet", "opus"),
+];
+
+/// A model substitution made because the prompt did not fit
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextUpgrade {
+    pub provider: String,
+    pub from: String,
+    pub to: String,
+    pub estimated_tokens: i64,
+}
+
+impl ContextUpgrade {
+    pub fn header_value(&self) -> String {
+        format!("{} -> {}", self.from, self.to)
+    }
+}
+
+fn enabled() -> bool {
+    crate::config::get_config()
+        .map(|config| config.model_routing.context_upgrade)
+        .unwrap_or(false)
+}
+
+/// Estimate prompt tokens from the request fields that are sent upstream
+pub fn estimate_prompt_tokens(request: &Value) -> i64 {
+    let chars: usize = [
+        "system",
+        "messages",
+        "tools",
+        "contents",
+        "systemInstruction",
+        "input",
+    ]
+    .iter()
+    .filter_map(|key| request.get(*key))
+    .map(|value| match value {
+        Value::String(text) => text.len(),
+        other => other.to_string().len(),
+    })
+    .sum();
+    (chars / CHARS_PER_TOKEN) as i64
+}
+
+/// Input context window of a model, if known
+pub fn context_window(provider: &str, model: &str) -> Option<i64> {
+    if provider == "kiro" {
+        return Some(kiro::get_max_input_tokens(model));
+    }
+    let model = model.to_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, suffix, _)| model.starts_with(prefix) && model.ends_with(suffix))
+        .map(|(_, _, window)| *window)
+}
+
+/// Models the provider currently lists, without the provider prefix
+fn provider_models(provider_id: &str) -> Vec<String> {
+    let Some(chat_provider) = provider::get(provider_id) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}/", provider_id);
+    chat_provider
+        .list_models()
+        .into_iter()
+        .map(|model| {
+            model
+                .id
+                .strip_prefix(&prefix)
+                .unwrap_or(&model.id)
+                .to_string()
+        })
+        .collect()
+}
+
+/// Larger-context variants of `model` among `available`, in order of preference
+fn upgrade_candidates(model: &str, available: &[String]) -> Vec<String> {
+    // Same model with a suffix, e.g. "claude-sonnet-4.5-1m"
+    let variant_prefix = format!("{}-", model);
+    let mut candidates: Vec<String> = available
+        .iter()
+        .filter(|candidate| candidate.starts_with(&variant_prefix))
+        .cloned()
+        .collect();
+
+    for (from, to) in TIER_UPGRADES {
+        if !model.contains(from) {
+            continue;
+        }
+        let upgraded = model.replacen(from, to, 1);
+        if available.contains(&upgraded) && !candidates.contains(&upgraded) {
+            candidates.push(upgraded);
+        }
+    }
+    candidates
+}
+
+/// Pick the smallest variant that fits the estimated prompt
+fn pick_upgrade(
+    provider: &str,
+    model: &str,
+    estimated_tokens: i64,
+    available: &[String],
+) -> Option<String> {
+    let window = context_window(provider, model)?;
+    if estimated_tokens <= window {
+        return None;
+    }
+    upgrade_candidates(model, available)
+        .into_iter()
+        .filter_map(|candidate| {
+            let window = context_window(provider, &candidate)?;
+            (window >= estimated_tokens).then_some((candidate, window))
+        })
+        .min_by_key(|(_, window)| *window)
+        .map(|(candidate, _)| candidate)
+}
+
+/// Rewrite the request model to a larger-context variant when enabled and needed
+/// The rewritten model carries its provider prefix so routing stays on the same provider.
+pub fn upgrade_request(request: &mut Value) -> Option<ContextUpgrade> {
+    if !enabled() {
+        return None;
+    }
+    let raw_model = request.get("model").and_then(|v| v.as_str())?.to_string();
+    let (provider, model) = match parse_provider_prefix(&raw_model) {
+        (Some(provider), model) => (provider, model),
+        (None, _) => match resolve_model(&raw_model, None) {
+            ResolvedModel::Explicit { provider, model }
+            | ResolvedModel::Aggregated {
+                provider, model, ..
+            } => (provider, model),
+            ResolvedModel::NoProvider { .. } => return None,
+        },
+    };
+
+    let estimated_tokens = estimate_prompt_tokens(request);
+    let upgraded = pick_upgrade(
+        &provider,
+        &model,
+        estimated_tokens,
+        &provider_models(&provider),
+    )?;
+
+    tracing::info!(
+        "[ContextUpgrade] ~{} prompt tokens exceed {}/{}, using {}/{}",
+        estimated_tokens,
+        provider,
+        model,
+        provider,
+        upgraded
+    );
+    request["model"] = json!(format!("{}/{}", provider, upgraded));
+    Some(ContextUpgrade {
+        provider,
+        from: model,
+        to: upgraded,
+        estimated_tokens,
+    })
+}
+
+/// Note a context upgrade on the response
+pub fn annotate(mut response: Response, upgrade: Option<ContextUpgrade>) -> Response {
+    if let Some(upgrade) = upgrade {
+        if let Ok(value) = HeaderValue::from_str(&upgrade.header_value()) {
+            response
+                .headers_mut()
+                .insert(X_ONEPROXY_CONTEXT_UPGRADE, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn models(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn prefers_suffix_variants_then_tier_upgrades() {
+        let available = models(&[
+            "claude-haiku-4-5",
+            "claude-sonnet-4-5",
+            "claude-sonnet-4-5-1m",
+            "gemini-2.5-flash",
+            "gemini-2.5-pro",
+        ]);
+        assert_eq!(
+            upgrade_candidates("claude-sonnet-4-5", &available),
+            models(&["claude-sonnet-4-5-1m"])
+        );
+        assert_eq!(
+            upgrade_candidates("gemini-2.5-flash", &available),
+            models(&["gemini-2.5-pro"])
+        );
+    }
+
+    #[test]
+    fn upgrades_to_a_larger_context_variant() {
+        let available = models(&[
+            "claude-sonnet-4-5",
+            "claude-sonnet-4-5-thinking",
+            "claude-sonnet-4-5-1m",
+        ]);
+        assert_eq!(
+            context_window("claude", "claude-sonnet-4-5-1m"),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            pick_upgrade("claude", "claude-sonnet-4-5", 300_000, &available),
+            Some("claude-sonnet-4-5-1m".to_string())
+        );
+        assert_eq!(
+            pick_upgrade("claude", "claude-sonnet-4-5", 150_000, &available),
+            None
+        );
+        // Nothing is large enough
+        assert_eq!(
+            pick_upgrade("claude", "claude-sonnet-4-5", 2_000_000, &available),
+            None
+        );
+    }
+
+    #[test]
+    fn upgrades_only_when_prompt_does_not_fit() {
+        let available = models(&["gpt-5-codex", "gpt-5-codex-mini"]);
+        assert_eq!(
+            pick_upgrade("codex", "gpt-5-codex-mini", 1_000, &available),
+            None
+        );
+        // No listed variant has a larger window than gpt-5 models
+        assert_eq!(
+            pick_upgrade("codex", "gpt-5-codex-mini", 300_000, &available),
+            None
+        );
+        assert_eq!(
+            context_window("gemini", "gemini-2.5-flash-lite"),
+            Some(1_048_576)
+        );
+        assert_eq!(context_window("codex", "unknown-model"), None);
+    }
+
+    #[test]
+    fn estimates_prompt_tokens_from_request_fields() {
+        let request = json!({
+            "model": "gemini/gemini-2.5-flash",
+            "system": "x".repeat(400),
+            "max_tokens": 10
+        });
+        assert_eq!(estimate_prompt_tokens(&request), 100);
+    }
+}
@@ -0,0 +1,169 @@
+// Auto-economy routing
+// With `model-routing.economy` enabled, requests whose estimated prompt is small and that use
+// no tools or extended reasoning are sent to the configured cheap model; everything else keeps
+// the requested (premium) model. Clients can override the decision per request with the
+// x-oneproxy-routing header ("economy" or "premium") and are told about a substitution
+// through the x-oneproxy-economy response header.
+
+use axum::http::{HeaderMap, HeaderValue};
+use axum::response::Response;
+use serde_json::{json, Value};
+
+use super::context_upgrade::estimate_prompt_tokens;
+use crate::config::EconomyRoutingConfig;
+
+/// Request header forcing the policy: "economy" always uses the cheap model, "premium" never
+pub const X_ONEPROXY_ROUTING: &str = "x-oneproxy-routing";
+
+/// Response header describing an economy substitution, e.g. "claude-opus-4-5 -> gemini-2.5-flash"
+pub const X_ONEPROXY_ECONOMY: &str = "x-oneproxy-economy";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Override {
+    Economy,
+    Premium,
+}
+
+fn parse_override(headers: &HeaderMap) -> Option<Override> {
+    let value = headers.get(X_ONEPROXY_ROUTING)?.to_str().ok()?;
+    match value.trim().to_ascii_lowercase().as_str() {
+        "economy" => Some(Override::Economy),
+        "premium" => Some(Override::Premium),
+        _ => None,
+    }
+}
+
+/// A model substitution made by the economy policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct EconomyRoute {
+    pub from: String,
+    pub to: String,
+    pub estimated_tokens: i64,
+}
+
+impl EconomyRoute {
+    pub fn header_value(&self) -> String {
+        format!("{} -> {}", self.from, self.to)
+    }
+}
+
+/// Tools, function calling or extended thinking mark a request as complex in any protocol
+fn is_complex(request: &Value) -> bool {
+    let non_empty = |key: &str| match request.get(key) {
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    };
+    let thinking_enabled = request
+        .pointer("/thinking/type")
+        .and_then(|v| v.as_str())
+        .is_some_and(|t| t != "disabled");
+    non_empty("tools")
+        || non_empty("functions")
+        || non_empty("reasoning_effort")
+        || non_empty("reasoning")
+        || thinking_enabled
+}
+
+/// Cheap model for the request, or `None` when it should keep the requested model
+fn pick_economy_model(
+    config: &EconomyRoutingConfig,
+    request: &Value,
+    forced: Option<Override>,
+) -> Option<EconomyRoute> {
+    let model = config.model.trim();
+    if model.is_empty() || forced == Some(Override::Premium) {
+        return None;
+    }
+    let requested = request.get("model").and_then(|v| v.as_str())?;
+    if requested == model {
+        return None;
+    }
+    let estimated_tokens = estimate_prompt_tokens(request);
+    let small = estimated_tokens <= config.max_prompt_tokens && !is_complex(request);
+    let use_economy = match forced {
+        Some(Override::Economy) => true,
+        _ => config.enable && small,
+    };
+    use_economy.then(|| EconomyRoute {
+        from: requested.to_string(),
+        to: model.to_string(),
+        estimated_tokens,
+    })
+}
+
+/// Rewrite the request model to the economy model when the policy (or the client) asks for it
+pub fn route_request(headers: &HeaderMap, request: &mut Value) -> Option<EconomyRoute> {
+    let config = crate::config::get_config()?.model_routing.economy;
+    let route = pick_economy_model(&config, request, parse_override(headers))?;
+    tracing::info!(
+        "[Economy] ~{} prompt tokens, routing {} to {}",
+        route.estimated_tokens,
+        route.from,
+        route.to
+    );
+    request["model"] = json!(route.to);
+    Some(route)
+}
+
+/// Note an economy substitution on the response
+pub fn annotate(mut response: Response, route: Option<EconomyRoute>) -> Response {
+    if let Some(route) = route {
+        if let Ok(value) = HeaderValue::from_str(&route.header_value()) {
+            response.headers_mut().insert(X_ONEPROXY_ECONOMY, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EconomyRoutingConfig {
+        EconomyRoutingConfig {
+            enable: true,
+            model: "gemini/gemini-2.5-flash".to_string(),
+            max_prompt_tokens: 100,
+        }
+    }
+
+    #[test]
+    fn routes_only_small_plain_prompts() {
+        let small =
+            json!({"model": "claude-opus-4-5", "messages": [{"role": "user", "content": "hi"}]});
+        let route = pick_economy_model(&config(), &small, None).unwrap();
+        assert_eq!(
+            route.header_value(),
+            "claude-opus-4-5 -> gemini/gemini-2.5-flash"
+        );
+
+        let large = json!({"model": "claude-opus-4-5", "messages": [{"role": "user", "content": "x".repeat(1_000)}]});
+        assert!(pick_economy_model(&config(), &large, None).is_none());
+
+        let tools = json!({"model": "gpt-5", "messages": [], "tools": [{"type": "function"}]});
+        assert!(pick_economy_model(&config(), &tools, None).is_none());
+
+        let thinking =
+            json!({"model": "claude-opus-4-5", "messages": [], "thinking": {"type": "enabled"}});
+        assert!(pick_economy_model(&config(), &thinking, None).is_none());
+    }
+
+    #[test]
+    fn header_overrides_the_policy() {
+        let small = json!({"model": "gpt-5", "messages": []});
+        assert!(pick_economy_model(&config(), &small, Some(Override::Premium)).is_none());
+
+        let disabled = EconomyRoutingConfig {
+            enable: false,
+            ..config()
+        };
+        assert!(pick_economy_model(&disabled, &small, None).is_none());
+        let tools = json!({"model": "gpt-5", "tools": [{"type": "function"}]});
+        assert!(pick_economy_model(&disabled, &tools, Some(Override::Economy)).is_some());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(X_ONEPROXY_ROUTING, HeaderValue::from_static("Premium"));
+        assert_eq!(parse_override(&headers), Some(Override::Premium));
+    }
+}
@@ -0,0 +1,50 @@
+// Backend -> frontend event stream (Tauri events)
+
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+static APP_HANDLE: OnceCell<AppHandle> = OnceCell::new();
+
+/// Progress of the startup warm-up tasks (payload: `WarmupProgress`)
+pub const WARMUP_PROGRESS: &str = "warmup-progress";
+
+/// An account was disabled automatically (payload: `{ account_id, provider, reason }`)
+pub const ACCOUNT_DISABLED: &str = "account-disabled";
+
+/// A new account was probed (payload: `{ account_id, provider, validation }`)
+pub const ACCOUNT_VALIDATED: &str = "account-validated";
+
+/// Auth files could not be written, or can be again (payload: warning text, or `null` when cleared)
+pub const AUTH_DIR_WARNING: &str = "auth-dir-warning";
+
+/// The routing mode changed, e.g. from the tray menu (payload: `"provider"` | `"model"`)
+pub const ROUTING_MODE_CHANGED: &str = "routing-mode-changed";
+
+/// A provider became healthy, degraded or down (payload: `ProviderStatus`)
+pub const PROVIDER_STATUS_CHANGED: &str = "provider-status-changed";
+
+/// Routing was paused or resumed (payload: `bool`, true when paused)
+pub const ROUTING_PAUSED_CHANGED: &str = "routing-paused-changed";
+
+/// config.yaml was re-read through SIGHUP or the management API (payload: `ReloadResult`)
+pub const CONFIG_RELOADED: &str = "config-reloaded";
+
+/// Register the app handle used to emit events. Safe to call more than once.
+pub fn init(app_handle: &AppHandle) {
+    APP_HANDLE.set(app_handle.clone()).ok();
+}
+
+/// App handle registered by `init`
+pub fn app_handle() -> Option<&'static AppHandle> {
+    APP_HANDLE.get()
+}
+
+/// Emit an event to all webviews. No-op until `init` has been called.
+pub fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(handle) = APP_HANDLE.get() {
+        if let Err(e) = handle.emit(event, payload) {
+            tracing::debug!("Failed to emit event '{}': {}", event, e);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+// N-best sampling across providers
+// `POST /v1/chat/completions/fan-out` takes a chat completion request and sends it, without
+// streaming, to the first `fan_out.providers` providers the model router would use for the
+// model (aggregation mode, no provider prefix). All candidates are returned side by side for
+// quality comparisons; with `fan_out.judge` the configured judge model
+// (`model-routing.fan-out-judge-model`) also picks the best one. The tokens of every
+// candidate and of the judge are added up in the top-level `usage`, so the request log and
+// key quotas count the whole fan-out.
+
+use axum::{http::StatusCode, response::IntoResponse, response::Response, Json};
+use http_body_util::BodyExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Instant;
+
+use super::race::{provider_targets, RaceTarget};
+use super::usage::{extract_usage, TokenUsage};
+
+/// Providers asked when the request does not say
+const DEFAULT_PROVIDERS: usize = 2;
+
+/// One provider's answer
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub provider: String,
+    pub model: String,
+    pub status: u16,
+    pub latency_ms: u64,
+    /// Chat completion (or error body) returned by the provider
+    pub response: Value,
+}
+
+/// Choice of the judge model
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub model: String,
+    /// Index into `candidates`, `None` when the judge gave no usable answer
+    pub best: Option<usize>,
+    pub reply: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FanOutResponse {
+    pub object: &'static str,
+    pub candidates: Vec<Candidate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge: Option<Verdict>,
+    /// Tokens of all candidates and the judge together
+    pub usage: Value,
+}
+
+fn bad_request(message: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": 400
+            }
+        })),
+    )
+        .into_response()
+}
+
+async fn into_json(response: Response) -> Value {
+    let bytes = match response.into_body().collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => return json!({"error": {"message": e.to_string()}}),
+    };
+    serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| json!({"error": {"message": String::from_utf8_lossy(&bytes)}}))
+}
+
+async fn ask(request: &Value, target: RaceTarget) -> Candidate {
+    let start = Instant::now();
+    let mut request = request.clone();
+    request["model"] = json!(target.model);
+    let response = super::handlers::serve_chat_completions(request).await;
+    let status = response.status().as_u16();
+    let response = into_json(response).await;
+    Candidate {
+        provider: target.provider,
+        model: target.model,
+        status,
+        latency_ms: start.elapsed().as_millis() as u64,
+        response,
+    }
+}
+
+fn answer_text(candidate: &Candidate) -> Option<&str> {
+    if !(200..300).contains(&candidate.status) {
+        return None;
+    }
+    candidate
+        .response
+        .pointer("/choices/0/message/content")
+        .and_then(|v| v.as_str())
+}
+
+/// Prompt asking the judge for the number of the best answer
+fn judge_request(model: &str, request: &Value, candidates: &[Candidate]) -> Value {
+    let conversation = request
+        .get("messages")
+        .map(|messages| messages.to_string())
+        .unwrap_or_default();
+    let mut prompt = format!("Conversation (JSON):\n{}\n\n", conversation);
+    for (index, candidate) in candidates.iter().enumerate() {
+        let answer = answer_text(candidate).unwrap_or("(no answer)");
+        prompt.push_str(&format!("Candidate {}:\n{}\n\n", index + 1, answer));
+    }
+    prompt.push_str(
+        "Which candidate is the best reply to the last message of the conversation? \
+         Answer with the candidate number only.",
+    );
+    json!({
+        "model": model,
+        "stream": false,
+        "temperature": 0,
+        "messages": [{"role": "user", "content": prompt}]
+    })
+}
+
+/// Candidate index named by the judge's reply; only answered candidates can win
+fn parse_verdict(reply: &str, candidates: &[Candidate]) -> Option<usize> {
+    let number: usize = reply
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|part| !part.is_empty())?
+        .parse()
+        .ok()?;
+    let index = number.checked_sub(1)?;
+    candidates.get(index).and_then(answer_text).map(|_| index)
+}
+
+/// The judge's verdict and the tokens it used
+async fn judge(
+    model: &str,
+    request: &Value,
+    candidates: &[Candidate],
+) -> (Verdict, Option<TokenUsage>) {
+    let response =
+        super::handlers::serve_chat_completions(judge_request(model, request, candidates)).await;
+    let body = into_json(response).await;
+    let reply = body
+        .pointer("/choices/0/message/content")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    let verdict = Verdict {
+        model: model.to_string(),
+        best: parse_verdict(&reply, candidates),
+        reply,
+    };
+    (verdict, extract_usage(&body))
+}
+
+/// OpenAI usage object adding up the candidates and the judge
+fn total_usage(candidates: &[Candidate], judge: Option<TokenUsage>) -> Value {
+    let (input, output) = candidates
+        .iter()
+        .filter_map(|candidate| extract_usage(&candidate.response))
+        .chain(judge)
+        .fold((0, 0), |(input, output), usage| {
+            (input + usage.input_tokens, output + usage.output_tokens)
+        });
+    json!({
+        "prompt_tokens": input,
+        "completion_tokens": output,
+        "total_tokens": input + output
+    })
+}
+
+/// Serve a fan-out request
+pub async fn fan_out(mut request: Value) -> Response {
+    let options = request
+        .as_object_mut()
+        .and_then(|object| object.remove("fan_out"))
+        .unwrap_or(Value::Null);
+    let count = options
+        .get("providers")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_PROVIDERS);
+    let use_judge = options.get("judge").and_then(|v| v.as_bool()) == Some(true);
+    if count < 2 {
+        return bad_request("fan_out.providers must be at least 2");
+    }
+
+    let judge_model = crate::config::get_config()
+        .map(|config| config.model_routing.fan_out_judge_model.trim().to_string())
+        .unwrap_or_default();
+    if use_judge && judge_model.is_empty() {
+        return bad_request(
+            "fan_out.judge needs model-routing.fan-out-judge-model to be configured",
+        );
+    }
+
+    let Some(model) = request
+        .get("model")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+    else {
+        return bad_request("model is required");
+    };
+    let targets = provider_targets(&model, count);
+    if targets.is_empty() {
+        return bad_request(
+            "Fan-out needs a model without provider prefix in model aggregation mode",
+        );
+    }
+
+    request["stream"] = json!(false);
+    tracing::info!(
+        "[FanOut] Asking {} provider(s) for {}",
+        targets.len(),
+        model
+    );
+    let candidates =
+        futures::future::join_all(targets.into_iter().map(|target| ask(&request, target))).await;
+
+    let (judge, judge_usage) = if use_judge && candidates.iter().any(|c| answer_text(c).is_some()) {
+        let (verdict, usage) = judge(&judge_model, &request, &candidates).await;
+        (Some(verdict), usage)
+    } else {
+        (None, None)
+    };
+
+    let usage = total_usage(&candidates, judge_usage);
+    Json(FanOutResponse {
+        object: "chat.completion.fan_out",
+        candidates,
+        judge,
+        usage,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(provider: &str, status: u16, content: &str) -> Candidate {
+        Candidate {
+            provider: provider.to_string(),
+            model: format!("{}/gpt-5", provider),
+            status,
+            latency_ms: 10,
+            response: json!({"choices": [{"message": {"role": "assistant", "content": content}}]}),
+        }
+    }
+
+    #[test]
+    fn judge_picks_an_answered_candidate() {
+        let candidates = vec![
+            candidate("codex", 200, "Paris"),
+            candidate("claude", 429, ""),
+            candidate("kiro", 200, "Paris, France"),
+        ];
+        let request = json!({"messages": [{"role": "user", "content": "Capital of France?"}]});
+        let prompt = judge_request("judge", &request, &candidates);
+        let text = prompt["messages"][0]["content"].as_str().unwrap();
+        assert!(text.contains("Candidate 2:\n(no answer)"));
+        assert!(text.contains("Candidate 3:\nParis, France"));
+
+        assert_eq!(parse_verdict("Candidate 3", &candidates), Some(2));
+        assert_eq!(parse_verdict("1", &candidates), Some(0));
+        assert_eq!(parse_verdict("2", &candidates), None);
+        assert_eq!(parse_verdict("none of them", &candidates), None);
+        assert_eq!(parse_verdict("0", &candidates), None);
+    }
+
+    #[test]
+    fn usage_adds_up_candidates_and_judge() {
+        let mut answered = candidate("codex", 200, "Paris");
+        answered.response["usage"] = json!({"prompt_tokens": 10, "completion_tokens": 4});
+        let mut claude = candidate("claude", 200, "Paris");
+        claude.response = json!({"usage": {"input_tokens": 12, "output_tokens": 6}});
+        let failed = candidate("kiro", 502, "");
+        let judge = TokenUsage {
+            input_tokens: 30,
+            output_tokens: 1,
+        };
+
+        assert_eq!(
+            total_usage(&[answered, claude, failed], Some(judge)),
+            json!({"prompt_tokens": 52, "completion_tokens": 11, "total_tokens": 63})
+        );
+    }
+}
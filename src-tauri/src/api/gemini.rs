@@ -1,8 +1,18 @@
 // Gemini API client for proxying requests
 // Uses Cloud Code Assist endpoint for OAuth tokens (same as CLIProxyAPI)
 
+use super::claude::ClaudeImageHandling;
+use super::handlers::{self, ModelInfo};
+use super::http_client;
+use super::mappers::builtin_tools;
 use super::mime_types::mime_type_for_extension;
+use super::provider::{
+    self, ChatContext, ChatProvider, EventStream, MessagesMapping, ProviderCapabilities,
+    ProviderError,
+};
+use super::sse;
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use axum::response::sse::Event;
 use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
@@ -118,7 +128,7 @@ impl GeminiClient {
     pub fn new(access_token: String) -> Self {
         Self {
             access_token,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::client_for("gemini"),
         }
     }
 
@@ -629,13 +639,28 @@ pub fn openai_to_gemini_cli_request(raw: &Value, model: &str) -> Value {
         );
     }
 
-    if let Some(tools) = raw.get("tools").and_then(|v| v.as_array()) {
+    let tools = raw
+        .get("tools")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    if !tools.is_empty() || builtin_tools::wants_search(raw) {
         let mut function_decls = Vec::new();
         let mut google_nodes = Vec::new();
         let mut code_nodes = Vec::new();
         let mut url_nodes = Vec::new();
+        // OpenAI-style web search maps to Gemini's grounding tool
+        let mut openai_search = builtin_tools::wants_search(raw);
 
-        for t in tools {
+        for t in &tools {
+            if t.get("google_search").is_none() && builtin_tools::is_search_tool(t) {
+                openai_search = true;
+                continue;
+            }
+            if t.get("code_execution").is_none() && builtin_tools::is_code_execution_tool(t) {
+                code_nodes.push(json!({ "codeExecution": {} }));
+                continue;
+            }
             if t.get("type").and_then(|v| v.as_str()) == Some("function") {
                 if let Some(mut fn_obj) = t.get("function").cloned() {
                     if let Some(params) = fn_obj.get("parameters").cloned() {
@@ -664,6 +689,10 @@ pub fn openai_to_gemini_cli_request(raw: &Value, model: &str) -> Value {
             }
         }
 
+        if openai_search && google_nodes.is_empty() {
+            google_nodes.push(json!({ "googleSearch": {} }));
+        }
+
         let mut tools_node = Vec::new();
         if !function_decls.is_empty() {
             tools_node.push(json!({ "functionDeclarations": function_decls }));
@@ -725,37 +754,20 @@ pub fn gemini_cli_stream_to_openai_chunks(
             unix_timestamp: 0,
             function_index: 0,
         };
-        let mut buffer = String::new();
-        let mut stream = response.bytes_stream();
+        let mut events = sse::data_stream(http_client::byte_stream(response));
 
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(b) => b,
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(data) => data,
                 Err(_) => break,
             };
-            let text = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&text);
-
-            while let Some(pos) = buffer.find('\n') {
-                let mut line = buffer[..pos].to_string();
-                buffer = buffer[pos + 1..].to_string();
-                line = line.trim_end_matches('\r').to_string();
-
-                if !line.starts_with("data:") {
-                    continue;
-                }
-                let data = line[5..].trim();
-                if data.is_empty() {
-                    continue;
-                }
-                if data == "[DONE]" {
-                    yield "[DONE]".to_string();
-                    return;
-                }
+            if data == "[DONE]" {
+                yield "[DONE]".to_string();
+                return;
+            }
 
-                for chunk in convert_gemini_cli_stream_chunk(data, &mut state) {
-                    yield chunk;
-                }
+            for chunk in convert_gemini_cli_stream_chunk(&data, &mut state) {
+                yield chunk;
             }
         }
 
@@ -857,6 +869,18 @@ fn convert_gemini_cli_stream_chunk(data: &str, state: &mut GeminiCliStreamState)
         .and_then(|v| v.as_array())
     {
         for part in parts {
+            if let Some(item) = builtin_tools::code_execution_part(part) {
+                if !template["choices"][0]["delta"]["code_execution"].is_array() {
+                    template["choices"][0]["delta"]["code_execution"] = json!([]);
+                }
+                if let Some(arr) = template["choices"][0]["delta"]["code_execution"].as_array_mut()
+                {
+                    arr.push(item);
+                }
+                template["choices"][0]["delta"]["role"] = json!("assistant");
+                continue;
+            }
+
             let part_text = part.get("text").and_then(|v| v.as_str());
             let function_call = part.get("functionCall");
             let thought_sig = part
@@ -974,6 +998,13 @@ fn convert_gemini_cli_stream_chunk(data: &str, state: &mut GeminiCliStreamState)
         template["choices"][0]["native_finish_reason"] = json!("tool_calls");
     }
 
+    if let Some(grounding) = response
+        .pointer("/candidates/0/groundingMetadata")
+        .and_then(builtin_tools::grounding_extension)
+    {
+        template["choices"][0]["delta"]["grounding"] = grounding;
+    }
+
     vec![template.to_string()]
 }
 
@@ -1086,6 +1117,17 @@ fn convert_gemini_response_to_openai(root: &Value) -> Value {
                 .and_then(|v| v.as_array())
             {
                 for part in parts {
+                    if let Some(item) = builtin_tools::code_execution_part(part) {
+                        if !choice["message"]["code_execution"].is_array() {
+                            choice["message"]["code_execution"] = json!([]);
+                        }
+                        if let Some(arr) = choice["message"]["code_execution"].as_array_mut() {
+                            arr.push(item);
+                        }
+                        choice["message"]["role"] = json!("assistant");
+                        continue;
+                    }
+
                     if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
                         if part
                             .get("thought")
@@ -1187,6 +1229,13 @@ fn convert_gemini_response_to_openai(root: &Value) -> Value {
                 choice["native_finish_reason"] = json!("tool_calls");
             }
 
+            if let Some(grounding) = candidate
+                .get("groundingMetadata")
+                .and_then(builtin_tools::grounding_extension)
+            {
+                choice["message"]["grounding"] = grounding;
+            }
+
             if let Some(arr) = template["choices"].as_array_mut() {
                 arr.push(choice);
             }
@@ -1195,3 +1244,92 @@ fn convert_gemini_response_to_openai(root: &Value) -> Value {
 
     template
 }
+
+/// Gemini CLI (Cloud Code Assist) behind the common provider interface
+pub struct GeminiProvider;
+
+impl GeminiProvider {
+    fn build_request(
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<(GeminiClient, Value), ProviderError> {
+        let (access_token, project_id) = ctx.credentials.access_token()?;
+        let mut gemini_request = openai_to_gemini_cli_request(request, ctx.model);
+        if let Some(project_id) = project_id {
+            gemini_request["project"] = json!(project_id);
+        }
+        Ok((GeminiClient::new(access_token), gemini_request))
+    }
+}
+
+#[async_trait]
+impl ChatProvider for GeminiProvider {
+    fn id(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            thinking: true,
+            count_tokens: false,
+        }
+    }
+
+    fn ignored_params(&self) -> &'static [&'static str] {
+        // Only temperature, top_p, top_k, n and the image options reach generationConfig
+        &[
+            "max_tokens",
+            "max_completion_tokens",
+            "stop",
+            "response_format",
+            "seed",
+            "frequency_penalty",
+            "presence_penalty",
+            "logit_bias",
+            "logprobs",
+            "top_logprobs",
+        ]
+    }
+
+    fn messages_mapping(&self, _model: &str) -> MessagesMapping {
+        MessagesMapping::OpenAiChat {
+            image_handling: ClaudeImageHandling::Base64Any,
+            guard_thinking: false,
+            reasoning_as_text: false,
+        }
+    }
+
+    fn list_models(&self) -> Vec<ModelInfo> {
+        handlers::build_prefixed_models(self.id(), &handlers::get_gemini_models())
+    }
+
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError> {
+        let (client, gemini_request) = Self::build_request(ctx, request)?;
+        let response = client.generate_content(&gemini_request).await?;
+        Ok(gemini_to_openai_response(
+            &response,
+            ctx.model,
+            ctx.request_id,
+        ))
+    }
+
+    async fn chat_stream(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<EventStream, ProviderError> {
+        let (client, gemini_request) = Self::build_request(ctx, request)?;
+        let response = client.stream_generate_content(&gemini_request).await?;
+        Ok(provider::chunk_events(
+            self.id(),
+            gemini_cli_stream_to_openai_chunks(response),
+        ))
+    }
+}
@@ -6,17 +6,27 @@ use axum::{
         ws::{Message, WebSocket, WebSocketUpgrade},
         Path, Query, State,
     },
-    http::{header, HeaderValue, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{sse::Event, Html, IntoResponse, Json, Response, Sse},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use super::antigravity::{self, AntigravityClient};
-use super::claude::{self, ClaudeClient, ClaudeRequest};
+use super::antigravity;
+use super::claude;
 use super::codex::{self, CodexClient};
-use super::gemini::{self, GeminiClient};
+use super::compat_warnings;
+use super::context_upgrade;
+use super::economy;
+use super::gemini::GeminiClient;
+use super::http_client;
 use super::kiro;
+use super::mappers::post_process;
+use super::models_cache;
+use super::moderation;
+use super::provider::{self, MessagesMapping};
+use super::race;
+use super::sse;
 use super::AppState;
 use crate::auth::providers::antigravity::QuotaData as AntigravityQuotaData;
 use crate::auth::{
@@ -24,12 +34,10 @@ use crate::auth::{
     providers::{anthropic, antigravity as antigravity_oauth, google, openai},
     AuthFile, TokenInfo,
 };
-use flate2::read::GzDecoder;
-use futures::{StreamExt, TryStreamExt};
+use futures::StreamExt;
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
-use std::io::Read;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
@@ -91,10 +99,6 @@ struct KiroAuthWithAccount {
     provider: String,
 }
 
-const KIMI_ANTHROPIC_BASE: &str = "https://api.kimi.com/coding/v1";
-
-const GLM_ANTHROPIC_BASE: &str = "https://open.bigmodel.cn/api/anthropic/v1";
-
 /// Helper function to add account_id, provider, and model headers to a response for logging
 fn with_log_info<T: IntoResponse>(
     response: T,
@@ -134,6 +138,9 @@ fn error_response(
         500 => StatusCode::INTERNAL_SERVER_ERROR,
         502 => StatusCode::BAD_GATEWAY,
         503 => StatusCode::SERVICE_UNAVAILABLE,
+        claude::OVERLOADED_STATUS => {
+            StatusCode::from_u16(status_code).unwrap_or(StatusCode::SERVICE_UNAVAILABLE)
+        }
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
@@ -165,15 +172,50 @@ pub async fn root() -> Json<Value> {
         "message": "CLI Proxy API Server (Tauri)",
         "endpoints": [
             "POST /v1/chat/completions",
+            "POST /v1/chat/completions/fan-out",
             "POST /v1/completions",
+            "POST /v1/moderations",
             "GET /v1/models",
             "POST /v1/messages",
             "GET /v1beta/models",
-            "POST /v1beta/models/*action"
+            "POST /v1beta/models/*action",
+            "GET /api/oauth/usage",
+            "GET /v1/usage/statusline",
+            "GET /metrics"
         ]
     }))
 }
 
+/// Prometheus metrics
+pub async fn metrics() -> Response {
+    (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; version=0.0.4"),
+        )],
+        super::stats::prometheus_text(),
+    )
+        .into_response()
+}
+
+/// Claude Code usage query, answered with the utilization of the account pool
+pub async fn oauth_usage() -> Json<super::pool_usage::PoolUsage> {
+    Json(super::pool_usage::pool_usage().await)
+}
+
+/// Plain-text pool utilization for a Claude Code `statusLine` command
+pub async fn usage_statusline() -> Response {
+    let usage = super::pool_usage::pool_usage().await;
+    (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        )],
+        super::pool_usage::statusline(&usage),
+    )
+        .into_response()
+}
+
 // OpenAI compatible endpoints
 #[derive(Debug, Serialize)]
 pub struct ModelInfo {
@@ -189,7 +231,43 @@ pub struct ModelsResponse {
     pub data: Vec<ModelInfo>,
 }
 
-pub async fn openai_models(State(_state): State<AppState>) -> Json<ModelsResponse> {
+/// `/v1/models`, served from the models cache with ETag revalidation
+pub async fn openai_models(State(_state): State<AppState>, headers: HeaderMap) -> Response {
+    let fingerprint = models_cache::fingerprint();
+    let (etag, body) = match models_cache::get(&fingerprint) {
+        Some(cached) => cached,
+        None => {
+            let body = serde_json::to_string(&build_openai_models().await).unwrap_or_default();
+            (models_cache::store(fingerprint, body.clone()), body)
+        }
+    };
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|value| crate::config::etag_matches(value, &etag));
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            )],
+            body,
+        )
+            .into_response()
+    };
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+async fn build_openai_models() -> ModelsResponse {
     let mut models = Vec::new();
     let mut has_gemini = false;
     let mut has_antigravity = false;
@@ -317,8 +395,12 @@ pub async fn openai_models(State(_state): State<AppState>) -> Json<ModelsRespons
                 continue;
             }
             let prefix = entry.prefix.as_ref().unwrap_or(&entry.name);
-            let custom_models: Vec<ModelInfo> = entry
-                .models
+            let model_ids = if entry.models.is_empty() {
+                discovered_custom_models(prefix)
+            } else {
+                entry.models.clone()
+            };
+            let custom_models: Vec<ModelInfo> = model_ids
                 .iter()
                 .map(|m| ModelInfo {
                     id: m.clone(),
@@ -346,8 +428,12 @@ pub async fn openai_models(State(_state): State<AppState>) -> Json<ModelsRespons
                 continue;
             }
             let prefix = entry.prefix.as_ref().unwrap_or(&entry.name);
-            let custom_models: Vec<ModelInfo> = entry
-                .models
+            let model_ids = if entry.models.is_empty() {
+                discovered_custom_models(prefix)
+            } else {
+                entry.models.clone()
+            };
+            let custom_models: Vec<ModelInfo> = model_ids
                 .iter()
                 .map(|m| ModelInfo {
                     id: m.clone(),
@@ -433,19 +519,19 @@ pub async fn openai_models(State(_state): State<AppState>) -> Json<ModelsRespons
         // Sort models alphabetically
         aggregated_models.sort_by(|a, b| a.id.cmp(&b.id));
 
-        return Json(ModelsResponse {
+        return ModelsResponse {
             object: "list".to_string(),
             data: aggregated_models,
-        });
+        };
     }
 
-    Json(ModelsResponse {
+    ModelsResponse {
         object: "list".to_string(),
         data: models,
-    })
+    }
 }
 
-fn build_prefixed_models(prefix: &str, base: &[ModelInfo]) -> Vec<ModelInfo> {
+pub(super) fn build_prefixed_models(prefix: &str, base: &[ModelInfo]) -> Vec<ModelInfo> {
     base.iter()
         .map(|m| ModelInfo {
             id: format!("{}/{}", prefix, m.id),
@@ -466,32 +552,13 @@ fn dedupe_models(models: Vec<ModelInfo>) -> Vec<ModelInfo> {
 
 /// Normalize model names to unify different naming conventions
 /// e.g., "claude-sonnet-4.5" and "claude-sonnet-4-5" are the same model
+/// (alias rules come from `model-routing.model-aliases` in config)
 fn normalize_model_name(name: &str) -> String {
-    let normalized = name.to_lowercase().replace('_', "-");
-
-    // Common model name mappings
-    let normalized = match normalized.as_str() {
-        // Claude models - normalize variations
-        "claude-sonnet-4.5" => "claude-sonnet-4-5".to_string(),
-        "claude-sonnet-4-5" => "claude-sonnet-4-5".to_string(),
-        "claude-4.5-sonnet" => "claude-sonnet-4-5".to_string(),
-        "claude-4-5-sonnet" => "claude-sonnet-4-5".to_string(),
-        "claude-opus-4.5" => "claude-opus-4-5".to_string(),
-        "claude-opus-4-5" => "claude-opus-4-5".to_string(),
-        "claude-4.5-opus" => "claude-opus-4-5".to_string(),
-        "claude-4-5-opus" => "claude-opus-4-5".to_string(),
-        "claude-haiku-4.5" => "claude-haiku-4-5".to_string(),
-        "claude-haiku-4-5" => "claude-haiku-4-5".to_string(),
-        "claude-4.5-haiku" => "claude-haiku-4-5".to_string(),
-        "claude-4-5-haiku" => "claude-haiku-4-5".to_string(),
-        _ => normalized,
-    };
-
-    normalized
+    super::model_router::canonical_model_name(name)
 }
 
 /// Build Codex models with reasoning_effort variants
-fn build_codex_models_with_reasoning(base: &[ModelInfo]) -> Vec<ModelInfo> {
+pub(super) fn build_codex_models_with_reasoning(base: &[ModelInfo]) -> Vec<ModelInfo> {
     let efforts = ["low", "medium", "high", "xhigh"];
     let mut models = Vec::new();
     for m in base {
@@ -624,7 +691,7 @@ fn extract_codex_plan_type(json: &Value, path: &std::path::Path) -> Option<Strin
         .or_else(|| infer_codex_plan_type_from_path(path))
 }
 
-fn get_available_codex_models(auth_dir: &std::path::Path) -> Vec<ModelInfo> {
+pub(super) fn get_available_codex_models(auth_dir: &std::path::Path) -> Vec<ModelInfo> {
     if !auth_dir.exists() {
         return Vec::new();
     }
@@ -703,7 +770,7 @@ struct RankedCodexCandidate {
 }
 
 /// Build Antigravity models with thinking-level variants
-fn build_antigravity_models_with_reasoning(base: &[ModelInfo]) -> Vec<ModelInfo> {
+pub(super) fn build_antigravity_models_with_reasoning(base: &[ModelInfo]) -> Vec<ModelInfo> {
     let mut models = Vec::new();
     for m in base {
         if let Some(levels) = antigravity_supported_levels(&m.id) {
@@ -771,7 +838,7 @@ fn antigravity_level_supported(model: &str, level: &str) -> bool {
 }
 
 /// Get static Gemini model definitions
-fn get_gemini_models() -> Vec<ModelInfo> {
+pub(super) fn get_gemini_models() -> Vec<ModelInfo> {
     vec![
         ModelInfo {
             id: "gemini-2.5-pro".to_string(),
@@ -807,7 +874,7 @@ fn get_gemini_models() -> Vec<ModelInfo> {
 }
 
 /// Get static Codex/OpenAI model definitions
-fn parse_provider_prefix(model: &str) -> (Option<String>, String) {
+pub(super) fn parse_provider_prefix(model: &str) -> (Option<String>, String) {
     let trimmed = model.trim();
     if let Some((prefix, rest)) = trimmed.split_once('/') {
         if let Some(normalized) = normalize_provider_prefix(prefix) {
@@ -942,12 +1009,13 @@ async fn handle_kiro_claude_request(payload: Value, is_stream: bool) -> axum::re
             }
         };
 
-        let response = match send_kiro_request_with_retry(auth, &kiro_payload).await {
+        let response = match kiro::send_kiro_request_with_retry(auth, &kiro_payload).await {
             Ok(r) => r,
             Err(msg) => {
                 let msg = msg;
                 tracing::error!("Kiro API error (account {}): {}", account_id, msg);
                 last_error = Some(msg.clone());
+                record_account_error(provider, account_id, &msg);
                 if should_rotate_kiro_error(&msg) && idx + 1 < total {
                     if should_mark_account_exhausted(&msg) {
                         mark_account_exhausted(provider, account_id);
@@ -994,6 +1062,7 @@ async fn handle_kiro_claude_request(payload: Value, is_stream: bool) -> axum::re
                 let msg = e.to_string();
                 tracing::error!("Kiro collect error (account {}): {}", account_id, msg);
                 last_error = Some(msg.clone());
+                record_account_error(provider, account_id, &msg);
                 if should_rotate_kiro_error(&msg) && idx + 1 < total {
                     if should_mark_account_exhausted(&msg) {
                         mark_account_exhausted(provider, account_id);
@@ -1022,28 +1091,25 @@ async fn handle_native_claude_request(
     is_stream: bool,
     model: &str,
 ) -> axum::response::Response {
-    let token = match get_claude_token(model).await {
-        Some(t) => t,
-        None => {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Claude credentials found. Please login first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-    };
-
-    forward_claude_compatible(
+    let response = forward_claude_rotating(
         payload,
         "https://api.anthropic.com/v1",
-        &token,
+        "claude",
+        model,
         is_stream,
         "Claude",
     )
-    .await
+    .await;
+    response.unwrap_or_else(|| {
+        Json(json!({
+            "error": {
+                "message": "No valid Claude credentials found. Please login first.",
+                "type": "authentication_error",
+                "code": 401
+            }
+        }))
+        .into_response()
+    })
 }
 
 /// Handle Codex OpenAI request (converted from Gemini format)
@@ -1095,6 +1161,7 @@ async fn handle_codex_openai_request(
                     let msg = e.to_string();
                     tracing::error!("Codex API error: {}", msg);
                     last_error = Some(msg.clone());
+                    record_account_error(&auth.provider, &auth.account_id, &msg);
                     if should_rotate_codex_error(&msg) && idx + 1 < total {
                         if should_mark_account_exhausted(&msg) {
                             mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1115,7 +1182,9 @@ async fn handle_codex_openai_request(
 
         match client.stream_responses(&codex_request, true).await {
             Ok(response) => {
-                match codex::collect_non_stream_response(response, &original_payload).await {
+                match codex::collect_non_stream_response(response, &original_payload, &actual_model)
+                    .await
+                {
                     Ok(openai_response) => {
                         clear_account_exhausted(&auth.provider, &auth.account_id);
                         return with_log_info(
@@ -1129,6 +1198,7 @@ async fn handle_codex_openai_request(
                         let msg = e.to_string();
                         tracing::error!("Codex API error: {}", msg);
                         last_error = Some(msg.clone());
+                        record_account_error(&auth.provider, &auth.account_id, &msg);
                         if should_rotate_codex_error(&msg) && idx + 1 < total {
                             if should_mark_account_exhausted(&msg) {
                                 mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1150,6 +1220,7 @@ async fn handle_codex_openai_request(
                 let msg = e.to_string();
                 tracing::error!("Codex API error: {}", msg);
                 last_error = Some(msg.clone());
+                record_account_error(&auth.provider, &auth.account_id, &msg);
                 if should_rotate_codex_error(&msg) && idx + 1 < total {
                     if should_mark_account_exhausted(&msg) {
                         mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1250,6 +1321,7 @@ pub async fn responses(State(_state): State<AppState>, Json(mut raw): Json<Value
                     let msg = e.to_string();
                     tracing::error!("Codex Responses API error: {}", msg);
                     last_error = Some(msg.clone());
+                    record_account_error(&auth.provider, &auth.account_id, &msg);
                     if should_rotate_codex_error(&msg) && idx + 1 < total {
                         if should_mark_account_exhausted(&msg) {
                             mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1283,6 +1355,7 @@ pub async fn responses(State(_state): State<AppState>, Json(mut raw): Json<Value
                     let msg = e.to_string();
                     tracing::error!("Codex Responses API error: {}", msg);
                     last_error = Some(msg.clone());
+                    record_account_error(&auth.provider, &auth.account_id, &msg);
                     if should_rotate_codex_error(&msg) && idx + 1 < total {
                         if should_mark_account_exhausted(&msg) {
                             mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1303,6 +1376,7 @@ pub async fn responses(State(_state): State<AppState>, Json(mut raw): Json<Value
                 let msg = e.to_string();
                 tracing::error!("Codex Responses API error: {}", msg);
                 last_error = Some(msg.clone());
+                record_account_error(&auth.provider, &auth.account_id, &msg);
                 if should_rotate_codex_error(&msg) && idx + 1 < total {
                     if should_mark_account_exhausted(&msg) {
                         mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1529,6 +1603,7 @@ async fn handle_responses_websocket_session(mut socket: WebSocket) {
                     let msg = err.to_string();
                     tracing::error!("Codex Responses API error: {}", msg);
                     last_error = Some(msg.clone());
+                    record_account_error(&auth.provider, &auth.account_id, &msg);
                     if should_rotate_codex_error(&msg) && idx + 1 < total {
                         if should_mark_account_exhausted(&msg) {
                             mark_account_exhausted(&auth.provider, &auth.account_id);
@@ -1557,13 +1632,12 @@ async fn handle_responses_websocket_session(mut socket: WebSocket) {
             continue;
         };
 
-        let mut buffer = String::new();
-        let mut stream = response.bytes_stream();
+        let mut events = sse::data_stream(http_client::byte_stream(response));
         let mut completed = false;
 
-        while let Some(chunk) = stream.next().await {
-            let bytes = match chunk {
-                Ok(bytes) => bytes,
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(data) => data,
                 Err(err) => {
                     let _ = send_responses_websocket_error(
                         &mut socket,
@@ -1575,40 +1649,22 @@ async fn handle_responses_websocket_session(mut socket: WebSocket) {
                     return;
                 }
             };
+            if data == "[DONE]" {
+                continue;
+            }
 
-            let text = String::from_utf8_lossy(&bytes);
-            buffer.push_str(&text);
-
-            while let Some(pos) = buffer.find('\n') {
-                let mut line = buffer[..pos].to_string();
-                buffer = buffer[pos + 1..].to_string();
-                line = line.trim_end_matches('\r').to_string();
-                if !line.starts_with("data:") {
-                    continue;
-                }
-
-                let data = line[5..].trim();
-                if data.is_empty() || data == "[DONE]" {
-                    continue;
-                }
-
-                let payload: Value = match serde_json::from_str(data) {
-                    Ok(payload) => payload,
-                    Err(_) => continue,
-                };
+            let payload: Value = match serde_json::from_str(&data) {
+                Ok(payload) => payload,
+                Err(_) => continue,
+            };
 
-                if payload.get("type").and_then(|v| v.as_str()) == Some("response.completed") {
-                    completed = true;
-                    last_response_output = codex::response_completed_output(&payload);
-                }
+            if payload.get("type").and_then(|v| v.as_str()) == Some("response.completed") {
+                completed = true;
+                last_response_output = codex::response_completed_output(&payload);
+            }
 
-                if socket
-                    .send(Message::Text(data.to_string().into()))
-                    .await
-                    .is_err()
-                {
-                    return;
-                }
+            if socket.send(Message::Text(data)).await.is_err() {
+                return;
             }
         }
 
@@ -1799,7 +1855,7 @@ fn convert_chat_stream_chunk_to_completions(chunk: &str) -> Option<String> {
     serde_json::to_string(&converted).ok()
 }
 
-fn strip_sse_data_line(chunk: &str) -> Option<String> {
+pub(super) fn strip_sse_data_line(chunk: &str) -> Option<String> {
     let trimmed = chunk.trim();
     let payload = trimmed.strip_prefix("data:")?.trim();
     if payload.is_empty() {
@@ -1809,17 +1865,6 @@ fn strip_sse_data_line(chunk: &str) -> Option<String> {
     }
 }
 
-fn maybe_decompress_gzip(bytes: &[u8]) -> Vec<u8> {
-    if bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b {
-        let mut decoder = GzDecoder::new(bytes);
-        let mut out = Vec::new();
-        if decoder.read_to_end(&mut out).is_ok() {
-            return out;
-        }
-    }
-    bytes.to_vec()
-}
-
 async fn forward_claude_compatible(
     payload: Value,
     base_url: &str,
@@ -1839,18 +1884,18 @@ async fn forward_claude_compatible(
         .into_response();
     }
     let url = format!("{}/messages", base);
-    let client = reqwest::Client::new();
-    let response = match client
-        .post(url)
-        .header("x-api-key", token)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&payload)
-        .send()
-        .await
-    {
+    let client = http_client::client_for(provider_label);
+    let send = || {
+        client
+            .post(&url)
+            .header("x-api-key", token)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&payload)
+    };
+    let response = match claude::send_messages(send, provider_label).await {
         Ok(r) => r,
-        Err(e) => {
+        Err(claude::MessagesError::Transport(e)) => {
             return Json(json!({
                 "type": "error",
                 "error": {
@@ -1860,24 +1905,15 @@ async fn forward_claude_compatible(
             }))
             .into_response();
         }
+        Err(claude::MessagesError::Status {
+            status,
+            body,
+            overloaded,
+        }) => return claude_error_passthrough(status, body, overloaded),
     };
 
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.bytes().await.unwrap_or_default();
-        let mut resp = Response::new(Body::from(body));
-        *resp.status_mut() = status;
-        resp.headers_mut().insert(
-            header::CONTENT_TYPE,
-            HeaderValue::from_static("application/json"),
-        );
-        return resp;
-    }
-
     if is_stream {
-        let stream = response
-            .bytes_stream()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+        let stream = http_client::byte_stream(response);
         let mut resp = Response::new(Body::from_stream(stream));
         *resp.status_mut() = StatusCode::OK;
         resp.headers_mut().insert(
@@ -1887,18 +1923,102 @@ async fn forward_claude_compatible(
         return resp;
     }
 
-    let body = response.bytes().await.unwrap_or_default();
-    let body = maybe_decompress_gzip(&body);
+    let body = http_client::read_body(response).await.unwrap_or_default();
     let json_body: Value = serde_json::from_slice(&body).unwrap_or_else(|_| json!({}));
     Json(json_body).into_response()
 }
 
+/// Pass an upstream Messages API error on; overload always reaches the client as 529 with an
+/// `overloaded_error`, whatever status the backend used
+fn claude_error_passthrough(status: StatusCode, body: Vec<u8>, overloaded: bool) -> Response {
+    let (status, body) = if overloaded && status.as_u16() != claude::OVERLOADED_STATUS {
+        let body = json!({
+            "type": "error",
+            "error": {
+                "type": "overloaded_error",
+                "message": claude::error_message(&body)
+            }
+        });
+        (
+            StatusCode::from_u16(claude::OVERLOADED_STATUS).unwrap_or(status),
+            body.to_string().into_bytes(),
+        )
+    } else {
+        (status, body)
+    };
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+/// Forward a native Messages request with the provider's accounts in turn, moving on to the
+/// next one while the backend stays overloaded; `None` when no account has credentials
+async fn forward_claude_rotating(
+    payload: Value,
+    base_url: &str,
+    provider_id: &str,
+    model: &str,
+    is_stream: bool,
+    provider_label: &str,
+) -> Option<Response> {
+    let mut overloaded = None;
+    for candidate in select_auth_candidates(provider_id, model) {
+        let Some(token) = load_token_from_candidate(provider_id, &candidate).await else {
+            continue;
+        };
+        let response =
+            forward_claude_compatible(payload.clone(), base_url, &token, is_stream, provider_label)
+                .await;
+        if response.status().as_u16() != claude::OVERLOADED_STATUS {
+            return Some(response);
+        }
+        tracing::warn!(
+            "{} is overloaded for account {}, trying the next one",
+            provider_label,
+            candidate.id
+        );
+        overloaded = Some(response);
+    }
+    overloaded
+}
+
 #[derive(Default)]
 struct ToolCallAccumulator {
     id: String,
     name: String,
     arguments: String,
+    /// Length of `arguments` already sent as input_json_delta
+    emitted_len: usize,
     started: bool,
+    stopped: bool,
+}
+
+/// Emit the tool arguments received since the last delta as an input_json_delta event
+fn flush_tool_arguments(
+    entry: &mut ToolCallAccumulator,
+    block_index: i32,
+    events: &mut Vec<Event>,
+) {
+    if entry.emitted_len >= entry.arguments.len() {
+        return;
+    }
+    let partial = &entry.arguments[entry.emitted_len..];
+    events.push(build_claude_event(
+        "content_block_delta",
+        json!({
+            "type": "content_block_delta",
+            "index": block_index,
+            "delta": {
+                "type": "input_json_delta",
+                "partial_json": partial
+            }
+        }),
+    ));
+    entry.emitted_len = entry.arguments.len();
 }
 
 #[derive(Default)]
@@ -2200,6 +2320,12 @@ fn openai_chunk_to_claude_events(
                         state.block_types.insert(block_index, "tool_use");
                         entry.started = true;
                     }
+
+                    // Stream argument fragments as they arrive; parallel calls can interleave
+                    // their fragments, so every tool block stays open until the stream ends
+                    if entry.started && !entry.stopped {
+                        flush_tool_arguments(entry, block_index, &mut events);
+                    }
                 }
             }
         }
@@ -2262,28 +2388,23 @@ fn finalize_claude_stream(state: &mut ClaudeStreamState) -> Vec<Event> {
         ));
     }
 
-    let mut tool_blocks: Vec<(i32, &ToolCallAccumulator)> = Vec::new();
-    for (tool_index, tool_call) in &state.tool_calls {
+    let mut tool_blocks: Vec<(i32, &mut ToolCallAccumulator)> = Vec::new();
+    for (tool_index, tool_call) in state.tool_calls.iter_mut() {
+        if tool_call.stopped {
+            continue;
+        }
         if let Some(block_index) = state.tool_call_block_index.get(tool_index) {
             tool_blocks.push((*block_index, tool_call));
         }
     }
     tool_blocks.sort_by_key(|(index, _)| *index);
     for (block_index, tool_call) in tool_blocks {
-        let args = if tool_call.arguments.trim().is_empty() {
-            "{}".to_string()
-        } else {
-            tool_call.arguments.clone()
-        };
-        let input_delta = json!({
-            "type": "content_block_delta",
-            "index": block_index,
-            "delta": {
-                "type": "input_json_delta",
-                "partial_json": args
-            }
-        });
-        events.push(build_claude_event("content_block_delta", input_delta));
+        // Send whatever arguments haven't been streamed yet
+        if tool_call.emitted_len == 0 && tool_call.arguments.trim().is_empty() {
+            tool_call.arguments = "{}".to_string();
+        }
+        flush_tool_arguments(tool_call, block_index, &mut events);
+        tool_call.stopped = true;
         let stop_payload = json!({
             "type": "content_block_stop",
             "index": block_index
@@ -2357,7 +2478,7 @@ where
 }
 
 /// Get static Antigravity model definitions
-fn get_antigravity_models() -> Vec<ModelInfo> {
+pub(super) fn get_antigravity_models() -> Vec<ModelInfo> {
     vec![
         // Gemini 3 系列
         ModelInfo {
@@ -2395,7 +2516,7 @@ fn get_antigravity_models() -> Vec<ModelInfo> {
 }
 
 /// Get static Claude model definitions
-fn get_claude_models() -> Vec<ModelInfo> {
+pub(super) fn get_claude_models() -> Vec<ModelInfo> {
     vec![
         ModelInfo {
             id: "claude-haiku-4-5-20251001".to_string(),
@@ -2437,7 +2558,7 @@ fn get_claude_models() -> Vec<ModelInfo> {
 }
 
 /// Get static Kimi model definitions
-fn get_kimi_models() -> Vec<ModelInfo> {
+pub(super) fn get_kimi_models() -> Vec<ModelInfo> {
     let created = chrono::Utc::now().timestamp();
     vec![ModelInfo {
         id: "kimi-for-coding".to_string(),
@@ -2448,7 +2569,7 @@ fn get_kimi_models() -> Vec<ModelInfo> {
 }
 
 /// Get static GLM model definitions
-fn get_glm_models() -> Vec<ModelInfo> {
+pub(super) fn get_glm_models() -> Vec<ModelInfo> {
     let created = chrono::Utc::now().timestamp();
     vec![
         ModelInfo {
@@ -2538,6 +2659,10 @@ fn is_account_exhausted(provider: &str, account_id: &str) -> bool {
 /// Clear exhausted status for an account (e.g., after successful request or quota recovery)
 fn clear_account_exhausted(provider: &str, account_id: &str) {
     let provider_key = provider.trim().to_lowercase();
+    FORBIDDEN_STRIKES
+        .lock()
+        .unwrap()
+        .remove(&format!("{}:{}", provider_key, account_id));
     let mut exhausted = EXHAUSTED_ACCOUNTS.lock().unwrap();
     if let Some(set) = exhausted.get_mut(&provider_key) {
         if set.remove(account_id) {
@@ -2566,6 +2691,125 @@ fn reset_provider_exhausted(provider: &str) {
     }
 }
 
+/// Consecutive 403 responses an account may return before it is auto-disabled
+const FORBIDDEN_STRIKE_LIMIT: u32 = 3;
+
+/// Consecutive forbidden (403) errors per account: key = "provider:account_id"
+static FORBIDDEN_STRIKES: Lazy<Mutex<HashMap<String, u32>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Check if an error says the account itself was banned/suspended (permanent, disable immediately)
+fn is_account_banned_error(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("account has been disabled")
+        || lower.contains("account_disabled")
+        || lower.contains("account has been suspended")
+        || lower.contains("account_suspended")
+        || lower.contains("account_deactivated")
+        || lower.contains("has been banned")
+        || lower.contains("permanently banned")
+}
+
+/// Check if an error is a 403 Forbidden from the upstream provider
+fn is_account_forbidden_error(message: &str) -> bool {
+    parse_antigravity_status(message) == Some(403)
+        || parse_codex_status(message) == Some(403)
+        || parse_request_failed_status(message, "Kiro request failed:") == Some(403)
+}
+
+/// Resolve the auth file behind an account id (file name, file stem, or Kiro email)
+fn find_account_file(provider: &str, account_id: &str) -> Option<PathBuf> {
+    let auth_dir = crate::config::resolve_auth_dir();
+    if account_id.contains('/') || account_id.contains('\\') {
+        return None;
+    }
+    let direct = auth_dir.join(account_id);
+    if direct.is_file() {
+        return Some(direct);
+    }
+    let with_ext = auth_dir.join(format!("{}.json", account_id));
+    if with_ext.is_file() {
+        return Some(with_ext);
+    }
+
+    let mut files = Vec::new();
+    collect_json_files(&auth_dir, &mut files);
+    files.into_iter().find(|path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .map(|json| {
+                let json_provider = json
+                    .get("provider")
+                    .or_else(|| json.get("type"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .trim()
+                    .to_lowercase();
+                json_provider == provider.trim().to_lowercase()
+                    && json.get("email").and_then(|v| v.as_str()) == Some(account_id)
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Record an upstream error for an account; bans and repeated 403s disable the account
+/// so it stops being selected instead of burning retries on every request.
+fn record_account_error(provider: &str, account_id: &str, message: &str) {
+    let banned = is_account_banned_error(message);
+    if !banned && !is_account_forbidden_error(message) {
+        return;
+    }
+
+    let key = format!("{}:{}", provider.trim().to_lowercase(), account_id);
+    let strikes = {
+        let mut strikes = FORBIDDEN_STRIKES.lock().unwrap();
+        let entry = strikes.entry(key.clone()).or_insert(0);
+        *entry += 1;
+        *entry
+    };
+    if !banned && strikes < FORBIDDEN_STRIKE_LIMIT {
+        tracing::warn!(
+            "Account {} returned 403 ({}/{} before auto-disable)",
+            key,
+            strikes,
+            FORBIDDEN_STRIKE_LIMIT
+        );
+        return;
+    }
+
+    let reason = if banned {
+        format!(
+            "Banned by provider: {}",
+            message.chars().take(200).collect::<String>()
+        )
+    } else {
+        format!(
+            "Forbidden by provider ({} consecutive 403 responses)",
+            strikes
+        )
+    };
+    let Some(path) = find_account_file(provider, account_id) else {
+        tracing::warn!("Cannot auto-disable {}: auth file not found", key);
+        return;
+    };
+    match auth::disable_auth_file(&path, &reason) {
+        Ok(true) => {
+            FORBIDDEN_STRIKES.lock().unwrap().remove(&key);
+            super::events::emit(
+                super::events::ACCOUNT_DISABLED,
+                json!({
+                    "account_id": normalize_account_id(account_id),
+                    "provider": provider,
+                    "reason": reason,
+                }),
+            );
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to auto-disable {}: {}", key, e),
+    }
+}
+
 /// Check if a provider has any non-exhausted accounts
 fn provider_has_available_accounts(provider: &str, account_ids: &[String]) -> bool {
     let provider_key = provider.trim().to_lowercase();
@@ -2667,46 +2911,239 @@ pub fn select_best_provider_for_aggregation(
     (primary_provider.to_string(), remaining)
 }
 
-fn collect_json_files(dir: &std::path::Path, out: &mut Vec<PathBuf>) {
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
+/// Count accounts (or API keys for custom providers) that could serve a model right now
+/// Uses the same filtering as request routing, without advancing round-robin cursors
+fn preflight_available_accounts(provider: &str, model: &str) -> usize {
+    let config = crate::config::get_config().unwrap_or_default();
+    if let Some(prefix) = provider.strip_prefix("openai-compat:") {
+        return config
+            .openai_compatibility
+            .iter()
+            .find(|e| e.prefix.as_ref().unwrap_or(&e.name).to_lowercase() == prefix)
+            .map(|e| e.api_key_entries.len())
+            .unwrap_or(0);
+    }
+    if let Some(prefix) = provider.strip_prefix("claude-compat:") {
+        return config
+            .claude_code_compatibility
+            .iter()
+            .find(|e| e.prefix.as_ref().unwrap_or(&e.name).to_lowercase() == prefix)
+            .map(|e| e.api_key_entries.len())
+            .unwrap_or(0);
+    }
+
+    preview_auth_candidates(provider, model)
+        .iter()
+        .filter(|candidate| !is_account_exhausted(provider, &candidate.id))
+        .count()
+}
+
+/// POST /v1/moderations
+/// OpenAI-compatible moderation scores from the configured backend
+pub async fn moderations(Json(raw): Json<Value>) -> Response {
+    let inputs = match moderation::parse_inputs(&raw) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            return error_response(400, &e.to_string(), "invalid_request_error", "", "", "");
+        }
+    };
+    let response_model = raw
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("omni-moderation-latest")
+        .to_string();
+    let config = crate::config::get_config().unwrap_or_default().moderation;
+
+    if config.backend == "gemini" {
+        for account in provider_accounts("gemini", &config.model).await {
+            let Ok((access_token, project_id)) = account.credentials.access_token() else {
                 continue;
+            };
+            let client = GeminiClient::new(access_token);
+            let mut results = Vec::with_capacity(inputs.len());
+            for input in &inputs {
+                if input.trim().is_empty() {
+                    results.push(moderation::moderation_result(moderation::heuristic_scores(
+                        input,
+                    )));
+                    continue;
+                }
+                match moderation::score_with_gemini(
+                    &client,
+                    project_id.as_deref(),
+                    &config.model,
+                    input,
+                )
+                .await
+                {
+                    Ok(scores) => results.push(moderation::moderation_result(scores)),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Gemini moderation failed (account {}): {}",
+                            account.account_id,
+                            e
+                        );
+                        break;
+                    }
+                }
             }
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
-                out.push(path);
+            if results.len() == inputs.len() {
+                return with_log_info(
+                    Json(moderation::moderation_response(&response_model, results)),
+                    &account.provider,
+                    &account.account_id,
+                    &config.model,
+                );
             }
         }
+        tracing::warn!("No Gemini account could score the moderation request, using heuristic");
     }
-}
 
-fn parse_candidate_priority(json: &Value) -> i32 {
-    match json.get("priority") {
-        Some(Value::Number(n)) => n.as_i64().unwrap_or(0) as i32,
-        Some(Value::String(s)) => s.trim().parse::<i32>().unwrap_or(0),
-        _ => 0,
-    }
+    let results = inputs
+        .iter()
+        .map(|input| moderation::moderation_result(moderation::heuristic_scores(input)))
+        .collect();
+    Json(moderation::moderation_response(&response_model, results)).into_response()
 }
 
-fn candidate_from_path(
-    provider: &str,
-    auth_dir: &std::path::Path,
-    path: &std::path::Path,
-) -> Option<AuthCandidate> {
-    let content = std::fs::read_to_string(path).ok()?;
-    let json: Value = serde_json::from_str(&content).ok()?;
-
-    let provider_key = provider.trim().to_lowercase();
-    let json_provider = json
-        .get("provider")
-        .or_else(|| json.get("type"))
+/// POST /v1/preflight
+/// Reports whether a model can currently be served (and by which provider) without
+/// contacting any upstream or consuming quota
+pub async fn preflight(Json(raw): Json<Value>) -> Response {
+    let raw_model = raw
+        .get("model")
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .trim()
-        .to_lowercase();
-    if json_provider != provider_key {
-        return None;
+        .to_string();
+    if raw_model.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Missing required field: model",
+                    "type": "invalid_request_error",
+                    "code": 400
+                }
+            })),
+        )
+            .into_response();
+    }
+    let estimated_tokens = raw.get("estimated_tokens").and_then(|v| v.as_i64());
+
+    // Resolve candidate providers the same way request handlers do
+    let (provider_override, model) = parse_provider_prefix(&raw_model);
+    let candidates: Vec<(String, String)> = match provider_override {
+        Some(provider) => vec![(provider, model)],
+        None => {
+            use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
+            match resolve_model(&raw_model, None) {
+                ResolvedModel::Explicit { provider, model } => vec![(provider, model)],
+                ResolvedModel::Aggregated {
+                    provider,
+                    model,
+                    fallbacks,
+                } => std::iter::once((provider, model))
+                    .chain(fallbacks.into_iter().map(|p| {
+                        let model = get_provider_model_name(&raw_model, &p);
+                        (p, model)
+                    }))
+                    .collect(),
+                ResolvedModel::NoProvider { .. } => Vec::new(),
+            }
+        }
+    };
+
+    let mut selected: Option<(String, String)> = None;
+    let mut providers = Vec::new();
+    for (provider, model) in candidates {
+        let available_accounts = preflight_available_accounts(&provider, &model);
+        let max_input_tokens = if provider == "kiro" {
+            Some(kiro::get_max_input_tokens(&model))
+        } else {
+            None
+        };
+        let fits_context = match (estimated_tokens, max_input_tokens) {
+            (Some(estimated), Some(max)) => estimated <= max,
+            _ => true,
+        };
+        if selected.is_none() && available_accounts > 0 && fits_context {
+            selected = Some((provider.clone(), model.clone()));
+        }
+        providers.push(json!({
+            "provider": provider,
+            "model": model,
+            "available_accounts": available_accounts,
+            "max_input_tokens": max_input_tokens,
+            "fits_context": fits_context
+        }));
+    }
+
+    let reason = if selected.is_some() {
+        None
+    } else if providers.is_empty() {
+        Some("No provider supports this model. Use a provider prefix or enable Model Aggregation Mode.")
+    } else if providers
+        .iter()
+        .all(|p| p["available_accounts"].as_u64().unwrap_or(0) == 0)
+    {
+        Some("No available credentials: accounts are missing, disabled or out of quota.")
+    } else {
+        Some("Estimated tokens exceed the context window of every available provider.")
+    };
+
+    Json(json!({
+        "model": raw_model,
+        "available": selected.is_some(),
+        "provider": selected.as_ref().map(|(p, _)| p),
+        "resolved_model": selected.as_ref().map(|(_, m)| m),
+        "estimated_tokens": estimated_tokens,
+        "providers": providers,
+        "reason": reason
+    }))
+    .into_response()
+}
+
+fn collect_json_files(dir: &std::path::Path, out: &mut Vec<PathBuf>) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                continue;
+            }
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                out.push(path);
+            }
+        }
+    }
+}
+
+fn parse_candidate_priority(json: &Value) -> i32 {
+    match json.get("priority") {
+        Some(Value::Number(n)) => n.as_i64().unwrap_or(0) as i32,
+        Some(Value::String(s)) => s.trim().parse::<i32>().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn candidate_from_path(
+    provider: &str,
+    auth_dir: &std::path::Path,
+    path: &std::path::Path,
+) -> Option<AuthCandidate> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let json: Value = serde_json::from_str(&content).ok()?;
+
+    let provider_key = provider.trim().to_lowercase();
+    let json_provider = json
+        .get("provider")
+        .or_else(|| json.get("type"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    if json_provider != provider_key {
+        return None;
     }
 
     let disabled = json
@@ -2980,180 +3417,167 @@ fn is_expired(expires_at: Option<chrono::DateTime<chrono::Utc>>) -> bool {
 /// Get a valid Gemini access token from stored credentials
 /// Supports CLIProxyAPI format (gemini-*.json)
 async fn get_gemini_auth(model: &str) -> Option<GeminiAuth> {
-    let candidates = select_auth_candidates("gemini", model);
-    for candidate in candidates {
-        let content = match std::fs::read_to_string(&candidate.path) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let mut json: serde_json::Value = match serde_json::from_str(&content) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+    for candidate in select_auth_candidates("gemini", model) {
+        if let Some(auth) = load_gemini_auth_from_candidate(&candidate, false).await {
+            return Some(auth);
+        }
+    }
+    None
+}
 
-        let snapshot = match parse_token_snapshot(&json) {
-            Some(s) => s,
-            None => continue,
-        };
+async fn load_gemini_auth_from_candidate(
+    candidate: &AuthCandidate,
+    force_refresh: bool,
+) -> Option<GeminiAuth> {
+    let content = auth::token_store::read_to_string(&candidate.path).ok()?;
+    let mut json: serde_json::Value = serde_json::from_str(&content).ok()?;
 
-        let project_id = json
-            .get("project_id")
-            .and_then(|v| v.as_str())
-            .map(|v| v.trim().to_string())
-            .filter(|v| !v.is_empty());
+    let snapshot = parse_token_snapshot(&json)?;
 
-        if !is_expired(snapshot.expires_at) {
-            return Some(GeminiAuth {
-                access_token: snapshot.access_token,
-                project_id,
-                account_id: candidate.id.clone(),
-                provider: candidate.provider.clone(),
-            });
-        }
+    let project_id = json
+        .get("project_id")
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
 
-        let refresh_token = match snapshot.refresh_token {
-            Some(v) => v,
-            None => continue,
-        };
+    if !force_refresh && !is_expired(snapshot.expires_at) {
+        return Some(GeminiAuth {
+            access_token: snapshot.access_token,
+            project_id,
+            account_id: candidate.id.clone(),
+            provider: candidate.provider.clone(),
+        });
+    }
 
-        if let Ok(new_tokens) = google::refresh_token(&refresh_token).await {
-            let new_expiry = new_tokens.expires_in.map(|secs| {
-                (chrono::Utc::now() + chrono::Duration::seconds(secs as i64)).to_rfc3339()
-            });
+    let refresh_token = snapshot.refresh_token?;
 
-            match snapshot.location {
-                TokenLocation::Nested => {
-                    if json.get("token").is_none() {
-                        json["token"] = json!({});
-                    }
-                    if let Some(obj) = json.get_mut("token").and_then(|v| v.as_object_mut()) {
-                        obj.insert(
-                            "access_token".to_string(),
-                            serde_json::json!(new_tokens.access_token),
-                        );
-                        if let Some(new_refresh) = &new_tokens.refresh_token {
-                            obj.insert("refresh_token".to_string(), serde_json::json!(new_refresh));
-                        }
-                        if let Some(exp) = new_expiry {
-                            let key = snapshot.expiry_key.unwrap_or("expiry");
-                            obj.insert(key.to_string(), serde_json::json!(exp));
-                        }
-                        obj.insert(
-                            "token_type".to_string(),
-                            serde_json::json!(new_tokens.token_type),
-                        );
-                    }
+    if let Ok(new_tokens) = google::refresh_token(&refresh_token).await {
+        let new_expiry = new_tokens
+            .expires_in
+            .map(|secs| (chrono::Utc::now() + chrono::Duration::seconds(secs as i64)).to_rfc3339());
+
+        match snapshot.location {
+            TokenLocation::Nested => {
+                if json.get("token").is_none() {
+                    json["token"] = json!({});
                 }
-                TokenLocation::Root => {
-                    json["access_token"] = serde_json::json!(new_tokens.access_token);
+                if let Some(obj) = json.get_mut("token").and_then(|v| v.as_object_mut()) {
+                    obj.insert(
+                        "access_token".to_string(),
+                        serde_json::json!(new_tokens.access_token),
+                    );
                     if let Some(new_refresh) = &new_tokens.refresh_token {
-                        json["refresh_token"] = serde_json::json!(new_refresh);
+                        obj.insert("refresh_token".to_string(), serde_json::json!(new_refresh));
                     }
                     if let Some(exp) = new_expiry {
-                        json["expired"] = serde_json::json!(exp);
+                        let key = snapshot.expiry_key.unwrap_or("expiry");
+                        obj.insert(key.to_string(), serde_json::json!(exp));
                     }
-                    json["token_type"] = serde_json::json!(new_tokens.token_type);
+                    obj.insert(
+                        "token_type".to_string(),
+                        serde_json::json!(new_tokens.token_type),
+                    );
                 }
             }
-
-            if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
-                let _ = std::fs::write(&candidate.path, updated_content);
+            TokenLocation::Root => {
+                json["access_token"] = serde_json::json!(new_tokens.access_token);
+                if let Some(new_refresh) = &new_tokens.refresh_token {
+                    json["refresh_token"] = serde_json::json!(new_refresh);
+                }
+                if let Some(exp) = new_expiry {
+                    json["expired"] = serde_json::json!(exp);
+                }
+                json["token_type"] = serde_json::json!(new_tokens.token_type);
             }
+        }
 
-            return Some(GeminiAuth {
-                access_token: new_tokens.access_token,
-                project_id,
-                account_id: candidate.id.clone(),
-                provider: candidate.provider.clone(),
-            });
+        if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
+            auth::token_store::write(&candidate.path, updated_content);
         }
+
+        return Some(GeminiAuth {
+            access_token: new_tokens.access_token,
+            project_id,
+            account_id: candidate.id.clone(),
+            provider: candidate.provider.clone(),
+        });
     }
     None
 }
 
-/// Get a valid Claude access token from stored credentials
-async fn get_claude_token(model: &str) -> Option<String> {
-    let candidates = select_auth_candidates("claude", model);
-    for candidate in candidates {
-        let content = match std::fs::read_to_string(&candidate.path) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let mut json: serde_json::Value = match serde_json::from_str(&content) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+async fn load_claude_token_from_candidate(
+    candidate: &AuthCandidate,
+    force_refresh: bool,
+) -> Option<String> {
+    let content = auth::token_store::read_to_string(&candidate.path).ok()?;
+    let mut json: serde_json::Value = serde_json::from_str(&content).ok()?;
 
-        let snapshot = match parse_token_snapshot(&json) {
-            Some(s) => s,
-            None => continue,
-        };
+    let snapshot = parse_token_snapshot(&json)?;
 
-        if !is_expired(snapshot.expires_at) {
-            return Some(snapshot.access_token);
-        }
+    if !force_refresh && !is_expired(snapshot.expires_at) {
+        return Some(snapshot.access_token);
+    }
 
-        let refresh_token = match snapshot.refresh_token {
-            Some(v) => v,
-            None => continue,
-        };
+    let refresh_token = snapshot.refresh_token?;
 
-        if let Ok(new_tokens) = anthropic::refresh_token(&refresh_token).await {
-            let new_expiry = new_tokens.expires_in.map(|secs| {
-                (chrono::Utc::now() + chrono::Duration::seconds(secs as i64)).to_rfc3339()
-            });
+    if let Ok(new_tokens) = anthropic::refresh_token(&refresh_token).await {
+        let new_expiry = new_tokens
+            .expires_in
+            .map(|secs| (chrono::Utc::now() + chrono::Duration::seconds(secs as i64)).to_rfc3339());
 
-            match snapshot.location {
-                TokenLocation::Nested => {
-                    if json.get("token").is_none() {
-                        json["token"] = json!({});
-                    }
-                    if let Some(obj) = json.get_mut("token").and_then(|v| v.as_object_mut()) {
-                        obj.insert(
-                            "access_token".to_string(),
-                            serde_json::json!(new_tokens.access_token),
-                        );
-                        if let Some(new_refresh) = &new_tokens.refresh_token {
-                            obj.insert("refresh_token".to_string(), serde_json::json!(new_refresh));
-                        }
-                        if let Some(exp) = new_expiry {
-                            let key = snapshot.expiry_key.unwrap_or("expires_at");
-                            obj.insert(key.to_string(), serde_json::json!(exp));
-                        }
-                        obj.insert(
-                            "token_type".to_string(),
-                            serde_json::json!(new_tokens.token_type),
-                        );
-                    }
+        match snapshot.location {
+            TokenLocation::Nested => {
+                if json.get("token").is_none() {
+                    json["token"] = json!({});
                 }
-                TokenLocation::Root => {
-                    json["access_token"] = serde_json::json!(new_tokens.access_token);
+                if let Some(obj) = json.get_mut("token").and_then(|v| v.as_object_mut()) {
+                    obj.insert(
+                        "access_token".to_string(),
+                        serde_json::json!(new_tokens.access_token),
+                    );
                     if let Some(new_refresh) = &new_tokens.refresh_token {
-                        json["refresh_token"] = serde_json::json!(new_refresh);
+                        obj.insert("refresh_token".to_string(), serde_json::json!(new_refresh));
                     }
                     if let Some(exp) = new_expiry {
-                        json["expired"] = serde_json::json!(exp);
+                        let key = snapshot.expiry_key.unwrap_or("expires_at");
+                        obj.insert(key.to_string(), serde_json::json!(exp));
                     }
+                    obj.insert(
+                        "token_type".to_string(),
+                        serde_json::json!(new_tokens.token_type),
+                    );
                 }
             }
-
-            if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
-                let _ = std::fs::write(&candidate.path, updated_content);
+            TokenLocation::Root => {
+                json["access_token"] = serde_json::json!(new_tokens.access_token);
+                if let Some(new_refresh) = &new_tokens.refresh_token {
+                    json["refresh_token"] = serde_json::json!(new_refresh);
+                }
+                if let Some(exp) = new_expiry {
+                    json["expired"] = serde_json::json!(exp);
+                }
             }
+        }
 
-            return Some(new_tokens.access_token);
+        if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
+            auth::token_store::write(&candidate.path, updated_content);
         }
+
+        return Some(new_tokens.access_token);
     }
     None
 }
 
-async fn load_codex_auth_from_candidate(candidate: &AuthCandidate) -> Option<CodexAuth> {
-    let content = std::fs::read_to_string(&candidate.path).ok()?;
+async fn load_codex_auth_from_candidate(
+    candidate: &AuthCandidate,
+    force_refresh: bool,
+) -> Option<CodexAuth> {
+    let content = auth::token_store::read_to_string(&candidate.path).ok()?;
     let mut json: serde_json::Value = serde_json::from_str(&content).ok()?;
 
     let snapshot = parse_token_snapshot(&json)?;
 
-    if !is_expired(snapshot.expires_at) {
+    if !force_refresh && !is_expired(snapshot.expires_at) {
         return Some(CodexAuth {
             access_token: snapshot.access_token,
             account_id: candidate.id.clone(),
@@ -3219,7 +3643,7 @@ async fn load_codex_auth_from_candidate(candidate: &AuthCandidate) -> Option<Cod
         }
 
         if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
-            let _ = std::fs::write(&candidate.path, updated_content);
+            auth::token_store::write(&candidate.path, updated_content);
         }
 
         return Some(CodexAuth {
@@ -3283,7 +3707,7 @@ async fn get_codex_auths(model: &str) -> Vec<CodexAuth> {
     let mut auths = Vec::new();
     for ranked_candidate in ranked {
         let candidate = ranked_candidate.candidate;
-        if let Some(auth) = load_codex_auth_from_candidate(&candidate).await {
+        if let Some(auth) = load_codex_auth_from_candidate(&candidate, false).await {
             auths.push(auth);
         }
     }
@@ -3323,6 +3747,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn claude_stream_emits_tool_arguments_incrementally() {
+        let mut state = ClaudeStreamState::default();
+        let chunks = [
+            r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","function":{"name":"search","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"q\":"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"rust\"}"}}]}}]}"#,
+        ];
+
+        let mut deltas = Vec::new();
+        for chunk in chunks {
+            deltas.extend(
+                openai_chunk_to_claude_events(chunk, &mut state, false)
+                    .into_iter()
+                    .map(|event| format!("{:?}", event))
+                    .filter(|event| event.contains("input_json_delta")),
+            );
+        }
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(state.tool_calls[&0].emitted_len, r#"{"q":"rust"}"#.len());
+
+        // Everything was already streamed, so finalizing only closes the block
+        let finalized: Vec<String> = finalize_claude_stream(&mut state)
+            .into_iter()
+            .map(|event| format!("{:?}", event))
+            .collect();
+        assert!(!finalized.iter().any(|e| e.contains("input_json_delta")));
+        assert!(finalized.iter().any(|e| e.contains("content_block_stop")));
+    }
+
+    #[test]
+    fn claude_stream_keeps_interleaved_tool_calls_open() {
+        let mut state = ClaudeStreamState::default();
+        let chunks = [
+            r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","function":{"name":"a","arguments":"{\"x\":"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_2","function":{"name":"b","arguments":"{}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"1}"}}]}}]}"#,
+        ];
+
+        let mut events: Vec<String> = Vec::new();
+        for chunk in chunks {
+            events.extend(
+                openai_chunk_to_claude_events(chunk, &mut state, false)
+                    .into_iter()
+                    .map(|event| format!("{:?}", event)),
+            );
+        }
+        // The late fragment of the first call is streamed, nothing was closed yet
+        assert_eq!(state.tool_calls[&0].arguments, r#"{"x":1}"#);
+        assert_eq!(state.tool_calls[&0].emitted_len, r#"{"x":1}"#.len());
+        assert!(!events.iter().any(|e| e.contains("content_block_stop")));
+
+        let stops = finalize_claude_stream(&mut state)
+            .into_iter()
+            .map(|event| format!("{:?}", event))
+            .filter(|e| e.contains("content_block_stop"))
+            .count();
+        assert_eq!(stops, 2);
+    }
+
     #[test]
     fn codex_models_follow_cliproxy_tiers() {
         let free_ids: Vec<String> = get_codex_models_for_plan(Some("free"))
@@ -3421,6 +3905,75 @@ mod tests {
         assert!(should_rotate_codex_error(error));
         assert!(should_mark_account_exhausted(error));
     }
+
+    #[test]
+    fn forbidden_and_banned_errors_are_detected() {
+        assert!(is_account_forbidden_error(
+            "Antigravity request failed: 403 {\"error\":\"PERMISSION_DENIED\"}"
+        ));
+        assert!(is_account_forbidden_error(
+            "Kiro request failed: 403 Forbidden {}"
+        ));
+        assert!(!is_account_forbidden_error(
+            "Codex request failed: 429 Too Many Requests"
+        ));
+        assert!(!is_account_banned_error(
+            "Codex request failed: 403 Forbidden"
+        ));
+        assert!(is_account_banned_error(
+            "Codex request failed: 401 {\"code\":\"account_deactivated\"}"
+        ));
+    }
+
+    #[tokio::test]
+    async fn chat_responses_are_converted_with_their_log_headers() {
+        let chat = json!({
+            "id": "chatcmpl-1",
+            "model": "gemini-2.5-pro",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop"
+            }]
+        });
+        let response = with_log_info(Json(chat), "gemini", "a.json", "gemini-2.5-pro");
+        let response = convert_chat_response(response, ChatOutput::Completions).await;
+        assert_eq!(
+            response.headers().get(super::super::X_ONEPROXY_PROVIDER).unwrap(),
+            "gemini"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let completion: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(completion["object"], "text_completion");
+        assert_eq!(completion["choices"][0]["text"], "Hi");
+
+        let chunks = futures::stream::iter(
+            [
+                json!({"id": "c", "choices": [{"index": 0, "delta": {"content": "Hi"}}]})
+                    .to_string(),
+                "[DONE]".to_string(),
+            ]
+            .map(|data| Ok::<Event, Infallible>(Event::default().data(data))),
+        );
+        let response = convert_chat_response(
+            Sse::new(chunks).into_response(),
+            ChatOutput::ClaudeMessages {
+                model: "gemini-2.5-pro",
+                request_id: "req",
+                reasoning_as_text: false,
+            },
+        )
+        .await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("event: message_start"));
+        assert!(body.contains("\"text\":\"Hi\""));
+        assert!(body.contains("event: message_stop"));
+    }
 }
 
 fn normalize_antigravity_model(model: &str) -> String {
@@ -3585,8 +4138,9 @@ async fn get_antigravity_auth(model: &str) -> Option<AntigravityAuth> {
 
 async fn load_antigravity_auth_from_candidate(
     candidate: &AuthCandidate,
+    force_refresh: bool,
 ) -> Option<AntigravityAuth> {
-    let content = std::fs::read_to_string(&candidate.path).ok()?;
+    let content = auth::token_store::read_to_string(&candidate.path).ok()?;
     let mut json: serde_json::Value = serde_json::from_str(&content).ok()?;
 
     let snapshot = parse_token_snapshot(&json)?;
@@ -3597,14 +4151,14 @@ async fn load_antigravity_auth_from_candidate(
         .map(|v| v.trim().to_string())
         .filter(|v| !v.is_empty());
 
-    if !is_expired(snapshot.expires_at) {
+    if !force_refresh && !is_expired(snapshot.expires_at) {
         if project_id.is_none() {
             if let Ok(pid) = antigravity_oauth::fetch_project_id(&snapshot.access_token).await {
                 if !pid.trim().is_empty() {
                     project_id = Some(pid.clone());
                     json["project_id"] = serde_json::json!(pid);
                     if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
-                        let _ = std::fs::write(&candidate.path, updated_content);
+                        auth::token_store::write(&candidate.path, updated_content);
                     }
                 }
             }
@@ -3683,7 +4237,7 @@ async fn load_antigravity_auth_from_candidate(
     }
 
     if let Ok(updated_content) = serde_json::to_string_pretty(&json) {
-        let _ = std::fs::write(&candidate.path, updated_content);
+        auth::token_store::write(&candidate.path, updated_content);
     }
 
     Some(AntigravityAuth {
@@ -3720,7 +4274,7 @@ async fn get_antigravity_auths(model: &str) -> Vec<AntigravityAuth> {
 
     let mut auths = Vec::new();
     for candidate in candidates {
-        if let Some(auth) = load_antigravity_auth_from_candidate(&candidate).await {
+        if let Some(auth) = load_antigravity_auth_from_candidate(&candidate, false).await {
             auths.push(auth);
         }
     }
@@ -3820,91 +4374,111 @@ async fn get_kiro_auths(model: &str) -> Vec<KiroAuthWithAccount> {
     auths
 }
 
-/// Send Kiro request with retry on 500 server errors (max 2 retries, 1s delay)
-async fn send_kiro_request_with_retry(
-    auth: &kiro::KiroAuth,
-    payload: &Value,
-) -> Result<reqwest::Response, String> {
-    const MAX_RETRIES: u32 = 10;
-    let mut last_err = String::new();
-    for attempt in 0..=MAX_RETRIES {
-        match kiro::send_kiro_request(auth, payload, true).await {
-            Ok(r) => return Ok(r),
-            Err(e) => {
-                let msg = e.to_string();
-                let is_server_error = msg.contains("500")
-                    || msg.to_lowercase().contains("internal server error")
-                    || msg.to_lowercase().contains("service unavailable")
-                    || msg.contains("503");
-                last_err = msg.clone();
-                if is_server_error && attempt < MAX_RETRIES {
-                    tracing::warn!(
-                        "Kiro server error (attempt {}/{}), retrying in 1s: {}",
-                        attempt + 1,
-                        MAX_RETRIES,
-                        msg
-                    );
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                    continue;
-                }
-                return Err(last_err);
-            }
-        }
+/// Refresh OAuth tokens that expire within `window`, so the first request after
+/// startup doesn't pay for a refresh round-trip. Returns (refreshed, failed).
+pub(super) async fn refresh_expiring_tokens(window: chrono::Duration) -> (usize, usize) {
+    let auth_dir = crate::config::resolve_auth_dir();
+    if !auth_dir.exists() {
+        return (0, 0);
     }
-    Err(last_err)
-}
 
-fn should_rotate_kiro_error(message: &str) -> bool {
-    let lower = message.to_lowercase();
-    lower.contains("quota_exhausted")
-        || lower.contains("resource_exhausted")
-        || lower.contains("rate_limit_exceeded")
-        || lower.contains("rate limit")
-        || lower.contains("too many requests")
-        || lower.contains("unauthorized")
-        || lower.contains("forbidden")
-        || lower.contains("401")
-        || lower.contains("403")
-        || lower.contains("429")
-        || lower.contains("500")
-}
+    let mut files = Vec::new();
+    collect_json_files(&auth_dir, &mut files);
 
-/// Get a Kimi API key from stored credentials
-async fn get_kimi_token(model: &str) -> Option<String> {
-    let candidates = select_auth_candidates("kimi", model);
-    for candidate in candidates {
-        let content = match std::fs::read_to_string(&candidate.path) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let json: serde_json::Value = match serde_json::from_str(&content) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        if let Some(token) = extract_api_key(&json) {
-            return Some(token);
+    let deadline = chrono::Utc::now() + window;
+    let mut refreshed = 0;
+    let mut failed = 0;
+
+    for provider in ["gemini", "antigravity", "codex", "claude", "kiro"] {
+        for path in &files {
+            let Some(candidate) = candidate_from_path(provider, &auth_dir, path) else {
+                continue;
+            };
+
+            let ok = if provider == "kiro" {
+                let snapshot = match kiro::load_kiro_auth(&candidate.path).await {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
+                if snapshot.refresh_token.is_none()
+                    || !snapshot.expires_at.map(|e| e <= deadline).unwrap_or(false)
+                {
+                    continue;
+                }
+                kiro::refresh_kiro_auth(&candidate.path, &snapshot)
+                    .await
+                    .is_ok()
+            } else {
+                let snapshot = std::fs::read_to_string(&candidate.path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+                    .and_then(|json| parse_token_snapshot(&json));
+                let Some(snapshot) = snapshot else {
+                    continue;
+                };
+                if snapshot.refresh_token.is_none()
+                    || !snapshot.expires_at.map(|e| e <= deadline).unwrap_or(false)
+                {
+                    continue;
+                }
+                match provider {
+                    "gemini" => load_gemini_auth_from_candidate(&candidate, true)
+                        .await
+                        .is_some(),
+                    "antigravity" => load_antigravity_auth_from_candidate(&candidate, true)
+                        .await
+                        .is_some(),
+                    "codex" => load_codex_auth_from_candidate(&candidate, true)
+                        .await
+                        .is_some(),
+                    _ => load_claude_token_from_candidate(&candidate, true)
+                        .await
+                        .is_some(),
+                }
+            };
+
+            if ok {
+                tracing::info!("[Warmup] Refreshed token for {}:{}", provider, candidate.id);
+                refreshed += 1;
+            } else {
+                tracing::warn!(
+                    "[Warmup] Failed to refresh token for {}:{}",
+                    provider,
+                    candidate.id
+                );
+                failed += 1;
+            }
         }
     }
-    None
+
+    (refreshed, failed)
 }
 
-/// Get a GLM API key from stored credentials
-async fn get_glm_token(model: &str) -> Option<String> {
-    let candidates = select_auth_candidates("glm", model);
-    for candidate in candidates {
-        let content = match std::fs::read_to_string(&candidate.path) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        let json: serde_json::Value = match serde_json::from_str(&content) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        if let Some(token) = extract_api_key(&json) {
-            return Some(token);
-        }
-    }
-    None
+/// Populate the Kiro model cache using the first usable Kiro account.
+/// Returns the number of models available afterwards, or None when no Kiro account exists.
+pub(super) async fn warm_kiro_model_cache() -> Result<Option<usize>, String> {
+    let Some(auth_with_account) = get_kiro_auth("auto").await else {
+        return Ok(None);
+    };
+    kiro::ensure_model_cache(&auth_with_account.auth)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(kiro::available_models().len()))
+}
+
+fn should_rotate_kiro_error(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("quota_exhausted")
+        || lower.contains("resource_exhausted")
+        || lower.contains("rate_limit_exceeded")
+        || lower.contains("rate limit")
+        || lower.contains("too many requests")
+        || lower.contains("unauthorized")
+        || lower.contains("forbidden")
+        || lower.contains("401")
+        || lower.contains("403")
+        || lower.contains("429")
+        || lower.contains("500")
 }
 
 /// Custom provider info for OpenAI-compatible or Claude Code-compatible providers
@@ -3982,6 +4556,145 @@ fn get_custom_provider_info(provider_key: &str) -> Option<CustomProviderInfo> {
     None
 }
 
+/// Model ids discovered from custom providers' `/models` endpoints, keyed by lowercase prefix.
+/// Only used for providers that don't list their models explicitly in config.
+static CUSTOM_PROVIDER_MODELS: Lazy<Mutex<HashMap<String, Vec<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn discovered_custom_models(prefix: &str) -> Vec<String> {
+    CUSTOM_PROVIDER_MODELS
+        .lock()
+        .unwrap()
+        .get(&prefix.to_lowercase())
+        .cloned()
+        .unwrap_or_default()
+}
+
+async fn fetch_custom_provider_models(
+    base_url: &str,
+    api_key: &str,
+    provider_type: CustomProviderType,
+) -> Result<Vec<String>, String> {
+    let base = base_url.trim_end_matches('/');
+    if base.is_empty() {
+        return Err("missing base URL".to_string());
+    }
+    let client = http_client::client_builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let request = client.get(format!("{}/models", base));
+    let request = match provider_type {
+        CustomProviderType::OpenAICompat => {
+            request.header("Authorization", format!("Bearer {}", api_key))
+        }
+        CustomProviderType::ClaudeCodeCompat => request
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01"),
+    };
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status.as_u16()));
+    }
+    let body = http_client::read_body(response)
+        .await
+        .map_err(|e| e.to_string())?;
+    let json: Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+    Ok(json
+        .get("data")
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get("id").and_then(|v| v.as_str()))
+                .map(|id| id.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Fetch model lists for custom providers that don't configure `models` explicitly.
+/// Returns (succeeded, failed).
+pub(super) async fn prefetch_custom_provider_models() -> (usize, usize) {
+    let Some(config) = crate::config::get_config() else {
+        return (0, 0);
+    };
+
+    let targets: Vec<(String, String, String, CustomProviderType)> = config
+        .openai_compatibility
+        .iter()
+        .filter(|e| e.models.is_empty())
+        .filter_map(|e| {
+            let key = e.api_key_entries.first()?.api_key.clone();
+            let prefix = e.prefix.as_ref().unwrap_or(&e.name).to_lowercase();
+            Some((
+                prefix,
+                e.base_url.clone(),
+                key,
+                CustomProviderType::OpenAICompat,
+            ))
+        })
+        .chain(
+            config
+                .claude_code_compatibility
+                .iter()
+                .filter(|e| e.models.is_empty())
+                .filter_map(|e| {
+                    let key = e.api_key_entries.first()?.api_key.clone();
+                    let prefix = e.prefix.as_ref().unwrap_or(&e.name).to_lowercase();
+                    Some((
+                        prefix,
+                        e.base_url.clone(),
+                        key,
+                        CustomProviderType::ClaudeCodeCompat,
+                    ))
+                }),
+        )
+        .collect();
+
+    let results = futures::future::join_all(targets.into_iter().map(
+        |(prefix, base_url, api_key, provider_type)| async move {
+            let result = fetch_custom_provider_models(&base_url, &api_key, provider_type).await;
+            (prefix, result)
+        },
+    ))
+    .await;
+
+    let mut succeeded = 0;
+    let mut failed = 0;
+    for (prefix, result) in results {
+        match result {
+            Ok(models) if !models.is_empty() => {
+                tracing::info!(
+                    "[Warmup] Discovered {} models for custom provider '{}'",
+                    models.len(),
+                    prefix
+                );
+                CUSTOM_PROVIDER_MODELS
+                    .lock()
+                    .unwrap()
+                    .insert(prefix, models);
+                succeeded += 1;
+            }
+            Ok(_) => {
+                tracing::warn!("[Warmup] Custom provider '{}' returned no models", prefix);
+                failed += 1;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "[Warmup] Failed to list models for custom provider '{}': {}",
+                    prefix,
+                    e
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    (succeeded, failed)
+}
+
 /// Forward request to OpenAI-compatible provider
 async fn forward_openai_compatible(
     payload: Value,
@@ -4002,7 +4715,7 @@ async fn forward_openai_compatible(
         .into_response();
     }
     let url = format!("{}/chat/completions", base);
-    let client = reqwest::Client::new();
+    let client = http_client::client_for(provider_label);
     let response = match client
         .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
@@ -4026,7 +4739,7 @@ async fn forward_openai_compatible(
 
     let status = response.status();
     if !status.is_success() {
-        let body = response.bytes().await.unwrap_or_default();
+        let body = http_client::read_body(response).await.unwrap_or_default();
         let mut resp = Response::new(Body::from(body));
         *resp.status_mut() = status;
         resp.headers_mut().insert(
@@ -4037,9 +4750,7 @@ async fn forward_openai_compatible(
     }
 
     if is_stream {
-        let stream = response
-            .bytes_stream()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+        let stream = http_client::byte_stream(response);
         let mut resp = Response::new(Body::from_stream(stream));
         *resp.status_mut() = StatusCode::OK;
         resp.headers_mut().insert(
@@ -4049,1671 +4760,758 @@ async fn forward_openai_compatible(
         return resp;
     }
 
-    let body = response.bytes().await.unwrap_or_default();
-    let body = maybe_decompress_gzip(&body);
+    let body = http_client::read_body(response).await.unwrap_or_default();
     let json_body: Value = serde_json::from_slice(&body).unwrap_or_else(|_| json!({}));
     Json(json_body).into_response()
 }
 
-pub async fn chat_completions(State(_state): State<AppState>, Json(raw): Json<Value>) -> Response {
-    let request_id = uuid::Uuid::new_v4().to_string();
-    let raw_model = raw
-        .get("model")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
-    let (provider_override, model) = parse_provider_prefix(&raw_model);
+/// An upstream account that can serve a registry provider
+struct ProviderAccount {
+    credentials: provider::Credentials,
+    account_id: String,
+    provider: String,
+}
 
-    // Use model router to resolve provider in aggregation mode
-    let (resolved_provider, resolved_model, fallback_providers) = if provider_override.is_some() {
-        (provider_override, model, Vec::new())
-    } else {
-        use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
-        match resolve_model(&raw_model, None) {
-            ResolvedModel::Explicit { provider, model } => (Some(provider), model, Vec::new()),
-            ResolvedModel::Aggregated {
-                provider,
-                model,
-                fallbacks,
-            } => {
-                // Use smart provider selection that checks for recovered high-priority providers
-                let (selected_provider, remaining_fallbacks) =
-                    select_best_provider_for_aggregation(&provider, &fallbacks);
+/// Accounts to try for a provider, in routing order
+async fn provider_accounts(provider_id: &str, model: &str) -> Vec<ProviderAccount> {
+    match provider_id {
+        "gemini" => get_gemini_auth(model)
+            .await
+            .into_iter()
+            .map(|auth| ProviderAccount {
+                credentials: provider::Credentials::Token {
+                    access_token: auth.access_token,
+                    project_id: auth.project_id,
+                },
+                account_id: auth.account_id,
+                provider: auth.provider,
+            })
+            .collect(),
+        "codex" => get_codex_auths(model)
+            .await
+            .into_iter()
+            .map(|auth| ProviderAccount {
+                credentials: provider::Credentials::token(auth.access_token),
+                account_id: auth.account_id,
+                provider: auth.provider,
+            })
+            .collect(),
+        "antigravity" => get_antigravity_auths(model)
+            .await
+            .into_iter()
+            .map(|auth| ProviderAccount {
+                credentials: provider::Credentials::Token {
+                    access_token: auth.access_token,
+                    project_id: auth.project_id,
+                },
+                account_id: auth.account_id,
+                provider: auth.provider,
+            })
+            .collect(),
+        "kiro" => get_kiro_auths(model)
+            .await
+            .into_iter()
+            .map(|auth| ProviderAccount {
+                credentials: provider::Credentials::Kiro(auth.auth),
+                account_id: auth.account_id,
+                provider: auth.provider,
+            })
+            .collect(),
+        "claude" | "kimi" | "glm" => token_accounts(provider_id, model).await,
+        _ => Vec::new(),
+    }
+}
 
-                // Convert model name to the selected provider's format
-                let actual_model = get_provider_model_name(&raw_model, &selected_provider);
+/// OAuth token (Claude) or API key (Kimi, GLM) of an account of a token-based provider
+async fn load_token_from_candidate(provider_id: &str, candidate: &AuthCandidate) -> Option<String> {
+    if provider_id == "claude" {
+        load_claude_token_from_candidate(candidate, false).await
+    } else {
+        std::fs::read_to_string(&candidate.path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|json| extract_api_key(&json))
+    }
+}
 
-                tracing::info!(
-                    "[ModelAggregation] Selected provider '{}' for model '{}' (original: '{}', fallbacks: {:?})",
-                    selected_provider,
-                    actual_model,
-                    raw_model,
-                    remaining_fallbacks
-                );
-                (Some(selected_provider), actual_model, remaining_fallbacks)
-            }
-            ResolvedModel::NoProvider { model } => {
-                return Json(json!({
-                    "error": {
-                        "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...'). Or enable Model Aggregation Mode in settings to use models without prefix.",
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
+/// Usable accounts of a token-based provider, in rotation order
+async fn token_accounts(provider_id: &str, model: &str) -> Vec<ProviderAccount> {
+    let mut accounts = Vec::new();
+    for candidate in select_auth_candidates(provider_id, model) {
+        if let Some(token) = load_token_from_candidate(provider_id, &candidate).await {
+            accounts.push(ProviderAccount {
+                credentials: provider::Credentials::token(token),
+                account_id: candidate.id.clone(),
+                provider: candidate.provider.clone(),
+            });
         }
-    };
-
-    let provider_override = resolved_provider;
-    let model = resolved_model;
-    let _fallback_providers = fallback_providers; // Available for future per-request fallback
-
-    if provider_override.is_none() {
-        return Json(json!({
-            "error": {
-                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
-                "type": "invalid_request_error",
-                "code": 400
-            }
-        }))
-        .into_response();
     }
+    accounts
+}
 
-    if provider_override.as_deref() == Some("gemini") {
-        // Get Gemini token
-        let auth = match get_gemini_auth(&model).await {
-            Some(a) => a,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Gemini credentials found. Please login with Google first.",
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let account_id = auth.account_id.clone();
-        let provider = auth.provider.clone();
-        let client = GeminiClient::new(auth.access_token);
+fn missing_credentials_message(provider_id: &str, display_name: &str) -> String {
+    let action = match provider_id {
+        "gemini" => "Please login with Google first.",
+        "claude" => "Please login with Anthropic first.",
+        "kimi" | "glm" => "Please add an API key first.",
+        "codex" => "Please login with Codex first.",
+        "antigravity" => "Please login with Antigravity first.",
+        "kiro" => "Please login with Kiro first.",
+        _ => "Please add an account first.",
+    };
+    format!("No valid {} credentials found. {}", display_name, action)
+}
 
-        let mut gemini_request = gemini::openai_to_gemini_cli_request(&raw, &model);
-        if let Some(project_id) = auth.project_id {
-            gemini_request["project"] = json!(project_id);
-        }
+/// Whether an upstream error should move the request on to the provider's next account
+fn should_rotate_provider_error(provider_id: &str, message: &str) -> bool {
+    match provider_id {
+        "codex" => should_rotate_codex_error(message),
+        "antigravity" => should_rotate_antigravity_error(message),
+        "kiro" => should_rotate_kiro_error(message),
+        _ => false,
+    }
+}
 
-        if is_stream {
-            match client.stream_generate_content(&gemini_request).await {
-                Ok(response) => {
-                    let stream = gemini::gemini_cli_stream_to_openai_events(response);
-                    return with_log_info(Sse::new(stream), &provider, &account_id, &model);
-                }
-                Err(e) => {
-                    tracing::error!("Gemini API error: {}", e);
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Gemini API error: {}", e),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
+/// Split the level prefix off a model name (e.g. "high/gpt-5-codex"); returns the model
+/// and the reasoning effort, or why the level isn't supported by the model
+fn split_model_level(provider_id: &str, model: &str) -> Result<(String, Option<String>), String> {
+    match provider_id {
+        "codex" => Ok(parse_codex_model_with_effort(model)),
+        "antigravity" => {
+            let (actual_model, reasoning_effort) = parse_antigravity_model_with_effort(model);
+            if let Some(ref effort) = reasoning_effort {
+                if !antigravity_level_supported(&actual_model, effort) {
+                    let supported = antigravity_supported_levels(&actual_model)
+                        .map(|levels| levels.join(", "))
+                        .unwrap_or_else(|| "none".to_string());
+                    return Err(format!(
+                        "Thinking level '{}' is not supported by model '{}'. Supported levels: {}. Remove the level prefix to use the default behavior.",
+                        effort, actual_model, supported
+                    ));
                 }
             }
+            Ok((actual_model, reasoning_effort))
         }
+        _ => Ok((model.to_string(), None)),
+    }
+}
 
-        match client.generate_content(&gemini_request).await {
-            Ok(response) => {
-                let openai_response =
-                    gemini::gemini_to_openai_response(&response, &model, &request_id);
-                return with_log_info(Json(openai_response), &provider, &account_id, &model);
-            }
-            Err(e) => {
-                tracing::error!("Gemini API error: {}", e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("Gemini API error: {}", e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
-            }
-        }
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// Payloads of the SSE events in a response body
+fn response_events(body: Body) -> impl futures::Stream<Item = String> {
+    sse::data_stream(
+        body.into_data_stream()
+            .map(|chunk| chunk.map_err(std::io::Error::other)),
+    )
+    .filter_map(|chunk| async move { chunk.ok() })
+}
+
+/// Apply the provider's response post-processing to a native Messages API response
+async fn post_process_claude_response(provider_id: &str, response: Response) -> Response {
+    let Some(processor) = post_process::PostProcessor::for_provider(provider_id) else {
+        return response;
+    };
+    if !response.status().is_success() {
+        return response;
     }
+    let is_stream = is_event_stream(&response);
+    let (mut parts, body) = response.into_parts();
 
-    if provider_override.as_deref() == Some("codex") {
-        // Parse reasoning_effort from model name (e.g., "high/gpt-5-codex")
-        let (actual_model, reasoning_effort) = parse_codex_model_with_effort(&model);
+    if is_stream {
+        let mut started = false;
+        let events = response_events(body).map(move |data| {
+            let data = processor.process_claude_event(&data, &mut started);
+            let event_type = serde_json::from_str::<Value>(&data)
+                .ok()
+                .and_then(|v| v.get("type")?.as_str().map(|s| s.to_string()));
+            let event = match event_type {
+                Some(event_type) => Event::default().event(event_type),
+                None => Event::default(),
+            };
+            Ok::<Event, Infallible>(event.data(data))
+        });
+        return Response::from_parts(parts, Sse::new(events).into_response().into_body());
+    }
 
-        let auths = get_codex_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Codex credentials found. Please login with Codex first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return error_response(
+                500,
+                &format!("Failed to read response: {}", e),
+                "api_error",
+                provider_id,
+                "",
+                "",
+            );
         }
+    };
+    let Ok(mut message) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    processor.process_claude_response(&mut message);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Json(message).into_response().into_body())
+}
 
-        // Inject reasoning_effort into request if parsed from model name
-        let mut modified_raw = raw.clone();
-        if let Some(effort) = reasoning_effort {
-            modified_raw["reasoning_effort"] = json!(effort);
-        }
+/// Wire format a chat completion from `dispatch_openai_chat` is returned in
+#[derive(Clone, Copy)]
+enum ChatOutput<'a> {
+    /// Legacy text completions (`/v1/completions`)
+    Completions,
+    /// Anthropic Messages (`/v1/messages`)
+    ClaudeMessages {
+        model: &'a str,
+        request_id: &'a str,
+        reasoning_as_text: bool,
+    },
+}
 
-        let codex_request = codex::openai_to_codex_request(&modified_raw, &actual_model, true);
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let client = CodexClient::new(auth.access_token.clone());
+/// Convert an OpenAI chat response from `dispatch_openai_chat` into another wire format,
+/// keeping status and logging headers; errors are passed through as they are
+async fn convert_chat_response(response: Response, output: ChatOutput<'_>) -> Response {
+    let is_stream = is_event_stream(&response);
+    let (mut parts, body) = response.into_parts();
 
-            if is_stream {
-                match client.stream_responses(&codex_request, true).await {
-                    Ok(response) => {
-                        clear_account_exhausted(&auth.provider, &auth.account_id);
-                        let stream = codex::codex_stream_to_openai_events(response, raw.clone());
-                        return with_log_info(
-                            Sse::new(stream),
-                            &auth.provider,
-                            &auth.account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Codex API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_codex_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&auth.provider, &auth.account_id);
-                            }
-                            continue;
+    if is_stream {
+        let chunks = response_events(body);
+        let body = match output {
+            ChatOutput::Completions => {
+                let events = chunks
+                    .filter_map(|chunk| async move {
+                        if chunk == "[DONE]" {
+                            Some(chunk)
+                        } else {
+                            convert_chat_stream_chunk_to_completions(&chunk)
                         }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Codex API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                }
+                    })
+                    .map(|data| Ok::<Event, Infallible>(Event::default().data(data)));
+                Sse::new(events).into_response().into_body()
             }
+            ChatOutput::ClaudeMessages {
+                model,
+                reasoning_as_text,
+                ..
+            } => Sse::new(openai_chunks_to_claude_events_with_options(
+                chunks,
+                model,
+                reasoning_as_text,
+            ))
+            .into_response()
+            .into_body(),
+        };
+        return Response::from_parts(parts, body);
+    }
 
-            match client.stream_responses(&codex_request, true).await {
-                Ok(response) => match codex::collect_non_stream_response(response, &raw).await {
-                    Ok(openai_response) => {
-                        clear_account_exhausted(&auth.provider, &auth.account_id);
-                        return with_log_info(
-                            Json(openai_response),
-                            &auth.provider,
-                            &auth.account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Codex API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_codex_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&auth.provider, &auth.account_id);
-                            }
-                            continue;
-                        }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Codex API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                },
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Codex API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_codex_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&auth.provider, &auth.account_id);
-                        }
-                        continue;
-                    }
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Codex API error: {}", msg),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
+    if !parts.status.is_success() {
+        return Response::from_parts(parts, body);
+    }
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return error_response(
+                500,
+                &format!("Failed to read response: {}", e),
+                "api_error",
+                "",
+                "",
+                "",
+            );
         }
+    };
+    let chat_response = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) if value.get("error").is_none() => value,
+        _ => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    let converted = match output {
+        ChatOutput::Completions => convert_chat_response_to_completions(&chat_response),
+        ChatOutput::ClaudeMessages {
+            model,
+            request_id,
+            reasoning_as_text,
+        } => claude::openai_to_claude_response_with_options(
+            &chat_response,
+            model,
+            request_id,
+            reasoning_as_text,
+        ),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Json(converted).into_response().into_body())
+}
+
+/// Serve an OpenAI chat completion through a registered provider, rotating to the next
+/// account on quota and auth errors
+async fn dispatch_openai_chat(
+    provider_id: &str,
+    model: &str,
+    request: &Value,
+    is_stream: bool,
+    request_id: &str,
+) -> Response {
+    let Some(chat_provider) = provider::get(provider_id) else {
+        return error_response(
+            400,
+            &format!("Unknown provider '{}'", provider_id),
+            "invalid_request_error",
+            provider_id,
+            "",
+            model,
+        );
+    };
+    let label = chat_provider.display_name();
 
+    let accounts = provider_accounts(provider_id, model).await;
+    if accounts.is_empty() {
         return Json(json!({
             "error": {
-                "message": format!("Codex API error: {}", last_error.unwrap_or_else(|| "unknown error".to_string())),
-                "type": "api_error",
-                "code": 500
+                "message": missing_credentials_message(provider_id, label),
+                "type": "authentication_error",
+                "code": 401
             }
         }))
         .into_response();
     }
 
-    if provider_override.as_deref() == Some("antigravity") {
-        let (actual_model, reasoning_effort) = parse_antigravity_model_with_effort(&model);
-        let mut openai_raw = raw.clone();
-        if let Some(ref effort) = reasoning_effort {
-            if !antigravity_level_supported(&actual_model, effort) {
-                let supported = antigravity_supported_levels(&actual_model)
-                    .map(|levels| levels.join(", "))
-                    .unwrap_or_else(|| "none".to_string());
-                return Json(json!({
-                    "error": {
-                        "message": format!(
-                            "Thinking level '{}' is not supported by model '{}'. Supported levels: {}. Remove the level prefix to use the default behavior.",
-                            effort, actual_model, supported
-                        ),
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-            openai_raw["reasoning_effort"] = json!(effort);
-        }
-
-        let auths = get_antigravity_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Antigravity credentials found. Please login with Antigravity first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
+    // Providers without streaming answer stream requests with a complete response
+    let stream = is_stream && chat_provider.capabilities().streaming;
+    compat_warnings::check_request(chat_provider.as_ref(), request);
+    let mut last_error: Option<String> = None;
+    let mut last_account: Option<(String, String)> = None;
+    let mut overloaded = false;
+    let total = accounts.len();
 
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let AntigravityAuth {
-                access_token,
-                project_id,
-                account_id,
-                provider,
-            } = auth;
-            let client = AntigravityClient::new(access_token);
-            let antigravity_request =
-                antigravity::openai_to_antigravity_request(&openai_raw, &actual_model, project_id);
+    for (idx, account) in accounts.into_iter().enumerate() {
+        let ctx = provider::ChatContext {
+            credentials: &account.credentials,
+            model,
+            request_id,
+        };
+        let result = if stream {
+            chat_provider
+                .chat_stream(&ctx, request)
+                .await
+                .map(|events| {
+                    with_log_info(
+                        Sse::new(events),
+                        &account.provider,
+                        &account.account_id,
+                        model,
+                    )
+                })
+        } else {
+            chat_provider.chat(&ctx, request).await.map(|mut response| {
+                post_process::process_openai_response(provider_id, &mut response);
+                with_log_info(
+                    Json(response),
+                    &account.provider,
+                    &account.account_id,
+                    model,
+                )
+            })
+        };
 
-            if is_stream {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => {
-                        clear_account_exhausted(&provider, &account_id);
-                        let stream = antigravity::antigravity_stream_to_openai_events(response);
-                        return with_log_info(
-                            Sse::new(stream),
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return error_response(
-                            500,
-                            &format!("Antigravity API error: {}", msg),
-                            "api_error",
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                }
+        let msg = match result {
+            Ok(response) => {
+                clear_account_exhausted(&account.provider, &account.account_id);
+                return response;
             }
-
-            if antigravity::should_use_stream_for_non_stream(&actual_model) {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => match antigravity::collect_antigravity_stream(response).await {
-                        Ok(payload) => {
-                            clear_account_exhausted(&provider, &account_id);
-                            let openai_response = gemini::gemini_to_openai_response(
-                                &payload,
-                                &actual_model,
-                                &request_id,
-                            );
-                            return with_log_info(
-                                Json(openai_response),
-                                &provider,
-                                &account_id,
-                                &actual_model,
-                            );
-                        }
-                        Err(e) => {
-                            tracing::error!("Antigravity API error: {}", e);
-                            return error_response(
-                                500,
-                                &format!("Antigravity API error: {}", e),
-                                "api_error",
-                                &provider,
-                                &account_id,
-                                &actual_model,
-                            );
-                        }
-                    },
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return error_response(
-                            500,
-                            &format!("Antigravity API error: {}", msg),
-                            "api_error",
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                }
+            Err(provider::ProviderError::InvalidRequest(msg)) => {
+                return error_response(
+                    400,
+                    &msg,
+                    "invalid_request_error",
+                    &account.provider,
+                    &account.account_id,
+                    model,
+                );
             }
-
-            match client.generate_content(&antigravity_request, None).await {
-                Ok(response) => {
-                    clear_account_exhausted(&provider, &account_id);
-                    let openai_response =
-                        gemini::gemini_to_openai_response(&response, &actual_model, &request_id);
-                    return with_log_info(
-                        Json(openai_response),
-                        &provider,
-                        &account_id,
-                        &actual_model,
-                    );
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Antigravity API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&provider, &account_id);
-                        }
-                        continue;
-                    }
-                    return error_response(
-                        500,
-                        &format!("Antigravity API error: {}", msg),
-                        "api_error",
-                        &provider,
-                        &account_id,
-                        &actual_model,
-                    );
-                }
+            Err(provider::ProviderError::AccountUnavailable(msg)) => {
+                tracing::warn!(
+                    "{} account {} unavailable: {}",
+                    label,
+                    account.account_id,
+                    msg
+                );
+                last_error = Some(msg);
+                last_account = Some((account.provider, account.account_id));
+                overloaded = false;
+                continue;
             }
-        }
+            // Overload is the backend's state, not the account's: no error is recorded
+            Err(provider::ProviderError::Overloaded(msg)) => {
+                tracing::warn!(
+                    "{} overloaded (account {}): {}",
+                    label,
+                    account.account_id,
+                    msg
+                );
+                last_error = Some(msg);
+                last_account = Some((account.provider, account.account_id));
+                overloaded = true;
+                continue;
+            }
+            Err(provider::ProviderError::Upstream(msg)) => msg,
+        };
 
-        let message = last_error.unwrap_or_else(|| "Antigravity API error".to_string());
-        return Json(json!({
-            "error": {
-                "message": format!("Antigravity API error: {}", message),
-                "type": "api_error",
-                "code": 500
+        tracing::error!(
+            "{} API error (account {}): {}",
+            label,
+            account.account_id,
+            msg
+        );
+        record_account_error(&account.provider, &account.account_id, &msg);
+        if should_rotate_provider_error(provider_id, &msg) && idx + 1 < total {
+            if should_mark_account_exhausted(&msg) {
+                mark_account_exhausted(&account.provider, &account.account_id);
             }
-        }))
-        .into_response();
+            last_error = Some(msg);
+            last_account = Some((account.provider, account.account_id));
+            continue;
+        }
+        return error_response(
+            500,
+            &format!("{} API error: {}", label, msg),
+            "api_error",
+            &account.provider,
+            &account.account_id,
+            model,
+        );
     }
 
-    if provider_override.as_deref() == Some("kiro") {
-        let auths = get_kiro_auths(&model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Kiro credentials found. Please login with Kiro first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
+    let (provider_name, account_id) =
+        last_account.unwrap_or_else(|| (provider_id.to_string(), String::new()));
+    let (status, error_type) = if overloaded {
+        (claude::OVERLOADED_STATUS, "overloaded_error")
+    } else {
+        (500, "api_error")
+    };
+    error_response(
+        status,
+        &format!(
+            "{} API error: {}",
+            label,
+            last_error.unwrap_or_else(|| "unknown error".to_string())
+        ),
+        error_type,
+        &provider_name,
+        &account_id,
+        model,
+    )
+}
+
+pub async fn chat_completions(
+    State(_state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut raw): Json<Value>,
+) -> Response {
+    let economy_route = economy::route_request(&headers, &mut raw);
+    let upgrade = context_upgrade::upgrade_request(&mut raw);
+    let response = match race::targets(&headers, &raw) {
+        Some(targets) => {
+            race::race(raw, targets, "/v1/chat/completions", serve_chat_completions).await
         }
+        None => serve_chat_completions(raw).await,
+    };
+    economy::annotate(context_upgrade::annotate(response, upgrade), economy_route)
+}
 
-        let resolution = kiro::resolve_model(&model);
-        let conversation_id = kiro::generate_conversation_id(raw.get("messages"));
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
+/// The same chat completion from several providers, see `fan_out`
+pub async fn chat_completions_fan_out(
+    State(_state): State<AppState>,
+    Json(raw): Json<Value>,
+) -> Response {
+    super::fan_out::fan_out(raw).await
+}
 
-        for (idx, kiro_auth) in auths.into_iter().enumerate() {
-            let auth = &kiro_auth.auth;
-            let account_id = &kiro_auth.account_id;
-            let provider = &kiro_auth.provider;
+pub(super) async fn serve_chat_completions(raw: Value) -> Response {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let raw_model = raw
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
+    let (provider_override, model) = parse_provider_prefix(&raw_model);
 
-            if kiro::ensure_model_cache(auth).await.is_err() {
-                last_error = Some("Failed to load Kiro models".to_string());
-                continue;
-            }
-
-            let profile_arn = if matches!(auth.auth_type, kiro::KiroAuthType::KiroDesktop) {
-                auth.profile_arn.clone()
-            } else {
-                None
-            };
-
-            let payload = match kiro::build_kiro_payload_from_openai(
-                &raw,
-                &resolution.internal_id,
-                conversation_id.clone(),
-                profile_arn,
-            ) {
-                Ok(p) => p,
-                Err(e) => {
-                    return error_response(
-                        400,
-                        &format!("Invalid Kiro request: {}", e),
-                        "invalid_request_error",
-                        provider,
-                        account_id,
-                        &model,
-                    );
-                }
-            };
-
-            let response = match send_kiro_request_with_retry(auth, &payload).await {
-                Ok(r) => r,
-                Err(msg) => {
-                    let msg = msg;
-                    tracing::error!("Kiro API error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
-                        }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
-                }
-            };
+    // Use model router to resolve provider in aggregation mode
+    let (resolved_provider, resolved_model, fallback_providers) = if provider_override.is_some() {
+        (provider_override, model, Vec::new())
+    } else {
+        use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
+        match resolve_model(&raw_model, None) {
+            ResolvedModel::Explicit { provider, model } => (Some(provider), model, Vec::new()),
+            ResolvedModel::Aggregated {
+                provider,
+                model,
+                fallbacks,
+            } => {
+                // Use smart provider selection that checks for recovered high-priority providers
+                let (selected_provider, remaining_fallbacks) =
+                    select_best_provider_for_aggregation(&provider, &fallbacks);
 
-            clear_account_exhausted(provider, account_id);
+                // Convert model name to the selected provider's format
+                let actual_model = get_provider_model_name(&raw_model, &selected_provider);
 
-            if is_stream {
-                let upstream = kiro::stream_kiro_to_openai(
-                    response,
-                    model.clone(),
-                    raw.get("messages").cloned(),
-                    raw.get("tools").cloned(),
+                tracing::info!(
+                    "[ModelAggregation] Selected provider '{}' for model '{}' (original: '{}', fallbacks: {:?})",
+                    selected_provider,
+                    actual_model,
+                    raw_model,
+                    remaining_fallbacks
                 );
-                let stream = upstream.filter_map(|chunk| async move {
-                    match chunk {
-                        Ok(data) => strip_sse_data_line(&data),
-                        Err(_) => None,
-                    }
-                });
-                let stream = stream.map(|p| Ok::<Event, Infallible>(Event::default().data(p)));
-                return with_log_info(Sse::new(stream), provider, account_id, &model);
-            }
-
-            match kiro::collect_stream_response(
-                response,
-                model.clone(),
-                raw.get("messages").cloned(),
-                raw.get("tools").cloned(),
-            )
-            .await
-            {
-                Ok(openai_response) => {
-                    return with_log_info(Json(openai_response), provider, account_id, &model)
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Kiro collect error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
-                        }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
-                }
-            }
-        }
-
-        let message = last_error.unwrap_or_else(|| "Kiro API error".to_string());
-        return Json(json!({
-            "error": {
-                "message": format!("Kiro API error: {}", message),
-                "type": "api_error",
-                "code": 500
-            }
-        }))
-        .into_response();
-    }
-
-    if matches!(provider_override.as_deref(), Some("kimi") | Some("glm")) {
-        let request: ChatCompletionRequest = match serde_json::from_value(raw.clone()) {
-            Ok(r) => r,
-            Err(e) => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("Invalid request: {}", e),
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let (token, base_url, provider_label) = match provider_override.as_deref() {
-            Some("kimi") => (get_kimi_token(&model).await, KIMI_ANTHROPIC_BASE, "Kimi"),
-            Some("glm") => (get_glm_token(&model).await, GLM_ANTHROPIC_BASE, "GLM"),
-            _ => (None, "", "Unknown"),
-        };
-
-        let token = match token {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("No valid {} credentials found. Please add an API key first.", provider_label),
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let client = ClaudeClient::new_with_base_url(token, base_url);
-        let (messages, system) = claude::openai_to_claude_messages(&request.messages);
-        let claude_request = ClaudeRequest {
-            model: model.clone(),
-            messages,
-            max_tokens: request.max_tokens.unwrap_or(4096),
-            temperature: request.temperature,
-            system,
-        };
-
-        match client.create_message(claude_request).await {
-            Ok(response) => {
-                let openai_response =
-                    claude::claude_to_openai_response(&response, &model, &request_id);
-                return Json(openai_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("{} API error: {}", provider_label, e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("{} API error: {}", provider_label, e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
+                (Some(selected_provider), actual_model, remaining_fallbacks)
             }
-        }
-    }
-
-    if provider_override.as_deref() == Some("claude") {
-        let request: ChatCompletionRequest = match serde_json::from_value(raw.clone()) {
-            Ok(r) => r,
-            Err(e) => {
+            ResolvedModel::NoProvider { model } => {
                 return Json(json!({
                     "error": {
-                        "message": format!("Invalid request: {}", e),
+                        "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...'). Or enable Model Aggregation Mode in settings to use models without prefix.",
                         "type": "invalid_request_error",
                         "code": 400
                     }
                 }))
                 .into_response();
             }
-        };
-        // Get Claude token
-        let token = match get_claude_token(&model).await {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Claude credentials found. Please login with Anthropic first.",
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let client = ClaudeClient::new(token);
-
-        // Convert messages to Claude format
-        let (messages, system) = claude::openai_to_claude_messages(&request.messages);
-
-        let claude_request = ClaudeRequest {
-            model: model.clone(),
-            messages,
-            max_tokens: request.max_tokens.unwrap_or(4096),
-            temperature: request.temperature,
-            system,
-        };
-
-        match client.create_message(claude_request).await {
-            Ok(response) => {
-                let openai_response =
-                    claude::claude_to_openai_response(&response, &model, &request_id);
-                return Json(openai_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("Claude API error: {}", e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("Claude API error: {}", e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
-            }
         }
-    }
-
-    // Handle custom providers (OpenAI-compatible and Claude Code-compatible)
-    if let Some(ref provider_key) = provider_override {
-        if provider_key.starts_with("openai-compat:") || provider_key.starts_with("claude-compat:")
-        {
-            let provider_info = match get_custom_provider_info(provider_key) {
-                Some(info) => info,
-                None => {
-                    let provider_name = provider_key.split(':').nth(1).unwrap_or("unknown");
-                    return Json(json!({
-                        "error": {
-                            "message": format!("No API key configured for custom provider '{}'. Please add an API key in settings.", provider_name),
-                            "type": "authentication_error",
-                            "code": 401
-                        }
-                    }))
-                    .into_response();
-                }
-            };
-
-            let provider_name = provider_key.split(':').nth(1).unwrap_or("custom");
-
-            // Prepare the request payload with the actual model name
-            let mut payload = raw.clone();
-            payload["model"] = json!(model);
-
-            match provider_info.provider_type {
-                CustomProviderType::OpenAICompat => {
-                    return forward_openai_compatible(
-                        payload,
-                        &provider_info.base_url,
-                        &provider_info.api_key,
-                        is_stream,
-                        provider_name,
-                    )
-                    .await;
-                }
-                CustomProviderType::ClaudeCodeCompat => {
-                    // Convert OpenAI request to Claude format, call API, convert response back
-                    let request: ChatCompletionRequest = match serde_json::from_value(raw.clone()) {
-                        Ok(r) => r,
-                        Err(e) => {
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Invalid request: {}", e),
-                                    "type": "invalid_request_error",
-                                    "code": 400
-                                }
-                            }))
-                            .into_response();
-                        }
-                    };
-
-                    let (messages, system) = claude::openai_to_claude_messages(&request.messages);
-                    let claude_payload = json!({
-                        "model": model,
-                        "messages": messages,
-                        "max_tokens": request.max_tokens.unwrap_or(4096),
-                        "temperature": request.temperature,
-                        "system": system,
-                        "stream": is_stream
-                    });
-
-                    if is_stream {
-                        // Streaming: forward and convert Claude stream to OpenAI stream
-                        let base = provider_info.base_url.trim_end_matches('/').to_string();
-                        let url = format!("{}/messages", base);
-                        let client = reqwest::Client::new();
-                        let response = match client
-                            .post(&url)
-                            .header("x-api-key", &provider_info.api_key)
-                            .header("anthropic-version", "2023-06-01")
-                            .header("content-type", "application/json")
-                            .json(&claude_payload)
-                            .send()
-                            .await
-                        {
-                            Ok(r) => r,
-                            Err(e) => {
-                                return Json(json!({
-                                    "error": {
-                                        "message": format!("{} API error: {}", provider_name, e),
-                                        "type": "api_error",
-                                        "code": 500
-                                    }
-                                }))
-                                .into_response();
-                            }
-                        };
-
-                        if !response.status().is_success() {
-                            let status = response.status();
-                            let body = response.bytes().await.unwrap_or_default();
-                            let mut resp = Response::new(Body::from(body));
-                            *resp.status_mut() = status;
-                            resp.headers_mut().insert(
-                                header::CONTENT_TYPE,
-                                HeaderValue::from_static("application/json"),
-                            );
-                            return resp;
-                        }
-
-                        // Convert Claude stream to OpenAI stream
-                        let byte_stream = response.bytes_stream();
-                        let model_clone = model.clone();
-                        let stream = byte_stream.map(move |result| {
-                            match result {
-                                Ok(bytes) => {
-                                    let text = String::from_utf8_lossy(&bytes);
-                                    let mut output = String::new();
-                                    for line in text.lines() {
-                                        if let Some(data) = line.strip_prefix("data: ") {
-                                            if data.trim().is_empty() || data.trim() == "[DONE]" {
-                                                continue;
-                                            }
-                                            if let Ok(event) = serde_json::from_str::<Value>(data) {
-                                                // Convert Claude event to OpenAI chunk
-                                                let openai_chunk =
-                                                    claude::claude_stream_to_openai_chunk(
-                                                        &event,
-                                                        &model_clone,
-                                                    );
-                                                if let Some(chunk) = openai_chunk {
-                                                    output.push_str(&format!(
-                                                        "data: {}\n\n",
-                                                        serde_json::to_string(&chunk)
-                                                            .unwrap_or_default()
-                                                    ));
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Ok::<_, std::io::Error>(Bytes::from(output))
-                                }
-                                Err(e) => Err(std::io::Error::new(std::io::ErrorKind::Other, e)),
-                            }
-                        });
-
-                        let mut resp = Response::new(Body::from_stream(stream));
-                        *resp.status_mut() = StatusCode::OK;
-                        resp.headers_mut().insert(
-                            header::CONTENT_TYPE,
-                            HeaderValue::from_static("text/event-stream"),
-                        );
-                        return resp;
-                    }
-
-                    // Non-streaming: call API and convert response
-                    let response = forward_claude_compatible(
-                        claude_payload,
-                        &provider_info.base_url,
-                        &provider_info.api_key,
-                        false,
-                        provider_name,
-                    )
-                    .await;
-
-                    // Extract the Claude response and convert to OpenAI format
-                    let (parts, body) = response.into_parts();
-                    let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
-                        Ok(b) => b,
-                        Err(e) => {
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Failed to read response: {}", e),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            }))
-                            .into_response();
-                        }
-                    };
-
-                    if !parts.status.is_success() {
-                        let mut resp = Response::new(Body::from(body_bytes));
-                        *resp.status_mut() = parts.status;
-                        resp.headers_mut().insert(
-                            header::CONTENT_TYPE,
-                            HeaderValue::from_static("application/json"),
-                        );
-                        return resp;
-                    }
-
-                    let claude_response: Value = match serde_json::from_slice(&body_bytes) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Failed to parse response: {}", e),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            }))
-                            .into_response();
-                        }
-                    };
-
-                    let openai_response = claude::claude_value_to_openai_response(
-                        &claude_response,
-                        &model,
-                        &request_id,
-                    );
-                    return Json(openai_response).into_response();
-                }
-            }
-        }
-    }
-
-    Json(json!({
-        "error": {
-            "message": "Unsupported provider. Use a supported provider prefix (gemini/..., claude/..., codex/..., antigravity/..., kimi/..., glm/..., kiro/..., or custom providers).",
-            "type": "invalid_request_error",
-            "code": 400
-        }
-    }))
-    .into_response()
-}
-
-pub async fn completions(State(_state): State<AppState>, Json(raw): Json<Value>) -> Response {
-    let request_id = uuid::Uuid::new_v4().to_string();
-    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
-    let chat_request = convert_completions_request_to_chat(&raw);
-    let raw_model = chat_request
-        .get("model")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    let (provider_override, model) = parse_provider_prefix(&raw_model);
-
-    // Use model router to resolve provider in aggregation mode
-    let (resolved_provider, resolved_model, _fallback_providers) = if provider_override.is_some() {
-        (provider_override, model, Vec::new())
-    } else {
-        use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
-        match resolve_model(&raw_model, None) {
-            ResolvedModel::Explicit { provider, model } => (Some(provider), model, Vec::new()),
-            ResolvedModel::Aggregated {
-                provider,
-                model: _,
-                fallbacks,
-            } => {
-                // Use smart provider selection that checks for recovered high-priority providers
-                let (selected_provider, remaining_fallbacks) =
-                    select_best_provider_for_aggregation(&provider, &fallbacks);
-
-                // Convert model name to the selected provider's format
-                let actual_model = get_provider_model_name(&raw_model, &selected_provider);
-
-                tracing::info!(
-                    "[ModelAggregation/completions] Selected provider '{}' for model '{}' (fallbacks: {:?})",
-                    selected_provider,
-                    actual_model,
-                    remaining_fallbacks
-                );
-                (Some(selected_provider), actual_model, remaining_fallbacks)
-            }
-            ResolvedModel::NoProvider { model } => {
-                return Json(json!({
-                    "error": {
-                        "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...'). Or enable Model Aggregation Mode in settings.",
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-        }
-    };
+    };
 
     let provider_override = resolved_provider;
     let model = resolved_model;
+    let _fallback_providers = fallback_providers; // Available for future per-request fallback
 
-    if provider_override.is_none() {
-        return Json(json!({
-            "error": {
-                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
-                "type": "invalid_request_error",
-                "code": 400
-            }
-        }))
-        .into_response();
-    }
-
-    if provider_override.as_deref() == Some("gemini") {
-        let auth = match get_gemini_auth(&model).await {
-            Some(a) => a,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Gemini credentials found. Please login with Google first.",
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let client = GeminiClient::new(auth.access_token);
-        let mut gemini_request = gemini::openai_to_gemini_cli_request(&chat_request, &model);
-        if let Some(project_id) = auth.project_id {
-            gemini_request["project"] = json!(project_id);
-        }
-
-        if is_stream {
-            match client.stream_generate_content(&gemini_request).await {
-                Ok(response) => {
-                    let upstream = gemini::gemini_cli_stream_to_openai_chunks(response);
-                    let stream = async_stream::stream! {
-                        futures::pin_mut!(upstream);
-                        while let Some(chunk) = upstream.next().await {
-                            if chunk == "[DONE]" {
-                                yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                                return;
-                            }
-                            if let Some(converted) = convert_chat_stream_chunk_to_completions(&chunk) {
-                                yield Ok::<Event, Infallible>(Event::default().data(converted));
-                            }
-                        }
-                        yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                    };
-                    return Sse::new(stream).into_response();
-                }
-                Err(e) => {
-                    tracing::error!("Gemini API error: {}", e);
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Gemini API error: {}", e),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
-        }
-
-        match client.generate_content(&gemini_request).await {
-            Ok(response) => {
-                let openai_response =
-                    gemini::gemini_to_openai_response(&response, &model, &request_id);
-                let completions_response = convert_chat_response_to_completions(&openai_response);
-                return Json(completions_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("Gemini API error: {}", e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("Gemini API error: {}", e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
-            }
-        }
-    }
-
-    if provider_override.as_deref() == Some("codex") {
-        // Parse reasoning_effort from model name (e.g., "high/gpt-5-codex")
-        let (actual_model, reasoning_effort) = parse_codex_model_with_effort(&model);
-
-        let auths = get_codex_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Codex credentials found. Please login with Codex first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-
-        // Inject reasoning_effort into request if parsed from model name
-        let mut modified_request = chat_request.clone();
-        if let Some(effort) = reasoning_effort {
-            modified_request["reasoning_effort"] = json!(effort);
-        }
-
-        let codex_request = codex::openai_to_codex_request(&modified_request, &actual_model, true);
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let client = CodexClient::new(auth.access_token.clone());
-
-            if is_stream {
-                match client.stream_responses(&codex_request, true).await {
-                    Ok(response) => {
-                        clear_account_exhausted(&auth.provider, &auth.account_id);
-                        let upstream =
-                            codex::codex_stream_to_openai_chunks(response, chat_request.clone());
-                        let stream = async_stream::stream! {
-                            futures::pin_mut!(upstream);
-                            while let Some(chunk) = upstream.next().await {
-                                if chunk == "[DONE]" {
-                                    yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                                    return;
-                                }
-                                if let Some(converted) = convert_chat_stream_chunk_to_completions(&chunk) {
-                                    yield Ok::<Event, Infallible>(Event::default().data(converted));
-                                }
-                            }
-                            yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                        };
-                        return with_log_info(
-                            Sse::new(stream),
-                            &auth.provider,
-                            &auth.account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Codex API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_codex_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&auth.provider, &auth.account_id);
-                            }
-                            continue;
-                        }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Codex API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                }
-            }
-
-            match client.stream_responses(&codex_request, true).await {
-                Ok(response) => {
-                    match codex::collect_non_stream_response(response, &chat_request).await {
-                        Ok(openai_response) => {
-                            clear_account_exhausted(&auth.provider, &auth.account_id);
-                            let completions_response =
-                                convert_chat_response_to_completions(&openai_response);
-                            return with_log_info(
-                                Json(completions_response),
-                                &auth.provider,
-                                &auth.account_id,
-                                &actual_model,
-                            );
-                        }
-                        Err(e) => {
-                            let msg = e.to_string();
-                            tracing::error!("Codex API error: {}", msg);
-                            last_error = Some(msg.clone());
-                            if should_rotate_codex_error(&msg) && idx + 1 < total {
-                                if should_mark_account_exhausted(&msg) {
-                                    mark_account_exhausted(&auth.provider, &auth.account_id);
-                                }
-                                continue;
-                            }
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Codex API error: {}", msg),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            }))
-                            .into_response();
-                        }
-                    }
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Codex API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_codex_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&auth.provider, &auth.account_id);
-                        }
-                        continue;
-                    }
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Codex API error: {}", msg),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
-        }
-
-        return Json(json!({
-            "error": {
-                "message": format!("Codex API error: {}", last_error.unwrap_or_else(|| "unknown error".to_string())),
-                "type": "api_error",
-                "code": 500
-            }
-        }))
-        .into_response();
-    }
-
-    if provider_override.as_deref() == Some("antigravity") {
-        let (actual_model, reasoning_effort) = parse_antigravity_model_with_effort(&model);
-        let mut openai_raw = chat_request.clone();
-        if let Some(ref effort) = reasoning_effort {
-            if !antigravity_level_supported(&actual_model, effort) {
-                let supported = antigravity_supported_levels(&actual_model)
-                    .map(|levels| levels.join(", "))
-                    .unwrap_or_else(|| "none".to_string());
-                return Json(json!({
-                    "error": {
-                        "message": format!(
-                            "Thinking level '{}' is not supported by model '{}'. Supported levels: {}. Remove the level prefix to use the default behavior.",
-                            effort, actual_model, supported
-                        ),
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-            openai_raw["reasoning_effort"] = json!(effort);
-        }
-
-        let auths = get_antigravity_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Antigravity credentials found. Please login with Antigravity first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let AntigravityAuth {
-                access_token,
-                project_id,
-                account_id,
-                provider,
-            } = auth;
-            let client = AntigravityClient::new(access_token);
-            let antigravity_request =
-                antigravity::openai_to_antigravity_request(&openai_raw, &actual_model, project_id);
-
-            if is_stream {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => {
-                        clear_account_exhausted(&provider, &account_id);
-                        let upstream = antigravity::antigravity_stream_to_openai_chunks(response);
-                        let stream = async_stream::stream! {
-                            futures::pin_mut!(upstream);
-                            while let Some(chunk) = upstream.next().await {
-                                if chunk == "[DONE]" {
-                                    yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                                    return;
-                                }
-                                if let Some(converted) = convert_chat_stream_chunk_to_completions(&chunk) {
-                                    yield Ok::<Event, Infallible>(Event::default().data(converted));
-                                }
-                            }
-                            yield Ok::<Event, Infallible>(Event::default().data("[DONE]"));
-                        };
-                        return with_log_info(
-                            Sse::new(stream),
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Antigravity API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                }
-            }
-
-            if antigravity::should_use_stream_for_non_stream(&actual_model) {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => match antigravity::collect_antigravity_stream(response).await {
-                        Ok(payload) => {
-                            clear_account_exhausted(&provider, &account_id);
-                            let openai_response = gemini::gemini_to_openai_response(
-                                &payload,
-                                &actual_model,
-                                &request_id,
-                            );
-                            let completions_response =
-                                convert_chat_response_to_completions(&openai_response);
-                            return with_log_info(
-                                Json(completions_response),
-                                &provider,
-                                &account_id,
-                                &actual_model,
-                            );
-                        }
-                        Err(e) => {
-                            tracing::error!("Antigravity API error: {}", e);
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Antigravity API error: {}", e),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            }))
-                            .into_response();
-                        }
-                    },
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Antigravity API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                }
-            }
-
-            match client.generate_content(&antigravity_request, None).await {
-                Ok(response) => {
-                    clear_account_exhausted(&provider, &account_id);
-                    let openai_response =
-                        gemini::gemini_to_openai_response(&response, &actual_model, &request_id);
-                    let completions_response =
-                        convert_chat_response_to_completions(&openai_response);
-                    return with_log_info(
-                        Json(completions_response),
-                        &provider,
-                        &account_id,
-                        &actual_model,
-                    );
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Antigravity API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&provider, &account_id);
-                        }
-                        continue;
-                    }
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Antigravity API error: {}", msg),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
-        }
-
-        let message = last_error.unwrap_or_else(|| "Antigravity API error".to_string());
+    if provider_override.is_none() {
         return Json(json!({
             "error": {
-                "message": format!("Antigravity API error: {}", message),
-                "type": "api_error",
-                "code": 500
+                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
+                "type": "invalid_request_error",
+                "code": 400
             }
         }))
         .into_response();
     }
 
-    if provider_override.as_deref() == Some("kiro") {
-        let auths = get_kiro_auths(&model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Kiro credentials found. Please login with Kiro first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-
-        let resolution = kiro::resolve_model(&model);
-        let conversation_id = kiro::generate_conversation_id(chat_request.get("messages"));
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-
-        for (idx, kiro_auth) in auths.into_iter().enumerate() {
-            let auth = &kiro_auth.auth;
-            let account_id = &kiro_auth.account_id;
-            let provider = &kiro_auth.provider;
-
-            if kiro::ensure_model_cache(auth).await.is_err() {
-                last_error = Some("Failed to load Kiro models".to_string());
-                continue;
-            }
-
-            let profile_arn = if matches!(auth.auth_type, kiro::KiroAuthType::KiroDesktop) {
-                auth.profile_arn.clone()
-            } else {
-                None
-            };
-
-            let payload = match kiro::build_kiro_payload_from_openai(
-                &chat_request,
-                &resolution.internal_id,
-                conversation_id.clone(),
-                profile_arn,
-            ) {
-                Ok(p) => p,
-                Err(e) => {
+    if let Some(provider_id) = provider_override.as_deref() {
+        if provider::get(provider_id).is_some() {
+            let (model, reasoning_effort) = match split_model_level(provider_id, &model) {
+                Ok(split) => split,
+                Err(msg) => {
                     return error_response(
                         400,
-                        &format!("Invalid Kiro request: {}", e),
+                        &msg,
                         "invalid_request_error",
-                        provider,
-                        account_id,
+                        provider_id,
+                        "",
                         &model,
                     );
                 }
             };
+            let mut request = raw.clone();
+            if let Some(effort) = reasoning_effort {
+                request["reasoning_effort"] = json!(effort);
+            }
+            return dispatch_openai_chat(provider_id, &model, &request, is_stream, &request_id)
+                .await;
+        }
+    }
 
-            let response = match send_kiro_request_with_retry(auth, &payload).await {
-                Ok(r) => r,
-                Err(msg) => {
-                    let msg = msg;
-                    tracing::error!("Kiro API error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
+    // Handle custom providers (OpenAI-compatible and Claude Code-compatible)
+    if let Some(ref provider_key) = provider_override {
+        if provider_key.starts_with("openai-compat:") || provider_key.starts_with("claude-compat:")
+        {
+            let provider_info = match get_custom_provider_info(provider_key) {
+                Some(info) => info,
+                None => {
+                    let provider_name = provider_key.split(':').nth(1).unwrap_or("unknown");
+                    return Json(json!({
+                        "error": {
+                            "message": format!("No API key configured for custom provider '{}'. Please add an API key in settings.", provider_name),
+                            "type": "authentication_error",
+                            "code": 401
                         }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
+                    }))
+                    .into_response();
                 }
             };
 
-            clear_account_exhausted(provider, account_id);
+            let provider_name = provider_key.split(':').nth(1).unwrap_or("custom");
 
-            if is_stream {
-                let upstream = kiro::stream_kiro_to_openai(
-                    response,
-                    model.clone(),
-                    chat_request.get("messages").cloned(),
-                    chat_request.get("tools").cloned(),
-                );
-                let stream = upstream.filter_map(|chunk| async move {
-                    match chunk {
-                        Ok(data) => strip_sse_data_line(&data).filter(|p| p != "[DONE]"),
-                        Err(_) => None,
-                    }
-                });
-                let stream = stream.map(|chunk| {
-                    convert_chat_stream_chunk_to_completions(&chunk)
-                        .map(|data| Ok::<Event, Infallible>(Event::default().data(data)))
-                        .unwrap_or_else(|| {
-                            Ok::<Event, Infallible>(Event::default().data("{\"choices\":[]}"))
-                        })
-                });
-                let stream = stream.chain(futures::stream::once(async {
-                    Ok(Event::default().data("[DONE]"))
-                }));
-                return with_log_info(Sse::new(stream), provider, account_id, &model);
-            }
+            // Prepare the request payload with the actual model name
+            let mut payload = raw.clone();
+            payload["model"] = json!(model);
 
-            match kiro::collect_stream_response(
-                response,
-                model.clone(),
-                chat_request.get("messages").cloned(),
-                chat_request.get("tools").cloned(),
-            )
-            .await
-            {
-                Ok(openai_response) => {
-                    let completions_response = convert_chat_response_to_completions(&openai_response);
-                    return with_log_info(Json(completions_response), provider, account_id, &model);
+            match provider_info.provider_type {
+                CustomProviderType::OpenAICompat => {
+                    return forward_openai_compatible(
+                        payload,
+                        &provider_info.base_url,
+                        &provider_info.api_key,
+                        is_stream,
+                        provider_name,
+                    )
+                    .await;
                 }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Kiro collect error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
+                CustomProviderType::ClaudeCodeCompat => {
+                    // Convert OpenAI request to Claude format, call API, convert response back
+                    let request: ChatCompletionRequest = match serde_json::from_value(raw.clone()) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            return Json(json!({
+                                "error": {
+                                    "message": format!("Invalid request: {}", e),
+                                    "type": "invalid_request_error",
+                                    "code": 400
+                                }
+                            }))
+                            .into_response();
                         }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
-                }
-            }
-        }
+                    };
 
-        let message = last_error.unwrap_or_else(|| "Kiro API error".to_string());
-        return Json(json!({
-            "error": {
-                "message": format!("Kiro API error: {}", message),
-                "type": "api_error",
-                "code": 500
-            }
-        }))
-        .into_response();
-    }
+                    let (messages, system) = claude::openai_to_claude_messages(&request.messages);
+                    let claude_payload = json!({
+                        "model": model,
+                        "messages": messages,
+                        "max_tokens": request.max_tokens.unwrap_or(4096),
+                        "temperature": request.temperature,
+                        "system": system,
+                        "stream": is_stream
+                    });
 
-    if matches!(provider_override.as_deref(), Some("kimi") | Some("glm")) {
-        let request: ChatCompletionRequest = match serde_json::from_value(chat_request.clone()) {
-            Ok(r) => r,
-            Err(e) => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("Invalid request: {}", e),
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-        };
+                    if is_stream {
+                        // Streaming: forward and convert Claude stream to OpenAI stream
+                        let base = provider_info.base_url.trim_end_matches('/').to_string();
+                        let url = format!("{}/messages", base);
+                        let client = http_client::client_for(provider_name);
+                        let send = || {
+                            client
+                                .post(&url)
+                                .header("x-api-key", &provider_info.api_key)
+                                .header("anthropic-version", "2023-06-01")
+                                .header("content-type", "application/json")
+                                .json(&claude_payload)
+                        };
+                        let response = match claude::send_messages(send, provider_name).await {
+                            Ok(r) => r,
+                            Err(claude::MessagesError::Transport(e)) => {
+                                return Json(json!({
+                                    "error": {
+                                        "message": format!("{} API error: {}", provider_name, e),
+                                        "type": "api_error",
+                                        "code": 500
+                                    }
+                                }))
+                                .into_response();
+                            }
+                            Err(claude::MessagesError::Status {
+                                status,
+                                body,
+                                overloaded,
+                            }) => return claude_error_passthrough(status, body, overloaded),
+                        };
 
-        let (token, base_url, provider_label) = match provider_override.as_deref() {
-            Some("kimi") => (get_kimi_token(&model).await, KIMI_ANTHROPIC_BASE, "Kimi"),
-            Some("glm") => (get_glm_token(&model).await, GLM_ANTHROPIC_BASE, "GLM"),
-            _ => (None, "", "Unknown"),
-        };
+                        // Convert Claude stream to OpenAI stream
+                        let events = sse::data_stream(http_client::byte_stream(response));
+                        let model_clone = model.clone();
+                        let stream = events.map(move |result| {
+                            result.map(|data| {
+                                if data == "[DONE]" {
+                                    return Bytes::new();
+                                }
+                                let mut output = String::new();
+                                if let Ok(event) = serde_json::from_str::<Value>(&data) {
+                                    // Convert Claude event to OpenAI chunk
+                                    if let Some(chunk) =
+                                        claude::claude_stream_to_openai_chunk(&event, &model_clone)
+                                    {
+                                        output.push_str(&format!(
+                                            "data: {}\n\n",
+                                            serde_json::to_string(&chunk).unwrap_or_default()
+                                        ));
+                                    }
+                                }
+                                Bytes::from(output)
+                            })
+                        });
 
-        let token = match token {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("No valid {} credentials found. Please add an API key first.", provider_label),
-                        "type": "authentication_error",
-                        "code": 401
+                        let mut resp = Response::new(Body::from_stream(stream));
+                        *resp.status_mut() = StatusCode::OK;
+                        resp.headers_mut().insert(
+                            header::CONTENT_TYPE,
+                            HeaderValue::from_static("text/event-stream"),
+                        );
+                        return resp;
                     }
-                }))
-                .into_response();
-            }
-        };
-
-        let client = ClaudeClient::new_with_base_url(token, base_url);
-        let (messages, system) = claude::openai_to_claude_messages(&request.messages);
-        let claude_request = ClaudeRequest {
-            model: model.clone(),
-            messages,
-            max_tokens: request.max_tokens.unwrap_or(4096),
-            temperature: request.temperature,
-            system,
-        };
 
-        match client.create_message(claude_request).await {
-            Ok(response) => {
-                let openai_response =
-                    claude::claude_to_openai_response(&response, &model, &request_id);
-                let completions_response = convert_chat_response_to_completions(&openai_response);
-                return Json(completions_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("{} API error: {}", provider_label, e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("{} API error: {}", provider_label, e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
-            }
-        }
-    }
+                    // Non-streaming: call API and convert response
+                    let response = forward_claude_compatible(
+                        claude_payload,
+                        &provider_info.base_url,
+                        &provider_info.api_key,
+                        false,
+                        provider_name,
+                    )
+                    .await;
 
-    if provider_override.as_deref() == Some("claude") {
-        let request: ChatCompletionRequest = match serde_json::from_value(chat_request.clone()) {
-            Ok(r) => r,
-            Err(e) => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("Invalid request: {}", e),
-                        "type": "invalid_request_error",
-                        "code": 400
-                    }
-                }))
-                .into_response();
-            }
-        };
+                    // Extract the Claude response and convert to OpenAI format
+                    let (parts, body) = response.into_parts();
+                    let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+                        Ok(b) => b,
+                        Err(e) => {
+                            return Json(json!({
+                                "error": {
+                                    "message": format!("Failed to read response: {}", e),
+                                    "type": "api_error",
+                                    "code": 500
+                                }
+                            }))
+                            .into_response();
+                        }
+                    };
 
-        let token = match get_claude_token(&model).await {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Claude credentials found. Please login with Anthropic first.",
-                        "type": "authentication_error",
-                        "code": 401
+                    if !parts.status.is_success() {
+                        let mut resp = Response::new(Body::from(body_bytes));
+                        *resp.status_mut() = parts.status;
+                        resp.headers_mut().insert(
+                            header::CONTENT_TYPE,
+                            HeaderValue::from_static("application/json"),
+                        );
+                        return resp;
                     }
-                }))
-                .into_response();
-            }
-        };
 
-        let client = ClaudeClient::new(token);
-        let (messages, system) = claude::openai_to_claude_messages(&request.messages);
-        let claude_request = ClaudeRequest {
-            model: model.clone(),
-            messages,
-            max_tokens: request.max_tokens.unwrap_or(4096),
-            temperature: request.temperature,
-            system,
-        };
+                    let claude_response: Value = match serde_json::from_slice(&body_bytes) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            return Json(json!({
+                                "error": {
+                                    "message": format!("Failed to parse response: {}", e),
+                                    "type": "api_error",
+                                    "code": 500
+                                }
+                            }))
+                            .into_response();
+                        }
+                    };
 
-        match client.create_message(claude_request).await {
-            Ok(response) => {
-                let openai_response =
-                    claude::claude_to_openai_response(&response, &model, &request_id);
-                let completions_response = convert_chat_response_to_completions(&openai_response);
-                return Json(completions_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("Claude API error: {}", e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("Claude API error: {}", e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
+                    let openai_response = claude::claude_value_to_openai_response(
+                        &claude_response,
+                        &model,
+                        &request_id,
+                    );
+                    return Json(openai_response).into_response();
+                }
             }
         }
     }
 
     Json(json!({
         "error": {
-            "message": "Unsupported provider. Use a supported provider prefix (gemini/..., claude/..., codex/..., antigravity/..., kimi/..., glm/..., kiro/...).",
+            "message": "Unsupported provider. Use a supported provider prefix (gemini/..., claude/..., codex/..., antigravity/..., kimi/..., glm/..., kiro/..., or custom providers).",
             "type": "invalid_request_error",
             "code": 400
         }
@@ -5721,16 +5519,16 @@ pub async fn completions(State(_state): State<AppState>, Json(raw): Json<Value>)
     .into_response()
 }
 
-// Claude compatible endpoint
-pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Value>) -> Response {
+pub async fn completions(State(_state): State<AppState>, Json(raw): Json<Value>) -> Response {
     let request_id = uuid::Uuid::new_v4().to_string();
-    let raw_model = raw
+    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
+    let chat_request = convert_completions_request_to_chat(&raw);
+    let raw_model = chat_request
         .get("model")
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .to_string();
     let (provider_override, model) = parse_provider_prefix(&raw_model);
-    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
 
     // Use model router to resolve provider in aggregation mode
     let (resolved_provider, resolved_model, _fallback_providers) = if provider_override.is_some() {
@@ -5752,7 +5550,7 @@ pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Val
                 let actual_model = get_provider_model_name(&raw_model, &selected_provider);
 
                 tracing::info!(
-                    "[ModelAggregation/claude_messages] Selected provider '{}' for model '{}' (fallbacks: {:?})",
+                    "[ModelAggregation/completions] Selected provider '{}' for model '{}' (fallbacks: {:?})",
                     selected_provider,
                     actual_model,
                     remaining_fallbacks
@@ -5778,635 +5576,200 @@ pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Val
     if provider_override.is_none() {
         return Json(json!({
             "error": {
-                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
-                "type": "invalid_request_error",
-                "code": 400
-            }
-        }))
-        .into_response();
-    }
-
-    if provider_override.as_deref() == Some("claude") {
-        let token = match get_claude_token(&model).await {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Claude credentials found. Please login with Anthropic first.",
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let mut payload = raw.clone();
-        payload["model"] = json!(model);
-        if is_stream {
-            payload["stream"] = json!(true);
-        }
-
-        return forward_claude_compatible(
-            payload,
-            "https://api.anthropic.com/v1",
-            &token,
-            is_stream,
-            "Claude",
-        )
-        .await;
-    }
-
-    if matches!(provider_override.as_deref(), Some("kimi") | Some("glm")) {
-        let (token, base_url, provider_label) = match provider_override.as_deref() {
-            Some("kimi") => (get_kimi_token(&model).await, KIMI_ANTHROPIC_BASE, "Kimi"),
-            Some("glm") => (get_glm_token(&model).await, GLM_ANTHROPIC_BASE, "GLM"),
-            _ => (None, "", "Unknown"),
-        };
-
-        let token = match token {
-            Some(t) => t,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": format!("No valid {} credentials found. Please add an API key first.", provider_label),
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let mut payload = raw.clone();
-        payload["model"] = json!(model);
-        if is_stream {
-            payload["stream"] = json!(true);
-        }
-
-        return forward_claude_compatible(payload, base_url, &token, is_stream, provider_label)
-            .await;
-    }
-
-    let image_handling = match provider_override.as_deref() {
-        Some("gemini") => claude::ClaudeImageHandling::Base64Any,
-        Some("codex") => claude::ClaudeImageHandling::Base64Any,
-        Some("antigravity") => claude::ClaudeImageHandling::Base64TypeOnly,
-        Some("kiro") => claude::ClaudeImageHandling::Base64TypeOnly,
-        _ => claude::ClaudeImageHandling::Base64AndUrl,
-    };
-    let guard_thinking = provider_override.as_deref() == Some("antigravity");
-    let mut openai_raw =
-        claude::claude_request_to_openai_chat(&raw, &model, image_handling, guard_thinking);
-
-    if provider_override.as_deref() == Some("gemini") {
-        let auth = match get_gemini_auth(&model).await {
-            Some(a) => a,
-            None => {
-                return Json(json!({
-                    "error": {
-                        "message": "No valid Gemini credentials found. Please login with Google first.",
-                        "type": "authentication_error",
-                        "code": 401
-                    }
-                }))
-                .into_response();
-            }
-        };
-
-        let client = GeminiClient::new(auth.access_token);
-        let mut gemini_request = gemini::openai_to_gemini_cli_request(&openai_raw, &model);
-        if let Some(project_id) = auth.project_id {
-            gemini_request["project"] = json!(project_id);
-        }
-
-        if is_stream {
-            match client.stream_generate_content(&gemini_request).await {
-                Ok(response) => {
-                    let upstream = gemini::gemini_cli_stream_to_openai_chunks(response);
-                    let stream = openai_chunks_to_claude_events(upstream, &model);
-                    return Sse::new(stream).into_response();
-                }
-                Err(e) => {
-                    tracing::error!("Gemini API error: {}", e);
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Gemini API error: {}", e),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
-        }
-
-        match client.generate_content(&gemini_request).await {
-            Ok(response) => {
-                let openai_response =
-                    gemini::gemini_to_openai_response(&response, &model, &request_id);
-                let claude_response =
-                    claude::openai_to_claude_response(&openai_response, &model, &request_id);
-                return Json(claude_response).into_response();
-            }
-            Err(e) => {
-                tracing::error!("Gemini API error: {}", e);
-                return Json(json!({
-                    "error": {
-                        "message": format!("Gemini API error: {}", e),
-                        "type": "api_error",
-                        "code": 500
-                    }
-                }))
-                .into_response();
-            }
-        }
-    }
-
-    if provider_override.as_deref() == Some("codex") {
-        // Parse reasoning_effort from model name (e.g., "high/gpt-5-codex")
-        let (actual_model, reasoning_effort) = parse_codex_model_with_effort(&model);
-
-        let auths = get_codex_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Codex credentials found. Please login with Codex first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-
-        // Inject reasoning_effort into request if parsed from model name
-        let mut modified_openai_raw = openai_raw.clone();
-        if let Some(effort) = reasoning_effort {
-            modified_openai_raw["reasoning_effort"] = json!(effort);
-        }
-
-        let codex_request =
-            codex::openai_to_codex_request(&modified_openai_raw, &actual_model, true);
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let client = CodexClient::new(auth.access_token.clone());
-
-            if is_stream {
-                match client.stream_responses(&codex_request, true).await {
-                    Ok(response) => {
-                        clear_account_exhausted(&auth.provider, &auth.account_id);
-                        let upstream = codex::codex_stream_to_openai_chunks(
-                            response,
-                            modified_openai_raw.clone(),
-                        );
-                        let stream = openai_chunks_to_claude_events(upstream, &actual_model);
-                        return with_log_info(
-                            Sse::new(stream),
-                            &auth.provider,
-                            &auth.account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Codex API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_codex_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&auth.provider, &auth.account_id);
-                            }
-                            continue;
-                        }
-                        return Json(json!({
-                            "error": {
-                                "message": format!("Codex API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        }))
-                        .into_response();
-                    }
-                }
-            }
-
-            match client.stream_responses(&codex_request, true).await {
-                Ok(response) => {
-                    match codex::collect_non_stream_response(response, &modified_openai_raw).await {
-                        Ok(openai_response) => {
-                            clear_account_exhausted(&auth.provider, &auth.account_id);
-                            let claude_response = claude::openai_to_claude_response(
-                                &openai_response,
-                                &actual_model,
-                                &request_id,
-                            );
-                            return with_log_info(
-                                Json(claude_response),
-                                &auth.provider,
-                                &auth.account_id,
-                                &actual_model,
-                            );
-                        }
-                        Err(e) => {
-                            let msg = e.to_string();
-                            tracing::error!("Codex API error: {}", msg);
-                            last_error = Some(msg.clone());
-                            if should_rotate_codex_error(&msg) && idx + 1 < total {
-                                if should_mark_account_exhausted(&msg) {
-                                    mark_account_exhausted(&auth.provider, &auth.account_id);
-                                }
-                                continue;
-                            }
-                            return Json(json!({
-                                "error": {
-                                    "message": format!("Codex API error: {}", msg),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            }))
-                            .into_response();
-                        }
-                    }
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Codex API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_codex_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&auth.provider, &auth.account_id);
-                        }
-                        continue;
-                    }
-                    return Json(json!({
-                        "error": {
-                            "message": format!("Codex API error: {}", msg),
-                            "type": "api_error",
-                            "code": 500
-                        }
-                    }))
-                    .into_response();
-                }
-            }
-        }
-
-        return Json(json!({
-            "error": {
-                "message": format!("Codex API error: {}", last_error.unwrap_or_else(|| "unknown error".to_string())),
-                "type": "api_error",
-                "code": 500
-            }
-        }))
-        .into_response();
-    }
-
-    if provider_override.as_deref() == Some("kiro") {
-        let auths = get_kiro_auths(&model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Kiro credentials found. Please login with Kiro first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
-
-        let resolution = kiro::resolve_model(&model);
-        let conversation_id = kiro::generate_conversation_id(openai_raw.get("messages"));
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-
-        for (idx, kiro_auth) in auths.into_iter().enumerate() {
-            let auth = &kiro_auth.auth;
-            let account_id = &kiro_auth.account_id;
-            let provider = &kiro_auth.provider;
-
-            if kiro::ensure_model_cache(auth).await.is_err() {
-                last_error = Some("Failed to load Kiro models".to_string());
-                continue;
-            }
-
-            let profile_arn = if matches!(auth.auth_type, kiro::KiroAuthType::KiroDesktop) {
-                auth.profile_arn.clone()
-            } else {
-                None
-            };
-
-            let payload = match kiro::build_kiro_payload_from_openai(
-                &openai_raw,
-                &resolution.internal_id,
-                conversation_id.clone(),
-                profile_arn,
-            ) {
-                Ok(p) => p,
-                Err(e) => {
-                    return error_response(
-                        400,
-                        &format!("Invalid Kiro request: {}", e),
-                        "invalid_request_error",
-                        provider,
-                        account_id,
-                        &model,
-                    );
-                }
-            };
-
-            let response = match send_kiro_request_with_retry(auth, &payload).await {
-                Ok(r) => r,
-                Err(msg) => {
-                    let msg = msg;
-                    tracing::error!("Kiro API error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
-                        }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
-                }
-            };
-
-            clear_account_exhausted(provider, account_id);
-
-            if is_stream {
-                let upstream = kiro::stream_kiro_to_openai(
-                    response,
-                    model.clone(),
-                    openai_raw.get("messages").cloned(),
-                    openai_raw.get("tools").cloned(),
-                );
-                let stream = upstream.filter_map(|chunk| async move {
-                    match chunk {
-                        Ok(data) => strip_sse_data_line(&data),
-                        Err(_) => None,
-                    }
-                });
-                let stream = openai_chunks_to_claude_events(stream, &model);
-                return with_log_info(Sse::new(stream), provider, account_id, &model);
-            }
-
-            match kiro::collect_stream_response(
-                response,
-                model.clone(),
-                openai_raw.get("messages").cloned(),
-                openai_raw.get("tools").cloned(),
-            )
-            .await
-            {
-                Ok(openai_response) => {
-                    let claude_response =
-                        claude::openai_to_claude_response(&openai_response, &model, &request_id);
-                    return with_log_info(Json(claude_response), provider, account_id, &model);
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Kiro collect error (account {}): {}", account_id, msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_kiro_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(provider, account_id);
-                        }
-                        continue;
-                    }
-                    return error_response(500, &format!("Kiro API error: {}", msg), "api_error", provider, account_id, &model);
-                }
+                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
+                "type": "invalid_request_error",
+                "code": 400
             }
-        }
+        }))
+        .into_response();
+    }
 
-        let message = last_error.unwrap_or_else(|| "Kiro API error".to_string());
+    let provider_id = provider_override.unwrap_or_default();
+    if provider::get(&provider_id).is_none() {
         return Json(json!({
             "error": {
-                "message": format!("Kiro API error: {}", message),
-                "type": "api_error",
-                "code": 500
+                "message": "Unsupported provider. Use a supported provider prefix (gemini/..., claude/..., codex/..., antigravity/..., kimi/..., glm/..., kiro/...).",
+                "type": "invalid_request_error",
+                "code": 400
             }
         }))
         .into_response();
     }
 
-    if provider_override.as_deref() == Some("antigravity") {
-        let (actual_model, reasoning_effort) = parse_antigravity_model_with_effort(&model);
-        let model_lower = actual_model.to_lowercase();
-        let supports_thinking =
-            model_lower.contains("-thinking") || model_lower.starts_with("claude-");
-        let reasoning_as_text = !supports_thinking;
+    let (model, reasoning_effort) = match split_model_level(&provider_id, &model) {
+        Ok(split) => split,
+        Err(msg) => {
+            return error_response(
+                400,
+                &msg,
+                "invalid_request_error",
+                &provider_id,
+                "",
+                &model,
+            );
+        }
+    };
+    let mut chat_request = chat_request;
+    if let Some(effort) = reasoning_effort {
+        chat_request["reasoning_effort"] = json!(effort);
+    }
+    let response =
+        dispatch_openai_chat(&provider_id, &model, &chat_request, is_stream, &request_id).await;
+    convert_chat_response(response, ChatOutput::Completions).await
+}
+
+// Claude compatible endpoint
+pub async fn claude_messages(
+    State(_state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut raw): Json<Value>,
+) -> Response {
+    let economy_route = economy::route_request(&headers, &mut raw);
+    let upgrade = context_upgrade::upgrade_request(&mut raw);
+    let response = match race::targets(&headers, &raw) {
+        Some(targets) => race::race(raw, targets, "/v1/messages", serve_claude_messages).await,
+        None => serve_claude_messages(raw).await,
+    };
+    economy::annotate(context_upgrade::annotate(response, upgrade), economy_route)
+}
+
+async fn serve_claude_messages(raw: Value) -> Response {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let raw_model = raw
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let (provider_override, model) = parse_provider_prefix(&raw_model);
+    let is_stream = raw.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    // Use model router to resolve provider in aggregation mode
+    let (resolved_provider, resolved_model, _fallback_providers) = if provider_override.is_some() {
+        (provider_override, model, Vec::new())
+    } else {
+        use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
+        match resolve_model(&raw_model, None) {
+            ResolvedModel::Explicit { provider, model } => (Some(provider), model, Vec::new()),
+            ResolvedModel::Aggregated {
+                provider,
+                model: _,
+                fallbacks,
+            } => {
+                // Use smart provider selection that checks for recovered high-priority providers
+                let (selected_provider, remaining_fallbacks) =
+                    select_best_provider_for_aggregation(&provider, &fallbacks);
+
+                // Convert model name to the selected provider's format
+                let actual_model = get_provider_model_name(&raw_model, &selected_provider);
 
-        if let Some(ref effort) = reasoning_effort {
-            if !antigravity_level_supported(&actual_model, effort) {
-                let supported = antigravity_supported_levels(&actual_model)
-                    .map(|levels| levels.join(", "))
-                    .unwrap_or_else(|| "none".to_string());
+                tracing::info!(
+                    "[ModelAggregation/claude_messages] Selected provider '{}' for model '{}' (fallbacks: {:?})",
+                    selected_provider,
+                    actual_model,
+                    remaining_fallbacks
+                );
+                (Some(selected_provider), actual_model, remaining_fallbacks)
+            }
+            ResolvedModel::NoProvider { model } => {
                 return Json(json!({
                     "error": {
-                        "message": format!(
-                            "Thinking level '{}' is not supported by model '{}'. Supported levels: {}. Remove the level prefix to use the default behavior.",
-                            effort, actual_model, supported
-                        ),
+                        "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...'). Or enable Model Aggregation Mode in settings.",
                         "type": "invalid_request_error",
                         "code": 400
                     }
                 }))
                 .into_response();
             }
-            openai_raw["reasoning_effort"] = json!(effort);
-        }
-
-        let auths = get_antigravity_auths(&actual_model).await;
-        if auths.is_empty() {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Antigravity credentials found. Please login with Antigravity first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
         }
+    };
 
-        let mut last_error: Option<String> = None;
-        let total = auths.len();
-        for (idx, auth) in auths.into_iter().enumerate() {
-            let AntigravityAuth {
-                access_token,
-                project_id,
-                account_id,
-                provider,
-            } = auth;
-            let client = AntigravityClient::new(access_token);
-            let antigravity_request =
-                antigravity::openai_to_antigravity_request(&openai_raw, &actual_model, project_id);
+    let provider_override = resolved_provider;
+    let model = resolved_model;
 
-            if is_stream {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => {
-                        clear_account_exhausted(&provider, &account_id);
-                        let upstream = antigravity::antigravity_stream_to_openai_chunks(response);
-                        let stream = openai_chunks_to_claude_events_with_options(
-                            upstream,
-                            &actual_model,
-                            reasoning_as_text,
-                        );
-                        return with_log_info(
-                            Sse::new(stream),
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return with_log_info(
-                            Json(json!({
-                                "error": {
-                                    "message": format!("Antigravity API error: {}", msg),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            })),
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                }
+    if provider_override.is_none() {
+        return Json(json!({
+            "error": {
+                "message": "Model must include provider prefix (e.g. 'gemini/...', 'claude/...', 'codex/...', 'antigravity/...', 'kimi/...', 'glm/...', 'kiro/...').",
+                "type": "invalid_request_error",
+                "code": 400
             }
+        }))
+        .into_response();
+    }
 
-            if antigravity::should_use_stream_for_non_stream(&actual_model) {
-                match client
-                    .stream_generate_content(&antigravity_request, None)
-                    .await
-                {
-                    Ok(response) => match antigravity::collect_antigravity_stream(response).await {
-                        Ok(payload) => {
-                            clear_account_exhausted(&provider, &account_id);
-                            let openai_response = gemini::gemini_to_openai_response(
-                                &payload,
-                                &actual_model,
-                                &request_id,
-                            );
-                            let claude_response = claude::openai_to_claude_response_with_options(
-                                &openai_response,
-                                &actual_model,
-                                &request_id,
-                                reasoning_as_text,
-                            );
-                            return with_log_info(
-                                Json(claude_response),
-                                &provider,
-                                &account_id,
-                                &actual_model,
-                            );
-                        }
-                        Err(e) => {
-                            tracing::error!("Antigravity API error: {}", e);
-                            return with_log_info(
-                                Json(json!({
-                                    "error": {
-                                        "message": format!("Antigravity API error: {}", e),
-                                        "type": "api_error",
-                                        "code": 500
-                                    }
-                                })),
-                                &provider,
-                                &account_id,
-                                &actual_model,
-                            );
-                        }
-                    },
-                    Err(e) => {
-                        let msg = e.to_string();
-                        tracing::error!("Antigravity API error: {}", msg);
-                        last_error = Some(msg.clone());
-                        if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                            if should_mark_account_exhausted(&msg) {
-                                mark_account_exhausted(&provider, &account_id);
-                            }
-                            continue;
-                        }
-                        return with_log_info(
-                            Json(json!({
-                                "error": {
-                                    "message": format!("Antigravity API error: {}", msg),
-                                    "type": "api_error",
-                                    "code": 500
-                                }
-                            })),
-                            &provider,
-                            &account_id,
-                            &actual_model,
-                        );
-                    }
-                }
+    if let Some(chat_provider) = provider_override.as_deref().and_then(provider::get) {
+        let provider_id = chat_provider.id();
+        let label = chat_provider.display_name();
+        let (model, reasoning_effort) = match split_model_level(provider_id, &model) {
+            Ok(split) => split,
+            Err(msg) => {
+                return error_response(
+                    400,
+                    &msg,
+                    "invalid_request_error",
+                    provider_id,
+                    "",
+                    &model,
+                );
             }
+        };
 
-            match client.generate_content(&antigravity_request, None).await {
-                Ok(response) => {
-                    clear_account_exhausted(&provider, &account_id);
-                    let openai_response =
-                        gemini::gemini_to_openai_response(&response, &actual_model, &request_id);
-                    let claude_response = claude::openai_to_claude_response_with_options(
-                        &openai_response,
-                        &actual_model,
-                        &request_id,
-                        reasoning_as_text,
-                    );
-                    return with_log_info(
-                        Json(claude_response),
-                        &provider,
-                        &account_id,
-                        &actual_model,
-                    );
-                }
-                Err(e) => {
-                    let msg = e.to_string();
-                    tracing::error!("Antigravity API error: {}", msg);
-                    last_error = Some(msg.clone());
-                    if should_rotate_antigravity_error(&msg) && idx + 1 < total {
-                        if should_mark_account_exhausted(&msg) {
-                            mark_account_exhausted(&provider, &account_id);
+        match chat_provider.messages_mapping(&model) {
+            MessagesMapping::Native { base_url } => {
+                let mut payload = raw.clone();
+                payload["model"] = json!(model);
+                if is_stream {
+                    payload["stream"] = json!(true);
+                }
+                let response = forward_claude_rotating(
+                    payload,
+                    base_url,
+                    provider_id,
+                    &model,
+                    is_stream,
+                    label,
+                )
+                .await;
+                let Some(response) = response else {
+                    return Json(json!({
+                        "error": {
+                            "message": missing_credentials_message(provider_id, label),
+                            "type": "authentication_error",
+                            "code": 401
                         }
-                        continue;
-                    }
-                    return with_log_info(
-                        Json(json!({
-                            "error": {
-                                "message": format!("Antigravity API error: {}", msg),
-                                "type": "api_error",
-                                "code": 500
-                            }
-                        })),
-                        &provider,
-                        &account_id,
-                        &actual_model,
-                    );
+                    }))
+                    .into_response();
+                };
+                return post_process_claude_response(provider_id, response).await;
+            }
+            MessagesMapping::OpenAiChat {
+                image_handling,
+                guard_thinking,
+                reasoning_as_text,
+            } => {
+                let mut request = claude::claude_request_to_openai_chat(
+                    &raw,
+                    &model,
+                    image_handling,
+                    guard_thinking,
+                );
+                if let Some(effort) = reasoning_effort {
+                    request["reasoning_effort"] = json!(effort);
                 }
+                let response =
+                    dispatch_openai_chat(provider_id, &model, &request, is_stream, &request_id)
+                        .await;
+                return convert_chat_response(
+                    response,
+                    ChatOutput::ClaudeMessages {
+                        model: &model,
+                        request_id: &request_id,
+                        reasoning_as_text,
+                    },
+                )
+                .await;
             }
         }
-
-        let message = last_error.unwrap_or_else(|| "Antigravity API error".to_string());
-        return Json(json!({
-            "error": {
-                "message": format!("Antigravity API error: {}", message),
-                "type": "api_error",
-                "code": 500
-            }
-        }))
-        .into_response();
     }
 
     // Handle custom providers (Claude Code-compatible only for /v1/messages endpoint)
@@ -6482,7 +5845,7 @@ pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Val
             if is_stream {
                 let base = provider_info.base_url.trim_end_matches('/').to_string();
                 let url = format!("{}/chat/completions", base);
-                let client = reqwest::Client::new();
+                let client = http_client::client_for(provider_name);
                 let response = match client
                     .post(&url)
                     .header("Authorization", format!("Bearer {}", provider_info.api_key))
@@ -6506,7 +5869,7 @@ pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Val
 
                 if !response.status().is_success() {
                     let status = response.status();
-                    let body = response.bytes().await.unwrap_or_default();
+                    let body = http_client::read_body(response).await.unwrap_or_default();
                     let mut resp = Response::new(Body::from(body));
                     *resp.status_mut() = status;
                     resp.headers_mut().insert(
@@ -6517,35 +5880,9 @@ pub async fn claude_messages(State(_state): State<AppState>, Json(raw): Json<Val
                 }
 
                 // Convert OpenAI stream to Claude stream
-                let byte_stream = response.bytes_stream();
-                let upstream = byte_stream
-                    .map(|result| result.map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
-                    .filter_map(|result| async move {
-                        match result {
-                            Ok(text) => {
-                                // Parse SSE lines
-                                let mut chunks = Vec::new();
-                                for line in text.lines() {
-                                    if let Some(data) = line.strip_prefix("data: ") {
-                                        if data.trim() != "[DONE]" && !data.trim().is_empty() {
-                                            chunks.push(data.to_string());
-                                        }
-                                    }
-                                }
-                                if chunks.is_empty() {
-                                    None
-                                } else {
-                                    Some(chunks.join("\n"))
-                                }
-                            }
-                            Err(_) => None,
-                        }
-                    })
-                    .flat_map(|text| {
-                        futures::stream::iter(
-                            text.lines().map(|s| s.to_string()).collect::<Vec<_>>(),
-                        )
-                    });
+                let upstream = sse::data_stream(http_client::byte_stream(response)).filter_map(
+                    |result| async move { result.ok().filter(|data| data != "[DONE]") },
+                );
 
                 let stream = openai_chunks_to_claude_events(upstream, &model);
                 return Sse::new(stream).into_response();
@@ -6628,7 +5965,11 @@ pub async fn claude_count_tokens(
         .to_string();
     let (provider_override, model) = parse_provider_prefix(&raw_model);
 
-    if provider_override.as_deref() != Some("claude") {
+    let counter = provider_override
+        .as_deref()
+        .and_then(provider::get)
+        .filter(|p| p.capabilities().count_tokens);
+    let Some(counter) = counter else {
         return Json(json!({
             "error": {
                 "message": "Model must include claude/ prefix for Claude token counting.",
@@ -6637,58 +5978,40 @@ pub async fn claude_count_tokens(
             }
         }))
         .into_response();
-    }
-
-    let token = match get_claude_token(&model).await {
-        Some(t) => t,
-        None => {
-            return Json(json!({
-                "error": {
-                    "message": "No valid Claude credentials found. Please login with Anthropic first.",
-                    "type": "authentication_error",
-                    "code": 401
-                }
-            }))
-            .into_response();
-        }
     };
 
-    let mut payload = raw.clone();
-    payload["model"] = json!(model);
-
-    let url = "https://api.anthropic.com/v1/messages/count_tokens";
-    let client = reqwest::Client::new();
-    let response = match client
-        .post(url)
-        .header("x-api-key", token)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&payload)
-        .send()
+    let Some(account) = provider_accounts(counter.id(), &model)
         .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            return Json(json!({
-                "type": "error",
-                "error": {
-                    "type": "api_error",
-                    "message": format!("Claude API error: {}", e)
-                }
-            }))
-            .into_response();
-        }
+        .into_iter()
+        .next()
+    else {
+        return Json(json!({
+            "error": {
+                "message": missing_credentials_message(counter.id(), counter.display_name()),
+                "type": "authentication_error",
+                "code": 401
+            }
+        }))
+        .into_response();
     };
 
-    let status = response.status();
-    let body = response.bytes().await.unwrap_or_default();
-    let mut resp = Response::new(Body::from(body));
-    *resp.status_mut() = status;
-    resp.headers_mut().insert(
-        header::CONTENT_TYPE,
-        HeaderValue::from_static("application/json"),
-    );
-    resp
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let ctx = provider::ChatContext {
+        credentials: &account.credentials,
+        model: &model,
+        request_id: &request_id,
+    };
+    match counter.count_tokens(&ctx, &raw).await {
+        Ok(count) => Json(count).into_response(),
+        Err(e) => Json(json!({
+            "type": "error",
+            "error": {
+                "type": "api_error",
+                "message": format!("{} API error: {}", counter.display_name(), e)
+            }
+        }))
+        .into_response(),
+    }
 }
 
 // Gemini compatible endpoints
@@ -6991,9 +6314,7 @@ pub async fn gemini_handler(
             let alt = params.get("alt").map(|v| v.as_str());
             match client.stream_generate_content_with_alt(&payload, alt).await {
                 Ok(response) => {
-                    let stream = response
-                        .bytes_stream()
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+                    let stream = http_client::byte_stream(response);
                     let mut resp = Response::new(Body::from_stream(stream));
                     *resp.status_mut() = StatusCode::OK;
                     let content_type = if alt.unwrap_or("sse") == "sse" {
@@ -0,0 +1,313 @@
+// Shared HTTP client setup for upstream provider requests
+// reqwest is built without its compression features, so clients ask upstreams for
+// uncompressed bodies and anything that still arrives gzip/deflate/br encoded is
+// decoded here for both buffered and streaming responses.
+
+use bytes::Bytes;
+use flate2::write::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// Client builder with compression negotiation disabled
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    reqwest::Client::builder().default_headers(headers)
+}
+
+/// Default client for upstream requests
+pub fn client() -> reqwest::Client {
+    client_builder().build().unwrap_or_else(|e| {
+        tracing::warn!("Failed to build upstream HTTP client: {}", e);
+        reqwest::Client::new()
+    })
+}
+
+/// Parse configured static headers, skipping entries that are not valid HTTP headers
+fn parse_headers(configured: &BTreeMap<String, String>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in configured {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => tracing::warn!("Ignoring invalid upstream header '{}'", name),
+        }
+    }
+    headers
+}
+
+/// Static headers configured for a provider under `upstream-headers` (name matched
+/// case-insensitively, so custom provider labels work as written)
+pub fn provider_headers(provider: &str) -> HeaderMap {
+    let Some(config) = crate::config::get_config() else {
+        return HeaderMap::new();
+    };
+    config
+        .upstream_headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(provider))
+        .map(|(_, headers)| parse_headers(headers))
+        .unwrap_or_default()
+}
+
+/// Client builder that also sends a provider's configured static headers. Headers set on a
+/// request (auth, content type, ...) still take precedence over these defaults.
+pub fn client_builder_for(provider: &str) -> reqwest::ClientBuilder {
+    let mut headers = provider_headers(provider);
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    reqwest::Client::builder().default_headers(headers)
+}
+
+/// Client for requests to one provider
+pub fn client_for(provider: &str) -> reqwest::Client {
+    client_builder_for(provider).build().unwrap_or_else(|e| {
+        tracing::warn!(
+            "Failed to build upstream HTTP client for {}: {}",
+            provider,
+            e
+        );
+        client()
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Identity,
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+/// Pick the decoder from Content-Encoding, falling back to sniffing the gzip magic bytes
+/// since some upstreams compress without declaring it
+fn detect_encoding(content_encoding: Option<&str>, first_bytes: &[u8]) -> Encoding {
+    let declared = content_encoding
+        .and_then(|value| value.split(',').next_back())
+        .map(|value| value.trim().to_ascii_lowercase());
+    match declared.as_deref() {
+        Some("gzip") | Some("x-gzip") => Encoding::Gzip,
+        Some("deflate") => Encoding::Deflate,
+        Some("br") => Encoding::Brotli,
+        _ if first_bytes.starts_with(&GZIP_MAGIC) => Encoding::Gzip,
+        _ => Encoding::Identity,
+    }
+}
+
+fn content_encoding(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// Decode a complete body. Returns the input unchanged if it cannot be decoded.
+pub fn decode_body(content_encoding: Option<&str>, bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let decoded = match detect_encoding(content_encoding, bytes) {
+        Encoding::Identity => return bytes.to_vec(),
+        Encoding::Gzip => flate2::read::MultiGzDecoder::new(bytes).read_to_end(&mut out),
+        Encoding::Deflate => flate2::read::ZlibDecoder::new(bytes)
+            .read_to_end(&mut out)
+            .or_else(|_| {
+                // Some servers send raw deflate without the zlib wrapper
+                out.clear();
+                flate2::read::DeflateDecoder::new(bytes).read_to_end(&mut out)
+            }),
+        Encoding::Brotli => {
+            brotli::Decompressor::new(bytes, BROTLI_BUFFER_SIZE).read_to_end(&mut out)
+        }
+    };
+    match decoded {
+        Ok(_) => out,
+        Err(e) => {
+            tracing::warn!("Failed to decode upstream body, using it as-is: {}", e);
+            bytes.to_vec()
+        }
+    }
+}
+
+/// Read and decode a complete upstream response body
+pub async fn read_body(response: reqwest::Response) -> reqwest::Result<Vec<u8>> {
+    let encoding = content_encoding(&response);
+    let bytes = response.bytes().await?;
+    Ok(decode_body(encoding.as_deref(), &bytes))
+}
+
+enum StreamDecoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Zlib(ZlibDecoder<Vec<u8>>),
+    Deflate(DeflateDecoder<Vec<u8>>),
+    Brotli(Box<brotli::DecompressorWriter<Vec<u8>>>),
+}
+
+impl StreamDecoder {
+    fn new(encoding: Encoding, first_bytes: &[u8]) -> Option<Self> {
+        match encoding {
+            Encoding::Identity => None,
+            Encoding::Gzip => Some(Self::Gzip(GzDecoder::new(Vec::new()))),
+            // zlib streams start with a CMF byte using method 8 (deflate)
+            Encoding::Deflate if first_bytes.first().is_some_and(|b| b & 0x0f == 8) => {
+                Some(Self::Zlib(ZlibDecoder::new(Vec::new())))
+            }
+            Encoding::Deflate => Some(Self::Deflate(DeflateDecoder::new(Vec::new()))),
+            Encoding::Brotli => Some(Self::Brotli(Box::new(brotli::DecompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+            )))),
+        }
+    }
+
+    /// Feed a compressed chunk and take whatever plaintext it produced
+    fn decode(&mut self, chunk: &[u8]) -> std::io::Result<Bytes> {
+        let out = match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Zlib(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Deflate(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Brotli(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(out)))
+    }
+}
+
+/// Stream an upstream response body, decoding it incrementally when compressed
+pub fn byte_stream(response: reqwest::Response) -> BoxStream<'static, std::io::Result<Bytes>> {
+    let encoding = content_encoding(&response);
+    decode_stream(encoding, response.bytes_stream()).boxed()
+}
+
+fn decode_stream<S, E>(
+    content_encoding: Option<String>,
+    upstream: S,
+) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    async_stream::stream! {
+        let mut upstream = Box::pin(upstream);
+        let mut decoder: Option<StreamDecoder> = None;
+        let mut first = true;
+
+        while let Some(chunk) = upstream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(std::io::Error::other(e));
+                    return;
+                }
+            };
+            if first {
+                first = false;
+                let encoding = detect_encoding(content_encoding.as_deref(), &chunk);
+                decoder = StreamDecoder::new(encoding, &chunk);
+            }
+
+            match decoder.as_mut() {
+                None => yield Ok(chunk),
+                Some(decoder) => match decoder.decode(&chunk) {
+                    Ok(decoded) if decoded.is_empty() => {}
+                    Ok(decoded) => yield Ok(decoded),
+                    Err(e) => {
+                        yield Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("failed to decode upstream stream: {}", e),
+                        ));
+                        return;
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+            encoder.write_all(data).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn decodes_declared_and_sniffed_encodings() {
+        let body = br#"{"ok":true}"#;
+
+        assert_eq!(decode_body(Some("gzip"), &gzip(body)), body);
+        assert_eq!(decode_body(None, &gzip(body)), body);
+        assert_eq!(decode_body(Some("br"), &brotli(body)), body);
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(body).unwrap();
+        assert_eq!(decode_body(Some("deflate"), &zlib.finish().unwrap()), body);
+
+        assert_eq!(decode_body(None, body), body);
+    }
+
+    #[tokio::test]
+    async fn decodes_compressed_stream_split_across_chunks() {
+        let body = b"data: {\"a\":1}\n\ndata: {\"b\":2}\n\n".repeat(20);
+        let compressed = brotli(&body);
+        let chunks: Vec<Result<Bytes, std::io::Error>> = compressed
+            .chunks(7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        let decoded: Vec<u8> = decode_stream(Some("br".to_string()), futures::stream::iter(chunks))
+            .map(|chunk| chunk.unwrap().to_vec())
+            .concat()
+            .await;
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn parses_static_headers_and_skips_invalid_ones() {
+        let configured = BTreeMap::from([
+            ("OpenAI-Organization".to_string(), "org-123".to_string()),
+            ("Helicone-Property-App".to_string(), "one-proxy".to_string()),
+            ("bad header".to_string(), "x".to_string()),
+            ("X-Bad-Value".to_string(), "line\nbreak".to_string()),
+        ]);
+        let headers = parse_headers(&configured);
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["openai-organization"], "org-123");
+        assert_eq!(headers["helicone-property-app"], "one-proxy");
+    }
+}
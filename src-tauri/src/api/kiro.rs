@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use async_stream::stream;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use once_cell::sync::Lazy;
@@ -14,6 +15,15 @@ use std::time::{Duration, Instant};
 use tokio::time::timeout;
 use uuid::Uuid;
 
+use super::claude::ClaudeImageHandling;
+use super::collector::ChunkCollector;
+use super::handlers::{self, ModelInfo};
+use super::http_client;
+use super::provider::{
+    self, ChatContext, ChatProvider, Credentials, EventStream, MessagesMapping,
+    ProviderCapabilities, ProviderError,
+};
+
 const DEFAULT_REGION: &str = "us-east-1";
 const KIRO_REFRESH_URL_TEMPLATE: &str = "https://prod.{region}.auth.desktop.kiro.dev/refreshToken";
 const AWS_SSO_OIDC_URL_TEMPLATE: &str = "https://oidc.{region}.amazonaws.com/token";
@@ -21,6 +31,7 @@ const KIRO_API_HOST_TEMPLATE: &str = "https://q.{region}.amazonaws.com";
 const KIRO_Q_HOST_TEMPLATE: &str = "https://q.{region}.amazonaws.com";
 const DEFAULT_MAX_INPUT_TOKENS: i64 = 200000;
 const MODEL_CACHE_TTL_SECS: u64 = 3600;
+const MODEL_CACHE_DB_KEY: &str = "kiro";
 const TOOL_DESCRIPTION_MAX_LENGTH: usize = 10000;
 const CLAUDE_CORRECTION_FACTOR: f64 = 1.15;
 
@@ -887,7 +898,7 @@ fn is_expired(expires_at: Option<DateTime<Utc>>) -> bool {
 }
 
 pub async fn load_kiro_auth(path: &Path) -> Result<KiroAuthSnapshot> {
-    let content = std::fs::read_to_string(path)?;
+    let content = crate::auth::token_store::read_to_string(path)?;
     let json: Value = serde_json::from_str(&content)?;
 
     let access_token = json
@@ -947,7 +958,7 @@ pub async fn refresh_kiro_auth(
         .as_ref()
         .ok_or_else(|| anyhow!("Missing refresh token"))?;
     let auth_type = detect_auth_type(snapshot);
-    let client = reqwest::Client::builder()
+    let client = http_client::client_builder()
         .timeout(Duration::from_secs(30))
         .build()?;
 
@@ -1020,7 +1031,7 @@ pub async fn refresh_kiro_auth(
         }
     }
 
-    let mut json: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let mut json: Value = serde_json::from_str(&crate::auth::token_store::read_to_string(path)?)?;
     json["access_token"] = Value::String(new_snapshot.access_token.clone());
     if let Some(refresh) = &new_snapshot.refresh_token {
         json["refresh_token"] = Value::String(refresh.clone());
@@ -1032,7 +1043,7 @@ pub async fn refresh_kiro_auth(
         json["profile_arn"] = Value::String(profile.clone());
     }
     let content = serde_json::to_string_pretty(&json)?;
-    std::fs::write(path, content)?;
+    crate::auth::token_store::write(path, content);
 
     Ok(new_snapshot)
 }
@@ -1074,6 +1085,11 @@ pub async fn ensure_model_cache(auth: &KiroAuth) -> Result<()> {
         return Ok(());
     }
 
+    // 内存缓存为空时先尝试从数据库恢复（重启后避免重新请求模型列表）
+    if load_persisted_model_cache() {
+        return Ok(());
+    }
+
     let (models, fetch_ok) = match fetch_models(auth).await {
         Ok(m) => {
             tracing::info!("[Kiro] fetch_models succeeded, got {} models: {:?}", m.len(), m.iter().filter_map(|v| v.get("modelId").and_then(|id| id.as_str())).collect::<Vec<_>>());
@@ -1084,17 +1100,64 @@ pub async fn ensure_model_cache(auth: &KiroAuth) -> Result<()> {
             (FALLBACK_MODELS.clone(), false)
         }
     };
+
+    if fetch_ok {
+        if let Ok(data) = serde_json::to_string(&models) {
+            if let Err(e) = crate::db::save_model_cache(MODEL_CACHE_DB_KEY, &data) {
+                tracing::debug!("[Kiro] Failed to persist model cache: {}", e);
+            }
+        }
+    }
+
     let mut cache = MODEL_CACHE.write();
+    populate_model_cache(&mut cache, models);
+    // 只有成功时才更新 last_update，失败时不设置，下次请求会立即重试
+    if fetch_ok {
+        cache.last_update = Some(Instant::now());
+    }
+
+    Ok(())
+}
+
+/// Restore the model cache from SQLite if a fresh-enough copy exists.
+/// Returns true when the in-memory cache was populated.
+fn load_persisted_model_cache() -> bool {
+    let cached = match crate::db::get_model_cache(MODEL_CACHE_DB_KEY) {
+        Ok(Some(cached)) => cached,
+        _ => return false,
+    };
+    let age_secs = (Utc::now().timestamp() - cached.last_updated).max(0) as u64;
+    if age_secs > MODEL_CACHE_TTL_SECS {
+        return false;
+    }
+    let models: Vec<Value> = match serde_json::from_str(&cached.models_data) {
+        Ok(models) => models,
+        Err(_) => return false,
+    };
+    if models.is_empty() {
+        return false;
+    }
+
+    tracing::info!(
+        "[Kiro] Restored {} models from persisted cache (age {}s)",
+        models.len(),
+        age_secs
+    );
+    let mut cache = MODEL_CACHE.write();
+    populate_model_cache(&mut cache, models);
+    cache.last_update = Instant::now()
+        .checked_sub(Duration::from_secs(age_secs))
+        .or_else(|| Some(Instant::now()));
+    true
+}
+
+fn populate_model_cache(cache: &mut KiroModelCache, models: Vec<Value>) {
     cache.models.clear();
     for model in models {
         if let Some(model_id) = model.get("modelId").and_then(|v| v.as_str()) {
             cache.models.insert(model_id.to_string(), model);
         }
     }
-    // 只有成功时才更新 last_update，失败时不设置，下次请求会立即重试
-    if fetch_ok {
-        cache.last_update = Some(Instant::now());
-    }
 
     for (display, internal) in HIDDEN_MODELS.iter() {
         if !cache.models.contains_key(display) {
@@ -1129,12 +1192,23 @@ pub async fn ensure_model_cache(auth: &KiroAuth) -> Result<()> {
             );
         }
     }
+}
 
+/// Drop both the in-memory and persisted model cache; the next request re-fetches.
+pub fn invalidate_model_cache() -> Result<()> {
+    {
+        let mut cache = MODEL_CACHE.write();
+        cache.models.clear();
+        cache.last_update = None;
+    }
+    super::models_cache::invalidate();
+    crate::db::delete_model_cache(MODEL_CACHE_DB_KEY)?;
+    tracing::info!("[Kiro] Model cache invalidated");
     Ok(())
 }
 
 async fn fetch_models(auth: &KiroAuth) -> Result<Vec<Value>> {
-    let client = reqwest::Client::builder()
+    let client = http_client::client_builder_for("kiro")
         .timeout(Duration::from_secs(30))
         .build()?;
     let url = format!("{}/ListAvailableModels", get_q_host(&auth.region));
@@ -2422,8 +2496,42 @@ pub async fn send_kiro_request(
     Ok(response)
 }
 
+/// Send Kiro request with retry on 500 server errors (max 2 retries, 1s delay)
+pub async fn send_kiro_request_with_retry(
+    auth: &KiroAuth,
+    payload: &Value,
+) -> Result<reqwest::Response, String> {
+    const MAX_RETRIES: u32 = 10;
+    let mut last_err = String::new();
+    for attempt in 0..=MAX_RETRIES {
+        match send_kiro_request(auth, payload, true).await {
+            Ok(r) => return Ok(r),
+            Err(e) => {
+                let msg = e.to_string();
+                let is_server_error = msg.contains("500")
+                    || msg.to_lowercase().contains("internal server error")
+                    || msg.to_lowercase().contains("service unavailable")
+                    || msg.contains("503");
+                last_err = msg.clone();
+                if is_server_error && attempt < MAX_RETRIES {
+                    tracing::warn!(
+                        "Kiro server error (attempt {}/{}), retrying in 1s: {}",
+                        attempt + 1,
+                        MAX_RETRIES,
+                        msg
+                    );
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+                return Err(last_err);
+            }
+        }
+    }
+    Err(last_err)
+}
+
 fn build_client() -> Result<reqwest::Client> {
-    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(300));
+    let mut builder = http_client::client_builder_for("kiro").timeout(Duration::from_secs(300));
     if let Some(config) = crate::config::get_config() {
         if !config.proxy_url.trim().is_empty() {
             builder = builder.proxy(reqwest::Proxy::all(config.proxy_url)?);
@@ -2450,7 +2558,7 @@ pub fn stream_kiro_to_openai(
 
         let mut parser = AwsEventStreamParser::default();
         let mut thinking_parser = if *FAKE_REASONING_ENABLED { Some(ThinkingParser::new()) } else { None };
-        let mut byte_stream = response.bytes_stream();
+        let mut byte_stream = http_client::byte_stream(response);
 
         let first_chunk_result = timeout(*FIRST_TOKEN_TIMEOUT, byte_stream.next()).await;
         let first_bytes = match first_chunk_result {
@@ -2765,87 +2873,15 @@ pub async fn collect_stream_response(
     request_messages: Option<Value>,
     request_tools: Option<Value>,
 ) -> Result<Value> {
-    let mut full_content = String::new();
-    let mut full_reasoning = String::new();
-    let mut tool_calls = Vec::new();
-    let mut final_usage: Option<Value> = None;
-    let completion_id = generate_completion_id();
-
     let stream = stream_kiro_to_openai(response, model.clone(), request_messages, request_tools);
     futures::pin_mut!(stream);
 
+    let mut collector = ChunkCollector::new();
     while let Some(chunk) = stream.next().await {
         let chunk_str = chunk.map_err(|err| anyhow!("Stream error: {:?}", err))?;
-        if !chunk_str.starts_with("data:") {
-            continue;
-        }
-        let data_str = chunk_str.trim_start_matches("data:").trim();
-        if data_str.is_empty() || data_str == "[DONE]" {
-            continue;
-        }
-        if let Ok(value) = serde_json::from_str::<Value>(data_str) {
-            let delta = value
-                .get("choices")
-                .and_then(|v| v.as_array())
-                .and_then(|v| v.first())
-                .and_then(|v| v.get("delta"))
-                .cloned()
-                .unwrap_or(Value::Null);
-            if let Some(content) = delta.get("content").and_then(|v| v.as_str()) {
-                full_content.push_str(content);
-            }
-            if let Some(reasoning) = delta.get("reasoning_content").and_then(|v| v.as_str()) {
-                full_reasoning.push_str(reasoning);
-            }
-            if let Some(calls) = delta.get("tool_calls").and_then(|v| v.as_array()) {
-                tool_calls.extend(calls.clone());
-            }
-            if let Some(usage) = value.get("usage") {
-                final_usage = Some(usage.clone());
-            }
-        }
-    }
-
-    let mut message = json!({"role": "assistant", "content": full_content});
-    if !full_reasoning.is_empty() {
-        message["reasoning_content"] = Value::String(full_reasoning);
-    }
-    if !tool_calls.is_empty() {
-        let mut cleaned = Vec::new();
-        for tc in &tool_calls {
-            let func = tc.get("function").unwrap_or(&Value::Null);
-            cleaned.push(json!({
-                "id": tc.get("id").and_then(|v| v.as_str()).unwrap_or(""),
-                "type": tc.get("type").and_then(|v| v.as_str()).unwrap_or("function"),
-                "function": {
-                    "name": func.get("name").and_then(|v| v.as_str()).unwrap_or(""),
-                    "arguments": func.get("arguments").and_then(|v| v.as_str()).unwrap_or("{}")
-                }
-            }));
-        }
-        message["tool_calls"] = Value::Array(cleaned);
+        collector.push_str(&chunk_str);
     }
-
-    let finish_reason = if tool_calls.is_empty() {
-        "stop"
-    } else {
-        "tool_calls"
-    };
-    let usage = final_usage
-        .unwrap_or_else(|| json!({"prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0}));
-
-    Ok(json!({
-        "id": completion_id,
-        "object": "chat.completion",
-        "created": chrono::Utc::now().timestamp(),
-        "model": model,
-        "choices": [{
-            "index": 0,
-            "message": message,
-            "finish_reason": finish_reason
-        }],
-        "usage": usage
-    }))
+    collector.finish(&model)
 }
 
 fn generate_completion_id() -> String {
@@ -2871,3 +2907,141 @@ fn ensure_non_empty(value: String, fallback: &str) -> String {
         value
     }
 }
+
+/// Kiro (Amazon Q) behind the common provider interface
+pub struct KiroProvider;
+
+impl KiroProvider {
+    async fn send(
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let auth = match ctx.credentials {
+            Credentials::Kiro(auth) => auth,
+            Credentials::Token { .. } => {
+                return Err(ProviderError::InvalidRequest(
+                    "Kiro requests need Kiro credentials".to_string(),
+                ))
+            }
+        };
+
+        if ensure_model_cache(auth).await.is_err() {
+            return Err(ProviderError::AccountUnavailable(
+                "Failed to load Kiro models".to_string(),
+            ));
+        }
+
+        let resolution = resolve_model(ctx.model);
+        let conversation_id = generate_conversation_id(request.get("messages"));
+        let profile_arn = if matches!(auth.auth_type, KiroAuthType::KiroDesktop) {
+            auth.profile_arn.clone()
+        } else {
+            None
+        };
+        let payload = build_kiro_payload_from_openai(
+            request,
+            &resolution.internal_id,
+            conversation_id,
+            profile_arn,
+        )
+        .map_err(|e| ProviderError::InvalidRequest(format!("Invalid Kiro request: {}", e)))?;
+
+        send_kiro_request_with_retry(auth, &payload)
+            .await
+            .map_err(ProviderError::Upstream)
+    }
+}
+
+#[async_trait]
+impl ChatProvider for KiroProvider {
+    fn id(&self) -> &'static str {
+        "kiro"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Kiro"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            thinking: true,
+            count_tokens: false,
+        }
+    }
+
+    fn ignored_params(&self) -> &'static [&'static str] {
+        // Kiro payloads carry messages and tools only
+        &[
+            "temperature",
+            "top_p",
+            "max_tokens",
+            "max_completion_tokens",
+            "stop",
+            "n",
+            "response_format",
+            "seed",
+            "frequency_penalty",
+            "presence_penalty",
+            "logit_bias",
+            "logprobs",
+            "top_logprobs",
+        ]
+    }
+
+    fn messages_mapping(&self, _model: &str) -> MessagesMapping {
+        MessagesMapping::OpenAiChat {
+            image_handling: ClaudeImageHandling::Base64TypeOnly,
+            guard_thinking: false,
+            reasoning_as_text: false,
+        }
+    }
+
+    fn list_models(&self) -> Vec<ModelInfo> {
+        let created = Utc::now().timestamp();
+        let base: Vec<ModelInfo> = available_models()
+            .into_iter()
+            .map(|id| ModelInfo {
+                id,
+                object: "model".to_string(),
+                created,
+                owned_by: "anthropic".to_string(),
+            })
+            .collect();
+        handlers::build_prefixed_models(self.id(), &base)
+    }
+
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError> {
+        let response = Self::send(ctx, request).await?;
+        Ok(collect_stream_response(
+            response,
+            ctx.model.to_string(),
+            request.get("messages").cloned(),
+            request.get("tools").cloned(),
+        )
+        .await?)
+    }
+
+    async fn chat_stream(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<EventStream, ProviderError> {
+        let response = Self::send(ctx, request).await?;
+        let upstream = stream_kiro_to_openai(
+            response,
+            ctx.model.to_string(),
+            request.get("messages").cloned(),
+            request.get("tools").cloned(),
+        );
+        let chunks = upstream.filter_map(|chunk| async move {
+            match chunk {
+                Ok(data) => handlers::strip_sse_data_line(&data),
+                Err(_) => None,
+            }
+        });
+        Ok(provider::chunk_events(self.id(), chunks))
+    }
+}
@@ -1,53 +1,81 @@
 // Management API handlers
 
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::Deserialize;
 use serde_json::json;
+use std::net::SocketAddr;
 
 use super::AppState;
 use crate::config;
 
-/// Parse auth file content (supports both new and legacy formats)
-fn parse_auth_info(content: &str) -> Option<(String, Option<String>, bool)> {
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(content) {
-        let obj = json.as_object()?;
-
-        let has_access_token = obj.contains_key("access_token")
-            || obj
-                .get("token")
-                .and_then(|value| value.as_object())
-                .map(|token| token.contains_key("access_token"))
-                .unwrap_or(false);
-
-        if !has_access_token {
-            return None;
-        }
+/// Header carrying the management secret, as an alternative to `Authorization: Bearer`
+const X_MANAGEMENT_KEY: &str = "x-management-key";
+
+/// Whether a caller may use the management write endpoints: with
+/// `remote-management.secret-key` set it must present the secret, otherwise only local
+/// processes are allowed and browsers (which always send `Origin` on cross-origin writes)
+/// are turned away, so a web page cannot reach the API through CORS
+fn write_access_allowed(
+    secret: &str,
+    presented: Option<&str>,
+    peer: Option<SocketAddr>,
+    has_origin: bool,
+) -> bool {
+    let secret = secret.trim();
+    if !secret.is_empty() {
+        return presented == Some(secret);
+    }
+    peer.is_some_and(|peer| peer.ip().is_loopback()) && !has_origin
+}
 
-        let provider = obj
-            .get("type")
-            .or_else(|| obj.get("provider"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-
-        let email = obj
-            .get("email")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let enabled = obj
-            .get("enabled")
-            .and_then(|v| v.as_bool())
-            .unwrap_or_else(|| {
-                !obj.get("disabled")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false)
-            });
-
-        return Some((provider, email, enabled));
+/// Guard for management endpoints that change the server's config or state
+pub async fn require_write_access(request: Request<Body>, next: Next) -> Response {
+    let config = config::get_config().unwrap_or_default();
+    let headers = request.headers();
+    let presented = headers
+        .get(X_MANAGEMENT_KEY)
+        .or_else(|| headers.get(header::AUTHORIZATION))
+        .and_then(|v| v.to_str().ok())
+        .map(|value| value.strip_prefix("Bearer ").unwrap_or(value));
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    if write_access_allowed(
+        &config.remote_management.secret_key,
+        presented,
+        peer,
+        headers.contains_key(header::ORIGIN),
+    ) {
+        return next.run(request).await;
     }
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": "management access denied: send remote-management.secret-key, or call from this machine without an Origin header"
+        })),
+    )
+        .into_response()
+}
 
-    None
+/// Parse auth file content (any supported shape, read through its canonical form)
+fn parse_auth_info(content: &str, filename: &str) -> Option<(String, Option<String>, bool)> {
+    let json = serde_json::from_str::<serde_json::Value>(content).ok()?;
+    let json = crate::auth::schema::canonicalize(&json, filename)?;
+    let provider = json.get("type").and_then(|v| v.as_str())?.to_string();
+    let email = json
+        .get("email")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let enabled = json.get("enabled").and_then(|v| v.as_bool()) == Some(true);
+    Some((provider, email, enabled))
 }
 
 /// List all auth files
@@ -79,7 +107,9 @@ pub async fn list_auth_files(State(_state): State<AppState>) -> impl IntoRespons
 
                         // Read file to get provider and email
                         if let Ok(content) = std::fs::read_to_string(&path) {
-                            if let Some((provider, email, enabled)) = parse_auth_info(&content) {
+                            if let Some((provider, email, enabled)) =
+                                parse_auth_info(&content, name.trim_end_matches(".json"))
+                            {
                                 file_info["provider"] = json!(provider);
                                 file_info["type"] = json!(provider);
                                 file_info["enabled"] = json!(enabled);
@@ -129,6 +159,8 @@ pub async fn delete_auth_file(
                     if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
                         if name.ends_with(".json") && !name.starts_with(".") {
                             if std::fs::remove_file(&path).is_ok() {
+                                let _ =
+                                    crate::db::delete_quota_cache(name.trim_end_matches(".json"));
                                 deleted += 1;
                             }
                         }
@@ -152,7 +184,10 @@ pub async fn delete_auth_file(
     let path = auth_dir.join(name);
 
     match std::fs::remove_file(&path) {
-        Ok(_) => Json(json!({ "status": "ok" })),
+        Ok(_) => {
+            let _ = crate::db::delete_quota_cache(name.trim_end_matches(".json"));
+            Json(json!({ "status": "ok" }))
+        }
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             Json(json!({ "error": "file not found" }))
         }
@@ -221,8 +256,53 @@ pub async fn patch_auth_status(
 /// Get current configuration
 pub async fn get_config(State(_state): State<AppState>) -> impl IntoResponse {
     match config::get_config() {
-        Some(cfg) => Json(json!(cfg)),
-        None => Json(json!({})),
+        Some(cfg) => config_response(StatusCode::OK, &cfg, json!(cfg)),
+        None => Json(json!({})).into_response(),
+    }
+}
+
+/// Build a config response carrying the config version as ETag
+fn config_response(
+    status: StatusCode,
+    cfg: &config::AppConfig,
+    body: serde_json::Value,
+) -> Response {
+    let mut response = (status, Json(body)).into_response();
+    if let Ok(etag) = HeaderValue::from_str(&config::config_etag(cfg)) {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    response
+}
+
+/// Apply a merge patch honoring the If-Match header
+fn apply_config_patch(headers: &HeaderMap, patch: &serde_json::Value) -> Response {
+    let if_match = headers.get(header::IF_MATCH).and_then(|v| v.to_str().ok());
+
+    match config::patch_config(patch, if_match) {
+        Ok(cfg) => config_response(
+            StatusCode::OK,
+            &cfg,
+            json!({ "status": "ok", "config": cfg }),
+        ),
+        Err(config::ConfigPatchError::VersionMismatch { current_etag }) => {
+            let mut response = (
+                StatusCode::PRECONDITION_FAILED,
+                Json(json!({
+                    "error": "config was modified by another client; reload it and retry",
+                    "etag": current_etag,
+                })),
+            )
+                .into_response();
+            if let Ok(etag) = HeaderValue::from_str(&current_etag) {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+            response
+        }
+        Err(config::ConfigPatchError::Invalid(e)) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("failed to save config: {}", e) })),
+        )
+            .into_response(),
     }
 }
 
@@ -237,27 +317,42 @@ pub struct UpdateConfigRequest {
 /// Update configuration
 pub async fn update_config(
     State(_state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<UpdateConfigRequest>,
 ) -> impl IntoResponse {
-    let mut cfg = config::get_config().unwrap_or_default();
+    let mut patch = serde_json::Map::new();
 
     if let Some(host) = request.host {
-        cfg.host = host;
+        patch.insert("host".to_string(), json!(host));
     }
     if let Some(port) = request.port {
-        cfg.port = port;
+        patch.insert("port".to_string(), json!(port));
     }
     if let Some(auth_dir) = request.auth_dir {
-        cfg.auth_dir = auth_dir;
+        patch.insert("auth-dir".to_string(), json!(auth_dir));
     }
     if let Some(api_keys) = request.api_keys {
-        cfg.api_keys = api_keys;
+        patch.insert("api-keys".to_string(), json!(api_keys));
     }
 
-    match config::update_config(cfg) {
-        Ok(_) => Json(json!({ "status": "ok" })),
-        Err(e) => Json(json!({ "error": format!("failed to save config: {}", e) })),
+    apply_config_patch(&headers, &serde_json::Value::Object(patch))
+}
+
+/// Partially update configuration with a JSON merge patch (RFC 7396)
+/// Send the ETag from GET /management/config as If-Match to avoid overwriting concurrent edits
+pub async fn patch_config(
+    State(_state): State<AppState>,
+    headers: HeaderMap,
+    Json(patch): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    if !patch.is_object() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "merge patch must be a JSON object" })),
+        )
+            .into_response();
     }
+    apply_config_patch(&headers, &patch)
 }
 
 /// Get server status
@@ -273,18 +368,46 @@ pub async fn get_server_status(State(_state): State<AppState>) -> impl IntoRespo
         status["host"] = json!(cfg.host);
         status["port"] = json!(cfg.port);
         if running {
-            let addr = if cfg.host.is_empty() {
-                format!("0.0.0.0:{}", cfg.port)
-            } else {
-                format!("{}:{}", cfg.host, cfg.port)
-            };
-            status["address"] = json!(addr);
+            if let Ok(addr) = config::bind_addr(&cfg) {
+                status["address"] = json!(addr.to_string());
+            }
         }
     }
 
     Json(status)
 }
 
+/// Re-read config.yaml and the auth files, like SIGHUP
+pub async fn reload_config(State(_state): State<AppState>) -> impl IntoResponse {
+    match crate::reload::reload() {
+        Ok(result) => Json(json!({ "status": "ok", "reload": result })).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("failed to reload config: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoutingPauseRequest {
+    pub paused: bool,
+}
+
+/// Whether routing is paused
+pub async fn get_routing_pause(State(_state): State<AppState>) -> impl IntoResponse {
+    Json(json!({ "paused": crate::api::is_routing_paused() }))
+}
+
+/// Pause or resume routing (panic switch for runaway clients)
+pub async fn set_routing_pause(
+    State(state): State<AppState>,
+    Json(request): Json<RoutingPauseRequest>,
+) -> impl IntoResponse {
+    crate::commands::apply_routing_paused(&state.app_handle, request.paused);
+    Json(json!({ "paused": crate::api::is_routing_paused() }))
+}
+
 /// List accounts (same as Tauri command)
 pub async fn list_accounts(State(_state): State<AppState>) -> impl IntoResponse {
     match crate::auth::list_accounts().await {
@@ -307,7 +430,9 @@ pub async fn get_auth_summary(State(_state): State<AppState>) -> impl IntoRespon
             if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
                 if name.ends_with(".json") && !name.starts_with(".") {
                     if let Ok(content) = std::fs::read_to_string(&path) {
-                        if let Some((provider, _email, is_enabled)) = parse_auth_info(&content) {
+                        if let Some((provider, _email, is_enabled)) =
+                            parse_auth_info(&content, name.trim_end_matches(".json"))
+                        {
                             total += 1;
                             if is_enabled {
                                 enabled += 1;
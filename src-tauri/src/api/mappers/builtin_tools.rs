@@ -0,0 +1,165 @@
+// Gemini built-in tools for OpenAI-format requests
+// OpenAI clients ask for web search with `web_search_options`, a `{"type": "web_search"}` style
+// tool or a function named web_search, while Gemini expects a `googleSearch` tool. The
+// grounding metadata of the answer is returned in a `grounding` extension field of the message
+// (or stream delta) with the search queries, the sources and the text spans they support.
+// Likewise `{"type": "code_execution"}` (or OpenAI's `code_interpreter`) enables Gemini's
+// `codeExecution`, and the code it ran and the results come back in a `code_execution` field.
+
+use serde_json::{json, Value};
+
+use super::common_utils::detects_networking_tool;
+
+/// Whether an OpenAI tool entry asks for web search
+pub fn is_search_tool(tool: &Value) -> bool {
+    let kind = tool.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    kind.starts_with("web_search") || detects_networking_tool(&Some(vec![tool.clone()]))
+}
+
+/// Whether the request enables search outside `tools` (OpenAI `web_search_options`)
+pub fn wants_search(request: &Value) -> bool {
+    request
+        .get("web_search_options")
+        .is_some_and(|options| !options.is_null())
+}
+
+/// `grounding` extension field for a candidate's `groundingMetadata`, `None` when it holds
+/// neither queries nor sources
+pub fn grounding_extension(metadata: &Value) -> Option<Value> {
+    let search_queries: Vec<Value> = metadata
+        .get("webSearchQueries")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let sources: Vec<Value> = metadata
+        .get("groundingChunks")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .map(|chunk| {
+            let source = chunk
+                .get("web")
+                .or_else(|| chunk.get("retrievedContext"))
+                .unwrap_or(chunk);
+            json!({
+                "title": source.get("title").cloned().unwrap_or(Value::Null),
+                "url": source.get("uri").cloned().unwrap_or(Value::Null),
+            })
+        })
+        .collect();
+    if search_queries.is_empty() && sources.is_empty() {
+        return None;
+    }
+
+    let citations: Vec<Value> = metadata
+        .get("groundingSupports")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .map(|support| {
+            let segment = support.get("segment").cloned().unwrap_or_else(|| json!({}));
+            json!({
+                "text": segment.get("text").cloned().unwrap_or(Value::Null),
+                "start_index": segment.get("startIndex").cloned().unwrap_or(json!(0)),
+                "end_index": segment.get("endIndex").cloned().unwrap_or(Value::Null),
+                "source_indices": support
+                    .get("groundingChunkIndices")
+                    .cloned()
+                    .unwrap_or_else(|| json!([])),
+            })
+        })
+        .collect();
+
+    Some(json!({
+        "search_queries": search_queries,
+        "sources": sources,
+        "citations": citations,
+    }))
+}
+
+/// Whether an OpenAI tool entry asks for backend-side code execution
+pub fn is_code_execution_tool(tool: &Value) -> bool {
+    matches!(
+        tool.get("type").and_then(|v| v.as_str()),
+        Some("code_execution" | "code_interpreter")
+    )
+}
+
+/// Structured form of an `executableCode` or `codeExecutionResult` part, for the
+/// `code_execution` extension field
+pub fn code_execution_part(part: &Value) -> Option<Value> {
+    if let Some(code) = part
+        .get("executableCode")
+        .or_else(|| part.get("executable_code"))
+    {
+        return Some(json!({
+            "type": "executable_code",
+            "language": code.get("language").cloned().unwrap_or(Value::Null),
+            "code": code.get("code").cloned().unwrap_or(Value::Null),
+        }));
+    }
+    let result = part
+        .get("codeExecutionResult")
+        .or_else(|| part.get("code_execution_result"))?;
+    Some(json!({
+        "type": "code_execution_result",
+        "outcome": result.get("outcome").cloned().unwrap_or(Value::Null),
+        "output": result.get("output").cloned().unwrap_or(Value::Null),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_openai_search_tools() {
+        assert!(is_search_tool(&json!({"type": "web_search_preview"})));
+        assert!(is_search_tool(
+            &json!({"type": "function", "function": {"name": "web_search"}})
+        ));
+        assert!(!is_search_tool(
+            &json!({"type": "function", "function": {"name": "read_file"}})
+        ));
+        assert!(wants_search(&json!({"web_search_options": {}})));
+        assert!(!wants_search(&json!({"web_search_options": null})));
+    }
+
+    #[test]
+    fn maps_grounding_metadata() {
+        let metadata = json!({
+            "webSearchQueries": ["rust 2024 edition"],
+            "groundingChunks": [{"web": {"uri": "https://blog.rust-lang.org", "title": "rust-lang.org"}}],
+            "groundingSupports": [{
+                "segment": {"startIndex": 0, "endIndex": 20, "text": "Rust 2024 shipped..."},
+                "groundingChunkIndices": [0]
+            }]
+        });
+        let grounding = grounding_extension(&metadata).unwrap();
+        assert_eq!(grounding["search_queries"][0], "rust 2024 edition");
+        assert_eq!(grounding["sources"][0]["url"], "https://blog.rust-lang.org");
+        assert_eq!(grounding["citations"][0]["end_index"], 20);
+        assert_eq!(grounding["citations"][0]["source_indices"], json!([0]));
+        assert!(grounding_extension(&json!({"searchEntryPoint": {}})).is_none());
+    }
+
+    #[test]
+    fn maps_code_execution_parts() {
+        assert!(is_code_execution_tool(&json!({"type": "code_interpreter"})));
+        assert!(!is_code_execution_tool(&json!({"type": "function"})));
+
+        let code = code_execution_part(
+            &json!({"executableCode": {"language": "PYTHON", "code": "print(1 + 1)"}}),
+        )
+        .unwrap();
+        assert_eq!(code["type"], "executable_code");
+        assert_eq!(code["code"], "print(1 + 1)");
+
+        let result = code_execution_part(
+            &json!({"codeExecutionResult": {"outcome": "OUTCOME_OK", "output": "2\n"}}),
+        )
+        .unwrap();
+        assert_eq!(result["outcome"], "OUTCOME_OK");
+        assert!(code_execution_part(&json!({"text": "hi"})).is_none());
+    }
+}
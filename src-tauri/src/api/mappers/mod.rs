@@ -1,7 +1,9 @@
 // Mappers 模块 - 协议转换器
 // 负责不同协议之间的格式转换
 
+pub mod builtin_tools;
 pub mod common_utils;
 pub mod error_classifier;
 pub mod gemini;
 pub mod openai;
+pub mod post_process;
@@ -40,6 +40,9 @@ pub struct OpenAIRequest {
     // [NEW] Thinking/Extended Thinking 支持 (兼容 Anthropic/Claude 协议)
     #[serde(default)]
     pub thinking: Option<ThinkingConfig>,
+    /// OpenAI web search switch, mapped to Gemini grounding
+    #[serde(default)]
+    pub web_search_options: Option<Value>,
 }
 
 /// Thinking 配置 (兼容 Anthropic 和 OpenAI 扩展协议)
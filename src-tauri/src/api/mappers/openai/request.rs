@@ -9,10 +9,16 @@ pub fn transform_openai_request(
     mapped_model: &str,
 ) -> Value {
     // 将 OpenAI 工具转为 Value 数组以便探测
-    let tools_val = request
+    let mut tools_val = request
         .tools
         .as_ref()
         .map(|list| list.iter().map(|v| v.clone()).collect::<Vec<_>>());
+    // `web_search_options` asks for search without a tool entry
+    if request.web_search_options.is_some() {
+        tools_val
+            .get_or_insert_with(Vec::new)
+            .push(json!({ "type": "web_search" }));
+    }
 
     let mapped_model_lower = mapped_model.to_lowercase();
 
@@ -63,6 +69,10 @@ pub fn transform_openai_request(
     let mut actual_include_thinking = is_thinking_model || user_enabled_thinking;
     if is_claude_thinking && has_incompatible_assistant_history && global_thought_sig.is_none() {
         tracing::warn!("[OpenAI-Thinking] Incompatible assistant history detected for Claude thinking model without global signature. Disabling thinking for this request to avoid 400 error.");
+        crate::api::compat_warnings::warn(
+            "thinking was disabled because earlier assistant messages have no reasoning_content"
+                .to_string(),
+        );
         actual_include_thinking = false;
     }
 
@@ -546,7 +556,14 @@ pub fn transform_openai_request(
     // 4. Handle Tools (Merged Cleaning)
     if let Some(tools) = &request.tools {
         let mut function_declarations: Vec<Value> = Vec::new();
+        let mut code_execution = false;
         for tool in tools.iter() {
+            // 后端代码执行 (code_execution / code_interpreter) 映射为 Gemini 内置 codeExecution
+            if crate::api::mappers::builtin_tools::is_code_execution_tool(tool) {
+                code_execution = true;
+                continue;
+            }
+
             let mut gemini_func = if let Some(func) = tool.get("function") {
                 func.clone()
             } else {
@@ -637,8 +654,15 @@ pub fn transform_openai_request(
             function_declarations.push(gemini_func);
         }
 
+        let mut gemini_tools: Vec<Value> = Vec::new();
         if !function_declarations.is_empty() {
-            inner_request["tools"] = json!([{ "functionDeclarations": function_declarations }]);
+            gemini_tools.push(json!({ "functionDeclarations": function_declarations }));
+        }
+        if code_execution {
+            gemini_tools.push(json!({ "codeExecution": {} }));
+        }
+        if !gemini_tools.is_empty() {
+            inner_request["tools"] = json!(gemini_tools);
         }
     }
 
@@ -753,6 +777,7 @@ mod tests {
             response_format: None,
             tools: None,
             tool_choice: None,
+            web_search_options: None,
             parallel_tool_calls: None,
             instructions: None,
             input: None,
@@ -793,6 +818,7 @@ mod tests {
             response_format: None,
             tools: None,
             tool_choice: None,
+            web_search_options: None,
             parallel_tool_calls: None,
             instructions: None,
             input: None,
@@ -842,6 +868,7 @@ mod tests {
             response_format: None,
             tools: None,
             tool_choice: None,
+            web_search_options: None,
             parallel_tool_calls: None,
             instructions: None,
             input: None,
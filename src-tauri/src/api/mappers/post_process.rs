@@ -0,0 +1,286 @@
+// Response post-processing
+// Some backends wrap answers in vendor banners or leak chat-template control tokens. The
+// `response-post-processors` config lists per-provider cleanup that is applied to the text of
+// OpenAI and Claude responses and stream chunks before they reach the client. In streams,
+// patterns anchored at the start (`^`, `\A`) only apply to the first text delta, so they
+// don't strip the start of every chunk.
+
+use futures::{Stream, StreamExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::config::ResponsePostProcessConfig;
+
+/// Chat-template control tokens, e.g. "<|im_end|>", "<|eot_id|>", "<end_of_turn>"
+static CONTROL_TOKENS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<\|[A-Za-z0-9_]+\|>|</?(?:start_of_turn|end_of_turn|bos|eos)>").unwrap()
+});
+
+/// Compiled cleanup rules of one provider
+#[derive(Debug, Clone)]
+pub struct PostProcessor {
+    trim_control_tokens: bool,
+    strip_patterns: Vec<Regex>,
+}
+
+impl PostProcessor {
+    /// Build from config, skipping patterns that fail to compile
+    pub fn from_config(config: &ResponsePostProcessConfig) -> Option<Self> {
+        let strip_patterns: Vec<Regex> = config
+            .strip_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid strip pattern '{}': {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        if !config.trim_control_tokens && strip_patterns.is_empty() {
+            return None;
+        }
+        Some(Self {
+            trim_control_tokens: config.trim_control_tokens,
+            strip_patterns,
+        })
+    }
+
+    /// Post-processor configured for a provider, if any
+    pub fn for_provider(provider: &str) -> Option<Self> {
+        let config = crate::config::get_config()?;
+        Self::from_config(config.response_post_processors.get(provider)?)
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.apply_at(text, true)
+    }
+
+    /// Clean text that is `at_start` of the answer or continues it, skipping anchored patterns
+    /// in the latter case
+    fn apply_at(&self, text: &str, at_start: bool) -> String {
+        let mut text = text.to_string();
+        if self.trim_control_tokens {
+            text = CONTROL_TOKENS.replace_all(&text, "").into_owned();
+        }
+        for pattern in &self.strip_patterns {
+            if at_start || !is_anchored(pattern) {
+                text = pattern.replace_all(&text, "").into_owned();
+            }
+        }
+        text
+    }
+
+    /// Clean a string field; returns whether it held text and was changed
+    fn apply_field(&self, object: &mut Value, field: &str, at_start: bool) -> bool {
+        match object.get_mut(field) {
+            Some(Value::String(text)) => {
+                let cleaned = self.apply_at(text, at_start);
+                let changed = cleaned != *text;
+                *text = cleaned;
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    /// Clean `choices[].message.content` of a `chat.completion`
+    pub fn process_response(&self, response: &mut Value) {
+        if let Some(choices) = response.get_mut("choices").and_then(|v| v.as_array_mut()) {
+            for choice in choices {
+                if let Some(message) = choice.get_mut("message") {
+                    self.apply_field(message, "content", true);
+                }
+            }
+        }
+    }
+
+    /// Clean `choices[].delta.content` of a `chat.completion.chunk`
+    /// Patterns are matched within a single delta, so text split across chunks is not stripped.
+    /// `started` holds the choice indexes that already streamed text.
+    pub fn process_chunk(&self, chunk: &str, started: &mut HashSet<u64>) -> String {
+        let Ok(mut value) = serde_json::from_str::<Value>(chunk) else {
+            return chunk.to_string();
+        };
+        let mut changed = false;
+        if let Some(choices) = value.get_mut("choices").and_then(|v| v.as_array_mut()) {
+            for choice in choices {
+                let index = choice.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                if let Some(delta) = choice.get_mut("delta") {
+                    if !has_text(delta, "content") {
+                        continue;
+                    }
+                    changed |= self.apply_field(delta, "content", !started.contains(&index));
+                    started.insert(index);
+                }
+            }
+        }
+        if changed {
+            value.to_string()
+        } else {
+            chunk.to_string()
+        }
+    }
+
+    /// Clean the text blocks of a Messages API response
+    pub fn process_claude_response(&self, response: &mut Value) {
+        let mut at_start = true;
+        if let Some(blocks) = response.get_mut("content").and_then(|v| v.as_array_mut()) {
+            for block in blocks {
+                if block.get("type").and_then(|v| v.as_str()) == Some("text") {
+                    self.apply_field(block, "text", at_start);
+                    at_start = false;
+                }
+            }
+        }
+    }
+
+    /// Clean the `text_delta` of a Messages API `content_block_delta` event
+    /// `started` is set once the answer streamed text.
+    pub fn process_claude_event(&self, data: &str, started: &mut bool) -> String {
+        let Ok(mut value) = serde_json::from_str::<Value>(data) else {
+            return data.to_string();
+        };
+        let Some(delta) = value
+            .get_mut("delta")
+            .filter(|delta| delta.get("type").and_then(|v| v.as_str()) == Some("text_delta"))
+        else {
+            return data.to_string();
+        };
+        if !has_text(delta, "text") {
+            return data.to_string();
+        }
+        let changed = self.apply_field(delta, "text", !*started);
+        *started = true;
+        if changed {
+            value.to_string()
+        } else {
+            data.to_string()
+        }
+    }
+}
+
+/// Whether a pattern only matches at the start of the text
+fn is_anchored(pattern: &Regex) -> bool {
+    let pattern = pattern.as_str();
+    pattern.starts_with('^') || pattern.starts_with("\\A")
+}
+
+fn has_text(object: &Value, field: &str) -> bool {
+    object
+        .get(field)
+        .and_then(|v| v.as_str())
+        .is_some_and(|text| !text.is_empty())
+}
+
+/// Apply a provider's post-processing to a `chat.completion`
+pub fn process_openai_response(provider: &str, response: &mut Value) {
+    if let Some(processor) = PostProcessor::for_provider(provider) {
+        processor.process_response(response);
+    }
+}
+
+/// Apply a provider's post-processing to a stream of `chat.completion.chunk` payloads
+pub fn process_openai_chunks<S>(provider: &str, chunks: S) -> impl Stream<Item = String>
+where
+    S: Stream<Item = String>,
+{
+    let processor = PostProcessor::for_provider(provider);
+    let mut started = HashSet::new();
+    chunks.map(move |chunk| match &processor {
+        Some(processor) => processor.process_chunk(&chunk, &mut started),
+        None => chunk,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn processor(trim_control_tokens: bool, strip_patterns: &[&str]) -> Option<PostProcessor> {
+        PostProcessor::from_config(&ResponsePostProcessConfig {
+            trim_control_tokens,
+            strip_patterns: strip_patterns.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn strips_patterns_and_control_tokens() {
+        let processor = processor(true, &[r"^\[Vendor banner\]\s*", "("]).unwrap();
+        assert_eq!(
+            processor.apply("[Vendor banner] Hello<|im_end|><end_of_turn>"),
+            "Hello"
+        );
+
+        let mut response = json!({
+            "choices": [{"message": {"role": "assistant", "content": "Hi<|eot_id|>"}}]
+        });
+        processor.process_response(&mut response);
+        assert_eq!(response["choices"][0]["message"]["content"], "Hi");
+    }
+
+    #[test]
+    fn leaves_unmatched_chunks_untouched() {
+        let trim_only = processor(true, &[]).unwrap();
+        let mut started = HashSet::new();
+        let chunk = r#"{"choices":[{"index":0,"delta":{"content":"plain"}}]}"#;
+        assert_eq!(trim_only.process_chunk(chunk, &mut started), chunk);
+        assert_eq!(
+            trim_only.process_chunk(
+                r#"{"choices":[{"delta":{"content":"a<|end|>"}}]}"#,
+                &mut started
+            ),
+            r#"{"choices":[{"delta":{"content":"a"}}]}"#
+        );
+        assert!(processor(false, &[]).is_none());
+    }
+
+    #[test]
+    fn anchored_patterns_only_strip_the_start_of_a_stream() {
+        let processor = processor(false, &[r"^\s*Note:\s*", r"\[ad\]"]).unwrap();
+        let chunk = |content: &str| {
+            json!({"choices": [{"index": 0, "delta": {"content": content}}]}).to_string()
+        };
+        let mut started = HashSet::new();
+        let deltas: Vec<String> = [
+            json!({"choices": [{"index": 0, "delta": {"role": "assistant", "content": ""}}]})
+                .to_string(),
+            chunk("Note: Hi"),
+            chunk(" Note: this[ad] stays"),
+        ]
+        .iter()
+        .map(|data| processor.process_chunk(data, &mut started))
+        .collect();
+        assert_eq!(deltas[1], chunk("Hi"));
+        assert_eq!(deltas[2], chunk(" Note: this stays"));
+
+        let mut started = false;
+        let event = |text: &str| {
+            json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": {"type": "text_delta", "text": text}
+            })
+            .to_string()
+        };
+        assert_eq!(
+            processor.process_claude_event(&event("Note: Hi"), &mut started),
+            event("Hi")
+        );
+        assert_eq!(
+            processor.process_claude_event(&event("\nNote: x"), &mut started),
+            event("\nNote: x")
+        );
+
+        let mut response = json!({"content": [
+            {"type": "thinking", "thinking": "Note: plan"},
+            {"type": "text", "text": "Note: Hello[ad]"}
+        ]});
+        processor.process_claude_response(&mut response);
+        assert_eq!(response["content"][0]["thinking"], "Note: plan");
+        assert_eq!(response["content"][1]["text"], "Hello");
+    }
+}
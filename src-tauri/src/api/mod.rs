@@ -3,40 +3,71 @@
 use anyhow::Result;
 use axum::{
     body::{Body, Bytes},
-    http::{header, Method, Request, StatusCode},
+    http::{header, HeaderValue, Method, Request, StatusCode},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post, put},
     Router,
 };
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
 use http_body_util::BodyExt;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use serde_json::Value;
+use std::future::IntoFuture;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::oneshot;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::db::TemporaryKeyStatus;
+
 pub mod antigravity;
 pub mod claude;
 pub mod codex;
+mod collector;
 pub mod common;
+mod compat_warnings;
 pub mod config;
+mod context_upgrade;
+mod economy;
+pub mod events;
+mod fan_out;
 pub mod gemini;
 mod handlers;
+pub mod http_client;
 pub mod kiro;
 pub mod management;
 pub mod mappers;
 mod mime_types;
 pub mod model_router;
+pub mod models_cache;
+mod moderation;
+mod pool_usage;
+pub mod priority_schedule;
+pub mod provider;
+pub mod provider_health;
+mod race;
+mod request_tags;
 mod schema_cleaner;
 pub mod signature_cache;
+pub mod smoke_test;
+pub mod sse;
+pub mod stats;
 pub mod streaming;
+mod structured_output;
+pub mod tenant;
+mod translation;
+pub mod usage;
+pub mod warmup;
 
 pub use handlers::{get_codex_routing_statuses, CodexRoutingStatusSnapshot};
 
 static SERVER_HANDLE: OnceCell<RwLock<Option<oneshot::Sender<()>>>> = OnceCell::new();
 
+/// Panic switch: while set, protected endpoints answer 503 and nothing is sent upstream;
+/// management endpoints and the UI keep working. Not persisted, a restart resumes routing.
+static ROUTING_PAUSED: AtomicBool = AtomicBool::new(false);
+
 #[derive(Clone)]
 pub struct AppState {
     pub app_handle: tauri::AppHandle,
@@ -46,6 +77,7 @@ pub struct AppState {
 fn protocol_from_path(path: &str) -> Option<String> {
     if path.starts_with("/v1/chat/completions")
         || path.starts_with("/v1/completions")
+        || path.starts_with("/v1/moderations")
         || path.starts_with("/v1/models")
     {
         Some("openai".to_string())
@@ -70,6 +102,24 @@ pub const X_ONEPROXY_PROVIDER: &str = "x-oneproxy-provider";
 /// This header will be stripped before sending response to client
 pub const X_ONEPROXY_MODEL: &str = "x-oneproxy-model";
 
+/// Internal header name for passing the name of the client's API key from the auth middleware
+/// to logging middleware (e.g. "api-key-1a2b3c4d", never the key itself)
+/// This header will be stripped before sending response to client
+const X_ONEPROXY_KEY_NAME: &str = "x-oneproxy-key-name";
+
+/// Name of the API key a request was authenticated with, set as a request extension by the
+/// auth middleware for work done on the client's behalf (e.g. response translation)
+#[derive(Debug, Clone)]
+pub struct KeyName(pub String);
+
+/// Stable short id of an API key, the start of its SHA-256, so usage stays with the key when
+/// keys are added, removed or reordered in the config
+pub(crate) fn key_id(key: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(key.as_bytes());
+    digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Extract model name from request body JSON
 fn extract_model_from_body(body: &[u8]) -> Option<String> {
     let json: serde_json::Value = serde_json::from_slice(body).ok()?;
@@ -247,6 +297,127 @@ async fn log_response_if_needed(
     Response::from_parts(parts, Body::from(bytes))
 }
 
+/// Request log entry that is saved once the response ends
+/// If it is dropped before that (the client disconnected while the upstream request
+/// was still running) it is saved as client_aborted with the partial duration
+struct RequestLogGuard {
+    start: std::time::Instant,
+    method: String,
+    path: String,
+    model: Option<String>,
+    provider: Option<String>,
+    account_id: Option<String>,
+    tags: Vec<String>,
+    schema_validation: Option<String>,
+    tenant: Option<String>,
+    key_name: Option<String>,
+    request_bytes: u64,
+    status: i32,
+    saved: bool,
+}
+
+impl RequestLogGuard {
+    fn new(start: std::time::Instant, method: &str, path: &str) -> Self {
+        Self {
+            start,
+            method: method.to_string(),
+            path: path.to_string(),
+            model: None,
+            provider: None,
+            account_id: None,
+            tags: Vec::new(),
+            schema_validation: None,
+            tenant: None,
+            key_name: None,
+            request_bytes: 0,
+            status: 0,
+            saved: false,
+        }
+    }
+
+    fn finish(
+        mut self,
+        token_usage: usage::TokenUsage,
+        end: usage::ResponseEnd,
+        response_bytes: u64,
+    ) {
+        self.save(token_usage, end, response_bytes);
+    }
+
+    fn save(
+        &mut self,
+        token_usage: usage::TokenUsage,
+        end: usage::ResponseEnd,
+        response_bytes: u64,
+    ) {
+        if self.saved {
+            return;
+        }
+        self.saved = true;
+
+        let (status, error_message) = match end {
+            usage::ResponseEnd::Completed if self.status >= 400 => {
+                (self.status, Some(format!("HTTP {}", self.status)))
+            }
+            usage::ResponseEnd::Completed => (self.status, None),
+            usage::ResponseEnd::ClientAborted => {
+                (CLIENT_CLOSED_REQUEST, Some("client_aborted".to_string()))
+            }
+            usage::ResponseEnd::Failed(e) => (502, Some(format!("stream failed: {}", e))),
+        };
+
+        if let Some(provider) = self.provider.as_deref() {
+            if status != CLIENT_CLOSED_REQUEST {
+                provider_health::record(provider, !provider_health::is_provider_failure(status));
+            }
+            stats::record(
+                provider,
+                self.start.elapsed().as_millis() as u64,
+                self.request_bytes,
+                response_bytes,
+            );
+        }
+
+        let _ = crate::db::save_request_log(&crate::db::NewRequestLog {
+            status,
+            method: &self.method,
+            model: self.model.as_deref(),
+            protocol: protocol_from_path(&self.path).as_deref(),
+            provider: self.provider.as_deref(),
+            account_id: self.account_id.as_deref(),
+            path: &self.path,
+            input_tokens: token_usage.input_tokens as i32,
+            output_tokens: token_usage.output_tokens as i32,
+            duration_ms: self.start.elapsed().as_millis() as i64,
+            error_message: error_message.as_deref(),
+            tags: &self.tags,
+            schema_validation: self.schema_validation.as_deref(),
+            tenant: self.tenant.as_deref(),
+        });
+        if let Some(key_name) = self.key_name.as_deref() {
+            let _ = crate::db::record_key_usage(
+                key_name,
+                token_usage.input_tokens as i64,
+                token_usage.output_tokens as i64,
+                status >= 400,
+            );
+        }
+    }
+}
+
+impl Drop for RequestLogGuard {
+    fn drop(&mut self) {
+        self.save(
+            usage::TokenUsage::default(),
+            usage::ResponseEnd::ClientAborted,
+            0,
+        );
+    }
+}
+
+/// Status recorded for requests the client abandoned (nginx convention)
+const CLIENT_CLOSED_REQUEST: i32 = 499;
+
 /// Request logging middleware
 async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
     let start = std::time::Instant::now();
@@ -254,9 +425,14 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
     let path = request.uri().path().to_string();
     let verbose = should_verbose_log();
 
-    // Skip logging for model list requests early
-    if path == "/v1/models" || (path.starts_with("/v1beta/models") && method == "GET") {
-        let response = next.run(request).await;
+    // Skip logging for model list and preflight requests early
+    if path == "/v1/models"
+        || path == "/v1/preflight"
+        || (path.starts_with("/v1beta/models") && method == "GET")
+    {
+        let mut response = next.run(request).await;
+        response.headers_mut().remove(tenant::X_ONEPROXY_TENANT);
+        response.headers_mut().remove(X_ONEPROXY_KEY_NAME);
         return log_response_if_needed(&method, &path, response, verbose).await;
     }
 
@@ -281,12 +457,18 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
             log_request_body(&method, &path, &bytes);
         }
 
+        // Logged as client_aborted if the client disconnects before the response is done
+        let mut log = RequestLogGuard::new(start, &method, &path);
+        log.model = model.as_deref().map(normalize_model_name);
+        log.tags = request_tags::extract_request_tags(&parts.headers, &bytes);
+        log.request_bytes = bytes.len() as u64;
+
         // Reconstruct the request with the buffered body
         let request = Request::from_parts(parts, Body::from(bytes.to_vec()));
         let mut response = next.run(request).await;
 
         // Extract and remove internal account_id header
-        let account_id = response
+        log.account_id = response
             .headers()
             .get(X_ONEPROXY_ACCOUNT_ID)
             .and_then(|v| v.to_str().ok())
@@ -294,7 +476,7 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
         response.headers_mut().remove(X_ONEPROXY_ACCOUNT_ID);
 
         // Extract and remove internal provider header
-        let provider = response
+        log.provider = response
             .headers()
             .get(X_ONEPROXY_PROVIDER)
             .and_then(|v| v.to_str().ok())
@@ -310,35 +492,47 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
         response.headers_mut().remove(X_ONEPROXY_MODEL);
 
         // Use handler-provided model if available, otherwise fall back to request body
-        let final_model = handler_model.or(model);
         // Normalize model name (remove provider prefix) for consistent logging
-        let normalized_model = final_model.map(|m| normalize_model_name(&m));
+        if let Some(handler_model) = handler_model {
+            log.model = Some(normalize_model_name(&handler_model));
+        }
 
-        let response = log_response_if_needed(&method, &path, response, verbose).await;
+        log.schema_validation = response
+            .headers()
+            .get(structured_output::X_ONEPROXY_SCHEMA_VALIDATION)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
 
-        let protocol = protocol_from_path(&path);
-        let duration_ms = start.elapsed().as_millis() as i64;
-        let status = response.status().as_u16() as i32;
+        // Extract and remove internal tenant header
+        log.tenant = response
+            .headers()
+            .get(tenant::X_ONEPROXY_TENANT)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        response.headers_mut().remove(tenant::X_ONEPROXY_TENANT);
 
-        let error_message = if status >= 400 {
-            Some(format!("HTTP {}", status))
-        } else {
-            None
-        };
+        // Extract and remove internal key name header
+        log.key_name = response
+            .headers()
+            .get(X_ONEPROXY_KEY_NAME)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        response.headers_mut().remove(X_ONEPROXY_KEY_NAME);
 
-        let _ = crate::db::save_request_log(
-            status,
-            &method,
-            normalized_model.as_deref(),
-            protocol.as_deref(),
-            provider.as_deref(),
-            account_id.as_deref(),
-            &path,
-            0,
-            0,
-            duration_ms,
-            error_message.as_deref(),
-        );
+        let response = log_response_if_needed(&method, &path, response, verbose).await;
+        log.status = response.status().as_u16() as i32;
+
+        // Report token usage and estimated cost to the client; the log is saved once the
+        // body has been fully sent (or the client went away)
+        let model = log.model.clone();
+        let response = usage::attach_usage(
+            response,
+            model.as_deref(),
+            Box::new(move |token_usage, end, response_bytes| {
+                log.finish(token_usage, end, response_bytes)
+            }),
+        )
+        .await;
 
         return response;
     }
@@ -346,6 +540,7 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
     if verbose {
         log_request_body(&method, &path, &[]);
     }
+    let tags = request_tags::tags_from_headers(request.headers());
     let mut response = next.run(request).await;
 
     // Extract and remove internal account_id header
@@ -364,6 +559,22 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
         .map(|s| s.to_string());
     response.headers_mut().remove(X_ONEPROXY_PROVIDER);
 
+    // Extract and remove internal tenant header
+    let tenant = response
+        .headers()
+        .get(tenant::X_ONEPROXY_TENANT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    response.headers_mut().remove(tenant::X_ONEPROXY_TENANT);
+
+    // Extract and remove internal key name header
+    let key_name = response
+        .headers()
+        .get(X_ONEPROXY_KEY_NAME)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    response.headers_mut().remove(X_ONEPROXY_KEY_NAME);
+
     let response = log_response_if_needed(&method, &path, response, verbose).await;
 
     let protocol = protocol_from_path(&path);
@@ -376,29 +587,51 @@ async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
         None
     };
 
-    let _ = crate::db::save_request_log(
+    let _ = crate::db::save_request_log(&crate::db::NewRequestLog {
         status,
-        &method,
-        None,
-        protocol.as_deref(),
-        provider.as_deref(),
-        account_id.as_deref(),
-        &path,
-        0,
-        0,
+        method: &method,
+        protocol: protocol.as_deref(),
+        provider: provider.as_deref(),
+        account_id: account_id.as_deref(),
+        path: &path,
         duration_ms,
-        error_message.as_deref(),
-    );
+        error_message: error_message.as_deref(),
+        tags: &tags,
+        tenant: tenant.as_deref(),
+        ..Default::default()
+    });
+    if let Some(key_name) = key_name.as_deref() {
+        let _ = crate::db::record_key_usage(key_name, 0, 0, status >= 400);
+    }
+
+    response
+}
+
+/// Make the name of the API key that was used available to inner middleware
+fn set_key_name(request: &mut Request<Body>, key_name: Option<&String>) {
+    if let Some(name) = key_name {
+        request.extensions_mut().insert(KeyName(name.clone()));
+    }
+}
 
+/// Tag a response with the name of the API key that was used, for usage accounting
+fn with_key_name(mut response: Response, key_name: Option<String>) -> Response {
+    if let Some(value) = key_name.and_then(|name| HeaderValue::from_str(&name).ok()) {
+        response.headers_mut().insert(X_ONEPROXY_KEY_NAME, value);
+    }
     response
 }
 
 /// API Key authentication middleware
-async fn auth_middleware(request: Request<Body>, next: Next) -> Response {
+async fn auth_middleware(mut request: Request<Body>, next: Next) -> Response {
     let config = crate::config::get_config().unwrap_or_default();
 
-    // If no API keys configured, allow all requests
-    if config.api_keys.is_empty() {
+    // If no API keys configured, allow all requests. Temporary keys still restrict access
+    // after the permanent keys they were created alongside are removed.
+    if config.api_keys.is_empty()
+        && config.tenants.is_empty()
+        && !crate::db::has_temporary_api_keys().unwrap_or(false)
+    {
         return next.run(request).await;
     }
 
@@ -408,25 +641,69 @@ async fn auth_middleware(request: Request<Body>, next: Next) -> Response {
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok());
 
-    let is_valid = match auth_header {
-        Some(auth) => {
-            // Support both "Bearer <key>" and raw key
-            let key = auth.strip_prefix("Bearer ").unwrap_or(auth);
-            config.api_keys.contains(&key.to_string())
+    // Support both "Bearer <key>" and raw key
+    let key = auth_header.map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth));
+    if let Some(tenant) = key.and_then(|key| tenant::find(&config.tenants, key)) {
+        let tenant = tenant.clone();
+        let key_name = key.map(|key| format!("{}:key-{}", tenant.name, key_id(key)));
+        set_key_name(&mut request, key_name.as_ref());
+        return with_key_name(tenant::serve(&tenant, request, next).await, key_name);
+    }
+    let permanent = key.filter(|key| config.api_keys.iter().any(|k| k == key));
+    let (status, key_name) = match (key, permanent) {
+        (_, Some(key)) => (
+            TemporaryKeyStatus::Valid,
+            Some(format!("api-key-{}", key_id(key))),
+        ),
+        // Only requests that reach a provider count against a temporary key's budget, so
+        // clients polling the model list don't use it up
+        (Some(key), None) => {
+            let (status, label) =
+                crate::db::use_temporary_api_key(key, request.method() != Method::GET)
+                    .unwrap_or((TemporaryKeyStatus::Unknown, None));
+            (status, label.map(|label| format!("temporary:{}", label)))
         }
-        None => false,
+        (None, None) => (TemporaryKeyStatus::Unknown, None),
     };
 
-    if is_valid {
-        next.run(request).await
-    } else {
-        (
-            StatusCode::UNAUTHORIZED,
-            [("Content-Type", "application/json")],
-            r#"{"error":{"message":"Invalid API key","type":"invalid_request_error","code":"invalid_api_key"}}"#,
-        )
-            .into_response()
+    let (code, message) = match status {
+        TemporaryKeyStatus::Valid => {
+            set_key_name(&mut request, key_name.as_ref());
+            return with_key_name(next.run(request).await, key_name);
+        }
+        TemporaryKeyStatus::Expired => ("api_key_expired", "API key has expired"),
+        TemporaryKeyStatus::Exhausted => (
+            "api_key_exhausted",
+            "API key has used up its request allowance",
+        ),
+        TemporaryKeyStatus::Unknown => ("invalid_api_key", "Invalid API key"),
+    };
+    (
+        StatusCode::UNAUTHORIZED,
+        [("Content-Type", "application/json")],
+        serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": code
+            }
+        })
+        .to_string(),
+    )
+        .into_response()
+}
+
+/// Reject requests with 503 while routing is paused
+async fn pause_middleware(request: Request<Body>, next: Next) -> Response {
+    if !is_routing_paused() {
+        return next.run(request).await;
     }
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [("Content-Type", "application/json")],
+        r#"{"error":{"message":"Routing is paused in One Proxy. Resume it from the tray menu or the app to send requests again.","type":"service_unavailable","code":"routing_paused"}}"#,
+    )
+        .into_response()
 }
 
 /// Kill any process using the specified port
@@ -459,11 +736,14 @@ fn kill_process_on_port(port: u16) {
     }
     #[cfg(target_os = "windows")]
     {
-        if let Ok(output) = std::process::Command::new("cmd")
-            .args(["/c", &format!("for /f \"tokens=5\" %a in ('netstat -aon ^| findstr :{} ^| findstr LISTENING') do taskkill /F /PID %a", port)])
-            .output()
-        {
-            tracing::info!("Killed processes on port {}: {:?}", port, output);
+        for pid in crate::win32::listening_pids(port) {
+            if pid == std::process::id() {
+                continue;
+            }
+            tracing::info!("Killing process {} on port {}", pid, port);
+            if !crate::win32::kill_process(pid) {
+                tracing::warn!("Could not kill process {} on port {}", pid, port);
+            }
         }
     }
 
@@ -471,16 +751,39 @@ fn kill_process_on_port(port: u16) {
     std::thread::sleep(std::time::Duration::from_millis(100));
 }
 
+/// Bind the API listener
+/// IPv6 sockets are dual-stack unless `ipv6_only` is set, so "::" also serves IPv4 clients
+fn bind_listener(
+    addr: std::net::SocketAddr,
+    ipv6_only: bool,
+) -> std::io::Result<tokio::net::TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(ipv6_only)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    tokio::net::TcpListener::from_std(socket.into())
+}
+
+/// Loopback address served next to a tailnet host, so the SSH tunnel and local clients, which
+/// connect to 127.0.0.1, keep working while the proxy is only exposed on the tailnet
+fn loopback_companion(addr: std::net::SocketAddr) -> Option<std::net::SocketAddr> {
+    crate::tailscale::is_tailnet_ip(&addr.ip())
+        .then(|| std::net::SocketAddr::new(std::net::Ipv4Addr::LOCALHOST.into(), addr.port()))
+}
+
 pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
     let config = crate::config::get_config().unwrap_or_default();
 
-    let host = if config.host.is_empty() {
-        "0.0.0.0"
-    } else {
-        &config.host
-    };
-    let addr = format!("{}:{}", host, config.port);
+    let addr = crate::config::bind_addr(&config)?;
 
+    events::init(&app_handle);
     let state = AppState { app_handle };
 
     let cors = CorsLayer::new()
@@ -489,16 +792,33 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
             Method::GET,
             Method::POST,
             Method::PUT,
+            Method::PATCH,
             Method::DELETE,
             Method::OPTIONS,
         ])
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([
+            header::ETAG,
+            header::HeaderName::from_static(usage::X_ONEPROXY_COST),
+            header::HeaderName::from_static(usage::X_ONEPROXY_TOKENS),
+            header::HeaderName::from_static(context_upgrade::X_ONEPROXY_CONTEXT_UPGRADE),
+            header::HeaderName::from_static(economy::X_ONEPROXY_ECONOMY),
+            header::HeaderName::from_static(race::X_ONEPROXY_RACE_RESULT),
+            header::HeaderName::from_static(structured_output::X_ONEPROXY_SCHEMA_VALIDATION),
+            header::HeaderName::from_static(compat_warnings::X_ONEPROXY_WARNINGS),
+        ]);
 
     // Routes that require API key authentication
     let protected_routes = Router::new()
         .route("/v1/models", get(handlers::openai_models))
         .route("/v1/chat/completions", post(handlers::chat_completions))
+        .route(
+            "/v1/chat/completions/fan-out",
+            post(handlers::chat_completions_fan_out),
+        )
         .route("/v1/completions", post(handlers::completions))
+        .route("/v1/preflight", post(handlers::preflight))
+        .route("/v1/moderations", post(handlers::moderations))
         .route(
             "/v1/responses",
             get(handlers::responses_websocket).post(handlers::responses),
@@ -508,6 +828,9 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
             "/v1/messages/count_tokens",
             post(handlers::claude_count_tokens),
         )
+        // Claude Code usage / statusline adapters
+        .route("/api/oauth/usage", get(handlers::oauth_usage))
+        .route("/v1/usage/statusline", get(handlers::usage_statusline))
         // Gemini protocol routes (both with and without /gemini prefix)
         .route("/v1beta/models", get(handlers::gemini_models))
         .route("/v1beta/models/*action", post(handlers::gemini_handler))
@@ -521,6 +844,12 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
             "/gemini/v1beta/models/*action",
             get(handlers::gemini_get_handler),
         )
+        .layer(middleware::from_fn(
+            structured_output::structured_output_middleware,
+        ))
+        .layer(middleware::from_fn(compat_warnings::warnings_middleware))
+        .layer(middleware::from_fn(translation::translation_middleware))
+        .layer(middleware::from_fn(pause_middleware))
         .layer(middleware::from_fn(auth_middleware))
         .layer(middleware::from_fn(logging_middleware));
 
@@ -548,9 +877,29 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
             get(management::get_auth_summary),
         )
         .route("/management/accounts", get(management::list_accounts))
-        .route("/management/config", get(management::get_config))
-        .route("/management/config", put(management::update_config))
-        .route("/management/status", get(management::get_server_status));
+        .route(
+            "/management/config",
+            get(management::get_config).merge(
+                put(management::update_config)
+                    .patch(management::patch_config)
+                    .route_layer(middleware::from_fn(management::require_write_access)),
+            ),
+        )
+        .route("/management/status", get(management::get_server_status))
+        .route(
+            "/management/reload",
+            post(management::reload_config)
+                .route_layer(middleware::from_fn(management::require_write_access)),
+        )
+        .route(
+            "/management/routing-pause",
+            get(management::get_routing_pause).merge(
+                put(management::set_routing_pause)
+                    .route_layer(middleware::from_fn(management::require_write_access)),
+            ),
+        )
+        // Prometheus scrape endpoint
+        .route("/metrics", get(handlers::metrics));
 
     let app = Router::new()
         .merge(protected_routes)
@@ -559,7 +908,7 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
         .with_state(state);
 
     // Try to bind, if port is in use, kill the process and retry
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
+    let listener = match bind_listener(addr, config.ipv6_only) {
         Ok(l) => l,
         Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
             tracing::warn!(
@@ -567,13 +916,32 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
                 config.port
             );
             kill_process_on_port(config.port);
-            tokio::net::TcpListener::bind(&addr).await?
+            bind_listener(addr, config.ipv6_only)?
         }
         Err(e) => return Err(e.into()),
     };
 
     tracing::info!("API server listening on {}", addr);
 
+    let loopback = loopback_companion(addr).and_then(|loopback_addr| {
+        match bind_listener(loopback_addr, false) {
+            Ok(listener) => {
+                tracing::info!("API server also listening on {}", loopback_addr);
+                Some(listener)
+            }
+            Err(e) => {
+                tracing::warn!("Could not listen on {}: {}", loopback_addr, e);
+                None
+            }
+        }
+    });
+
+    // Warm caches in the background so the first request isn't slow
+    tokio::spawn(warmup::run());
+
+    // Reverse tunnel to the configured remote host, if enabled
+    crate::tunnel::start(config.port);
+
     let (tx, rx) = oneshot::channel::<()>();
 
     SERVER_HANDLE
@@ -581,11 +949,20 @@ pub async fn start_server(app_handle: tauri::AppHandle) -> Result<()> {
         .write()
         .replace(tx);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async {
-            rx.await.ok();
-        })
-        .await?;
+    let shutdown = async {
+        rx.await.ok();
+    }
+    .shared();
+    // The peer address lets management writes be limited to local callers
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let serve = axum::serve(listener, app.clone()).with_graceful_shutdown(shutdown.clone());
+    match loopback {
+        Some(loopback) => {
+            let serve_loopback = axum::serve(loopback, app).with_graceful_shutdown(shutdown);
+            tokio::try_join!(serve.into_future(), serve_loopback.into_future())?;
+        }
+        None => serve.await?,
+    }
 
     Ok(())
 }
@@ -594,6 +971,7 @@ pub async fn stop_server() -> Result<()> {
     if let Some(lock) = SERVER_HANDLE.get() {
         if let Some(tx) = lock.write().take() {
             let _ = tx.send(());
+            crate::tunnel::stop();
             tracing::info!("API server stopped");
         }
     }
@@ -606,3 +984,21 @@ pub fn is_server_running() -> bool {
         .map(|lock| lock.read().is_some())
         .unwrap_or(false)
 }
+
+pub fn is_routing_paused() -> bool {
+    ROUTING_PAUSED.load(Ordering::Relaxed)
+}
+
+/// Pause or resume routing; returns whether the state changed
+pub fn set_routing_paused(paused: bool) -> bool {
+    if ROUTING_PAUSED.swap(paused, Ordering::Relaxed) == paused {
+        return false;
+    }
+    if paused {
+        tracing::warn!("Routing paused, API requests are rejected with 503");
+    } else {
+        tracing::info!("Routing resumed");
+    }
+    events::emit(events::ROUTING_PAUSED_CHANGED, paused);
+    true
+}
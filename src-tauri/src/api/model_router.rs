@@ -4,6 +4,9 @@
 use crate::config::{get_config, ProviderPriority};
 use std::collections::HashMap;
 
+use super::priority_schedule;
+use super::provider_health;
+
 /// Known models and which providers support them
 /// Format: (model_pattern, vec![provider_names])
 static MODEL_PROVIDER_MAP: &[(&str, &[&str])] = &[
@@ -86,6 +89,36 @@ fn normalize_model_name(name: &str) -> String {
     normalized.replace(".", "-").replace("_", "-")
 }
 
+/// Look up a model name in the configured alias table (`model-routing.model-aliases`)
+/// Returns the canonical name if the model is a known alias
+fn lookup_model_alias(name: &str) -> Option<String> {
+    let normalized = name.trim().to_lowercase().replace('_', "-");
+    let config = get_config().unwrap_or_default();
+    config
+        .model_routing
+        .model_aliases
+        .iter()
+        .find(|(alias, _)| alias.trim().to_lowercase().replace('_', "-") == normalized)
+        .map(|(_, canonical)| canonical.trim().to_lowercase())
+}
+
+/// Map a model name to its canonical name for aggregation
+/// Lowercases and unifies separators; configured aliases are resolved to their canonical name
+pub fn canonical_model_name(name: &str) -> String {
+    lookup_model_alias(name).unwrap_or_else(|| name.trim().to_lowercase().replace('_', "-"))
+}
+
+/// Resolve a configured alias while keeping a reasoning prefix like "high/"
+/// Models that aren't aliases are returned unchanged
+fn resolve_model_alias(model: &str) -> String {
+    let (prefix, base) = extract_reasoning_prefix(model);
+    match (prefix, lookup_model_alias(&base)) {
+        (Some(prefix), Some(canonical)) => format!("{}/{}", prefix, canonical),
+        (None, Some(canonical)) => canonical,
+        _ => model.to_string(),
+    }
+}
+
 /// Get supported providers for a model name
 pub fn get_providers_for_model(model: &str) -> Vec<String> {
     // Strip reasoning effort prefix if present (e.g., "high/gemini-3-flash" -> "gemini-3-flash")
@@ -148,10 +181,65 @@ fn strip_reasoning_prefix(model: &str) -> String {
     model.to_string()
 }
 
-/// Get provider priorities from config, sorted by priority (highest first)
+/// Get the "-thinking" / non-thinking counterpart of a Claude model
+/// e.g., "claude-opus-4-5-thinking" -> "claude-opus-4-5" and vice versa
+fn thinking_counterpart(model: &str) -> Option<String> {
+    let normalized = normalize_model_name(model);
+    if !normalized.starts_with("claude-") {
+        return None;
+    }
+    match normalized.strip_suffix("-thinking") {
+        Some(base) => Some(base.to_string()),
+        None => Some(format!("{}-thinking", normalized)),
+    }
+}
+
+/// Get providers for a thinking variant counterpart
+/// Thinking variants only count when explicitly listed, so that prefix matching
+/// doesn't report a "-thinking" model for every provider of the base model
+fn get_providers_for_counterpart(counterpart: &str) -> Vec<String> {
+    if !counterpart.ends_with("-thinking") {
+        return get_providers_for_model(counterpart);
+    }
+    MODEL_PROVIDER_MAP
+        .iter()
+        .find(|(pattern, _)| normalize_model_name(pattern) == counterpart)
+        .map(|(_, providers)| providers.iter().map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Get the thinking variant fallback preference from config
+fn thinking_variant_fallback() -> String {
+    let config = get_config().unwrap_or_default();
+    config.model_routing.thinking_variant_fallback
+}
+
+/// Pick the model variant a provider actually serves
+/// Returns the counterpart when the provider lacks the requested variant but has the other one
+fn variant_for_provider(model: &str, provider: &str) -> Option<String> {
+    if thinking_variant_fallback() == "off" {
+        return None;
+    }
+    if get_providers_for_model(model).iter().any(|p| p == provider) {
+        return None;
+    }
+    let counterpart = thinking_counterpart(model)?;
+    if get_providers_for_counterpart(&counterpart)
+        .iter()
+        .any(|p| p == provider)
+    {
+        Some(counterpart)
+    } else {
+        None
+    }
+}
+
+/// Get provider priorities from config, with the currently active schedule window applied,
+/// sorted by priority (highest first)
 pub fn get_sorted_priorities() -> Vec<ProviderPriority> {
     let config = get_config().unwrap_or_default();
     let mut priorities = config.model_routing.provider_priorities;
+    priority_schedule::apply(&mut priorities, &config.model_routing.priority_schedule);
     priorities.sort_by(|a, b| b.priority.cmp(&a.priority));
     priorities
 }
@@ -165,9 +253,23 @@ pub fn is_aggregation_mode() -> bool {
 /// Get the provider-specific model name for a normalized model name
 /// Returns the provider's preferred model name, or the original name if no mapping exists
 pub fn get_provider_model_name(normalized_model: &str, provider: &str) -> String {
+    let normalized_model = resolve_model_alias(normalized_model);
+    let normalized_model = normalized_model.as_str();
+
     // Extract reasoning prefix if present
     let (reasoning_prefix, base_model) = extract_reasoning_prefix(normalized_model);
 
+    // Switch to the thinking/non-thinking variant if the provider only serves that one
+    if let Some(variant) = variant_for_provider(&base_model, provider) {
+        return get_provider_model_name(
+            &match reasoning_prefix {
+                Some(prefix) => format!("{}/{}", prefix, variant),
+                None => variant,
+            },
+            provider,
+        );
+    }
+
     // Normalize the base model
     let normalized = normalize_model_name(&base_model);
 
@@ -224,9 +326,28 @@ pub fn resolve_model(raw_model: &str, explicit_provider: Option<&str>) -> Resolv
         };
     }
 
-    // In model aggregation mode, find providers
-    let available_providers = get_providers_for_model(raw_model);
-    if available_providers.is_empty() {
+    // In model aggregation mode, find providers (using the canonical name so
+    // user-configured aliases route the same way they are listed)
+    let canonical_model = resolve_model_alias(raw_model);
+    let raw_model = canonical_model.as_str();
+    let mut available_providers = get_providers_for_model(raw_model);
+
+    // Providers that only serve the thinking/non-thinking counterpart of the model
+    let fallback_mode = config.model_routing.thinking_variant_fallback.as_str();
+    let mut variant_providers: Vec<String> = Vec::new();
+    if fallback_mode != "off" {
+        if let Some(counterpart) = thinking_counterpart(&strip_reasoning_prefix(raw_model)) {
+            variant_providers = get_providers_for_counterpart(&counterpart)
+                .into_iter()
+                .filter(|p| !available_providers.contains(p))
+                .collect();
+        }
+    }
+    if fallback_mode == "priority" {
+        available_providers.append(&mut variant_providers);
+    }
+
+    if available_providers.is_empty() && variant_providers.is_empty() {
         return ResolvedModel::NoProvider {
             model: raw_model.to_string(),
         };
@@ -236,17 +357,24 @@ pub fn resolve_model(raw_model: &str, explicit_provider: Option<&str>) -> Resolv
     let priorities = get_sorted_priorities();
     let mut ordered_providers: Vec<String> = Vec::new();
 
-    for priority in &priorities {
-        if priority.enabled && available_providers.contains(&priority.provider) {
-            ordered_providers.push(priority.provider.clone());
+    for providers in [&available_providers, &variant_providers] {
+        let mut group: Vec<String> = Vec::new();
+        for priority in &priorities {
+            if priority.enabled && providers.contains(&priority.provider) {
+                group.push(priority.provider.clone());
+            }
         }
-    }
 
-    // Add any remaining providers not in priorities
-    for provider in &available_providers {
-        if !ordered_providers.contains(provider) {
-            ordered_providers.push(provider.clone());
+        // Add any remaining providers not in priorities
+        for provider in providers {
+            if !ordered_providers.contains(provider) && !group.contains(provider) {
+                group.push(provider.clone());
+            }
         }
+
+        // Providers with a recent outage are tried after healthy ones
+        provider_health::order_by_health(&mut group);
+        ordered_providers.append(&mut group);
     }
 
     if ordered_providers.is_empty() {
@@ -294,4 +422,52 @@ mod tests {
         assert!(providers.contains(&"gemini".to_string()));
         assert!(providers.contains(&"antigravity".to_string()));
     }
+
+    #[test]
+    fn test_thinking_counterpart() {
+        assert_eq!(
+            thinking_counterpart("claude-opus-4-5-thinking").as_deref(),
+            Some("claude-opus-4-5")
+        );
+        assert_eq!(
+            thinking_counterpart("claude-sonnet-4.5").as_deref(),
+            Some("claude-sonnet-4-5-thinking")
+        );
+        assert_eq!(thinking_counterpart("gemini-2.5-pro"), None);
+    }
+
+    #[test]
+    fn test_thinking_variant_falls_back_to_available_provider_model() {
+        // Kiro has no thinking variants, so it gets the non-thinking model
+        assert_eq!(
+            get_provider_model_name("claude-sonnet-4-5-thinking", "kiro"),
+            "claude-sonnet-4.5"
+        );
+        assert_eq!(
+            get_provider_model_name("high/claude-opus-4-5-thinking", "kiro"),
+            "high/claude-opus-4-5"
+        );
+        // Antigravity serves the thinking variant as requested
+        assert_eq!(
+            get_provider_model_name("claude-sonnet-4-5-thinking", "antigravity"),
+            "claude-sonnet-4-5-thinking"
+        );
+        // No counterpart exists for models without a listed thinking variant
+        assert!(get_providers_for_counterpart("claude-3-haiku-thinking").is_empty());
+    }
+
+    #[test]
+    fn test_canonical_model_name_uses_default_aliases() {
+        assert_eq!(
+            canonical_model_name("claude-sonnet-4.5"),
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(canonical_model_name("Claude_4.5_Opus"), "claude-opus-4-5");
+        assert_eq!(canonical_model_name("gemini-2.5-pro"), "gemini-2.5-pro");
+        assert_eq!(
+            resolve_model_alias("high/claude-4.5-haiku"),
+            "high/claude-haiku-4-5"
+        );
+        assert_eq!(resolve_model_alias("Gemini-2.5-Pro"), "Gemini-2.5-Pro");
+    }
 }
@@ -0,0 +1,118 @@
+// Cached `/v1/models` response
+// Building the model list reads every auth file and may ask Kiro for its models, while some
+// IDEs poll the endpoint every few seconds. The serialized list is reused until the config or
+// the auth directory changes (or the TTL passes, for upstream model lists), and clients that
+// send the ETag back in If-None-Match get a 304.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Upper bound on reusing a list, so upstream model list changes are picked up
+const TTL: Duration = Duration::from_secs(300);
+
+struct CachedModels {
+    fingerprint: String,
+    etag: String,
+    body: String,
+    built_at: Instant,
+}
+
+static CACHE: Lazy<RwLock<Option<CachedModels>>> = Lazy::new(|| RwLock::new(None));
+
+fn hex_digest(hasher: Sha256) -> String {
+    hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Version of the inputs the model list is built from: the config and the names, sizes
+/// and modification times of the auth files (metadata only, files are not read)
+pub fn fingerprint() -> String {
+    let mut hasher = Sha256::new();
+    if let Some(config) = crate::config::get_config() {
+        hasher.update(crate::config::config_etag(&config));
+    }
+
+    let auth_dir = crate::config::resolve_auth_dir();
+    let mut entries: Vec<(String, u64, u128)> = std::fs::read_dir(&auth_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or(0);
+            Some((
+                entry.file_name().to_string_lossy().to_string(),
+                metadata.len(),
+                modified,
+            ))
+        })
+        .collect();
+    entries.sort();
+    for (name, len, modified) in entries {
+        hasher.update(format!("{}:{}:{};", name, len, modified));
+    }
+    hex_digest(hasher)
+}
+
+/// Cached body and ETag if they were built from the same inputs and are still fresh
+pub fn get(fingerprint: &str) -> Option<(String, String)> {
+    let cache = CACHE.read();
+    let cached = cache.as_ref()?;
+    (cached.fingerprint == fingerprint && cached.built_at.elapsed() < TTL)
+        .then(|| (cached.etag.clone(), cached.body.clone()))
+}
+
+/// Store a freshly built body, returning its ETag
+pub fn store(fingerprint: String, body: String) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(&body);
+    let etag = format!("\"{}\"", hex_digest(hasher));
+    *CACHE.write() = Some(CachedModels {
+        fingerprint,
+        etag: etag.clone(),
+        body,
+        built_at: Instant::now(),
+    });
+    etag
+}
+
+/// Drop the cached list, e.g. after an upstream model list was refreshed
+pub fn invalidate() {
+    CACHE.write().take();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_body_only_for_the_same_fingerprint() {
+        let etag = store(
+            "a".to_string(),
+            r#"{"object":"list","data":[]}"#.to_string(),
+        );
+        assert_eq!(get("a").map(|(tag, _)| tag), Some(etag.clone()));
+        assert!(get("b").is_none());
+
+        // The ETag depends on the body, not on the inputs
+        assert_eq!(
+            store(
+                "b".to_string(),
+                r#"{"object":"list","data":[]}"#.to_string()
+            ),
+            etag
+        );
+
+        invalidate();
+        assert!(get("b").is_none());
+    }
+}
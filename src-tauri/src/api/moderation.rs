@@ -0,0 +1,306 @@
+// OpenAI moderations facade
+// Some frameworks refuse to start without a moderation endpoint. `/v1/moderations` answers in
+// the OpenAI format using either Gemini's safety ratings or a local keyword heuristic, picked
+// by the `moderation.backend` config.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+
+use super::gemini::GeminiClient;
+
+/// Score at which a category is flagged
+const FLAG_THRESHOLD: f64 = 0.5;
+
+/// Categories reported by the OpenAI moderation API
+pub const CATEGORIES: &[&str] = &[
+    "harassment",
+    "harassment/threatening",
+    "hate",
+    "hate/threatening",
+    "illicit",
+    "illicit/violent",
+    "self-harm",
+    "self-harm/intent",
+    "self-harm/instructions",
+    "sexual",
+    "sexual/minors",
+    "violence",
+    "violence/graphic",
+];
+
+/// Gemini harm categories and the OpenAI categories they score
+const GEMINI_CATEGORY_MAP: &[(&str, &[&str])] = &[
+    ("HARM_CATEGORY_HARASSMENT", &["harassment"]),
+    ("HARM_CATEGORY_HATE_SPEECH", &["hate"]),
+    ("HARM_CATEGORY_SEXUALLY_EXPLICIT", &["sexual"]),
+    ("HARM_CATEGORY_DANGEROUS_CONTENT", &["illicit", "violence"]),
+];
+
+/// Keywords for the local heuristic, matched as whole lowercase words
+const HEURISTIC_KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "harassment",
+        &["idiot", "moron", "loser", "worthless", "stupid"],
+    ),
+    (
+        "harassment/threatening",
+        &[
+            "i will kill you",
+            "i'll kill you",
+            "you will die",
+            "watch your back",
+        ],
+    ),
+    (
+        "hate",
+        &[
+            "subhuman",
+            "vermin",
+            "inferior race",
+            "go back to your country",
+        ],
+    ),
+    (
+        "illicit",
+        &[
+            "cocaine",
+            "meth",
+            "counterfeit",
+            "launder money",
+            "buy drugs",
+        ],
+    ),
+    (
+        "illicit/violent",
+        &["build a bomb", "make a bomb", "buy a gun illegally"],
+    ),
+    ("self-harm", &["self-harm", "cut myself", "hurt myself"]),
+    (
+        "self-harm/intent",
+        &["kill myself", "end my life", "want to die", "suicide"],
+    ),
+    ("sexual", &["porn", "nude", "explicit sex", "nsfw"]),
+    // A bare "kill" is common in technical text ("kill the process"), so it needs a target
+    (
+        "violence",
+        &[
+            "kill him",
+            "kill her",
+            "kill them",
+            "kill people",
+            "kill everyone",
+            "murder",
+            "stab",
+            "shoot",
+            "assault",
+        ],
+    ),
+    (
+        "violence/graphic",
+        &["gore", "dismember", "decapitate", "mutilate"],
+    ),
+];
+
+/// Scores of one input, keyed by OpenAI category
+pub type CategoryScores = Map<String, Value>;
+
+fn empty_scores() -> CategoryScores {
+    CATEGORIES
+        .iter()
+        .map(|category| (category.to_string(), json!(0.0)))
+        .collect()
+}
+
+fn raise_score(scores: &mut CategoryScores, category: &str, score: f64) {
+    let current = scores.get(category).and_then(|v| v.as_f64()).unwrap_or(0.0);
+    if score > current {
+        scores.insert(category.to_string(), json!(score));
+    }
+}
+
+/// Texts to moderate: a string, an array of strings, or multimodal `{type: "text"}` parts
+/// Items without text (e.g. images) are kept as empty texts, so results line up with inputs.
+pub fn parse_inputs(request: &Value) -> Result<Vec<String>> {
+    match request.get("input") {
+        Some(Value::String(text)) => Ok(vec![text.clone()]),
+        Some(Value::Array(items)) if !items.is_empty() => Ok(items
+            .iter()
+            .map(|item| match item {
+                Value::String(text) => text.clone(),
+                Value::Object(part)
+                    if part.get("type").and_then(|v| v.as_str()) == Some("text") =>
+                {
+                    part.get("text")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                }
+                _ => String::new(),
+            })
+            .collect()),
+        _ => Err(anyhow!("Missing required field: input")),
+    }
+}
+
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let end = start + phrase.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// Keyword-based scores, used when no model backend is configured or available
+pub fn heuristic_scores(text: &str) -> CategoryScores {
+    let text = text.to_lowercase();
+    let mut scores = empty_scores();
+    for (category, keywords) in HEURISTIC_KEYWORDS {
+        let hits = keywords
+            .iter()
+            .filter(|keyword| contains_phrase(&text, keyword))
+            .count();
+        if hits > 0 {
+            raise_score(&mut scores, category, (0.4 + 0.3 * hits as f64).min(0.99));
+        }
+    }
+    scores
+}
+
+/// Map a Gemini safety probability to a score
+fn probability_score(rating: &Value) -> f64 {
+    if let Some(score) = rating.get("probabilityScore").and_then(|v| v.as_f64()) {
+        return score;
+    }
+    match rating.get("probability").and_then(|v| v.as_str()) {
+        Some("LOW") => 0.2,
+        Some("MEDIUM") => 0.6,
+        Some("HIGH") => 0.95,
+        _ => 0.01,
+    }
+}
+
+/// Scores from Gemini `safetyRatings`, prompt feedback preferred over candidate ratings
+pub fn gemini_scores(response: &Value) -> CategoryScores {
+    let response = response.get("response").unwrap_or(response);
+    let ratings = response
+        .pointer("/promptFeedback/safetyRatings")
+        .or_else(|| response.pointer("/candidates/0/safetyRatings"))
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut scores = empty_scores();
+    for rating in &ratings {
+        let Some(category) = rating.get("category").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let Some((_, targets)) = GEMINI_CATEGORY_MAP.iter().find(|(c, _)| *c == category) else {
+            continue;
+        };
+        let score = probability_score(rating);
+        for target in *targets {
+            raise_score(&mut scores, target, score);
+        }
+    }
+    scores
+}
+
+/// Score a text with Gemini safety ratings through the Code Assist endpoint
+pub async fn score_with_gemini(
+    client: &GeminiClient,
+    project_id: Option<&str>,
+    model: &str,
+    text: &str,
+) -> Result<CategoryScores> {
+    let safety_settings: Vec<Value> = GEMINI_CATEGORY_MAP
+        .iter()
+        .map(|(category, _)| json!({"category": category, "threshold": "BLOCK_NONE"}))
+        .collect();
+    let mut payload = json!({
+        "model": model,
+        "request": {
+            "contents": [{"role": "user", "parts": [{"text": text}]}],
+            "safetySettings": safety_settings,
+            "generationConfig": {"maxOutputTokens": 1}
+        }
+    });
+    if let Some(project_id) = project_id {
+        payload["project"] = json!(project_id);
+    }
+    let response = client.generate_content(&payload).await?;
+    Ok(gemini_scores(&response))
+}
+
+/// One OpenAI moderation result
+pub fn moderation_result(scores: CategoryScores) -> Value {
+    let categories: Map<String, Value> = scores
+        .iter()
+        .map(|(category, score)| {
+            let flagged = score.as_f64().unwrap_or(0.0) >= FLAG_THRESHOLD;
+            (category.clone(), json!(flagged))
+        })
+        .collect();
+    let flagged = categories.values().any(|v| v.as_bool() == Some(true));
+    json!({
+        "flagged": flagged,
+        "categories": categories,
+        "category_scores": scores
+    })
+}
+
+/// OpenAI moderation response body
+pub fn moderation_response(model: &str, results: Vec<Value>) -> Value {
+    json!({
+        "id": format!("modr-{}", uuid::Uuid::new_v4().simple()),
+        "model": model,
+        "results": results
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristic_flags_whole_word_matches_only() {
+        let result = moderation_result(heuristic_scores("I will MURDER them"));
+        assert_eq!(result["flagged"], true);
+        assert_eq!(result["categories"]["violence"], true);
+        assert_eq!(result["categories"]["hate"], false);
+
+        let result = moderation_result(heuristic_scores("the skill tree looks fine"));
+        assert_eq!(result["flagged"], false);
+        let result = moderation_result(heuristic_scores("kill the process and restart it"));
+        assert_eq!(result["flagged"], false);
+        assert_eq!(
+            result["category_scores"].as_object().unwrap().len(),
+            CATEGORIES.len()
+        );
+    }
+
+    #[test]
+    fn maps_gemini_safety_ratings() {
+        let response = json!({"response": {"candidates": [{"safetyRatings": [
+            {"category": "HARM_CATEGORY_HARASSMENT", "probability": "HIGH"},
+            {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "LOW"}
+        ]}]}});
+        let result = moderation_result(gemini_scores(&response));
+        assert_eq!(result["categories"]["harassment"], true);
+        assert_eq!(result["categories"]["violence"], false);
+        assert_eq!(result["category_scores"]["illicit"], 0.2);
+    }
+
+    #[test]
+    fn parses_string_array_and_multimodal_inputs() {
+        assert_eq!(parse_inputs(&json!({"input": "a"})).unwrap(), vec!["a"]);
+        assert_eq!(
+            parse_inputs(
+                &json!({"input": ["a", {"type": "text", "text": "b"}, {"type": "image_url"}]})
+            )
+            .unwrap(),
+            vec!["a", "b", ""]
+        );
+        assert!(parse_inputs(&json!({})).is_err());
+    }
+}
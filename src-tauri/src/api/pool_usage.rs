@@ -0,0 +1,318 @@
+// Remaining quota of the routed account pool
+// Claude Code asks `/api/oauth/usage` for its five-hour and weekly utilization and shows it in
+// `/usage` and custom statuslines. Behind the proxy there is no single subscription, so the
+// same shape is filled in from the cached quotas of all enabled accounts: short windows
+// (Codex 5h, Antigravity and Gemini resets) count towards `five_hour`, long ones (Codex
+// weekly, Kiro monthly) towards `seven_day`. Quotas are read from the cache only, refreshing
+// them stays with the quota page and the scheduled refresh.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Utilization of one window, in the shape Claude Code expects
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageWindow {
+    /// Percent used, averaged over the accounts reporting this window
+    pub utilization: f64,
+    /// Earliest reset among those accounts (RFC 3339)
+    pub resets_at: Option<String>,
+}
+
+/// Pool summary of one provider
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub accounts: usize,
+    pub five_hour: Option<UsageWindow>,
+    pub seven_day: Option<UsageWindow>,
+}
+
+/// Details that have no place in the Anthropic shape
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolDetails {
+    /// Enabled accounts with a cached quota
+    pub accounts: usize,
+    /// Unix seconds of the oldest cached quota that went into the numbers
+    pub oldest_update: Option<i64>,
+    pub providers: Vec<ProviderUsage>,
+}
+
+/// Response of `/api/oauth/usage`
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolUsage {
+    pub five_hour: Option<UsageWindow>,
+    pub seven_day: Option<UsageWindow>,
+    pub seven_day_opus: Option<UsageWindow>,
+    pub one_proxy: PoolDetails,
+}
+
+/// Used percent and reset time of the windows of one account
+#[derive(Debug, Default)]
+struct AccountWindows {
+    short: Option<(f64, Option<String>)>,
+    long: Option<(f64, Option<String>)>,
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Reset time normalized to UTC, so the earliest one can be picked by comparing strings
+fn reset_field(value: &Value, key: &str) -> Option<String> {
+    let raw = str_field(value, key)?;
+    chrono::DateTime::parse_from_rfc3339(&raw).ok().map(|time| {
+        time.with_timezone(&chrono::Utc)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    })
+}
+
+/// Lowest remaining percentage of a model list and its earliest reset
+fn tightest_model(
+    models: &[Value],
+    remaining: impl Fn(&Value) -> Option<f64>,
+    reset_key: &str,
+) -> Option<(f64, Option<String>)> {
+    let remaining_percent = models
+        .iter()
+        .filter_map(&remaining)
+        .fold(None, |min: Option<f64>, r| {
+            Some(min.map_or(r, |m| m.min(r)))
+        })?;
+    let resets_at = models
+        .iter()
+        .filter_map(|m| reset_field(m, reset_key))
+        .min();
+    Some(((100.0 - remaining_percent).clamp(0.0, 100.0), resets_at))
+}
+
+/// Windows of a cached quota, `None` when it is an error entry or reports nothing usable
+fn account_windows(provider: &str, quota: &Value) -> Option<AccountWindows> {
+    if quota.get("is_error").and_then(|v| v.as_bool()) == Some(true)
+        || quota.get("is_forbidden").and_then(|v| v.as_bool()) == Some(true)
+    {
+        return None;
+    }
+    let mut windows = AccountWindows::default();
+    match provider {
+        "codex" => {
+            windows.short = Some((
+                quota.get("primary_used")?.as_f64()?,
+                reset_field(quota, "primary_resets_at"),
+            ));
+            windows.long = quota
+                .get("secondary_used")
+                .and_then(|v| v.as_f64())
+                .map(|used| (used, reset_field(quota, "secondary_resets_at")));
+        }
+        "antigravity" => {
+            let models = quota.get("models")?.as_array()?;
+            // Claude Code is routed to the Claude models when the account has them
+            let claude: Vec<Value> = models
+                .iter()
+                .filter(|m| {
+                    m.get("name")
+                        .and_then(|n| n.as_str())
+                        .is_some_and(|n| n.contains("claude"))
+                })
+                .cloned()
+                .collect();
+            let models = if claude.is_empty() {
+                models.clone()
+            } else {
+                claude
+            };
+            windows.short = tightest_model(
+                &models,
+                |m| m.get("percentage").and_then(|p| p.as_f64()),
+                "reset_time",
+            );
+        }
+        "gemini" => {
+            windows.short = tightest_model(
+                quota.get("models")?.as_array()?,
+                |m| {
+                    m.get("remaining_fraction")
+                        .and_then(|f| f.as_f64())
+                        .map(|f| f * 100.0)
+                },
+                "reset_time",
+            );
+        }
+        "kiro" => {
+            let limit = quota.get("usage_limit")?.as_f64()?;
+            let used = quota.get("current_usage")?.as_f64()?;
+            if limit > 0.0 {
+                let resets_at =
+                    quota
+                        .get("days_until_reset")
+                        .and_then(|d| d.as_i64())
+                        .map(|days| {
+                            (chrono::Utc::now() + chrono::Duration::days(days))
+                                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                        });
+                windows.long = Some(((used / limit * 100.0).clamp(0.0, 100.0), resets_at));
+            }
+        }
+        _ => return None,
+    }
+    (windows.short.is_some() || windows.long.is_some()).then_some(windows)
+}
+
+fn average(windows: &[&(f64, Option<String>)]) -> Option<UsageWindow> {
+    if windows.is_empty() {
+        return None;
+    }
+    let total: f64 = windows.iter().map(|(used, _)| used).sum();
+    Some(UsageWindow {
+        utilization: (total / windows.len() as f64 * 10.0).round() / 10.0,
+        resets_at: windows.iter().filter_map(|(_, reset)| reset.clone()).min(),
+    })
+}
+
+/// Summarize `(provider, cached quota JSON, last updated)` entries of enabled accounts
+fn summarize(entries: &[(String, String, i64)]) -> PoolUsage {
+    let mut by_provider: BTreeMap<&str, Vec<AccountWindows>> = BTreeMap::new();
+    let mut oldest_update: Option<i64> = None;
+    for (provider, quota_data, last_updated) in entries {
+        let Ok(quota) = serde_json::from_str::<Value>(quota_data) else {
+            continue;
+        };
+        if let Some(windows) = account_windows(provider, &quota) {
+            by_provider.entry(provider).or_default().push(windows);
+            oldest_update = Some(oldest_update.map_or(*last_updated, |t| t.min(*last_updated)));
+        }
+    }
+
+    let all: Vec<&AccountWindows> = by_provider.values().flatten().collect();
+    let shorts: Vec<_> = all.iter().filter_map(|w| w.short.as_ref()).collect();
+    let longs: Vec<_> = all.iter().filter_map(|w| w.long.as_ref()).collect();
+    let providers = by_provider
+        .iter()
+        .map(|(provider, accounts)| ProviderUsage {
+            provider: provider.to_string(),
+            accounts: accounts.len(),
+            five_hour: average(
+                &accounts
+                    .iter()
+                    .filter_map(|w| w.short.as_ref())
+                    .collect::<Vec<_>>(),
+            ),
+            seven_day: average(
+                &accounts
+                    .iter()
+                    .filter_map(|w| w.long.as_ref())
+                    .collect::<Vec<_>>(),
+            ),
+        })
+        .collect();
+
+    PoolUsage {
+        five_hour: average(&shorts),
+        seven_day: average(&longs),
+        seven_day_opus: None,
+        one_proxy: PoolDetails {
+            accounts: all.len(),
+            oldest_update,
+            providers,
+        },
+    }
+}
+
+/// Utilization of the enabled accounts, from the quota cache
+pub async fn pool_usage() -> PoolUsage {
+    let enabled: Vec<String> = crate::auth::list_accounts()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|account| account.enabled)
+        .map(|account| account.id)
+        .collect();
+    let cache = crate::db::get_all_quota_cache().unwrap_or_default();
+    let entries: Vec<(String, String, i64)> = enabled
+        .iter()
+        .filter_map(|id| cache.get(id))
+        .map(|cached| {
+            (
+                cached.provider.clone(),
+                cached.quota_data.clone(),
+                cached.last_updated,
+            )
+        })
+        .collect();
+    summarize(&entries)
+}
+
+/// One line for a Claude Code `statusLine` command, e.g. "pool 5h 42% · 7d 18% · 6 accounts"
+pub fn statusline(usage: &PoolUsage) -> String {
+    if usage.one_proxy.accounts == 0 {
+        return "pool: no quota data".to_string();
+    }
+    let mut parts = Vec::new();
+    if let Some(window) = &usage.five_hour {
+        parts.push(format!("5h {:.0}%", window.utilization));
+    }
+    if let Some(window) = &usage.seven_day {
+        parts.push(format!("7d {:.0}%", window.utilization));
+    }
+    parts.push(format!("{} accounts", usage.one_proxy.accounts));
+    format!("pool {}", parts.join(" · "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn averages_windows_across_providers() {
+        let entries = vec![
+            (
+                "codex".to_string(),
+                json!({
+                    "primary_used": 40.0,
+                    "primary_resets_at": "2026-01-01T05:00:00+00:00",
+                    "secondary_used": 10.0,
+                    "secondary_resets_at": "2026-01-07T00:00:00+00:00"
+                })
+                .to_string(),
+                200,
+            ),
+            (
+                "antigravity".to_string(),
+                json!({
+                    "models": [
+                        {"name": "gemini-3-pro", "percentage": 5, "reset_time": "2026-01-01T01:00:00Z"},
+                        {"name": "claude-sonnet-4-5", "percentage": 80, "reset_time": "2026-01-01T03:00:00Z"}
+                    ]
+                })
+                .to_string(),
+                100,
+            ),
+            (
+                "kiro".to_string(),
+                json!({"usage_limit": 1000, "current_usage": 500}).to_string(),
+                300,
+            ),
+            (
+                "codex".to_string(),
+                json!({"is_error": true, "primary_used": 100.0}).to_string(),
+                50,
+            ),
+        ];
+        let usage = summarize(&entries);
+
+        // Codex 40% used and Antigravity's Claude models 20% used
+        let five_hour = usage.five_hour.clone().unwrap();
+        assert_eq!(five_hour.utilization, 30.0);
+        assert_eq!(five_hour.resets_at.as_deref(), Some("2026-01-01T03:00:00Z"));
+        assert_eq!(usage.seven_day.clone().unwrap().utilization, 30.0);
+        assert_eq!(usage.one_proxy.accounts, 3);
+        assert_eq!(usage.one_proxy.oldest_update, Some(100));
+        assert_eq!(statusline(&usage), "pool 5h 30% · 7d 30% · 3 accounts");
+    }
+}
@@ -0,0 +1,219 @@
+// Time-of-day provider priorities
+// `model-routing.priority-schedule` lists windows written as cron-like expressions
+// ("minute hour day-of-month month day-of-week", local time unless `utc` is set). While a
+// window matches the current minute, its priorities replace the configured ones for the
+// providers it lists, e.g. to prefer Kiro at night in the US when it is less rate-limited.
+// The first matching window wins.
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+use crate::config::{PriorityWindow, ProviderPriority};
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression, one bit per allowed value of each field
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day-of-month / day-of-week were restricted; when both are, either may match
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Numeric value or, for months and weekdays, a three-letter name (first name is `min`)
+fn parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32> {
+    if let Ok(number) = value.parse::<u32>() {
+        return Ok(number);
+    }
+    names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+        .map(|index| index as u32 + min)
+        .ok_or_else(|| anyhow!("invalid value '{}'", value))
+}
+
+/// One field: `*`, values, `a-b` ranges and `/step`, separated by commas
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| anyhow!("invalid step in '{}'", part))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, names)?,
+                parse_value(end, min, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, names)?;
+            // "5/15" means every 15 starting at 5
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("'{}' is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!(
+                "expected 5 fields (minute hour day-of-month month day-of-week) in '{}'",
+                expression
+            ));
+        };
+        let with_context = |e: anyhow::Error| anyhow!("{} in '{}'", e, expression);
+        let mut weekdays = parse_field(weekday, 0, 7, WEEKDAY_NAMES).map_err(with_context)?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[]).map_err(with_context)?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(with_context)?,
+            days: parse_field(day, 1, 31, &[]).map_err(with_context)?,
+            months: parse_field(month, 1, 12, MONTH_NAMES).map_err(with_context)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month())
+            && day_matches
+    }
+}
+
+/// First window matching the given local and UTC times; invalid schedules never match
+pub fn active_window<'a>(
+    windows: &'a [PriorityWindow],
+    local: &NaiveDateTime,
+    utc: &NaiveDateTime,
+) -> Option<&'a PriorityWindow> {
+    windows.iter().find(|window| {
+        CronSchedule::parse(&window.schedule)
+            .map(|schedule| schedule.matches(if window.utc { utc } else { local }))
+            .unwrap_or(false)
+    })
+}
+
+/// Override priorities with those of a window; providers it lists that are not configured
+/// are added as enabled
+pub fn apply_window(priorities: &mut Vec<ProviderPriority>, window: &PriorityWindow) {
+    for (provider, priority) in &window.priorities {
+        match priorities.iter_mut().find(|p| &p.provider == provider) {
+            Some(existing) => existing.priority = *priority,
+            None => priorities.push(ProviderPriority {
+                provider: provider.clone(),
+                priority: *priority,
+                enabled: true,
+            }),
+        }
+    }
+}
+
+/// Apply the window active right now, if any
+pub fn apply(priorities: &mut Vec<ProviderPriority>, windows: &[PriorityWindow]) {
+    if windows.is_empty() {
+        return;
+    }
+    let local = chrono::Local::now().naive_local();
+    let utc = chrono::Utc::now().naive_utc();
+    if let Some(window) = active_window(windows, &local, &utc) {
+        apply_window(priorities, window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn matches_cron_fields() {
+        let night = CronSchedule::parse("* 22-23,0-5 * * mon-fri").unwrap();
+        // 2026-01-05 is a Monday
+        assert!(night.matches(&at("2026-01-05 23:30")));
+        assert!(night.matches(&at("2026-01-06 04:59")));
+        assert!(!night.matches(&at("2026-01-06 06:00")));
+        assert!(!night.matches(&at("2026-01-10 23:00")));
+
+        let quarter = CronSchedule::parse("*/15 9 1 jan 7").unwrap();
+        assert!(quarter.matches(&at("2026-01-01 09:45")));
+        // Day-of-month or day-of-week: 2026-01-04 is a Sunday
+        assert!(quarter.matches(&at("2026-01-04 09:00")));
+        assert!(!quarter.matches(&at("2026-01-02 09:00")));
+
+        assert!(CronSchedule::parse("* 24 * * *").is_err());
+        assert!(CronSchedule::parse("* * *").is_err());
+    }
+
+    #[test]
+    fn first_matching_window_overrides_priorities() {
+        let windows = vec![
+            PriorityWindow {
+                schedule: "* 0-5 * * *".to_string(),
+                utc: true,
+                priorities: BTreeMap::from([("kiro".to_string(), 200), ("glm".to_string(), 150)]),
+            },
+            PriorityWindow {
+                schedule: "* * * * *".to_string(),
+                utc: false,
+                priorities: BTreeMap::from([("codex".to_string(), 300)]),
+            },
+        ];
+        let utc_night = at("2026-01-05 03:00");
+        let local_day = at("2026-01-05 12:00");
+        let window = active_window(&windows, &local_day, &utc_night).unwrap();
+
+        let mut priorities = vec![ProviderPriority {
+            provider: "kiro".to_string(),
+            priority: 100,
+            enabled: false,
+        }];
+        apply_window(&mut priorities, window);
+        assert_eq!(priorities[0].priority, 200);
+        assert!(!priorities[0].enabled);
+        assert_eq!(priorities[1].provider, "glm");
+
+        let window = active_window(&windows, &local_day, &local_day).unwrap();
+        assert_eq!(window.priorities.get("codex"), Some(&300));
+    }
+}
@@ -0,0 +1,261 @@
+// Common interface for upstream chat providers
+// Each provider module translates OpenAI chat requests into its own wire format and back.
+// Handlers look providers up in the registry instead of hard-coding one branch per provider;
+// account selection and rotation stay in the handlers.
+
+use async_trait::async_trait;
+use axum::response::sse::Event;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use super::claude::ClaudeImageHandling;
+use super::handlers::ModelInfo;
+use super::kiro::KiroAuth;
+use super::mappers::post_process;
+
+/// OpenAI-format SSE events produced by a streaming chat call
+pub type EventStream = BoxStream<'static, Result<Event, Infallible>>;
+
+/// What a provider supports on the OpenAI chat surface
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ProviderCapabilities {
+    pub streaming: bool,
+    pub tools: bool,
+    pub vision: bool,
+    pub thinking: bool,
+    pub count_tokens: bool,
+}
+
+/// How a provider serves Anthropic Messages requests (`/v1/messages`)
+#[derive(Clone, Copy)]
+pub enum MessagesMapping {
+    /// The backend speaks the Messages API at this base URL, requests are forwarded as-is
+    Native { base_url: &'static str },
+    /// Requests are converted to OpenAI chat and served through `chat`/`chat_stream`
+    OpenAiChat {
+        image_handling: ClaudeImageHandling,
+        /// Drop thinking blocks from the history that the backend can't verify
+        guard_thinking: bool,
+        /// Return reasoning as text blocks, for models without thinking support
+        reasoning_as_text: bool,
+    },
+}
+
+/// Credentials of the upstream account serving a request
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    /// OAuth access token or API key, plus the Google Cloud project for Gemini-style backends
+    Token {
+        access_token: String,
+        project_id: Option<String>,
+    },
+    Kiro(KiroAuth),
+}
+
+impl Credentials {
+    pub fn token(access_token: String) -> Self {
+        Self::Token {
+            access_token,
+            project_id: None,
+        }
+    }
+
+    /// Access token and project of token-based credentials
+    pub fn access_token(&self) -> Result<(String, Option<String>), ProviderError> {
+        match self {
+            Self::Token {
+                access_token,
+                project_id,
+            } => Ok((access_token.clone(), project_id.clone())),
+            Self::Kiro(_) => Err(ProviderError::InvalidRequest(
+                "Kiro credentials cannot be used with this provider".to_string(),
+            )),
+        }
+    }
+}
+
+/// Per-request inputs shared by all provider calls
+pub struct ChatContext<'a> {
+    pub credentials: &'a Credentials,
+    /// Model name as the provider expects it (prefix and effort level already stripped)
+    pub model: &'a str,
+    pub request_id: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub enum ProviderError {
+    /// The request cannot be translated for this provider
+    InvalidRequest(String),
+    /// This account cannot serve the request right now, the next account should be tried
+    AccountUnavailable(String),
+    /// The upstream call failed
+    Upstream(String),
+    /// The backend is overloaded (Anthropic 529) even after retries; the next account is
+    /// tried, and the client gets 529 when none is left
+    Overloaded(String),
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRequest(msg)
+            | Self::AccountUnavailable(msg)
+            | Self::Upstream(msg)
+            | Self::Overloaded(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ProviderError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<Overloaded>() {
+            Ok(overloaded) => Self::Overloaded(overloaded.0),
+            Err(e) => Self::Upstream(e.to_string()),
+        }
+    }
+}
+
+/// Error returned by upstream clients when the backend reports overload, so it can be told
+/// apart from other failures through `anyhow`
+#[derive(Debug)]
+pub struct Overloaded(pub String);
+
+impl std::fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+/// Turn `chat.completion.chunk` payloads into SSE events, applying the provider's
+/// configured response post-processing
+pub fn chunk_events<S>(provider_id: &str, chunks: S) -> EventStream
+where
+    S: Stream<Item = String> + Send + 'static,
+{
+    post_process::process_openai_chunks(provider_id, chunks)
+        .map(|chunk| Ok(Event::default().data(chunk)))
+        .boxed()
+}
+
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    /// Routing key, the prefix used in `provider/model`
+    fn id(&self) -> &'static str;
+
+    /// Name used in error messages
+    fn display_name(&self) -> &'static str;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    /// OpenAI chat parameters the request mapping does not forward
+    fn ignored_params(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// How Messages requests for `model` reach this provider
+    fn messages_mapping(&self, _model: &str) -> MessagesMapping {
+        MessagesMapping::OpenAiChat {
+            image_handling: ClaudeImageHandling::Base64AndUrl,
+            guard_thinking: false,
+            reasoning_as_text: false,
+        }
+    }
+
+    /// Models this provider exposes, already prefixed with its id
+    fn list_models(&self) -> Vec<ModelInfo>;
+
+    /// Non-streaming chat, returns an OpenAI `chat.completion` object
+    async fn chat(&self, ctx: &ChatContext<'_>, request: &Value) -> Result<Value, ProviderError>;
+
+    /// Streaming chat, returns OpenAI `chat.completion.chunk` events
+    async fn chat_stream(
+        &self,
+        ctx: &ChatContext<'_>,
+        request: &Value,
+    ) -> Result<EventStream, ProviderError>;
+
+    /// Count input tokens for a request in the provider's native format
+    async fn count_tokens(
+        &self,
+        _ctx: &ChatContext<'_>,
+        _request: &Value,
+    ) -> Result<Value, ProviderError> {
+        Err(ProviderError::InvalidRequest(format!(
+            "{} does not support token counting",
+            self.display_name()
+        )))
+    }
+}
+
+static REGISTRY: Lazy<RwLock<Vec<Arc<dyn ChatProvider>>>> = Lazy::new(|| {
+    RwLock::new(vec![
+        Arc::new(super::gemini::GeminiProvider) as Arc<dyn ChatProvider>,
+        Arc::new(super::codex::CodexProvider),
+        Arc::new(super::antigravity::AntigravityProvider),
+        Arc::new(super::claude::ClaudeProvider::anthropic()),
+        Arc::new(super::claude::ClaudeProvider::kimi()),
+        Arc::new(super::claude::ClaudeProvider::glm()),
+        Arc::new(super::kiro::KiroProvider),
+    ])
+});
+
+/// Look up a provider by its routing key
+pub fn get(id: &str) -> Option<Arc<dyn ChatProvider>> {
+    REGISTRY
+        .read()
+        .iter()
+        .find(|provider| provider.id() == id)
+        .cloned()
+}
+
+/// All registered providers in registration order
+pub fn all() -> Vec<Arc<dyn ChatProvider>> {
+    REGISTRY.read().clone()
+}
+
+/// Register an additional provider, replacing any existing one with the same id
+pub fn register(provider: Arc<dyn ChatProvider>) {
+    let mut registry = REGISTRY.write();
+    registry.retain(|existing| existing.id() != provider.id());
+    registry.push(provider);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_contains_builtin_providers() {
+        for id in [
+            "gemini",
+            "codex",
+            "antigravity",
+            "claude",
+            "kimi",
+            "glm",
+            "kiro",
+        ] {
+            let provider = get(id).unwrap_or_else(|| panic!("{} is not registered", id));
+            assert_eq!(provider.id(), id);
+        }
+        assert!(get("unknown").is_none());
+    }
+
+    #[test]
+    fn only_claude_supports_token_counting() {
+        let counting: Vec<&str> = all()
+            .iter()
+            .filter(|provider| provider.capabilities().count_tokens)
+            .map(|provider| provider.id())
+            .collect();
+        assert_eq!(counting, vec!["claude"]);
+    }
+}
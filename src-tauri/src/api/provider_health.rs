@@ -0,0 +1,194 @@
+// Provider outage detection
+// Every proxied request reports its outcome per provider. Providers whose recent upstream
+// error rate crosses a threshold are marked degraded or down; aggregation routing tries them
+// last until the errors age out of the window, and the UI shows the state as a banner.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::events;
+
+/// Outcomes older than this no longer count towards a provider's status
+const WINDOW: Duration = Duration::from_secs(300);
+/// Fewer outcomes than this in the window are not enough to judge a provider
+const MIN_SAMPLES: usize = 5;
+/// Error rate at which a provider is considered degraded
+const DEGRADED_ERROR_RATE: f64 = 0.25;
+/// Error rate at which a provider is considered down
+const DOWN_ERROR_RATE: f64 = 0.6;
+/// Outcomes kept per provider, older ones are dropped first
+const MAX_SAMPLES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Healthy,
+    Degraded,
+    Down,
+}
+
+/// Status of one provider over the recent window (payload of `get_provider_status`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub provider: String,
+    pub state: HealthState,
+    pub requests: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Unix millis of the last state change, if there was one
+    pub since: Option<i64>,
+}
+
+#[derive(Default)]
+struct ProviderHealth {
+    outcomes: VecDeque<(Instant, bool)>,
+    state: Option<HealthState>,
+    since: Option<i64>,
+}
+
+impl ProviderHealth {
+    fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.outcomes.front() {
+            if now.duration_since(*at) <= WINDOW && self.outcomes.len() <= MAX_SAMPLES {
+                break;
+            }
+            self.outcomes.pop_front();
+        }
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        let errors = self.outcomes.iter().filter(|(_, ok)| !ok).count();
+        (self.outcomes.len(), errors)
+    }
+
+    fn evaluate(&self) -> HealthState {
+        let (requests, errors) = self.counts();
+        state_for(requests, errors)
+    }
+}
+
+static HEALTH: Lazy<Mutex<HashMap<String, ProviderHealth>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn state_for(requests: usize, errors: usize) -> HealthState {
+    if requests < MIN_SAMPLES {
+        return HealthState::Healthy;
+    }
+    let rate = errors as f64 / requests as f64;
+    if rate >= DOWN_ERROR_RATE {
+        HealthState::Down
+    } else if rate >= DEGRADED_ERROR_RATE {
+        HealthState::Degraded
+    } else {
+        HealthState::Healthy
+    }
+}
+
+/// Whether a response status counts as a provider failure
+/// Client errors and rate limits are about the request or the account, not the provider.
+pub fn is_provider_failure(status: i32) -> bool {
+    status >= 500
+}
+
+/// Record the outcome of a request served by `provider`
+pub fn record(provider: &str, success: bool) {
+    let now = Instant::now();
+    let changed = {
+        let mut health = HEALTH.lock();
+        let entry = health.entry(provider.to_string()).or_default();
+        entry.outcomes.push_back((now, success));
+        entry.prune(now);
+
+        let state = entry.evaluate();
+        let previous = entry.state.replace(state).unwrap_or(HealthState::Healthy);
+        if state != previous {
+            entry.since = Some(chrono::Utc::now().timestamp_millis());
+            Some((previous, state))
+        } else {
+            None
+        }
+    };
+
+    if let Some((previous, state)) = changed {
+        tracing::warn!(
+            "[ProviderHealth] {} changed from {:?} to {:?}",
+            provider,
+            previous,
+            state
+        );
+        events::emit(events::PROVIDER_STATUS_CHANGED, status_of(provider));
+    }
+}
+
+/// Current state of a provider, providers without recent traffic are healthy
+pub fn state_of(provider: &str) -> HealthState {
+    let mut health = HEALTH.lock();
+    match health.get_mut(provider) {
+        Some(entry) => {
+            entry.prune(Instant::now());
+            entry.evaluate()
+        }
+        None => HealthState::Healthy,
+    }
+}
+
+fn status_of(provider: &str) -> ProviderStatus {
+    let mut health = HEALTH.lock();
+    let entry = health.entry(provider.to_string()).or_default();
+    entry.prune(Instant::now());
+    let (requests, errors) = entry.counts();
+    ProviderStatus {
+        provider: provider.to_string(),
+        state: state_for(requests, errors),
+        requests,
+        errors,
+        error_rate: if requests == 0 {
+            0.0
+        } else {
+            errors as f64 / requests as f64
+        },
+        since: entry.since,
+    }
+}
+
+/// Status of every registered provider
+pub fn get_provider_status() -> Vec<ProviderStatus> {
+    super::provider::all()
+        .iter()
+        .map(|provider| status_of(provider.id()))
+        .collect()
+}
+
+/// Move degraded and down providers behind healthy ones, keeping priority order otherwise
+pub fn order_by_health(providers: &mut [String]) {
+    providers.sort_by_key(|provider| state_of(provider));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_error_rates_once_there_are_enough_samples() {
+        assert_eq!(state_for(3, 3), HealthState::Healthy);
+        assert_eq!(state_for(10, 1), HealthState::Healthy);
+        assert_eq!(state_for(10, 3), HealthState::Degraded);
+        assert_eq!(state_for(10, 7), HealthState::Down);
+    }
+
+    #[test]
+    fn routes_around_failing_providers() {
+        for _ in 0..MIN_SAMPLES {
+            record("test-down", false);
+            record("test-up", true);
+        }
+        assert_eq!(state_of("test-down"), HealthState::Down);
+
+        let mut providers = vec!["test-down".to_string(), "test-up".to_string()];
+        order_by_health(&mut providers);
+        assert_eq!(providers, vec!["test-up", "test-down"]);
+    }
+}
@@ -0,0 +1,260 @@
+// Race mode
+// For latency-critical requests the same request is sent to the two providers the model
+// router would try first, and the first successful response (for streams: the first stream
+// that starts) is returned; the other attempt is dropped, which cancels its upstream request.
+// Clients opt in with the x-oneproxy-race header, or always through
+// `model-routing.race-api-keys`. It only applies to models without a provider prefix in model
+// aggregation mode, and trades quota for latency.
+//
+// The winner is logged by the logging middleware as usual; the other attempt gets its own
+// request log entry tagged "race", as failed or as cancelled (499).
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::Response;
+use futures::future::{self, Either, FutureExt};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::time::Instant;
+
+use super::handlers::{parse_provider_prefix, select_best_provider_for_aggregation};
+use super::model_router::{get_provider_model_name, resolve_model, ResolvedModel};
+
+/// Request header asking for race mode ("1", "true" or "on")
+pub const X_ONEPROXY_RACE: &str = "x-oneproxy-race";
+
+/// Response header naming the providers of a race, e.g. "won=codex; lost=claude"
+pub const X_ONEPROXY_RACE_RESULT: &str = "x-oneproxy-race-result";
+
+/// One side of a race: provider and the prefixed model sent to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaceTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+fn race_requested(headers: &HeaderMap, race_api_keys: &[String]) -> bool {
+    let by_header = headers
+        .get(X_ONEPROXY_RACE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "on"));
+    let by_key = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth))
+        .is_some_and(|key| race_api_keys.iter().any(|k| k == key));
+    by_header || by_key
+}
+
+/// Up to `count` providers for a model without a provider prefix, in the order the model
+/// router would try them, each with the prefixed model to send
+pub fn provider_targets(raw_model: &str, count: usize) -> Vec<RaceTarget> {
+    if parse_provider_prefix(raw_model).0.is_some() {
+        return Vec::new();
+    }
+    let ResolvedModel::Aggregated {
+        provider,
+        fallbacks,
+        ..
+    } = resolve_model(raw_model, None)
+    else {
+        return Vec::new();
+    };
+    let (first, remaining) = select_best_provider_for_aggregation(&provider, &fallbacks);
+    std::iter::once(first)
+        .chain(remaining)
+        .take(count)
+        .map(|provider| RaceTarget {
+            model: format!(
+                "{}/{}",
+                provider,
+                get_provider_model_name(raw_model, &provider)
+            ),
+            provider,
+        })
+        .collect()
+}
+
+/// The two providers to race for a request, `None` when it should be served normally
+pub fn targets(headers: &HeaderMap, request: &Value) -> Option<[RaceTarget; 2]> {
+    let config = crate::config::get_config()?;
+    if !race_requested(headers, &config.model_routing.race_api_keys) {
+        return None;
+    }
+    let raw_model = request.get("model").and_then(|v| v.as_str())?;
+    provider_targets(raw_model, 2).try_into().ok()
+}
+
+/// Save the request log of an attempt that did not produce the response; `None` means it
+/// was cancelled because the other provider answered first
+fn log_attempt(path: &str, target: &RaceTarget, response: Option<&Response>, start: Instant) {
+    let (status, error, account_id) = match response {
+        Some(response) => {
+            let status = response.status().as_u16() as i32;
+            super::provider_health::record(
+                &target.provider,
+                !super::provider_health::is_provider_failure(status),
+            );
+            let account_id = response
+                .headers()
+                .get(super::X_ONEPROXY_ACCOUNT_ID)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            (
+                status,
+                format!("race attempt failed: HTTP {}", status),
+                account_id,
+            )
+        }
+        None => (
+            super::CLIENT_CLOSED_REQUEST,
+            "race lost, cancelled".to_string(),
+            None,
+        ),
+    };
+    let model = super::normalize_model_name(parse_provider_prefix(&target.model).1.as_str());
+    let _ = crate::db::save_request_log(&crate::db::NewRequestLog {
+        status,
+        method: "POST",
+        model: Some(&model),
+        protocol: super::protocol_from_path(path).as_deref(),
+        provider: Some(&target.provider),
+        account_id: account_id.as_deref(),
+        path,
+        duration_ms: start.elapsed().as_millis() as i64,
+        error_message: Some(&error),
+        tags: &["race".to_string()],
+        tenant: super::tenant::current().as_deref(),
+        ..Default::default()
+    });
+}
+
+fn annotate(mut response: Response, winner: &RaceTarget, loser: &RaceTarget) -> Response {
+    let result = format!("won={}; lost={}", winner.provider, loser.provider);
+    if let Ok(value) = HeaderValue::from_str(&result) {
+        response.headers_mut().insert(X_ONEPROXY_RACE_RESULT, value);
+    }
+    response
+}
+
+/// Serve `request` from both targets and return the first successful response; if the first
+/// to finish failed, the other one is awaited and returned whatever its outcome
+pub async fn race<F, Fut>(
+    request: Value,
+    targets: [RaceTarget; 2],
+    path: &str,
+    serve: F,
+) -> Response
+where
+    F: Fn(Value) -> Fut,
+    Fut: Future<Output = Response> + Send,
+{
+    let start = Instant::now();
+    let [first, second] = targets;
+    tracing::info!("[Race] Racing {} against {}", first.model, second.model);
+    let attempt = |target: &RaceTarget| {
+        let mut request = request.clone();
+        request["model"] = json!(target.model);
+        serve(request).boxed()
+    };
+
+    let (done, done_target, pending, pending_target) =
+        match future::select(attempt(&first), attempt(&second)).await {
+            Either::Left((response, pending)) => (response, first, pending, second),
+            Either::Right((response, pending)) => (response, second, pending, first),
+        };
+
+    if done.status().is_success() {
+        // Dropping the other attempt cancels its upstream request
+        drop(pending);
+        tracing::info!(
+            "[Race] {} answered first after {} ms, cancelled {}",
+            done_target.provider,
+            start.elapsed().as_millis(),
+            pending_target.provider
+        );
+        log_attempt(path, &pending_target, None, start);
+        return annotate(done, &done_target, &pending_target);
+    }
+
+    tracing::warn!(
+        "[Race] {} failed with {}, waiting for {}",
+        done_target.provider,
+        done.status(),
+        pending_target.provider
+    );
+    log_attempt(path, &done_target, Some(&done), start);
+    let response = pending.await;
+    annotate(response, &pending_target, &done_target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use std::time::Duration;
+
+    #[test]
+    fn race_is_opt_in() {
+        let mut headers = HeaderMap::new();
+        assert!(!race_requested(&headers, &[]));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer sk-fast"),
+        );
+        assert!(race_requested(&headers, &["sk-fast".to_string()]));
+        assert!(!race_requested(&headers, &["sk-other".to_string()]));
+        headers.insert(X_ONEPROXY_RACE, HeaderValue::from_static("True"));
+        assert!(race_requested(&headers, &[]));
+    }
+
+    #[tokio::test]
+    async fn returns_the_first_successful_response() {
+        let targets = || {
+            [
+                RaceTarget {
+                    provider: "codex".to_string(),
+                    model: "codex/gpt-5".to_string(),
+                },
+                RaceTarget {
+                    provider: "claude".to_string(),
+                    model: "claude/gpt-5".to_string(),
+                },
+            ]
+        };
+        // The faster provider fails, so the slower one's answer is used
+        let serve = |request: Value| async move {
+            let (delay, status) = match request["model"].as_str() {
+                Some("codex/gpt-5") => (1, StatusCode::TOO_MANY_REQUESTS),
+                _ => (20, StatusCode::OK),
+            };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let request = json!({"model": "gpt-5"});
+        let response = race(request.clone(), targets(), "/v1/chat/completions", serve).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[X_ONEPROXY_RACE_RESULT],
+            "won=claude; lost=codex"
+        );
+
+        let fast_ok = |request: Value| async move {
+            let delay = if request["model"] == "claude/gpt-5" {
+                1
+            } else {
+                500
+            };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Response::new(Body::empty())
+        };
+        let response = race(request, targets(), "/v1/chat/completions", fast_ok).await;
+        assert_eq!(
+            response.headers()[X_ONEPROXY_RACE_RESULT],
+            "won=claude; lost=codex"
+        );
+    }
+}
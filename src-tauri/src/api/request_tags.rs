@@ -0,0 +1,120 @@
+// Request tags for usage attribution
+// Clients label requests with the X-OneProxy-Tag header or with the `metadata`/`user` fields
+// OpenAI and Anthropic already accept. Tags are stored with the request log so usage can be
+// filtered by project or script.
+
+use axum::http::HeaderMap;
+use serde_json::Value;
+
+/// Request header carrying comma-separated tags, may be repeated
+pub const X_ONEPROXY_TAG: &str = "x-oneproxy-tag";
+
+/// Upper bound on tags kept per request
+const MAX_TAGS: usize = 16;
+/// Upper bound on the length of a single tag, in characters
+const MAX_TAG_LEN: usize = 64;
+
+/// Tags from the X-OneProxy-Tag header only
+pub fn tags_from_headers(headers: &HeaderMap) -> Vec<String> {
+    let mut tags = Vec::new();
+    for value in headers.get_all(X_ONEPROXY_TAG) {
+        if let Ok(value) = value.to_str() {
+            for tag in value.split(',') {
+                push_tag(&mut tags, tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Tags from the header plus the request body
+/// `metadata.tag`/`metadata.tags` are taken as-is, other string metadata entries become
+/// `key:value`, and the OpenAI `user` / Anthropic `metadata.user_id` become `user:<id>`.
+pub fn extract_request_tags(headers: &HeaderMap, body: &[u8]) -> Vec<String> {
+    let mut tags = tags_from_headers(headers);
+    let Ok(json) = serde_json::from_slice::<Value>(body) else {
+        return tags;
+    };
+
+    if let Some(metadata) = json.get("metadata").and_then(|v| v.as_object()) {
+        for (key, value) in metadata {
+            match (key.as_str(), value) {
+                ("tag" | "tags", Value::String(value)) => {
+                    for tag in value.split(',') {
+                        push_tag(&mut tags, tag);
+                    }
+                }
+                ("tag" | "tags", Value::Array(values)) => {
+                    for tag in values.iter().filter_map(|v| v.as_str()) {
+                        push_tag(&mut tags, tag);
+                    }
+                }
+                ("user_id", Value::String(user)) => push_tag(&mut tags, &format!("user:{}", user)),
+                (_, Value::String(value)) => push_tag(&mut tags, &format!("{}:{}", key, value)),
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(user) = json.get("user").and_then(|v| v.as_str()) {
+        push_tag(&mut tags, &format!("user:{}", user));
+    }
+    tags
+}
+
+/// Add a trimmed tag, skipping empty ones and duplicates
+/// Commas are the storage separator, so they are removed from tag text.
+fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    if tags.len() >= MAX_TAGS {
+        return;
+    }
+    let tag: String = tag
+        .trim()
+        .chars()
+        .filter(|c| *c != ',' && !c.is_control())
+        .take(MAX_TAG_LEN)
+        .collect();
+    if !tag.is_empty() && !tags.contains(&tag) {
+        tags.push(tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn collects_tags_from_header_metadata_and_user() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            X_ONEPROXY_TAG,
+            HeaderValue::from_static("project-a, nightly"),
+        );
+        headers.append(X_ONEPROXY_TAG, HeaderValue::from_static("nightly"));
+        let body = br#"{
+            "model": "gpt-5",
+            "user": "alice",
+            "metadata": {"tags": ["batch", ""], "script": "sync.py", "retries": 3}
+        }"#;
+
+        assert_eq!(
+            extract_request_tags(&headers, body),
+            vec![
+                "project-a",
+                "nightly",
+                "script:sync.py",
+                "batch",
+                "user:alice"
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_anthropic_user_id_and_ignores_invalid_bodies() {
+        let headers = HeaderMap::new();
+        let body = br#"{"metadata": {"user_id": "user_123"}}"#;
+        assert_eq!(extract_request_tags(&headers, body), vec!["user:user_123"]);
+        assert!(extract_request_tags(&headers, b"not json").is_empty());
+    }
+}
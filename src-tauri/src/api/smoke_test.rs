@@ -0,0 +1,324 @@
+// End-to-end smoke test
+// Sends a tiny prompt through the running server for every provider with an enabled account
+// (and every configured custom provider), once per protocol endpoint: OpenAI chat, Claude
+// messages, Gemini generateContent and, for Codex, the Responses API. The result is a
+// pass/fail matrix with latencies, to check a new install or a config change end to end.
+// Requests go over loopback with the first configured API key and carry the `smoke-test`
+// request tag, so they can be told apart in the request log.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use super::provider;
+use super::request_tags::X_ONEPROXY_TAG;
+
+const PROMPT: &str = "Reply with the single word OK.";
+
+/// Upper bound per request, thinking models can take a while even for a tiny prompt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Error text kept per failed check
+const MAX_ERROR_LEN: usize = 300;
+
+/// Protocol endpoint exercised by a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    Openai,
+    Claude,
+    Gemini,
+    Responses,
+}
+
+/// One provider/protocol cell of the matrix
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeCheck {
+    pub provider: String,
+    pub model: String,
+    pub protocol: Protocol,
+    pub passed: bool,
+    /// HTTP status, `None` when no response arrived
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeTestReport {
+    pub base_url: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub checks: Vec<SmokeCheck>,
+}
+
+/// A provider to test and the model used for it, with the provider prefix
+#[derive(Debug, Clone, PartialEq)]
+struct Target {
+    provider: String,
+    model: String,
+}
+
+/// Registry id of the provider an account belongs to
+fn registry_id(account_provider: &str) -> &str {
+    match account_provider {
+        "google" => "gemini",
+        "openai" => "codex",
+        "anthropic" => "claude",
+        other => other,
+    }
+}
+
+/// Loopback address reaching the server whatever host it is bound to
+fn local_base_url(bind: SocketAddr) -> String {
+    let ip = match bind.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    format!("http://{}", SocketAddr::new(ip, bind.port()))
+}
+
+/// Providers to test, one model each
+async fn targets(config: &crate::config::AppConfig) -> Result<Vec<Target>> {
+    let enabled: BTreeSet<String> = crate::auth::list_accounts()
+        .await?
+        .into_iter()
+        .filter(|account| account.enabled)
+        .map(|account| registry_id(&account.provider).to_string())
+        .collect();
+
+    let mut targets: Vec<Target> = provider::all()
+        .into_iter()
+        .filter(|chat_provider| enabled.contains(chat_provider.id()))
+        .filter_map(|chat_provider| {
+            // Image models answer a text prompt with an error or an image
+            let model = chat_provider
+                .list_models()
+                .into_iter()
+                .map(|model| model.id)
+                .find(|id| !id.contains("image"))?;
+            Some(Target {
+                provider: chat_provider.id().to_string(),
+                model,
+            })
+        })
+        .collect();
+
+    let custom = config
+        .openai_compatibility
+        .iter()
+        .map(|entry| {
+            (
+                &entry.name,
+                &entry.prefix,
+                &entry.api_key_entries,
+                &entry.models,
+            )
+        })
+        .chain(config.claude_code_compatibility.iter().map(|entry| {
+            (
+                &entry.name,
+                &entry.prefix,
+                &entry.api_key_entries,
+                &entry.models,
+            )
+        }));
+    for (name, prefix, keys, models) in custom {
+        let Some(model) = models.first().filter(|_| !keys.is_empty()) else {
+            continue;
+        };
+        let prefix = prefix.as_ref().unwrap_or(name).to_lowercase();
+        targets.push(Target {
+            provider: prefix.clone(),
+            model: format!("{}/{}", prefix, model),
+        });
+    }
+    Ok(targets)
+}
+
+/// Endpoint and body of a check
+fn request_for(protocol: Protocol, model: &str) -> (String, Value) {
+    match protocol {
+        Protocol::Openai => (
+            "/v1/chat/completions".to_string(),
+            json!({
+                "model": model,
+                "stream": false,
+                "messages": [{"role": "user", "content": PROMPT}]
+            }),
+        ),
+        Protocol::Claude => (
+            "/v1/messages".to_string(),
+            json!({
+                "model": model,
+                "max_tokens": 256,
+                "messages": [{"role": "user", "content": PROMPT}]
+            }),
+        ),
+        Protocol::Gemini => (
+            format!("/v1beta/models/{}:generateContent", model),
+            json!({"contents": [{"role": "user", "parts": [{"text": PROMPT}]}]}),
+        ),
+        Protocol::Responses => (
+            "/v1/responses".to_string(),
+            json!({"model": model, "stream": false, "input": PROMPT}),
+        ),
+    }
+}
+
+/// Protocols checked for a provider; the Responses API only serves Codex
+fn protocols_for(provider: &str) -> Vec<Protocol> {
+    let mut protocols = vec![Protocol::Openai, Protocol::Claude, Protocol::Gemini];
+    if provider == "codex" {
+        protocols.push(Protocol::Responses);
+    }
+    protocols
+}
+
+/// Error of a finished request; some handlers report errors with a 200 status
+fn failure(status: u16, body: &str) -> Option<String> {
+    let json = serde_json::from_str::<Value>(body).ok();
+    let error = json.as_ref().and_then(|json| json.get("error"));
+    if (200..300).contains(&status) && error.is_none() && json.is_some() {
+        return None;
+    }
+    let message = error
+        .and_then(|error| {
+            error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .or_else(|| error.as_str().map(|s| s.to_string()))
+        })
+        .unwrap_or_else(|| body.to_string());
+    Some(message.chars().take(MAX_ERROR_LEN).collect())
+}
+
+async fn check(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    target: &Target,
+    protocol: Protocol,
+) -> SmokeCheck {
+    let (path, body) = request_for(protocol, &target.model);
+    let mut request = client
+        .post(format!("{}{}", base_url, path))
+        .header(X_ONEPROXY_TAG, "smoke-test")
+        .json(&body);
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+
+    let start = Instant::now();
+    let (status, error) = match request.send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let text = response.text().await.unwrap_or_default();
+            (Some(status), failure(status, &text))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    };
+    SmokeCheck {
+        provider: target.provider.clone(),
+        model: target.model.clone(),
+        protocol,
+        passed: error.is_none(),
+        status,
+        latency_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// Run the smoke test against the running server
+pub async fn run() -> Result<SmokeTestReport> {
+    if !super::is_server_running() {
+        return Err(anyhow!("The API server is not running"));
+    }
+    let config = crate::config::get_config().ok_or_else(|| anyhow!("Config not initialized"))?;
+    let api_key = config.api_keys.first().cloned();
+    if api_key.is_none() && !config.tenants.is_empty() {
+        return Err(anyhow!(
+            "The smoke test needs an entry in api-keys when tenants are configured"
+        ));
+    }
+    let base_url = local_base_url(crate::config::bind_addr(&config)?);
+
+    let targets = targets(&config).await?;
+    if targets.is_empty() {
+        return Err(anyhow!("No enabled accounts or custom providers to test"));
+    }
+
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    tracing::info!("[SmokeTest] Testing {} provider(s)", targets.len());
+    // Providers run side by side, the protocols of one provider one after another
+    let per_provider = futures::future::join_all(targets.iter().map(|target| {
+        let client = &client;
+        let base_url = &base_url;
+        let api_key = api_key.as_deref();
+        async move {
+            let mut checks = Vec::new();
+            for protocol in protocols_for(&target.provider) {
+                checks.push(check(client, base_url, api_key, target, protocol).await);
+            }
+            checks
+        }
+    }))
+    .await;
+
+    let checks: Vec<SmokeCheck> = per_provider.into_iter().flatten().collect();
+    let passed = checks.iter().filter(|check| check.passed).count();
+    let failed = checks.len() - passed;
+    tracing::info!("[SmokeTest] {} passed, {} failed", passed, failed);
+    Ok(SmokeTestReport {
+        base_url,
+        passed,
+        failed,
+        checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_responses_and_addresses() {
+        assert_eq!(failure(200, r#"{"choices": []}"#), None);
+        assert_eq!(
+            failure(
+                200,
+                r#"{"error": {"message": "No valid Codex credentials"}}"#
+            )
+            .as_deref(),
+            Some("No valid Codex credentials")
+        );
+        assert_eq!(failure(502, "bad gateway").as_deref(), Some("bad gateway"));
+        assert_eq!(
+            failure(400, r#"{"error": "failed"}"#).as_deref(),
+            Some("failed")
+        );
+
+        assert_eq!(
+            local_base_url("0.0.0.0:8317".parse().unwrap()),
+            "http://127.0.0.1:8317"
+        );
+        assert_eq!(
+            local_base_url("[::]:8317".parse().unwrap()),
+            "http://[::1]:8317"
+        );
+        assert_eq!(
+            local_base_url("100.64.0.1:8317".parse().unwrap()),
+            "http://100.64.0.1:8317"
+        );
+        assert_eq!(protocols_for("codex").len(), 4);
+        assert_eq!(protocols_for("kiro").len(), 3);
+    }
+}
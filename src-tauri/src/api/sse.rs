@@ -0,0 +1,195 @@
+// Server-sent event reassembly for upstream streams
+// Upstream SSE bodies arrive in arbitrary network frames: a frame can end mid-line, inside a
+// multi-byte UTF-8 character or, with some providers, in the middle of a JSON payload that
+// continues on the next `data:` line. `SseBuffer` keeps partial input across frames and only
+// hands out complete event payloads, so nothing is dropped as unparsable.
+
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+
+/// Incremental SSE parser yielding the data of each complete event
+#[derive(Debug, Default)]
+pub struct SseBuffer {
+    /// Bytes after the last newline
+    pending: Vec<u8>,
+    /// `data:` lines of the event being assembled
+    data: Vec<String>,
+}
+
+/// A JSON object/array that parses, or the OpenAI end marker
+fn is_complete(payload: &str) -> bool {
+    payload == "[DONE]"
+        || (payload.starts_with(['{', '['])
+            && serde_json::from_str::<serde::de::IgnoredAny>(payload).is_ok())
+}
+
+/// Payload of the data lines if it is complete, joined per the SSE spec or, for upstreams that
+/// wrap one JSON document over several `data:` lines mid-token, concatenated
+fn complete_payload(lines: &[String]) -> Option<String> {
+    let joined = lines.join("\n");
+    if is_complete(joined.trim()) {
+        return Some(joined.trim().to_string());
+    }
+    if lines.len() > 1 {
+        let concatenated = lines.concat();
+        if is_complete(concatenated.trim()) {
+            return Some(concatenated.trim().to_string());
+        }
+    }
+    None
+}
+
+impl SseBuffer {
+    /// Feed a network frame, returning the payloads of the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            // A newline byte never occurs inside a multi-byte character, so complete lines
+            // always decode cleanly
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line[..pos]);
+            self.push_line(line.trim_end_matches('\r'), &mut events);
+        }
+        events
+    }
+
+    /// Flush at the end of the stream: a last line without a newline and an event missing its
+    /// terminating blank line are still delivered
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        if !self.pending.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).to_string();
+            self.push_line(line.trim_end_matches('\r'), &mut events);
+        }
+        self.flush(&mut events);
+        events
+    }
+
+    fn push_line(&mut self, line: &str, events: &mut Vec<String>) {
+        if line.is_empty() {
+            self.flush(events);
+            return;
+        }
+        // `event:`, `id:`, `retry:` and comments carry nothing the converters use
+        let Some(value) = line.strip_prefix("data:") else {
+            return;
+        };
+        self.data
+            .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        // Most upstreams send one JSON document per line and some omit the blank line between
+        // events, so a complete payload is delivered right away instead of waiting for it
+        if let Some(payload) = complete_payload(&self.data) {
+            self.data.clear();
+            events.push(payload);
+        }
+    }
+
+    fn flush(&mut self, events: &mut Vec<String>) {
+        if self.data.is_empty() {
+            return;
+        }
+        let payload =
+            complete_payload(&self.data).unwrap_or_else(|| self.data.join("\n").trim().to_string());
+        self.data.clear();
+        if !payload.is_empty() {
+            events.push(payload);
+        }
+    }
+}
+
+/// Payloads of the events in an upstream SSE byte stream (see `http_client::byte_stream`)
+pub fn data_stream<S>(upstream: S) -> BoxStream<'static, std::io::Result<String>>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+{
+    async_stream::stream! {
+        let mut upstream = Box::pin(upstream);
+        let mut buffer = SseBuffer::default();
+        while let Some(chunk) = upstream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    for payload in buffer.push(&bytes) {
+                        yield Ok(payload);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        for payload in buffer.finish() {
+            yield Ok(payload);
+        }
+    }
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = concat!(
+        ": keep-alive\n",
+        "event: message\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"héllo 世界\"}}]}\r\n",
+        "\r\n",
+        "data: {\"choices\":[{\"delta\":\n",
+        "data: {\"content\":\"split\"}}]}\n",
+        "\n",
+        "data: {\"type\":\"response.output_text.delta\",\"delta\":\"mid-tok\n",
+        "data: en\"}\n",
+        "data: {\"no\":\"blank line\"}\n",
+        "data: [DONE]",
+    );
+
+    fn expected() -> Vec<String> {
+        vec![
+            r#"{"choices":[{"delta":{"content":"héllo 世界"}}]}"#.to_string(),
+            "{\"choices\":[{\"delta\":\n{\"content\":\"split\"}}]}".to_string(),
+            r#"{"type":"response.output_text.delta","delta":"mid-token"}"#.to_string(),
+            r#"{"no":"blank line"}"#.to_string(),
+            "[DONE]".to_string(),
+        ]
+    }
+
+    fn parse_frames(frames: &[&[u8]]) -> Vec<String> {
+        let mut buffer = SseBuffer::default();
+        let mut events: Vec<String> = frames.iter().flat_map(|f| buffer.push(f)).collect();
+        events.extend(buffer.finish());
+        events
+    }
+
+    #[test]
+    fn reassembles_events_at_every_split_point() {
+        let bytes = FIXTURE.as_bytes();
+        assert_eq!(parse_frames(&[bytes]), expected());
+        for split in 1..bytes.len() {
+            let (head, tail) = bytes.split_at(split);
+            assert_eq!(
+                parse_frames(&[head, tail]),
+                expected(),
+                "split at {}",
+                split
+            );
+        }
+        let single_bytes: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(parse_frames(&single_bytes), expected());
+    }
+
+    #[tokio::test]
+    async fn data_stream_flushes_trailing_event() {
+        let frames: Vec<std::io::Result<Bytes>> = FIXTURE
+            .as_bytes()
+            .chunks(5)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let events: Vec<String> = data_stream(futures::stream::iter(frames))
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events, expected());
+    }
+}
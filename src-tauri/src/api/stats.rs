@@ -0,0 +1,202 @@
+// Per-provider request statistics
+// Bucketed latency and payload-size histograms kept in memory since the server started, for
+// capacity planning. Exposed through the `get_request_stats` command and, in Prometheus text
+// format, on `/metrics`.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Upper bounds of the latency buckets in milliseconds
+const LATENCY_BUCKETS_MS: &[u64] = &[
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000,
+];
+
+/// Upper bounds of the payload-size buckets in bytes
+const SIZE_BUCKETS_BYTES: &[u64] = &[
+    1 << 10,
+    4 << 10,
+    16 << 10,
+    64 << 10,
+    256 << 10,
+    1 << 20,
+    4 << 20,
+];
+
+/// Fixed-bucket histogram; `counts` has one entry per bound plus a final overflow bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub bounds: &'static [u64],
+    pub counts: Vec<u64>,
+    pub sum: u64,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [u64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: u64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Prometheus `_bucket`/`_sum`/`_count` lines (buckets are cumulative there)
+    fn write_prometheus(&self, out: &mut String, name: &str, provider: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{provider=\"{}\",le=\"{}\"}} {}",
+                name, provider, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{provider=\"{}\",le=\"+Inf\"}} {}",
+            name, provider, self.count
+        );
+        let _ = writeln!(
+            out,
+            "{}_sum{{provider=\"{}\"}} {}",
+            name, provider, self.sum
+        );
+        let _ = writeln!(
+            out,
+            "{}_count{{provider=\"{}\"}} {}",
+            name, provider, self.count
+        );
+    }
+}
+
+/// Histograms of one provider (payload of `get_request_stats`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStats {
+    pub provider: String,
+    pub latency_ms: Histogram,
+    pub request_bytes: Histogram,
+    pub response_bytes: Histogram,
+}
+
+impl ProviderStats {
+    fn new(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            latency_ms: Histogram::new(LATENCY_BUCKETS_MS),
+            request_bytes: Histogram::new(SIZE_BUCKETS_BYTES),
+            response_bytes: Histogram::new(SIZE_BUCKETS_BYTES),
+        }
+    }
+}
+
+static STATS: Lazy<Mutex<BTreeMap<String, ProviderStats>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Record one finished request
+pub fn record(provider: &str, latency_ms: u64, request_bytes: u64, response_bytes: u64) {
+    let mut stats = STATS.lock();
+    let entry = stats
+        .entry(provider.to_string())
+        .or_insert_with(|| ProviderStats::new(provider));
+    entry.latency_ms.observe(latency_ms);
+    entry.request_bytes.observe(request_bytes);
+    entry.response_bytes.observe(response_bytes);
+}
+
+pub fn get_request_stats() -> Vec<ProviderStats> {
+    STATS.lock().values().cloned().collect()
+}
+
+/// Label values may not contain unescaped quotes, backslashes or newlines
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Name, help text and histogram of an exported metric
+type HistogramMetric = (&'static str, &'static str, fn(&ProviderStats) -> &Histogram);
+
+fn render_prometheus(stats: &[ProviderStats]) -> String {
+    let metrics: [HistogramMetric; 3] = [
+        (
+            "oneproxy_request_duration_milliseconds",
+            "Time from request to end of response body",
+            |s| &s.latency_ms,
+        ),
+        (
+            "oneproxy_request_size_bytes",
+            "Size of request bodies",
+            |s| &s.request_bytes,
+        ),
+        (
+            "oneproxy_response_size_bytes",
+            "Size of response bodies sent to clients",
+            |s| &s.response_bytes,
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, histogram) in metrics {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for provider_stats in stats {
+            histogram(provider_stats).write_prometheus(
+                &mut out,
+                name,
+                &escape_label(&provider_stats.provider),
+            );
+        }
+    }
+    out
+}
+
+/// Current statistics in the Prometheus text exposition format
+pub fn prometheus_text() -> String {
+    render_prometheus(&get_request_stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_observations_and_renders_cumulative_counts() {
+        let mut stats = ProviderStats::new("codex");
+        for latency in [80, 100, 700, 200_000] {
+            stats.latency_ms.observe(latency);
+        }
+        assert_eq!(stats.latency_ms.counts[0], 2);
+        assert_eq!(stats.latency_ms.counts[3], 1);
+        assert_eq!(*stats.latency_ms.counts.last().unwrap(), 1);
+        assert_eq!(stats.latency_ms.sum, 200_880);
+
+        let text = render_prometheus(&[stats]);
+        assert!(text.contains(
+            "oneproxy_request_duration_milliseconds_bucket{provider=\"codex\",le=\"500\"} 2\n"
+        ));
+        assert!(text.contains(
+            "oneproxy_request_duration_milliseconds_bucket{provider=\"codex\",le=\"120000\"} 3\n"
+        ));
+        assert!(text.contains(
+            "oneproxy_request_duration_milliseconds_bucket{provider=\"codex\",le=\"+Inf\"} 4\n"
+        ));
+        assert!(text.contains("oneproxy_response_size_bytes_count{provider=\"codex\"} 0\n"));
+    }
+}
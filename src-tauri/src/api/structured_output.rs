@@ -0,0 +1,674 @@
+// Structured output checking
+// When a chat completion request asks for `response_format: {type: "json_schema"}`, the
+// model's answer is checked against the schema before it is returned, since not every backend
+// enforces it. Depending on `structured-output.mode` (or the entry for the client's API key)
+// a mismatch is only reported, or the model is asked once more with the validation errors
+// and the corrected answer is returned when it matches. The tokens of the repair round-trip
+// are added to the returned `usage`, so the request log and key quotas count both calls. The
+// outcome is sent in the x-oneproxy-schema-validation header and saved with the request log.
+// Streaming requests are passed through unchecked.
+
+use axum::{
+    body::Body,
+    http::{header, response::Parts, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::cell::Cell;
+
+use super::usage::{extract_usage, TokenUsage};
+use crate::config::StructuredOutputMode;
+
+/// Response header with the check result: valid, invalid, repaired or repair_failed
+pub const X_ONEPROXY_SCHEMA_VALIDATION: &str = "x-oneproxy-schema-validation";
+
+/// Violations reported per check, enough for a repair prompt without flooding it
+const MAX_ERRORS: usize = 20;
+
+/// Nesting of schema and value checked, deeper inputs are reported instead of recursed into
+const MAX_DEPTH: usize = 128;
+
+/// Schema nodes visited per validation: anyOf/oneOf branches are each checked against the
+/// whole value, so nested alternatives would otherwise multiply the work
+const MAX_NODES: usize = 10_000;
+
+/// Largest request body buffered to look for a schema
+const MAX_REQUEST_BYTES: usize = 10 * 1024 * 1024;
+
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => value
+            .as_f64()
+            .is_some_and(|n| n.fract() == 0.0 && n.is_finite()),
+        "number" => value.is_number(),
+        other => json_kind(value) == other,
+    }
+}
+
+/// Schema a value is validated against, with the nodes left to visit
+struct Validation<'a> {
+    root: &'a Value,
+    nodes_left: Cell<usize>,
+    too_complex: Cell<bool>,
+}
+
+/// `refs` holds the references followed since the last step into `value`, so a reference
+/// cycle that never reaches a nested value (`{"$ref": "#"}`) is followed only once
+fn check<'a>(
+    validation: &Validation<'a>,
+    schema: &'a Value,
+    value: &Value,
+    path: &str,
+    refs: &[&'a str],
+    depth: usize,
+    errors: &mut Vec<String>,
+) {
+    if errors.len() >= MAX_ERRORS {
+        return;
+    }
+    if validation.nodes_left.get() == 0 {
+        validation.too_complex.set(true);
+        errors.push(format!("{}: the schema is too complex to check", path));
+        return;
+    }
+    validation.nodes_left.set(validation.nodes_left.get() - 1);
+    if depth > MAX_DEPTH {
+        errors.push(format!(
+            "{}: the schema is nested too deeply to check",
+            path
+        ));
+        return;
+    }
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(format!("{}: no value is allowed here", path));
+            return;
+        }
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(|v| v.as_str()) {
+        // Only local references ("#/$defs/Item") can be resolved
+        if let Some(target) = reference
+            .strip_prefix('#')
+            .and_then(|pointer| validation.root.pointer(pointer))
+            .filter(|_| !refs.contains(&reference))
+        {
+            let mut followed = refs.to_vec();
+            followed.push(reference);
+            check(
+                validation,
+                target,
+                value,
+                path,
+                &followed,
+                depth + 1,
+                errors,
+            );
+        }
+    }
+
+    for key in ["anyOf", "oneOf"] {
+        if let Some(branches) = schema.get(key).and_then(|v| v.as_array()) {
+            let matches_one = branches.iter().any(|branch| {
+                let mut branch_errors = Vec::new();
+                check(
+                    validation,
+                    branch,
+                    value,
+                    path,
+                    refs,
+                    depth + 1,
+                    &mut branch_errors,
+                );
+                branch_errors.is_empty()
+            });
+            if !matches_one {
+                errors.push(format!(
+                    "{}: does not match any of the allowed schemas",
+                    path
+                ));
+            }
+        }
+    }
+    for branch in schema
+        .get("allOf")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        check(validation, branch, value, path, refs, depth + 1, errors);
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|v| v.as_array()) {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            errors.push(format!("{}: expected {}", path, constant));
+        }
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+        errors.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            types.join(" or "),
+            json_kind(value)
+        ));
+        return;
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(|v| v.as_object());
+            for required in schema
+                .get("required")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+            {
+                if !object.contains_key(required) {
+                    errors.push(format!(
+                        "{}: missing required property '{}'",
+                        path, required
+                    ));
+                }
+            }
+            for (key, item) in object {
+                let item_path = format!("{}.{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => check(
+                        validation,
+                        property,
+                        item,
+                        &item_path,
+                        &[],
+                        depth + 1,
+                        errors,
+                    ),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{}: property is not allowed", item_path))
+                        }
+                        Some(additional) => check(
+                            validation,
+                            additional,
+                            item,
+                            &item_path,
+                            &[],
+                            depth + 1,
+                            errors,
+                        ),
+                        None => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            let count = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                if count < min {
+                    errors.push(format!(
+                        "{}: expected at least {} items, got {}",
+                        path, min, count
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                if count > max {
+                    errors.push(format!(
+                        "{}: expected at most {} items, got {}",
+                        path, max, count
+                    ));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check(
+                        validation,
+                        item_schema,
+                        item,
+                        &format!("{}[{}]", path, index),
+                        &[],
+                        depth + 1,
+                        errors,
+                    );
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if length < min {
+                    errors.push(format!("{}: shorter than {} characters", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if length > max {
+                    errors.push(format!("{}: longer than {} characters", path, max));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+                // Patterns the regex crate cannot compile are not enforced
+                if let Ok(regex) = regex::Regex::new(pattern) {
+                    if !regex.is_match(text) {
+                        errors.push(format!("{}: does not match pattern {}", path, pattern));
+                    }
+                }
+            }
+        }
+        Value::Number(number) => {
+            let n = number.as_f64().unwrap_or_default();
+            let bound = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+            if bound("minimum").is_some_and(|min| n < min)
+                || bound("exclusiveMinimum").is_some_and(|min| n <= min)
+            {
+                errors.push(format!("{}: {} is below the minimum", path, number));
+            }
+            if bound("maximum").is_some_and(|max| n > max)
+                || bound("exclusiveMaximum").is_some_and(|max| n >= max)
+            {
+                errors.push(format!("{}: {} is above the maximum", path, number));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Violations of `schema` by `value`, for the subset of JSON Schema structured output uses
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let validation = Validation {
+        root: schema,
+        nodes_left: Cell::new(MAX_NODES),
+        too_complex: Cell::new(false),
+    };
+    let mut errors = Vec::new();
+    check(&validation, schema, value, "$", &[], 0, &mut errors);
+    if validation.too_complex.get() {
+        return vec!["$: the schema is too complex to check".to_string()];
+    }
+    errors.truncate(MAX_ERRORS);
+    errors
+}
+
+/// Violations of the answer text, which must be JSON (optionally inside a code fence)
+fn output_errors(schema: &Value, content: Option<&str>) -> Vec<String> {
+    let Some(content) = content else {
+        return vec!["$: the response has no text content".to_string()];
+    };
+    let mut text = content.trim();
+    if let Some(fenced) = text.strip_prefix("```") {
+        let fenced = fenced.trim_end().trim_end_matches("```");
+        text = fenced
+            .split_once('\n')
+            .map(|(_, body)| body)
+            .unwrap_or(fenced)
+            .trim();
+    }
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => validate(schema, &value),
+        Err(e) => vec![format!("$: the response is not valid JSON ({})", e)],
+    }
+}
+
+/// Schema of a non-streaming chat completion request, if it asks for one
+fn requested_schema(request: &Value) -> Option<&Value> {
+    if request.get("stream").and_then(|v| v.as_bool()) == Some(true) {
+        return None;
+    }
+    let format = request.get("response_format")?;
+    if format.get("type").and_then(|v| v.as_str()) != Some("json_schema") {
+        return None;
+    }
+    format.pointer("/json_schema/schema")
+}
+
+fn answer_text(response: &Value) -> Option<&str> {
+    response
+        .pointer("/choices/0/message/content")
+        .and_then(|v| v.as_str())
+}
+
+/// The original request followed by the invalid answer and the errors found in it
+fn repair_request(request: &Value, answer: &str, errors: &[String]) -> Option<Value> {
+    let mut repair = request.clone();
+    let messages = repair.get_mut("messages")?.as_array_mut()?;
+    messages.push(json!({"role": "assistant", "content": answer}));
+    messages.push(json!({
+        "role": "user",
+        "content": format!(
+            "Your previous reply does not match the required JSON schema:\n- {}\n\
+             Reply again with only the corrected JSON.",
+            errors.join("\n- ")
+        )
+    }));
+    Some(repair)
+}
+
+fn mode_for(request: &Request<Body>) -> StructuredOutputMode {
+    let Some(config) = crate::config::get_config() else {
+        return StructuredOutputMode::default();
+    };
+    let key = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth));
+    config.structured_output.mode_for_key(key)
+}
+
+fn with_result(mut response: Response, result: &'static str) -> Response {
+    response.headers_mut().insert(
+        X_ONEPROXY_SCHEMA_VALIDATION,
+        HeaderValue::from_static(result),
+    );
+    response
+}
+
+/// Add the tokens of another call to an OpenAI response's `usage`
+fn add_usage(response: &mut Value, extra: TokenUsage) {
+    let Some(object) = response.as_object_mut() else {
+        return;
+    };
+    let usage = object.entry("usage").or_insert_with(|| json!({}));
+    if !usage.is_object() {
+        *usage = json!({});
+    }
+    let tokens = |usage: &Value, key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let prompt = tokens(usage, "prompt_tokens") + extra.input_tokens;
+    let completion = tokens(usage, "completion_tokens") + extra.output_tokens;
+    usage["prompt_tokens"] = json!(prompt);
+    usage["completion_tokens"] = json!(completion);
+    usage["total_tokens"] = json!(prompt + completion);
+}
+
+/// Repair round-trip: the corrected response when it matches the schema, and the tokens the
+/// round-trip used either way
+async fn repair(
+    request: &Value,
+    schema: &Value,
+    answer: &str,
+    errors: &[String],
+) -> (Option<(Parts, Value)>, Option<TokenUsage>) {
+    let Some(request) = repair_request(request, answer, errors) else {
+        return (None, None);
+    };
+    let response = super::handlers::serve_chat_completions(request).await;
+    if !response.status().is_success() {
+        tracing::warn!(
+            "[StructuredOutput] Repair request failed with {}",
+            response.status()
+        );
+        return (None, None);
+    }
+    let (parts, body) = response.into_parts();
+    let json = match body.collect().await {
+        Ok(collected) => serde_json::from_slice::<Value>(&collected.to_bytes()).ok(),
+        Err(_) => None,
+    };
+    let Some(json) = json else {
+        return (None, None);
+    };
+    let usage = extract_usage(&json);
+    let remaining = output_errors(schema, answer_text(&json));
+    if !remaining.is_empty() {
+        tracing::warn!(
+            "[StructuredOutput] Repaired output still has {} schema error(s): {}",
+            remaining.len(),
+            remaining.join("; ")
+        );
+        return (None, usage);
+    }
+    (Some((parts, json)), usage)
+}
+
+/// Check chat completion answers against the JSON schema the client asked for
+pub async fn structured_output_middleware(request: Request<Body>, next: Next) -> Response {
+    if request.method() != Method::POST || request.uri().path() != "/v1/chat/completions" {
+        return next.run(request).await;
+    }
+    let mode = mode_for(&request);
+    if mode == StructuredOutputMode::Off {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_REQUEST_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let too_large = e.into_inner().is::<http_body_util::LengthLimitError>();
+            let (status, message) = if too_large {
+                (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!(
+                        "Request body is larger than {} MB",
+                        MAX_REQUEST_BYTES / 1024 / 1024
+                    ),
+                )
+            } else {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "Failed to read request body".to_string(),
+                )
+            };
+            tracing::warn!("[StructuredOutput] {}", message);
+            return (
+                status,
+                Json(json!({
+                    "error": {
+                        "message": message,
+                        "type": "invalid_request_error",
+                        "code": status.as_u16()
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+    let request_json: Option<Value> = serde_json::from_slice(&bytes).ok();
+    let schema = request_json.as_ref().and_then(requested_schema).cloned();
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    let (Some(request_json), Some(schema)) = (request_json, schema) else {
+        return response;
+    };
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            tracing::warn!("[StructuredOutput] Failed to read response body: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(response_json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let answer = answer_text(&response_json);
+    let errors = output_errors(&schema, answer);
+    if errors.is_empty() {
+        return with_result(Response::from_parts(parts, Body::from(bytes)), "valid");
+    }
+    tracing::warn!(
+        "[StructuredOutput] Output does not match the schema: {}",
+        errors.join("; ")
+    );
+    let (StructuredOutputMode::Repair, Some(answer)) = (mode, answer) else {
+        return with_result(Response::from_parts(parts, Body::from(bytes)), "invalid");
+    };
+
+    // Whichever answer is returned, its usage covers both calls
+    let (repaired, repair_usage) = repair(&request_json, &schema, answer, &errors).await;
+    let (mut parts, mut json, extra, result) = match repaired {
+        Some((parts, json)) => (parts, json, extract_usage(&response_json), "repaired"),
+        None => (parts, response_json, repair_usage, "repair_failed"),
+    };
+    if let Some(extra) = extra {
+        add_usage(&mut json, extra);
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = serde_json::to_vec(&json).unwrap_or_default();
+    with_result(Response::from_parts(parts, Body::from(body)), result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_schema_subset() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": "integer", "minimum": 0},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}},
+                "kind": {"enum": ["person", "bot"]}
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false,
+            "$defs": {"tag": {"type": "string"}}
+        });
+        let valid = json!({"name": "Ada", "age": 36, "tags": ["math"], "kind": "person"});
+        assert!(validate(&schema, &valid).is_empty());
+
+        let invalid = json!({"name": "", "age": -1.5, "tags": [1], "kind": "cat", "extra": true});
+        let errors = validate(&schema, &invalid);
+        assert!(errors.contains(&"$.name: shorter than 1 characters".to_string()));
+        assert!(errors.contains(&"$.age: expected integer, got number".to_string()));
+        assert!(errors.contains(&"$.tags[0]: expected string, got integer".to_string()));
+        assert!(errors.contains(&"$.extra: property is not allowed".to_string()));
+        assert_eq!(errors.len(), 5);
+
+        let missing = validate(&schema, &json!({"name": "Ada"}));
+        assert_eq!(missing, vec!["$: missing required property 'age'"]);
+    }
+
+    #[test]
+    fn follows_self_referencing_schemas_without_looping() {
+        assert!(validate(&json!({"$ref": "#"}), &json!({"a": 1})).is_empty());
+        let cycle =
+            json!({"anyOf": [{"$ref": "#"}, {"$ref": "#/$defs/a"}], "$defs": {"a": {"$ref": "#"}}});
+        assert!(validate(&cycle, &json!(1)).is_empty());
+
+        // Recursive schemas still apply at every level of the value
+        let tree = json!({
+            "type": "object",
+            "properties": {
+                "value": {"type": "integer"},
+                "children": {"type": "array", "items": {"$ref": "#"}}
+            }
+        });
+        let value = json!({"value": 1, "children": [{"value": 2, "children": [{"value": "3"}]}]});
+        assert_eq!(
+            validate(&tree, &value),
+            vec!["$.children[0].children[0].value: expected integer, got string"]
+        );
+
+        let mut deep = json!(1);
+        for _ in 0..MAX_DEPTH {
+            deep = json!([deep]);
+        }
+        let nested = json!({"items": {"$ref": "#"}});
+        assert_eq!(validate(&nested, &deep).len(), 1);
+    }
+
+    #[test]
+    fn gives_up_on_schemas_with_too_many_alternatives() {
+        // Every level doubles the branches checked, 2^40 without a budget
+        let mut defs = serde_json::Map::new();
+        defs.insert("l0".to_string(), json!({"type": "string"}));
+        for level in 1..=40 {
+            let below = json!({"$ref": format!("#/$defs/l{}", level - 1)});
+            defs.insert(
+                format!("l{}", level),
+                json!({"anyOf": [below.clone(), below]}),
+            );
+        }
+        let schema = json!({"$ref": "#/$defs/l40", "$defs": defs});
+        assert_eq!(
+            validate(&schema, &json!(1)),
+            vec!["$: the schema is too complex to check"]
+        );
+    }
+
+    #[test]
+    fn adds_repair_tokens_to_usage() {
+        let mut response = json!({
+            "choices": [],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        });
+        let repair = TokenUsage {
+            input_tokens: 30,
+            output_tokens: 4,
+        };
+        add_usage(&mut response, repair);
+        assert_eq!(
+            response["usage"],
+            json!({"prompt_tokens": 40, "completion_tokens": 9, "total_tokens": 49})
+        );
+
+        let mut without_usage = json!({"choices": []});
+        add_usage(&mut without_usage, repair);
+        assert_eq!(without_usage["usage"]["total_tokens"], 34);
+    }
+
+    #[test]
+    fn checks_answer_text_and_builds_repair_prompt() {
+        let schema = json!({"type": "object", "required": ["ok"]});
+        assert!(output_errors(&schema, Some("```json\n{\"ok\": true}\n```")).is_empty());
+        assert_eq!(output_errors(&schema, Some("{}")).len(), 1);
+        assert!(output_errors(&schema, Some("sure!"))[0].contains("not valid JSON"));
+
+        let request = json!({
+            "model": "gpt-5",
+            "messages": [{"role": "user", "content": "status?"}],
+            "response_format": {"type": "json_schema", "json_schema": {"name": "s", "schema": schema}}
+        });
+        assert_eq!(requested_schema(&request), Some(&schema));
+        let repair = repair_request(
+            &request,
+            "{}",
+            &["$: missing required property 'ok'".to_string()],
+        )
+        .unwrap();
+        let messages = repair["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[2]["content"].as_str().unwrap().contains("'ok'"));
+    }
+}
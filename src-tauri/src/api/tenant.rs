@@ -0,0 +1,140 @@
+// Multi-tenant mode
+// Each entry of `tenants` owns a set of API keys. Requests made with one of them run in the
+// tenant's scope: `config::resolve_auth_dir` points at `<auth-dir>/tenants/<name>`, so only the
+// tenant's own accounts are used (and refreshed tokens are written there), the request log
+// entries carry the tenant name, and the tenant's daily request limit applies. The regular
+// `api-keys` and the desktop app keep using the shared auth dir and see every log.
+
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+
+use crate::config::TenantConfig;
+
+tokio::task_local! {
+    static CURRENT_TENANT: String;
+}
+
+/// Internal response header carrying the tenant to the logging middleware
+pub const X_ONEPROXY_TENANT: &str = "x-oneproxy-tenant";
+
+/// Tenant of the request being served, if it was made with a tenant key
+pub fn current() -> Option<String> {
+    CURRENT_TENANT.try_with(|tenant| tenant.clone()).ok()
+}
+
+/// Auth dir of a tenant inside the shared auth dir
+pub fn auth_dir(shared: &Path, tenant: &str) -> PathBuf {
+    shared.join("tenants").join(tenant)
+}
+
+/// Whether a tenant name is safe to use as a directory name
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Tenant owning an API key; tenants with an unusable name are ignored
+pub fn find<'a>(tenants: &'a [TenantConfig], key: &str) -> Option<&'a TenantConfig> {
+    tenants
+        .iter()
+        .find(|tenant| tenant.api_keys.iter().any(|k| k == key))
+        .filter(|tenant| {
+            let valid = valid_name(&tenant.name);
+            if !valid {
+                tracing::warn!("Ignoring tenant with invalid name {:?}", tenant.name);
+            }
+            valid
+        })
+}
+
+/// Whether the tenant used up today's requests (UTC day)
+fn over_limit(tenant: &TenantConfig) -> bool {
+    if tenant.daily_request_limit == 0 {
+        return false;
+    }
+    let day_start = chrono::Utc::now()
+        .date_naive()
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+        .timestamp_millis();
+    crate::db::count_tenant_requests(&tenant.name, day_start)
+        .is_ok_and(|count| count >= tenant.daily_request_limit as i64)
+}
+
+/// Run the rest of the middleware stack, and the response body, in the tenant's scope
+pub async fn serve(tenant: &TenantConfig, request: Request<Body>, next: Next) -> Response {
+    if over_limit(tenant) {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("Content-Type", "application/json")],
+            serde_json::json!({
+                "error": {
+                    "message": format!(
+                        "Tenant {} has used up its {} requests for today",
+                        tenant.name, tenant.daily_request_limit
+                    ),
+                    "type": "rate_limit_error",
+                    "code": "tenant_quota_exceeded"
+                }
+            })
+            .to_string(),
+        )
+            .into_response();
+    }
+
+    let name = tenant.name.clone();
+    let response = CURRENT_TENANT.scope(name.clone(), next.run(request)).await;
+
+    // Streams may pick or refresh accounts while the body is sent, after the scope above ended
+    let (mut parts, body) = response.into_parts();
+    if let Ok(value) = HeaderValue::from_str(&name) {
+        parts.headers.insert(X_ONEPROXY_TENANT, value);
+    }
+    let mut stream = body.into_data_stream();
+    let scoped = futures::stream::poll_fn(move |cx| {
+        CURRENT_TENANT.sync_scope(name.clone(), || stream.poll_next_unpin(cx))
+    });
+    Response::from_parts(parts, Body::from_stream(scoped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tenant_keys_scope_the_auth_dir() {
+        let tenants = vec![
+            TenantConfig {
+                name: "team-a".to_string(),
+                api_keys: vec!["sk-a".to_string()],
+                daily_request_limit: 0,
+            },
+            TenantConfig {
+                name: "../escape".to_string(),
+                api_keys: vec!["sk-bad".to_string()],
+                daily_request_limit: 0,
+            },
+        ];
+        assert_eq!(find(&tenants, "sk-a").unwrap().name, "team-a");
+        assert!(find(&tenants, "sk-bad").is_none());
+        assert!(find(&tenants, "sk-other").is_none());
+
+        assert_eq!(current(), None);
+        let inside = CURRENT_TENANT
+            .scope("team-a".to_string(), async { current() })
+            .await;
+        assert_eq!(inside.as_deref(), Some("team-a"));
+        assert_eq!(
+            auth_dir(Path::new("/data/auth"), "team-a"),
+            PathBuf::from("/data/auth/tenants/team-a")
+        );
+    }
+}
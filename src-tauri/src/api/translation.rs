@@ -0,0 +1,448 @@
+// Response post-translation
+// Clients whose API key has a language in `response-translation.languages` get the text of
+// non-streaming responses translated by a cheap model before it is returned, so English-only
+// backends can serve them. The translation request goes through the normal chat routing and
+// is saved as its own request log, tagged `translation`, with its tokens counted against the
+// client's key; results are cached by text, and any failure returns the original response
+// unchanged. Streaming responses, requests for a JSON response format and answers that are
+// JSON are passed through as they are.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http_body_util::BodyExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use super::KeyName;
+
+/// Translations kept; the oldest is evicted first
+const CACHE_LIMIT: usize = 512;
+
+#[derive(Default)]
+struct TranslationCache {
+    entries: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl TranslationCache {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: String, translation: String) {
+        if self.entries.insert(key.clone(), translation).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > CACHE_LIMIT {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+static CACHE: Lazy<Mutex<TranslationCache>> = Lazy::new(|| Mutex::new(TranslationCache::default()));
+
+fn cache_key(model: &str, language: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model);
+    hasher.update([0]);
+    hasher.update(language);
+    hasher.update([0]);
+    hasher.update(text);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `type: <kind>` blocks of a content array and their `text`
+fn typed_texts<'a>(blocks: &'a mut Value, kind: &str, out: &mut Vec<&'a mut String>) {
+    for block in blocks.as_array_mut().into_iter().flatten() {
+        if block.get("type").and_then(|v| v.as_str()) != Some(kind) {
+            continue;
+        }
+        if let Some(Value::String(text)) = block.get_mut("text") {
+            out.push(text);
+        }
+    }
+}
+
+/// Answer text of an OpenAI chat, Responses, Claude or Gemini response; reasoning, tool calls
+/// and thought parts are left alone
+fn text_fields(body: &mut Value) -> Vec<&mut String> {
+    let mut fields = Vec::new();
+    let Value::Object(object) = body else {
+        return fields;
+    };
+    for (key, value) in object.iter_mut() {
+        match key.as_str() {
+            // OpenAI chat completion
+            "choices" => {
+                for choice in value.as_array_mut().into_iter().flatten() {
+                    if let Some(Value::String(text)) = choice.pointer_mut("/message/content") {
+                        fields.push(text);
+                    }
+                }
+            }
+            // Claude message
+            "content" => typed_texts(value, "text", &mut fields),
+            // OpenAI Responses API
+            "output" => {
+                for item in value.as_array_mut().into_iter().flatten() {
+                    if item.get("type").and_then(|v| v.as_str()) == Some("message") {
+                        if let Some(content) = item.get_mut("content") {
+                            typed_texts(content, "output_text", &mut fields);
+                        }
+                    }
+                }
+            }
+            // Gemini, possibly wrapped in `response` by Code Assist style backends
+            "candidates" => {
+                for candidate in value.as_array_mut().into_iter().flatten() {
+                    let Some(parts) = candidate.pointer_mut("/content/parts") else {
+                        continue;
+                    };
+                    for part in parts.as_array_mut().into_iter().flatten() {
+                        if part.get("thought").and_then(|v| v.as_bool()) == Some(true) {
+                            continue;
+                        }
+                        if let Some(Value::String(text)) = part.get_mut("text") {
+                            fields.push(text);
+                        }
+                    }
+                }
+            }
+            "response" => fields.extend(text_fields(value)),
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn translation_request(model: &str, language: &str, text: &str) -> Value {
+    json!({
+        "model": model,
+        "stream": false,
+        "temperature": 0,
+        "messages": [
+            {
+                "role": "system",
+                "content": format!(
+                    "Translate the user's message into {}. Keep Markdown formatting, code blocks, \
+                     inline code, URLs and identifiers unchanged. Reply with the translation only.",
+                    language
+                )
+            },
+            {"role": "user", "content": text}
+        ]
+    })
+}
+
+/// Whether a request asks for a JSON answer (OpenAI `response_format`, Responses API
+/// `text.format`, Gemini `responseMimeType`)
+fn wants_json(request: &Value) -> bool {
+    let is_json_format = |format: Option<&Value>| {
+        matches!(
+            format.and_then(|f| f.get("type")).and_then(|v| v.as_str()),
+            Some("json_object" | "json_schema")
+        )
+    };
+    let generation_config = request
+        .get("generationConfig")
+        .or_else(|| request.pointer("/request/generationConfig"));
+    is_json_format(request.get("response_format"))
+        || is_json_format(request.pointer("/text/format"))
+        || generation_config
+            .and_then(|config| config.get("responseMimeType"))
+            .and_then(|v| v.as_str())
+            == Some("application/json")
+}
+
+/// Save the request log of a translation call and count its tokens against the client's key
+fn log_translation(
+    key_name: Option<&str>,
+    status: u16,
+    headers: &HeaderMap,
+    body: Option<&Value>,
+    start: Instant,
+) {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let tokens = |field: &str| {
+        body.and_then(|body| body.get("usage"))
+            .and_then(|usage| usage.get(field))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0)
+    };
+    let (input_tokens, output_tokens) = (tokens("prompt_tokens"), tokens("completion_tokens"));
+    let failed = status >= 400 || body.is_none_or(|body| body.get("error").is_some());
+    let error = failed.then(|| format!("translation failed: HTTP {}", status));
+    let model = header(super::X_ONEPROXY_MODEL).map(super::normalize_model_name);
+    let path = "/v1/chat/completions";
+    let _ = crate::db::save_request_log(&crate::db::NewRequestLog {
+        status: status as i32,
+        method: "POST",
+        model: model.as_deref(),
+        protocol: super::protocol_from_path(path).as_deref(),
+        provider: header(super::X_ONEPROXY_PROVIDER),
+        account_id: header(super::X_ONEPROXY_ACCOUNT_ID),
+        path,
+        input_tokens: input_tokens as i32,
+        output_tokens: output_tokens as i32,
+        duration_ms: start.elapsed().as_millis() as i64,
+        error_message: error.as_deref(),
+        tags: &["translation".to_string()],
+        tenant: super::tenant::current().as_deref(),
+        ..Default::default()
+    });
+    if let Some(key_name) = key_name {
+        let _ = crate::db::record_key_usage(key_name, input_tokens, output_tokens, failed);
+    }
+}
+
+/// Translate one text, from the cache when it was seen before
+async fn translate(
+    model: &str,
+    language: &str,
+    text: &str,
+    key_name: Option<&str>,
+) -> Result<String> {
+    let key = cache_key(model, language, text);
+    if let Some(cached) = CACHE.lock().get(&key) {
+        return Ok(cached);
+    }
+
+    let start = Instant::now();
+    let response =
+        super::handlers::serve_chat_completions(translation_request(model, language, text)).await;
+    let status = response.status();
+    let (parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            log_translation(key_name, 502, &parts.headers, None, start);
+            return Err(e.into());
+        }
+    };
+    let body = serde_json::from_slice::<Value>(&bytes).ok();
+    log_translation(
+        key_name,
+        status.as_u16(),
+        &parts.headers,
+        body.as_ref(),
+        start,
+    );
+    if !status.is_success() {
+        return Err(anyhow!(
+            "translation model answered {}: {}",
+            status,
+            String::from_utf8_lossy(&bytes)
+        ));
+    }
+    let body = body.ok_or_else(|| anyhow!("translation model returned invalid JSON"))?;
+    let translation = body
+        .pointer("/choices/0/message/content")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("translation model returned no text"))?;
+
+    CACHE.lock().insert(key, translation.clone());
+    Ok(translation)
+}
+
+/// Translate the answer text of a JSON response body in place; texts that are JSON documents
+/// are kept as they are
+async fn translate_body(
+    body: &mut Value,
+    model: &str,
+    language: &str,
+    key_name: Option<&str>,
+) -> Result<()> {
+    let fields = text_fields(body);
+    let translations = futures::future::join_all(fields.iter().map(|text| text.to_string()).map(
+        |text| async move {
+            if text.trim().is_empty() || serde_json::from_str::<Value>(&text).is_ok() {
+                Ok(text)
+            } else {
+                translate(model, language, &text, key_name).await
+            }
+        },
+    ))
+    .await;
+    // All or nothing, a half-translated answer is worse than the original
+    let translations = translations.into_iter().collect::<Result<Vec<_>>>()?;
+    for (field, translation) in fields.into_iter().zip(translations) {
+        *field = translation;
+    }
+    Ok(())
+}
+
+/// Target language configured for the API key of a request
+fn target_language(request: &Request<Body>) -> Option<(String, String)> {
+    let config = crate::config::get_config()?.response_translation;
+    let auth = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())?;
+    let key = auth.strip_prefix("Bearer ").unwrap_or(auth);
+    let language = config.languages.get(key)?.trim();
+    (!language.is_empty()).then(|| (config.model.clone(), language.to_string()))
+}
+
+/// Translate successful non-streaming JSON responses for keys with a configured language
+pub async fn translation_middleware(request: Request<Body>, next: Next) -> Response {
+    let target = (request.method() == Method::POST)
+        .then(|| target_language(&request))
+        .flatten();
+    let Some((model, language)) = target else {
+        return next.run(request).await;
+    };
+    let key_name = request
+        .extensions()
+        .get::<KeyName>()
+        .map(|KeyName(name)| name.clone());
+
+    // Structured output must stay machine-readable
+    let (parts, body) = request.into_parts();
+    let request_bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            tracing::warn!("[Translation] Failed to read request body: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": {
+                        "message": format!("Failed to read request body: {}", e),
+                        "type": "invalid_request_error",
+                        "code": 400
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+    let wants_json = serde_json::from_slice::<Value>(&request_bytes)
+        .ok()
+        .is_some_and(|request| wants_json(&request));
+    let response = next
+        .run(Request::from_parts(parts, Body::from(request_bytes)))
+        .await;
+    if wants_json {
+        return response;
+    }
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            tracing::warn!("[Translation] Failed to read response body: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    match translate_body(&mut json, &model, &language, key_name.as_deref()).await {
+        Ok(()) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(json.to_string()))
+        }
+        Err(e) => {
+            tracing::warn!(
+                "[Translation] Returning untranslated response ({}): {}",
+                language,
+                e
+            );
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_answer_text_of_each_protocol() {
+        let mut openai =
+            json!({"choices": [{"message": {"role": "assistant", "content": "Hello"}}]});
+        assert_eq!(text_fields(&mut openai).len(), 1);
+
+        let mut claude = json!({"content": [
+            {"type": "thinking", "thinking": "hmm"},
+            {"type": "text", "text": "Hi"},
+            {"type": "tool_use", "name": "f"}
+        ]});
+        let fields = text_fields(&mut claude);
+        assert_eq!(fields.len(), 1);
+        *fields.into_iter().next().unwrap() = "你好".to_string();
+        assert_eq!(claude["content"][1]["text"], "你好");
+
+        let mut responses = json!({"output": [
+            {"type": "reasoning", "summary": []},
+            {"type": "message", "content": [{"type": "output_text", "text": "Done"}]}
+        ]});
+        assert_eq!(text_fields(&mut responses).len(), 1);
+
+        let mut gemini = json!({"response": {"candidates": [{"content": {"parts": [
+            {"text": "plan", "thought": true},
+            {"text": "Answer"}
+        ]}}]}});
+        let fields = text_fields(&mut gemini);
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].as_str(), "Answer");
+    }
+
+    #[tokio::test]
+    async fn leaves_json_answers_and_formats_alone() {
+        assert!(wants_json(
+            &json!({"response_format": {"type": "json_schema", "json_schema": {}}})
+        ));
+        assert!(wants_json(
+            &json!({"text": {"format": {"type": "json_object"}}})
+        ));
+        assert!(wants_json(
+            &json!({"generationConfig": {"responseMimeType": "application/json"}})
+        ));
+        assert!(!wants_json(&json!({"response_format": {"type": "text"}})));
+
+        // JSON answers are kept without calling the translation model
+        let mut body = json!({"choices": [{"message": {"content": "{\"ok\": true}"}}]});
+        translate_body(&mut body, "gemini/gemini-2.5-flash", "German", None)
+            .await
+            .unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "{\"ok\": true}");
+    }
+
+    #[test]
+    fn cache_evicts_oldest_entries() {
+        let mut cache = TranslationCache::default();
+        for i in 0..=CACHE_LIMIT {
+            cache.insert(i.to_string(), format!("t{}", i));
+        }
+        assert_eq!(cache.entries.len(), CACHE_LIMIT);
+        assert!(cache.get("0").is_none());
+        assert_eq!(cache.get("1").as_deref(), Some("t1"));
+    }
+}
@@ -0,0 +1,401 @@
+// Per-call usage reporting
+// Extracts token usage from upstream responses and exposes it (with an estimated cost)
+// to clients via x-oneproxy-tokens / x-oneproxy-cost headers or trailing SSE comments
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{header, HeaderValue},
+    response::Response,
+};
+use futures::Stream;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Response header carrying the estimated cost of the call in USD
+pub const X_ONEPROXY_COST: &str = "x-oneproxy-cost";
+
+/// Response header carrying the token usage of the call
+pub const X_ONEPROXY_TOKENS: &str = "x-oneproxy-tokens";
+
+/// Published prices in USD per million tokens: (model pattern, input, output)
+/// More specific patterns must come before general ones
+static MODEL_PRICES: &[(&str, f64, f64)] = &[
+    // Claude models
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    // Gemini models
+    ("gemini-3-pro", 2.0, 12.0),
+    ("gemini-3-flash", 0.5, 3.0),
+    ("gemini-2-5-pro", 1.25, 10.0),
+    ("gemini-2-5-flash-lite", 0.1, 0.4),
+    ("gemini-2-5-flash", 0.3, 2.5),
+    ("gemini-2-0-flash", 0.1, 0.4),
+    // OpenAI/Codex models
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-4-5", 75.0, 150.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+];
+
+/// Token usage of a single call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    fn is_empty(&self) -> bool {
+        self.input_tokens == 0 && self.output_tokens == 0
+    }
+
+    /// Merge usage reported in a later event, keeping values already seen
+    fn merge(&mut self, other: TokenUsage) {
+        if other.input_tokens > 0 {
+            self.input_tokens = other.input_tokens;
+        }
+        if other.output_tokens > 0 {
+            self.output_tokens = other.output_tokens;
+        }
+    }
+
+    fn header_value(&self) -> String {
+        format!(
+            "input={}; output={}; total={}",
+            self.input_tokens,
+            self.output_tokens,
+            self.input_tokens + self.output_tokens
+        )
+    }
+}
+
+fn as_u64(value: Option<&Value>) -> u64 {
+    value.and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+/// Extract token usage from an OpenAI, Claude or Gemini response (or stream event) body
+pub fn extract_usage(json: &Value) -> Option<TokenUsage> {
+    // Antigravity / Gemini CLI wrap the Gemini response
+    let json = json.get("response").unwrap_or(json);
+
+    if let Some(meta) = json.get("usageMetadata") {
+        return Some(TokenUsage {
+            input_tokens: as_u64(meta.get("promptTokenCount")),
+            output_tokens: as_u64(meta.get("candidatesTokenCount"))
+                + as_u64(meta.get("thoughtsTokenCount")),
+        });
+    }
+
+    // Claude message_start carries usage inside the message
+    let usage = json
+        .get("usage")
+        .or_else(|| json.get("message").and_then(|m| m.get("usage")))?;
+    if !usage.is_object() {
+        return None;
+    }
+
+    Some(TokenUsage {
+        input_tokens: as_u64(usage.get("input_tokens")) + as_u64(usage.get("prompt_tokens")),
+        output_tokens: as_u64(usage.get("output_tokens")) + as_u64(usage.get("completion_tokens")),
+    })
+}
+
+/// Estimate the cost of a call in USD from published per-token prices
+/// Returns None for models without known pricing
+pub fn estimate_cost(model: &str, usage: &TokenUsage) -> Option<f64> {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    let normalized = model.to_lowercase().replace(['.', '_'], "-");
+    let (_, input_price, output_price) = MODEL_PRICES
+        .iter()
+        .find(|(pattern, _, _)| normalized.starts_with(pattern))?;
+
+    Some(
+        (usage.input_tokens as f64 * input_price + usage.output_tokens as f64 * output_price)
+            / 1_000_000.0,
+    )
+}
+
+fn format_cost(cost: f64) -> String {
+    format!("{:.6}", cost)
+}
+
+/// Build the trailing SSE comment lines reporting usage for a stream
+fn sse_usage_comment(model: Option<&str>, usage: &TokenUsage) -> String {
+    let mut comment = format!(": {}: {}\n", X_ONEPROXY_TOKENS, usage.header_value());
+    if let Some(cost) = model.and_then(|m| estimate_cost(m, usage)) {
+        comment.push_str(&format!(": {}: {}\n", X_ONEPROXY_COST, format_cost(cost)));
+    }
+    comment.push('\n');
+    comment
+}
+
+/// Scan complete SSE lines for usage, keeping any incomplete trailing line buffered
+fn scan_sse_lines(buffer: &mut String, usage: &mut TokenUsage) {
+    while let Some(pos) = buffer.find('\n') {
+        let line: String = buffer.drain(..=pos).collect();
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            continue;
+        };
+        if let Ok(json) = serde_json::from_str::<Value>(data.trim()) {
+            if let Some(found) = extract_usage(&json) {
+                usage.merge(found);
+            }
+        }
+    }
+}
+
+/// How a response body ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseEnd {
+    /// The whole body was delivered
+    Completed,
+    /// The client disconnected before the body was fully sent
+    ClientAborted,
+    /// The upstream stream failed midway
+    Failed(String),
+}
+
+/// Called once with the usage seen so far and the number of body bytes sent when the response
+/// body ends
+pub type OnResponseEnd = Box<dyn FnOnce(TokenUsage, ResponseEnd, u64) + Send>;
+
+/// SSE body wrapper that scans events for usage and reports how the stream ended
+///
+/// Dropping it before the upstream finished means the client went away; the upstream
+/// stream is dropped with it, which closes the upstream connection.
+struct UsageTrackingStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Bytes, axum::Error>> + Send>>,
+    buffer: String,
+    usage: TokenUsage,
+    sent_bytes: u64,
+    model: Option<String>,
+    on_end: Option<OnResponseEnd>,
+}
+
+impl UsageTrackingStream {
+    fn end(&mut self, end: ResponseEnd) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(self.usage, end, self.sent_bytes);
+        }
+    }
+}
+
+impl Stream for UsageTrackingStream {
+    type Item = Result<Bytes, axum::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.on_end.is_none() {
+            return Poll::Ready(None);
+        }
+
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(bytes))) => {
+                this.sent_bytes += bytes.len() as u64;
+                this.buffer.push_str(&String::from_utf8_lossy(&bytes));
+                scan_sse_lines(&mut this.buffer, &mut this.usage);
+                Poll::Ready(Some(Ok(bytes)))
+            }
+            Poll::Ready(Some(Err(err))) => {
+                this.end(ResponseEnd::Failed(err.to_string()));
+                Poll::Ready(Some(Err(err)))
+            }
+            Poll::Ready(None) => {
+                this.end(ResponseEnd::Completed);
+                if this.usage.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    let trailer = sse_usage_comment(this.model.as_deref(), &this.usage);
+                    Poll::Ready(Some(Ok(Bytes::from(trailer))))
+                }
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for UsageTrackingStream {
+    fn drop(&mut self) {
+        self.end(ResponseEnd::ClientAborted);
+    }
+}
+
+/// Attach usage information to a response and report when its body ends
+///
+/// Successful non-streaming JSON responses get x-oneproxy-tokens / x-oneproxy-cost headers.
+/// Streaming responses can't add headers once started, so the same values are sent
+/// as SSE comment lines after the final event, which SSE clients ignore.
+/// `on_end` receives the usage seen and whether the body completed, was aborted by the
+/// client or failed upstream; if the client disconnects before that it is never called
+/// and the caller's own drop handling applies.
+pub async fn attach_usage(
+    response: Response,
+    model: Option<&str>,
+    on_end: OnResponseEnd,
+) -> Response {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    if content_type.starts_with("text/event-stream") {
+        let (parts, body) = response.into_parts();
+        let stream = UsageTrackingStream {
+            inner: Box::pin(body.into_data_stream()),
+            buffer: String::new(),
+            usage: TokenUsage::default(),
+            sent_bytes: 0,
+            model: model.map(|m| m.to_string()),
+            on_end: Some(on_end),
+        };
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+
+    if !response.status().is_success() || !content_type.starts_with("application/json") {
+        let size = response.body().size_hint().exact().unwrap_or(0);
+        on_end(TokenUsage::default(), ResponseEnd::Completed, size);
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            on_end(TokenUsage::default(), ResponseEnd::Failed(e.to_string()), 0);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let usage = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|json| extract_usage(&json))
+        .unwrap_or_default();
+
+    if !usage.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&usage.header_value()) {
+            parts.headers.insert(X_ONEPROXY_TOKENS, value);
+        }
+        if let Some(cost) = model.and_then(|m| estimate_cost(m, &usage)) {
+            if let Ok(value) = HeaderValue::from_str(&format_cost(cost)) {
+                parts.headers.insert(X_ONEPROXY_COST, value);
+            }
+        }
+    }
+
+    on_end(usage, ResponseEnd::Completed, bytes.len() as u64);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_usage_from_each_protocol() {
+        let openai = json!({"usage": {"prompt_tokens": 10, "completion_tokens": 5}});
+        let claude = json!({"usage": {"input_tokens": 7, "output_tokens": 3}});
+        let gemini = json!({"response": {"usageMetadata": {
+            "promptTokenCount": 4, "candidatesTokenCount": 2, "thoughtsTokenCount": 1
+        }}});
+
+        assert_eq!(
+            extract_usage(&openai),
+            Some(TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5
+            })
+        );
+        assert_eq!(
+            extract_usage(&claude),
+            Some(TokenUsage {
+                input_tokens: 7,
+                output_tokens: 3
+            })
+        );
+        assert_eq!(
+            extract_usage(&gemini),
+            Some(TokenUsage {
+                input_tokens: 4,
+                output_tokens: 3
+            })
+        );
+        assert_eq!(extract_usage(&json!({"choices": []})), None);
+    }
+
+    #[test]
+    fn scans_usage_across_split_sse_chunks() {
+        let mut buffer = String::new();
+        let mut usage = TokenUsage::default();
+
+        buffer.push_str("event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_");
+        scan_sse_lines(&mut buffer, &mut usage);
+        buffer.push_str("tokens\":12,\"output_tokens\":1}}}\n\n");
+        scan_sse_lines(&mut buffer, &mut usage);
+        buffer.push_str("data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":8}}\n\n");
+        scan_sse_lines(&mut buffer, &mut usage);
+
+        assert_eq!(
+            usage,
+            TokenUsage {
+                input_tokens: 12,
+                output_tokens: 8
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_client_abort_with_partial_usage() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let chunks: Vec<Result<Bytes, axum::Error>> = vec![Ok(Bytes::from(
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":9}}}\n\n",
+        ))];
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .body(Body::from_stream(
+                futures::stream::iter(chunks).chain(futures::stream::pending()),
+            ))
+            .unwrap();
+
+        let response = attach_usage(
+            response,
+            Some("claude-sonnet-4-5"),
+            Box::new(move |usage, end, _| tx.send((usage, end)).unwrap()),
+        )
+        .await;
+        let mut stream = response.into_body().into_data_stream();
+        assert!(stream.next().await.is_some());
+        drop(stream);
+
+        let (usage, end) = rx.recv().unwrap();
+        assert_eq!(end, ResponseEnd::ClientAborted);
+        assert_eq!(usage.input_tokens, 9);
+    }
+
+    #[test]
+    fn estimates_cost_for_known_models() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+        };
+        assert_eq!(estimate_cost("claude-sonnet-4.5", &usage), Some(18.0));
+        assert_eq!(estimate_cost("kiro/claude-haiku-4-5", &usage), Some(6.0));
+        assert_eq!(estimate_cost("my-local-model", &usage), None);
+    }
+}
@@ -0,0 +1,107 @@
+// Startup warm-up: prefetch model caches and refresh soon-to-expire tokens
+// so the first user request after a (re)start isn't multi-second slow.
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::events;
+use super::handlers;
+
+/// Tokens expiring within this window are refreshed during warm-up
+const TOKEN_REFRESH_WINDOW_MINUTES: i64 = 10;
+
+const TASK_KIRO_MODELS: &str = "kiro-models";
+const TASK_CUSTOM_MODELS: &str = "custom-provider-models";
+const TASK_TOKEN_REFRESH: &str = "token-refresh";
+
+static LAST_PROGRESS: RwLock<Option<WarmupProgress>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupProgress {
+    /// Task name, or "all" for the overall start/finish events
+    pub task: String,
+    /// "running" | "done" | "skipped" | "failed"
+    pub status: String,
+    pub completed: usize,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Latest warm-up progress event, for UIs that subscribe after it was emitted
+pub fn last_progress() -> Option<WarmupProgress> {
+    LAST_PROGRESS.read().clone()
+}
+
+fn report(task: &str, status: &str, completed: usize, total: usize, detail: Option<String>) {
+    let progress = WarmupProgress {
+        task: task.to_string(),
+        status: status.to_string(),
+        completed,
+        total,
+        detail,
+    };
+    *LAST_PROGRESS.write() = Some(progress.clone());
+    events::emit(events::WARMUP_PROGRESS, progress);
+}
+
+/// Run all warm-up tasks concurrently, emitting progress as each one finishes
+pub async fn run() {
+    const TOTAL: usize = 3;
+    let completed = AtomicUsize::new(0);
+    let finish = |task: &str, status: &str, detail: String| {
+        let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+        report(task, status, done, TOTAL, Some(detail));
+    };
+
+    report("all", "running", 0, TOTAL, None);
+    let start = std::time::Instant::now();
+
+    let kiro = async {
+        match handlers::warm_kiro_model_cache().await {
+            Ok(Some(count)) => finish(TASK_KIRO_MODELS, "done", format!("{} models", count)),
+            Ok(None) => finish(TASK_KIRO_MODELS, "skipped", "no Kiro account".to_string()),
+            Err(e) => finish(TASK_KIRO_MODELS, "failed", e),
+        }
+    };
+
+    let custom = async {
+        let (ok, failed) = handlers::prefetch_custom_provider_models().await;
+        let status = if failed > 0 {
+            "failed"
+        } else if ok == 0 {
+            "skipped"
+        } else {
+            "done"
+        };
+        finish(
+            TASK_CUSTOM_MODELS,
+            status,
+            format!("{} fetched, {} failed", ok, failed),
+        );
+    };
+
+    let tokens = async {
+        let window = chrono::Duration::minutes(TOKEN_REFRESH_WINDOW_MINUTES);
+        let (refreshed, failed) = handlers::refresh_expiring_tokens(window).await;
+        let status = if failed > 0 { "failed" } else { "done" };
+        finish(
+            TASK_TOKEN_REFRESH,
+            status,
+            format!("{} refreshed, {} failed", refreshed, failed),
+        );
+    };
+
+    tokio::join!(kiro, custom, tokens);
+
+    let elapsed_ms = start.elapsed().as_millis();
+    tracing::info!("[Warmup] Completed in {}ms", elapsed_ms);
+    report(
+        "all",
+        "done",
+        TOTAL,
+        TOTAL,
+        Some(format!("{}ms", elapsed_ms)),
+    );
+}
@@ -0,0 +1,195 @@
+// Loopback callback servers for OAuth flows
+// Google and Codex logins run a short-lived HTTP server that receives the authorization code.
+// Each provider has at most one listener; a new login for the same provider replaces the
+// previous one, and listeners tear themselves down once the login times out.
+
+use anyhow::Result;
+use axum::Router;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+
+/// How long a callback listener waits for the browser before shutting down
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long to wait for a replaced listener to release its port
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Whether the provider accepts a redirect URI on a port other than its registered one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortPolicy {
+    /// The redirect URI is registered with a fixed port
+    Fixed,
+    /// Any loopback port is accepted, fall back to an OS-assigned one when busy
+    AnyLoopback,
+}
+
+struct ActiveServer {
+    id: u64,
+    cancel: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+static ACTIVE_SERVERS: Lazy<Mutex<HashMap<&'static str, ActiveServer>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_SERVER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A bound callback port that has not started serving yet
+pub struct CallbackListener {
+    provider: &'static str,
+    listener: TcpListener,
+    port: u16,
+}
+
+impl CallbackListener {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serve the callback router until the returned sender fires, the login is replaced
+    /// or `CALLBACK_TIMEOUT` elapses
+    pub fn serve(self, app: Router) -> oneshot::Sender<()> {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let cancel = Arc::new(Notify::new());
+        let id = NEXT_SERVER_ID.fetch_add(1, Ordering::Relaxed);
+        let provider = self.provider;
+        let port = self.port;
+
+        let server_cancel = cancel.clone();
+        let task = tokio::spawn(async move {
+            axum::serve(self.listener, app)
+                .with_graceful_shutdown(async move {
+                    tokio::select! {
+                        _ = shutdown_rx => {}
+                        _ = server_cancel.notified() => {
+                            tracing::info!("{} OAuth callback on port {} replaced by a new login", provider, port);
+                        }
+                        _ = tokio::time::sleep(CALLBACK_TIMEOUT) => {
+                            tracing::warn!("{} OAuth callback on port {} timed out, shutting down", provider, port);
+                        }
+                    }
+                })
+                .await
+                .ok();
+
+            let mut active = ACTIVE_SERVERS.lock();
+            if active.get(provider).map(|server| server.id) == Some(id) {
+                active.remove(provider);
+            }
+        });
+
+        ACTIVE_SERVERS
+            .lock()
+            .insert(provider, ActiveServer { id, cancel, task });
+        shutdown_tx
+    }
+}
+
+/// Bind the callback port for a provider login
+/// Replaces a pending login of the same provider. When the preferred port is taken by
+/// another program, providers with `PortPolicy::AnyLoopback` get an OS-assigned port and
+/// fixed-port providers get an error naming the port.
+pub async fn bind(
+    provider: &'static str,
+    preferred_port: u16,
+    policy: PortPolicy,
+) -> Result<CallbackListener> {
+    stop_existing(provider).await;
+
+    let preferred = SocketAddr::from((Ipv4Addr::LOCALHOST, preferred_port));
+    let listener = match TcpListener::bind(preferred).await {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => match policy {
+            PortPolicy::AnyLoopback => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+                tracing::warn!(
+                    "{} OAuth callback port {} is in use, using port {} instead",
+                    provider,
+                    preferred_port,
+                    listener.local_addr()?.port()
+                );
+                listener
+            }
+            PortPolicy::Fixed => {
+                return Err(anyhow::anyhow!(
+                    "{} login needs port {} for its OAuth callback, but another program is using it. Close that program and try again.",
+                    provider,
+                    preferred_port
+                ));
+            }
+        },
+        Err(e) => {
+            return Err(anyhow::anyhow!(
+                "Failed to start {} OAuth callback server on port {}: {}",
+                provider,
+                preferred_port,
+                e
+            ));
+        }
+    };
+
+    let port = listener.local_addr()?.port();
+    tracing::info!(
+        "{} OAuth callback server listening on 127.0.0.1:{}",
+        provider,
+        port
+    );
+    Ok(CallbackListener {
+        provider,
+        listener,
+        port,
+    })
+}
+
+async fn stop_existing(provider: &'static str) {
+    let Some(server) = ACTIVE_SERVERS.lock().remove(provider) else {
+        return;
+    };
+    server.cancel.notify_one();
+    if tokio::time::timeout(RELEASE_TIMEOUT, server.task)
+        .await
+        .is_err()
+    {
+        tracing::warn!("Previous {} OAuth callback did not stop in time", provider);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn falls_back_to_free_port_only_when_allowed() {
+        let blocker = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let busy_port = blocker.local_addr().unwrap().port();
+
+        let err = bind("test-fixed", busy_port, PortPolicy::Fixed)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains(&busy_port.to_string()));
+
+        let listener = bind("test-any", busy_port, PortPolicy::AnyLoopback)
+            .await
+            .unwrap();
+        assert_ne!(listener.port(), busy_port);
+    }
+
+    #[tokio::test]
+    async fn new_login_replaces_pending_listener() {
+        let first = bind("test-replace", 0, PortPolicy::AnyLoopback)
+            .await
+            .unwrap();
+        let port = first.port();
+        let _shutdown = first.serve(Router::new());
+
+        let second = bind("test-replace", port, PortPolicy::Fixed).await.unwrap();
+        assert_eq!(second.port(), port);
+    }
+}
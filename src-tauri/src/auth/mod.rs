@@ -7,7 +7,11 @@ use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 
+pub mod callback;
+pub mod onboarding;
 pub mod providers;
+pub mod schema;
+pub mod token_store;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenInfo {
@@ -69,83 +73,28 @@ fn default_true() -> bool {
     true
 }
 
+/// Read an auth file of any supported shape through its canonical form (see `schema`)
 fn parse_auth_file(content: &str, filename: &str) -> Option<AuthAccount> {
-    // Try new format first (AuthFile with provider field)
-    if let Ok(auth_file) = serde_json::from_str::<AuthFile>(content) {
-        return Some(AuthAccount {
-            id: filename.to_string(),
-            provider: auth_file.provider,
-            email: auth_file.email,
-            enabled: auth_file.enabled,
-            prefix: auth_file.prefix,
-        });
-    }
-
-    // Try CLIProxyAPI Gemini format (GeminiAuthFile with nested token object)
-    if let Ok(gemini_auth) = serde_json::from_str::<GeminiAuthFile>(content) {
-        // Check if token has access_token
-        if gemini_auth.token.get("access_token").is_some() {
-            return Some(AuthAccount {
-                id: filename.to_string(),
-                provider: "gemini".to_string(),
-                email: Some(gemini_auth.email),
-                enabled: true, // GeminiAuthFile doesn't have enabled field, default to true
-                prefix: None,
-            });
-        }
-    }
-
-    // Try parsing as generic JSON for legacy formats
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(content) {
-        let obj = json.as_object()?;
-
-        // Check for access_token at root level or in nested token object
-        let has_access_token = obj.contains_key("access_token")
-            || obj
-                .get("token")
-                .and_then(|t| t.as_object())
-                .map(|t| t.contains_key("access_token"))
-                .unwrap_or(false);
-
-        if !has_access_token {
-            return None;
-        }
-
-        // Get provider from "type" or "provider" field, or from filename
-        let provider = obj
-            .get("type")
-            .or_else(|| obj.get("provider"))
+    let raw = serde_json::from_str::<serde_json::Value>(content).ok()?;
+    let json = schema::canonicalize(&raw, filename)?;
+    let text = |key: &str| {
+        json.get(key)
             .and_then(|v| v.as_str())
             .map(|s| s.to_string())
-            .or_else(|| {
-                // Extract provider from filename like "antigravity-email.json" or "gemini-email.json"
-                let parts: Vec<&str> = filename.split(|c| c == '-' || c == '_').collect();
-                parts.first().map(|s| s.to_string())
-            })
-            .unwrap_or_else(|| "unknown".to_string());
-
-        let email = obj
-            .get("email")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let enabled = obj.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true);
-
-        let prefix = obj
-            .get("prefix")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        return Some(AuthAccount {
-            id: filename.to_string(),
-            provider,
-            email,
-            enabled,
-            prefix,
-        });
-    }
+    };
 
-    None
+    Some(AuthAccount {
+        id: filename.to_string(),
+        provider: text("type")?,
+        email: text("email"),
+        enabled: json.get("enabled").and_then(|v| v.as_bool()) == Some(true),
+        prefix: text("prefix"),
+        disabled_reason: text("disabled_reason"),
+        tags: account_tags(&json),
+        validation: json
+            .get("validation")
+            .and_then(|v| serde_json::from_value(v.clone()).ok()),
+    })
 }
 
 pub async fn list_accounts() -> Result<Vec<AuthAccount>> {
@@ -157,16 +106,34 @@ pub async fn list_accounts() -> Result<Vec<AuthAccount>> {
         return Ok(vec![]);
     }
 
-    let mut accounts = Vec::new();
+    let accounts: Vec<AuthAccount> = scan_auth_files(&auth_dir)?
+        .into_iter()
+        .map(|file| file.account)
+        .collect();
 
-    let entries = std::fs::read_dir(&auth_dir)?;
+    tracing::info!("Found {} accounts", accounts.len());
+    Ok(accounts)
+}
+
+struct ScannedAuthFile {
+    path: PathBuf,
+    content: String,
+    account: AuthAccount,
+}
+
+/// Read and parse every auth file in the auth directory
+fn scan_auth_files(auth_dir: &std::path::Path) -> Result<Vec<ScannedAuthFile>> {
+    let mut files = Vec::new();
+
+    let entries = std::fs::read_dir(auth_dir)?;
     for entry in entries.flatten() {
         let path = entry.path();
         if path.extension().map(|e| e == "json").unwrap_or(false) {
             let filename = path
                 .file_stem()
                 .and_then(|s| s.to_str())
-                .unwrap_or("unknown");
+                .unwrap_or("unknown")
+                .to_string();
 
             // Skip config.yaml and other non-auth files
             if filename == "config" {
@@ -174,10 +141,14 @@ pub async fn list_accounts() -> Result<Vec<AuthAccount>> {
             }
 
             if let Ok(content) = std::fs::read_to_string(&path) {
-                match parse_auth_file(&content, filename) {
+                match parse_auth_file(&content, &filename) {
                     Some(account) => {
                         tracing::debug!("Parsed account: {} ({})", filename, account.provider);
-                        accounts.push(account);
+                        files.push(ScannedAuthFile {
+                            path,
+                            content,
+                            account,
+                        });
                     }
                     None => {
                         tracing::warn!("Failed to parse auth file: {}", filename);
@@ -187,14 +158,25 @@ pub async fn list_accounts() -> Result<Vec<AuthAccount>> {
         }
     }
 
-    tracing::info!("Found {} accounts", accounts.len());
-    Ok(accounts)
+    Ok(files)
 }
 
+/// Run an OAuth flow, then validate the account files it wrote
 pub async fn start_oauth(provider: OAuthProvider, project_id: Option<String>) -> Result<String> {
+    let (message, written) = run_oauth(provider, project_id).await?;
+    onboarding::validate_new_accounts(&written);
+    Ok(message)
+}
+
+/// Result message of the flow and the auth files it wrote; flows finished by a callback
+/// only return the authorization URL here
+async fn run_oauth(
+    provider: OAuthProvider,
+    project_id: Option<String>,
+) -> Result<(String, Vec<PathBuf>)> {
     match provider {
         OAuthProvider::Google => {
-            // Google uses a dedicated callback server on port 8085
+            // Google uses a dedicated callback server, preferring port 8085
             match providers::google::start_oauth_with_callback().await {
                 Ok(result) => {
                     let email = result
@@ -237,28 +219,33 @@ pub async fn start_oauth(provider: OAuthProvider, project_id: Option<String>) ->
                     std::fs::write(&path, content)?;
 
                     tracing::info!("Saved Gemini auth file to {:?}", path);
-                    Ok("OAuth completed successfully".to_string())
+                    Ok(("OAuth completed successfully".to_string(), vec![path]))
                 }
                 Err(e) => Err(e),
             }
         }
-        OAuthProvider::Anthropic => providers::anthropic::start_oauth().await,
+        OAuthProvider::Anthropic => Ok((providers::anthropic::start_oauth().await?, Vec::new())),
         OAuthProvider::OpenAI => {
-            // OpenAI uses a special flow with its own callback server on port 1455
+            // OpenAI uses a special flow with its own callback server on fixed port 1455
             match providers::openai::start_oauth_with_callback().await {
                 Ok(result) => {
                     let path = save_codex_oauth_result(&result)?;
 
                     tracing::info!("Saved Codex auth file to {:?}", path);
-                    Ok("OAuth completed successfully".to_string())
+                    Ok(("OAuth completed successfully".to_string(), vec![path]))
                 }
                 Err(e) => Err(e),
             }
         }
-        OAuthProvider::Qwen => providers::qwen::start_oauth().await,
-        OAuthProvider::IFlow => providers::iflow::start_oauth().await,
-        OAuthProvider::Antigravity => providers::antigravity::start_oauth().await,
-        OAuthProvider::Kiro => providers::kiro::start_oauth().await,
+        OAuthProvider::Qwen => Ok((providers::qwen::start_oauth().await?, Vec::new())),
+        OAuthProvider::IFlow => Ok((providers::iflow::start_oauth().await?, Vec::new())),
+        OAuthProvider::Antigravity => {
+            Ok((providers::antigravity::start_oauth().await?, Vec::new()))
+        }
+        OAuthProvider::Kiro => {
+            let (message, path) = providers::kiro::start_oauth().await?;
+            Ok((message, vec![path]))
+        }
     }
 }
 
@@ -269,6 +256,7 @@ pub async fn start_codex_device_oauth() -> Result<providers::openai::DeviceOAuth
 pub async fn finish_codex_device_oauth(session_id: &str) -> Result<String> {
     let result = providers::openai::finish_device_oauth(session_id).await?;
     let path = save_codex_oauth_result(&result)?;
+    onboarding::validate_new_accounts(std::slice::from_ref(&path));
     tracing::info!("Saved Codex device auth file to {:?}", path);
     Ok("OAuth completed successfully".to_string())
 }
@@ -310,7 +298,10 @@ pub fn get_auth_file_path(provider: &str, identifier: &str) -> PathBuf {
 }
 
 pub fn save_auth_file(auth_file: &AuthFile, path: &PathBuf) -> Result<()> {
-    let content = serde_json::to_string_pretty(auth_file)?;
+    let filename = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let json = serde_json::to_value(auth_file)?;
+    let json = schema::canonicalize(&json, filename).unwrap_or(json);
+    let content = serde_json::to_string_pretty(&json)?;
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
@@ -497,6 +488,9 @@ pub fn save_api_key_account(
         email: Some(display_name),
         enabled: true,
         prefix: None,
+        disabled_reason: None,
+        tags: Vec::new(),
+        validation: None,
     })
 }
 
@@ -507,12 +501,20 @@ pub fn delete_account(account_id: &str) -> Result<()> {
     if path.exists() {
         std::fs::remove_file(&path)?;
         tracing::info!("Deleted account file: {:?}", path);
+        forget_quotas(account_id);
         Ok(())
     } else {
         Err(anyhow::anyhow!("Account file not found: {}", account_id))
     }
 }
 
+/// Drop the cached quota and its history once the account is gone
+fn forget_quotas(account_id: &str) {
+    if let Err(e) = crate::db::delete_quota_cache(account_id) {
+        tracing::warn!("Failed to delete quota data of {}: {}", account_id, e);
+    }
+}
+
 pub fn set_account_enabled(account_id: &str, enabled: bool) -> Result<()> {
     let auth_dir = crate::config::resolve_auth_dir();
     let path = auth_dir.join(format!("{}.json", account_id));
@@ -523,9 +525,7 @@ pub fn set_account_enabled(account_id: &str, enabled: bool) -> Result<()> {
 
     let content = std::fs::read_to_string(&path)?;
     let mut json: serde_json::Value = serde_json::from_str(&content)?;
-
-    json["enabled"] = serde_json::json!(enabled);
-    json["disabled"] = serde_json::json!(!enabled);
+    apply_enabled(&mut json, enabled);
 
     let content = serde_json::to_string_pretty(&json)?;
     std::fs::write(&path, content)?;
@@ -533,6 +533,235 @@ pub fn set_account_enabled(account_id: &str, enabled: bool) -> Result<()> {
     Ok(())
 }
 
+fn apply_enabled(json: &mut serde_json::Value, enabled: bool) {
+    json["enabled"] = serde_json::json!(enabled);
+    json["disabled"] = serde_json::json!(!enabled);
+    if enabled {
+        if let Some(obj) = json.as_object_mut() {
+            obj.remove("disabled_reason");
+            obj.remove("disabled_at");
+        }
+    }
+}
+
+fn account_tags(json: &serde_json::Value) -> Vec<String> {
+    json.get("tags")
+        .and_then(|v| v.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|t| t.as_str())
+                .map(|t| t.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Selects the accounts a bulk operation applies to. Every criterion that is set must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountFilter {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub account_ids: Vec<String>,
+}
+
+impl AccountFilter {
+    fn is_empty(&self) -> bool {
+        self.provider.as_deref().is_none_or(|p| p.trim().is_empty())
+            && self.tag.as_deref().is_none_or(|t| t.trim().is_empty())
+            && self.account_ids.is_empty()
+    }
+
+    fn matches(&self, account: &AuthAccount) -> bool {
+        if let Some(provider) = self.provider.as_deref().map(str::trim) {
+            if !provider.is_empty() && !account.provider.eq_ignore_ascii_case(provider) {
+                return false;
+            }
+        }
+        if let Some(tag) = self.tag.as_deref().map(str::trim) {
+            if !tag.is_empty() && !account.tags.iter().any(|t| t == tag) {
+                return false;
+            }
+        }
+        self.account_ids.is_empty() || self.account_ids.contains(&account.id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BulkAccountAction {
+    Enable,
+    Disable,
+    Delete,
+    AddTag { tag: String },
+    RemoveTag { tag: String },
+}
+
+impl BulkAccountAction {
+    /// Trim tag names and reject empty ones
+    fn normalized(&self) -> Result<Self> {
+        let trimmed = |tag: &str| {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                Err(anyhow::anyhow!("tag is required"))
+            } else {
+                Ok(tag.to_string())
+            }
+        };
+        Ok(match self {
+            Self::AddTag { tag } => Self::AddTag { tag: trimmed(tag)? },
+            Self::RemoveTag { tag } => Self::RemoveTag { tag: trimmed(tag)? },
+            other => other.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkAccountResult {
+    /// Accounts selected by the filter
+    pub matched: usize,
+    /// Accounts whose auth file was actually modified or removed
+    pub changed: usize,
+    pub changed_ids: Vec<String>,
+}
+
+/// Apply a bulk action to an auth file's JSON. Returns None when the file should be deleted.
+fn apply_bulk_action(
+    json: &serde_json::Value,
+    action: &BulkAccountAction,
+) -> Option<serde_json::Value> {
+    let mut updated = json.clone();
+    match action {
+        BulkAccountAction::Enable => apply_enabled(&mut updated, true),
+        BulkAccountAction::Disable => apply_enabled(&mut updated, false),
+        BulkAccountAction::Delete => return None,
+        BulkAccountAction::AddTag { tag } => {
+            let mut tags = account_tags(&updated);
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+            updated["tags"] = serde_json::json!(tags);
+        }
+        BulkAccountAction::RemoveTag { tag } => {
+            let tags: Vec<String> = account_tags(&updated)
+                .into_iter()
+                .filter(|t| t != tag)
+                .collect();
+            if tags.is_empty() {
+                if let Some(obj) = updated.as_object_mut() {
+                    obj.remove("tags");
+                }
+            } else {
+                updated["tags"] = serde_json::json!(tags);
+            }
+        }
+    }
+    Some(updated)
+}
+
+/// Enable, disable, delete or tag every account matching the filter.
+/// All auth files are prepared before any is written; if a write fails, files already
+/// changed are restored so the operation applies to all matched accounts or none.
+pub fn bulk_update_accounts(
+    filter: &AccountFilter,
+    action: &BulkAccountAction,
+) -> Result<BulkAccountResult> {
+    if filter.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Bulk operations need a provider, tag or account list"
+        ));
+    }
+    let action = action.normalized()?;
+
+    let auth_dir = crate::config::resolve_auth_dir();
+    if !auth_dir.exists() {
+        return Ok(BulkAccountResult::default());
+    }
+
+    let matched: Vec<ScannedAuthFile> = scan_auth_files(&auth_dir)?
+        .into_iter()
+        .filter(|file| filter.matches(&file.account))
+        .collect();
+
+    // Prepare every change up front so a malformed file aborts before anything is touched
+    let mut changes: Vec<(&ScannedAuthFile, Option<String>)> = Vec::new();
+    for file in &matched {
+        let json: serde_json::Value = serde_json::from_str(&file.content)?;
+        match apply_bulk_action(&json, &action) {
+            Some(updated) if updated == json => {}
+            Some(updated) => changes.push((file, Some(serde_json::to_string_pretty(&updated)?))),
+            None => changes.push((file, None)),
+        }
+    }
+
+    let mut applied: Vec<&ScannedAuthFile> = Vec::new();
+    for (file, content) in &changes {
+        let result = match content {
+            Some(content) => std::fs::write(&file.path, content),
+            None => std::fs::remove_file(&file.path),
+        };
+        if let Err(e) = result {
+            for done in applied.iter().rev() {
+                if let Err(restore_err) = std::fs::write(&done.path, &done.content) {
+                    tracing::error!("Failed to restore {:?}: {}", done.path, restore_err);
+                }
+            }
+            return Err(anyhow::anyhow!(
+                "Bulk update failed on account {}: {}. No accounts were changed.",
+                file.account.id,
+                e
+            ));
+        }
+        applied.push(file);
+    }
+    for (file, content) in &changes {
+        if content.is_none() {
+            forget_quotas(&file.account.id);
+        }
+    }
+
+    let changed_ids: Vec<String> = applied.iter().map(|file| file.account.id.clone()).collect();
+    tracing::info!(
+        "Bulk {:?}: {} matched, {} changed",
+        action,
+        matched.len(),
+        changed_ids.len()
+    );
+    Ok(BulkAccountResult {
+        matched: matched.len(),
+        changed: changed_ids.len(),
+        changed_ids,
+    })
+}
+
+/// Disable an auth file automatically and record why (e.g. the provider banned the account).
+/// Returns false if the file was already disabled.
+pub fn disable_auth_file(path: &std::path::Path, reason: &str) -> Result<bool> {
+    let content = std::fs::read_to_string(path)?;
+    let mut json: serde_json::Value = serde_json::from_str(&content)?;
+    if !json.is_object() {
+        return Err(anyhow::anyhow!("invalid auth file: expected object"));
+    }
+
+    let already_disabled = json.get("disabled").and_then(|v| v.as_bool()) == Some(true)
+        || json.get("enabled").and_then(|v| v.as_bool()) == Some(false);
+    if already_disabled {
+        return Ok(false);
+    }
+
+    json["enabled"] = serde_json::json!(false);
+    json["disabled"] = serde_json::json!(true);
+    json["disabled_reason"] = serde_json::json!(reason);
+    json["disabled_at"] = serde_json::json!(chrono::Utc::now().to_rfc3339());
+
+    let content = serde_json::to_string_pretty(&json)?;
+    std::fs::write(path, content)?;
+    tracing::warn!("Auto-disabled account {:?}: {}", path, reason);
+    Ok(true)
+}
+
 /// Fetch quota for an Antigravity account
 pub async fn fetch_antigravity_quota(
     account_id: &str,
@@ -544,7 +773,7 @@ pub async fn fetch_antigravity_quota(
         return Err(anyhow::anyhow!("Account file not found: {}", account_id));
     }
 
-    let content = std::fs::read_to_string(&path)?;
+    let content = token_store::read_to_string(&path)?;
     let json: serde_json::Value = serde_json::from_str(&content)?;
 
     // Check if it's an antigravity account
@@ -607,7 +836,21 @@ pub async fn fetch_antigravity_quota(
     updated_json["quota_is_forbidden"] = serde_json::json!(quota.is_forbidden);
 
     let updated_content = serde_json::to_string_pretty(&updated_json)?;
-    std::fs::write(&path, updated_content)?;
+    token_store::write(&path, updated_content);
+
+    if quota.is_forbidden {
+        let reason = "Forbidden by provider (quota check returned 403)";
+        if disable_auth_file(&path, reason).unwrap_or(false) {
+            crate::api::events::emit(
+                crate::api::events::ACCOUNT_DISABLED,
+                serde_json::json!({
+                    "account_id": account_id,
+                    "provider": "antigravity",
+                    "reason": reason,
+                }),
+            );
+        }
+    }
 
     // Cache quota to SQLite
     if let Ok(quota_json) = serde_json::to_string(&quota) {
@@ -626,7 +869,7 @@ pub async fn fetch_codex_quota(account_id: &str) -> Result<providers::openai::Co
         return Err(anyhow::anyhow!("Account file not found: {}", account_id));
     }
 
-    let content = std::fs::read_to_string(&path)?;
+    let content = token_store::read_to_string(&path)?;
     let json: serde_json::Value = serde_json::from_str(&content)?;
 
     // Check if it's an openai/codex account
@@ -688,7 +931,7 @@ pub async fn fetch_codex_quota(account_id: &str) -> Result<providers::openai::Co
     updated_json["codex_plan_type"] = serde_json::json!(&quota.plan_type);
 
     let updated_content = serde_json::to_string_pretty(&updated_json)?;
-    std::fs::write(&path, updated_content)?;
+    token_store::write(&path, updated_content);
 
     // Cache quota to SQLite
     if let Ok(quota_json) = serde_json::to_string(&quota) {
@@ -707,7 +950,7 @@ pub async fn fetch_gemini_quota(account_id: &str) -> Result<providers::google::G
         return Err(anyhow::anyhow!("Account file not found: {}", account_id));
     }
 
-    let content = std::fs::read_to_string(&path)?;
+    let content = token_store::read_to_string(&path)?;
     let json: serde_json::Value = serde_json::from_str(&content)?;
 
     // Check if it's a gemini/google account
@@ -763,7 +1006,7 @@ pub async fn fetch_gemini_quota(account_id: &str) -> Result<providers::google::G
     updated_json["gemini_quota_last_updated"] = serde_json::json!(quota.last_updated);
 
     let updated_content = serde_json::to_string_pretty(&updated_json)?;
-    std::fs::write(&path, updated_content)?;
+    token_store::write(&path, updated_content);
 
     // Cache quota to SQLite
     if let Ok(quota_json) = serde_json::to_string(&quota) {
@@ -827,7 +1070,7 @@ pub fn import_accounts(json_content: &str) -> Result<i32> {
     let auth_dir = crate::config::resolve_auth_dir();
     std::fs::create_dir_all(&auth_dir)?;
 
-    let mut imported = 0;
+    let mut written = Vec::new();
 
     for account in accounts {
         // Determine filename based on provider and email/id
@@ -857,10 +1100,11 @@ pub fn import_accounts(json_content: &str) -> Result<i32> {
         std::fs::write(&path, content)?;
 
         tracing::info!("Imported account to {:?}", path);
-        imported += 1;
+        written.push(path);
     }
 
-    Ok(imported)
+    onboarding::validate_new_accounts(&written);
+    Ok(written.len() as i32)
 }
 
 /// Export all accounts directly to a file
@@ -876,3 +1120,68 @@ pub fn import_accounts_from_file(file_path: &str) -> Result<i32> {
     let content = std::fs::read_to_string(file_path)?;
     import_accounts(&content)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(provider: &str, tags: &[&str]) -> AuthAccount {
+        AuthAccount {
+            id: format!("{}-test", provider),
+            provider: provider.to_string(),
+            email: None,
+            enabled: true,
+            prefix: None,
+            disabled_reason: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            validation: None,
+        }
+    }
+
+    #[test]
+    fn account_filter_requires_all_criteria() {
+        let filter = AccountFilter {
+            provider: Some("Kiro".to_string()),
+            tag: Some("team-a".to_string()),
+            account_ids: Vec::new(),
+        };
+        assert!(filter.matches(&account("kiro", &["team-a"])));
+        assert!(!filter.matches(&account("kiro", &["team-b"])));
+        assert!(!filter.matches(&account("codex", &["team-a"])));
+        assert!(AccountFilter::default().is_empty());
+    }
+
+    #[test]
+    fn bulk_actions_update_auth_json() {
+        let json = serde_json::json!({
+            "type": "kiro",
+            "disabled": true,
+            "disabled_reason": "banned",
+            "tags": ["team-a"]
+        });
+
+        let enabled = apply_bulk_action(&json, &BulkAccountAction::Enable).unwrap();
+        assert_eq!(enabled["enabled"], true);
+        assert!(enabled.get("disabled_reason").is_none());
+
+        let tagged = apply_bulk_action(
+            &json,
+            &BulkAccountAction::AddTag {
+                tag: "team-a".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(tagged, json);
+
+        let untagged = apply_bulk_action(
+            &json,
+            &BulkAccountAction::RemoveTag {
+                tag: "team-a".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(untagged.get("tags").is_none());
+
+        assert!(apply_bulk_action(&json, &BulkAccountAction::Delete).is_none());
+    }
+}
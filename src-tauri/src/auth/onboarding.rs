@@ -0,0 +1,146 @@
+// Validation of newly added accounts
+// After an OAuth flow or an import writes auth files, the files it wrote are probed in the
+// background: the refresh token must yield an access token, the project id must resolve
+// (Gemini, Antigravity) and the quota must be readable. The outcome is stored in the file's
+// `validation` object and sent to the UI as an `account-validated` event, so a broken login
+// shows up right away instead of on the first real request.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Result of the onboarding probe, stored as `validation` in the auth file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountValidation {
+    /// "ok", "failed" or "skipped" (providers without a probe, e.g. API key accounts)
+    pub status: String,
+    /// RFC 3339 time of the probe
+    pub checked_at: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl AccountValidation {
+    fn new(status: &str, error: Option<String>) -> Self {
+        Self {
+            status: status.to_string(),
+            checked_at: chrono::Utc::now().to_rfc3339(),
+            error,
+        }
+    }
+}
+
+/// Account ids of the auth files at `paths`, once each
+fn account_ids(paths: &[PathBuf]) -> Vec<String> {
+    let mut ids: Vec<String> = paths
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str().map(|s| s.to_string()))
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Probe an account and store the result in its auth file
+pub async fn validate_account(account_id: &str) -> Result<AccountValidation> {
+    let path = crate::config::resolve_auth_dir().join(format!("{}.json", account_id));
+    let content = std::fs::read_to_string(&path)?;
+    let json: serde_json::Value = serde_json::from_str(&content)?;
+    let provider = json
+        .get("type")
+        .or_else(|| json.get("provider"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let outcome: Option<Result<()>> =
+        match provider.as_str() {
+            "antigravity" => Some(super::fetch_antigravity_quota(account_id).await.and_then(
+                |quota| {
+                    if quota.is_forbidden {
+                        Err(anyhow::anyhow!(
+                            "the provider refused the quota check (403)"
+                        ))
+                    } else if quota.project_id.is_none() {
+                        Err(anyhow::anyhow!("project_id could not be resolved"))
+                    } else {
+                        Ok(())
+                    }
+                },
+            )),
+            "codex" | "openai" => Some(super::fetch_codex_quota(account_id).await.map(|_| ())),
+            "gemini" | "google" => {
+                let has_project = json
+                    .get("project_id")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|p| !p.trim().is_empty());
+                Some(if has_project {
+                    super::fetch_gemini_quota(account_id).await.map(|_| ())
+                } else {
+                    Err(anyhow::anyhow!("no project_id, set one for this account"))
+                })
+            }
+            "kiro" => Some(super::fetch_kiro_quota(account_id).await.map(|_| ())),
+            _ => None,
+        };
+    let validation = match outcome {
+        Some(Ok(())) => AccountValidation::new("ok", None),
+        Some(Err(e)) => AccountValidation::new("failed", Some(e.to_string())),
+        None => AccountValidation::new("skipped", None),
+    };
+
+    // The probe may have rewritten the file (refreshed token, project id), so read it again
+    let content = std::fs::read_to_string(&path)?;
+    let mut json: serde_json::Value = serde_json::from_str(&content)?;
+    json["validation"] = serde_json::to_value(&validation)?;
+    std::fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+
+    if validation.status == "failed" {
+        tracing::warn!(
+            "Onboarding validation failed for {}: {}",
+            account_id,
+            validation.error.as_deref().unwrap_or("")
+        );
+    }
+    crate::api::events::emit(
+        crate::api::events::ACCOUNT_VALIDATED,
+        serde_json::json!({
+            "account_id": account_id,
+            "provider": provider,
+            "validation": validation,
+        }),
+    );
+    Ok(validation)
+}
+
+/// Validate, in the background, the accounts whose auth files were just written
+pub fn validate_new_accounts(paths: &[PathBuf]) {
+    let ids = account_ids(paths);
+    if ids.is_empty() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        for account_id in ids {
+            if let Err(e) = validate_account(&account_id).await {
+                tracing::warn!("Could not validate account {}: {}", account_id, e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_each_written_file_once() {
+        let dir = PathBuf::from("/tmp/auth");
+        let paths = vec![
+            dir.join("kiro_c.json"),
+            dir.join("codex-a-plus.json"),
+            dir.join("kiro_c.json"),
+        ];
+        assert_eq!(account_ids(&paths), vec!["codex-a-plus", "kiro_c"]);
+        assert!(account_ids(&[]).is_empty());
+    }
+}
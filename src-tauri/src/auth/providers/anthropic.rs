@@ -131,7 +131,7 @@ pub async fn exchange_code(code: &str, state: &str) -> Result<TokenResponse> {
 
     let (parsed_code, new_state) = parse_code_and_state(code);
 
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let mut body = serde_json::json!({
         "code": parsed_code,
@@ -169,7 +169,7 @@ pub async fn exchange_code(code: &str, state: &str) -> Result<TokenResponse> {
 
 /// Refresh access token using refresh token
 pub async fn refresh_token(refresh_token: &str) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let body = serde_json::json!({
         "client_id": ANTHROPIC_CLIENT_ID,
@@ -135,7 +135,7 @@ pub async fn exchange_code(code: &str, state: &str) -> Result<TokenResponse> {
         .remove(state)
         .ok_or_else(|| anyhow::anyhow!("Invalid or expired OAuth state"))?;
 
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let redirect_uri = get_redirect_uri();
     let client_id = get_client_id();
@@ -164,7 +164,7 @@ pub async fn exchange_code(code: &str, state: &str) -> Result<TokenResponse> {
 
 /// Get user info using access token
 pub async fn get_user_info(access_token: &str) -> Result<UserInfo> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let response = client
         .get(USERINFO_URL)
@@ -183,7 +183,7 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo> {
 
 /// Refresh access token using refresh token
 pub async fn refresh_token(refresh_token: &str) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let client_id = get_client_id();
     let client_secret = get_client_secret();
@@ -231,7 +231,7 @@ async fn load_code_assist(access_token: &str) -> Result<serde_json::Value> {
     });
 
     let endpoint = format!("{}/{}:loadCodeAssist", API_ENDPOINT, API_VERSION);
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
     let response = client
         .post(&endpoint)
         .bearer_auth(access_token)
@@ -268,7 +268,7 @@ async fn onboard_user(access_token: &str, tier_id: &str) -> Result<String> {
     });
 
     let endpoint = format!("{}/{}:onboardUser", API_ENDPOINT, API_VERSION);
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     for _ in 0..5 {
         let response = client
@@ -423,7 +423,7 @@ struct TierInfo {
 pub async fn fetch_project_and_tier(
     access_token: &str,
 ) -> Result<(Option<String>, Option<String>)> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
     let body = serde_json::json!({
         "metadata": {
             "ideType": "ANTIGRAVITY"
@@ -481,7 +481,7 @@ pub async fn fetch_quota(
         .clone()
         .unwrap_or_else(|| "bamboo-precept-lgxtn".to_string());
 
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
     let body = serde_json::json!({
         "project": final_project_id
     });
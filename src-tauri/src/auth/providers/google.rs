@@ -13,6 +13,8 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::oneshot;
 
+use crate::auth::callback::{self, PortPolicy};
+
 // Google OAuth endpoints - same as golang.org/x/oauth2/google
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/auth";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
@@ -74,6 +76,7 @@ pub struct OAuthFlowHandle {
 // Shared state for the callback server
 struct CallbackState {
     result_tx: Option<oneshot::Sender<Result<OAuthResult>>>,
+    redirect_uri: String,
 }
 
 const SUCCESS_HTML: &str = r#"
@@ -120,104 +123,18 @@ const ERROR_HTML: &str = r#"
 </html>
 "#;
 
-/// Kill any process using the OAuth callback port
-fn kill_process_on_port(port: u16) {
-    #[cfg(target_os = "macos")]
-    {
-        if let Ok(output) = std::process::Command::new("lsof")
-            .args(["-ti", &format!(":{}", port)])
-            .output()
-        {
-            let pids = String::from_utf8_lossy(&output.stdout);
-            for pid in pids.lines() {
-                if let Ok(pid_num) = pid.trim().parse::<i32>() {
-                    tracing::info!("Killing process {} on port {}", pid_num, port);
-                    let _ = std::process::Command::new("kill")
-                        .args(["-9", &pid_num.to_string()])
-                        .output();
-                }
-            }
-        }
-    }
-    #[cfg(target_os = "linux")]
-    {
-        if let Ok(output) = std::process::Command::new("fuser")
-            .args(["-k", &format!("{}/tcp", port)])
-            .output()
-        {
-            tracing::info!("Killed processes on port {}: {:?}", port, output);
-        }
-    }
-    #[cfg(target_os = "windows")]
-    {
-        if let Ok(output) = std::process::Command::new("cmd")
-            .args(["/c", &format!("for /f \"tokens=5\" %a in ('netstat -aon ^| findstr :{} ^| findstr LISTENING') do taskkill /F /PID %a", port)])
-            .output()
-        {
-            tracing::info!("Killed processes on port {}: {:?}", port, output);
-        }
-    }
-
-    // Give the OS a moment to release the port
-    std::thread::sleep(std::time::Duration::from_millis(100));
+fn callback_redirect_uri(port: u16) -> String {
+    format!("http://localhost:{}/oauth2callback", port)
 }
 
 /// Start OAuth flow with dedicated callback server - CLIProxyAPI compatible
 /// This matches the getTokenFromWeb function in CLIProxyAPI
 pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
-    // CLIProxyAPI uses fixed "state-token" string
-    let state = "state-token".to_string();
-
-    tracing::info!("Starting Google OAuth flow (CLIProxyAPI compatible)");
-
-    let scopes = SCOPES.join(" ");
-
-    // Build auth URL exactly like CLIProxyAPI:
-    // config.AuthCodeURL("state-token", oauth2.AccessTypeOffline, oauth2.SetAuthURLParam("prompt", "consent"))
-    let auth_url = format!(
-        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&access_type=offline&prompt=consent&state={}",
-        GOOGLE_AUTH_URL,
-        GOOGLE_CLIENT_ID,
-        urlencoding::encode(REDIRECT_URI),
-        urlencoding::encode(&scopes),
-        state
-    );
-
-    // Create channel for result
-    let (result_tx, result_rx) = oneshot::channel();
-
-    // Create shared state
-    let callback_state = Arc::new(RwLock::new(CallbackState {
-        result_tx: Some(result_tx),
-    }));
-
-    // Create callback handler
-    let state_clone = callback_state.clone();
-    let callback_handler = move |Query(params): Query<OAuthCallbackParams>| {
-        let state = state_clone.clone();
-        async move { handle_callback(params, state).await }
-    };
-
-    // Build router
-    let app = Router::new().route("/oauth2callback", get(callback_handler));
-
-    // Kill any existing process on the port
-    kill_process_on_port(OAUTH_CALLBACK_PORT);
-
-    // Try to bind to the port
-    let addr = format!("127.0.0.1:{}", OAUTH_CALLBACK_PORT);
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            return Err(anyhow::anyhow!(
-                "Failed to start OAuth callback server on port {}: {}",
-                OAUTH_CALLBACK_PORT,
-                e
-            ));
-        }
-    };
-
-    tracing::info!("Google OAuth callback server listening on {}", addr);
+    let OAuthFlowHandle {
+        auth_url,
+        result_rx,
+        shutdown_tx,
+    } = start_oauth_with_callback_url().await?;
 
     // Open browser using system command
     #[cfg(target_os = "macos")]
@@ -226,8 +143,10 @@ pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
     }
     #[cfg(target_os = "windows")]
     {
+        use std::os::windows::process::CommandExt;
         let _ = std::process::Command::new("cmd")
             .args(["/c", "start", "", &auth_url])
+            .creation_flags(crate::win32::CREATE_NO_WINDOW)
             .spawn();
     }
     #[cfg(target_os = "linux")]
@@ -237,46 +156,43 @@ pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
             .spawn();
     }
 
-    // Spawn server with graceful shutdown
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-
-    tokio::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                shutdown_rx.await.ok();
-            })
-            .await
-            .ok();
-    });
-
     // Wait for result with timeout (5 minutes) - same as CLIProxyAPI
-    let result = tokio::time::timeout(std::time::Duration::from_secs(300), result_rx).await;
+    let result = tokio::time::timeout(callback::CALLBACK_TIMEOUT, result_rx).await;
 
     // Shutdown server
     let _ = shutdown_tx.send(());
 
     match result {
         Ok(Ok(res)) => res,
-        Ok(Err(_)) => Err(anyhow::anyhow!("OAuth callback channel closed")),
+        Ok(Err(_)) => Err(anyhow::anyhow!(
+            "OAuth login was cancelled or replaced by a newer login"
+        )),
         Err(_) => Err(anyhow::anyhow!("OAuth flow timed out after 5 minutes")),
     }
 }
 
 /// Start OAuth flow and return the auth URL plus a handle to await the result.
 /// The caller is responsible for opening the auth URL and handling the result.
+/// The callback server shuts itself down after `callback::CALLBACK_TIMEOUT`.
 pub async fn start_oauth_with_callback_url() -> Result<OAuthFlowHandle> {
     // CLIProxyAPI uses fixed "state-token" string
     let state = "state-token".to_string();
 
     tracing::info!("Starting Google OAuth flow (CLIProxyAPI compatible)");
 
+    // Installed-app clients accept any loopback port, so a busy 8085 is not fatal
+    let listener = callback::bind("Google", OAUTH_CALLBACK_PORT, PortPolicy::AnyLoopback).await?;
+    let redirect_uri = callback_redirect_uri(listener.port());
+
     let scopes = SCOPES.join(" ");
 
+    // Build auth URL exactly like CLIProxyAPI:
+    // config.AuthCodeURL("state-token", oauth2.AccessTypeOffline, oauth2.SetAuthURLParam("prompt", "consent"))
     let auth_url = format!(
         "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&access_type=offline&prompt=consent&state={}",
         GOOGLE_AUTH_URL,
         GOOGLE_CLIENT_ID,
-        urlencoding::encode(REDIRECT_URI),
+        urlencoding::encode(&redirect_uri),
         urlencoding::encode(&scopes),
         state
     );
@@ -287,6 +203,7 @@ pub async fn start_oauth_with_callback_url() -> Result<OAuthFlowHandle> {
     // Create shared state
     let callback_state = Arc::new(RwLock::new(CallbackState {
         result_tx: Some(result_tx),
+        redirect_uri,
     }));
 
     // Create callback handler
@@ -299,35 +216,7 @@ pub async fn start_oauth_with_callback_url() -> Result<OAuthFlowHandle> {
     // Build router
     let app = Router::new().route("/oauth2callback", get(callback_handler));
 
-    // Kill any existing process on the port
-    kill_process_on_port(OAUTH_CALLBACK_PORT);
-
-    // Try to bind to the port
-    let addr = format!("127.0.0.1:{}", OAUTH_CALLBACK_PORT);
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            return Err(anyhow::anyhow!(
-                "Failed to start OAuth callback server on port {}: {}",
-                OAUTH_CALLBACK_PORT,
-                e
-            ));
-        }
-    };
-
-    tracing::info!("Google OAuth callback server listening on {}", addr);
-
-    // Spawn server with graceful shutdown
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-
-    tokio::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                shutdown_rx.await.ok();
-            })
-            .await
-            .ok();
-    });
+    let shutdown_tx = listener.serve(app);
 
     Ok(OAuthFlowHandle {
         auth_url,
@@ -373,7 +262,8 @@ async fn handle_callback(
     }
 
     // Exchange code for tokens - same as CLIProxyAPI config.Exchange()
-    match exchange_code_internal(&code).await {
+    let redirect_uri = state.read().redirect_uri.clone();
+    match exchange_code_internal(&code, &redirect_uri).await {
         Ok(token_response) => {
             // Get user info using v1 API like CLIProxyAPI
             let email = match get_user_info(&token_response.access_token).await {
@@ -408,15 +298,15 @@ async fn handle_callback(
 }
 
 /// Exchange authorization code for tokens - matches CLIProxyAPI config.Exchange()
-async fn exchange_code_internal(code: &str) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+async fn exchange_code_internal(code: &str, redirect_uri: &str) -> Result<TokenResponse> {
+    let client = crate::api::http_client::client();
 
     let params = [
         ("client_id", GOOGLE_CLIENT_ID),
         ("client_secret", GOOGLE_CLIENT_SECRET),
         ("code", code),
         ("grant_type", "authorization_code"),
-        ("redirect_uri", REDIRECT_URI),
+        ("redirect_uri", redirect_uri),
     ];
 
     let response = client.post(GOOGLE_TOKEN_URL).form(&params).send().await?;
@@ -434,7 +324,7 @@ async fn exchange_code_internal(code: &str) -> Result<TokenResponse> {
 
 /// Get user info using access token - uses v1 API like CLIProxyAPI
 pub async fn get_user_info(access_token: &str) -> Result<UserInfo> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let response = client
         .get(GOOGLE_USERINFO_URL)
@@ -454,7 +344,7 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo> {
 
 /// Refresh access token using refresh token
 pub async fn refresh_token(refresh_token: &str) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let params = [
         ("client_id", GOOGLE_CLIENT_ID),
@@ -481,7 +371,7 @@ pub async fn exchange_code(code: &str, state: &str) -> Result<TokenResponse> {
     if state != "state-token" {
         return Err(anyhow::anyhow!("Invalid state parameter"));
     }
-    exchange_code_internal(code).await
+    exchange_code_internal(code, REDIRECT_URI).await
 }
 
 // Gemini Quota API
@@ -523,7 +413,7 @@ pub async fn fetch_gemini_quota(
     access_token: &str,
     project_id: Option<&str>,
 ) -> Result<GeminiQuotaData> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let body = serde_json::json!({
         "project": project_id.unwrap_or("")
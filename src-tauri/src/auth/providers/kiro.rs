@@ -138,7 +138,7 @@ struct IdcTokenResponse {
 
 /// Refresh token using Desktop Auth API (for social accounts)
 async fn refresh_token_desktop(refresh_token: &str) -> Result<RefreshTokenResponse> {
-    let client = reqwest::Client::builder()
+    let client = crate::api::http_client::client_builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()?;
 
@@ -177,7 +177,7 @@ async fn refresh_token_idc(
     client_secret: &str,
     refresh_token: &str,
 ) -> Result<IdcTokenResponse> {
-    let client = reqwest::Client::builder()
+    let client = crate::api::http_client::client_builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()?;
 
@@ -218,7 +218,7 @@ async fn refresh_token_idc(
 
 /// Get usage limits and user info from Desktop API (for social accounts)
 async fn get_usage_limits(access_token: &str) -> Result<UsageLimitsResponse> {
-    let client = reqwest::Client::builder()
+    let client = crate::api::http_client::client_builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()?;
 
@@ -253,7 +253,7 @@ async fn get_usage_limits(access_token: &str) -> Result<UsageLimitsResponse> {
 
 /// Get usage limits for IdC accounts (requires special headers)
 async fn get_usage_limits_idc(access_token: &str) -> Result<UsageLimitsResponse> {
-    let client = reqwest::Client::builder()
+    let client = crate::api::http_client::client_builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()?;
 
@@ -543,7 +543,8 @@ fn load_device_registration(client_id_hash: &str) -> Result<(Option<String>, Opt
 }
 
 /// Start OAuth flow - for Kiro, this imports local credentials
-pub async fn start_oauth() -> Result<String> {
+/// Returns the result message and the auth file written
+pub async fn start_oauth() -> Result<(String, PathBuf)> {
     // Import credentials from kiro-auth-token.json
     let result = import_local_credentials().await?;
 
@@ -588,9 +589,9 @@ pub async fn start_oauth() -> Result<String> {
 
     tracing::info!("Saved Kiro auth file to {:?}", path);
 
-    Ok(format!(
-        "Kiro account imported successfully: {}",
-        identifier
+    Ok((
+        format!("Kiro account imported successfully: {}", identifier),
+        path,
     ))
 }
 
@@ -19,6 +19,8 @@ use std::time::Duration;
 use tokio::sync::oneshot;
 use uuid::Uuid;
 
+use crate::auth::callback::{self, PortPolicy};
+
 const OPENAI_AUTH_URL: &str = "https://auth.openai.com/oauth/authorize";
 const OPENAI_TOKEN_URL: &str = "https://auth.openai.com/oauth/token";
 fn get_client_id() -> String {
@@ -263,7 +265,7 @@ async fn exchange_code_with_redirect(
     code_verifier: &str,
     redirect_uri: &str,
 ) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let client_id = get_client_id();
     let params = [
@@ -298,7 +300,7 @@ async fn exchange_code_with_redirect(
 
 /// Refresh access token using refresh_token
 pub async fn refresh_token(refresh_token: &str) -> Result<TokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let client_id = get_client_id();
     let params = [
@@ -407,50 +409,6 @@ const ERROR_HTML: &str = r#"
 </html>
 "#;
 
-/// Kill any process using the OAuth callback port
-fn kill_process_on_port(port: u16) {
-    #[cfg(target_os = "macos")]
-    {
-        // Use lsof to find and kill process on the port
-        if let Ok(output) = std::process::Command::new("lsof")
-            .args(["-ti", &format!(":{}", port)])
-            .output()
-        {
-            let pids = String::from_utf8_lossy(&output.stdout);
-            for pid in pids.lines() {
-                if let Ok(pid_num) = pid.trim().parse::<i32>() {
-                    tracing::info!("Killing process {} on port {}", pid_num, port);
-                    let _ = std::process::Command::new("kill")
-                        .args(["-9", &pid_num.to_string()])
-                        .output();
-                }
-            }
-        }
-    }
-    #[cfg(target_os = "linux")]
-    {
-        if let Ok(output) = std::process::Command::new("fuser")
-            .args(["-k", &format!("{}/tcp", port)])
-            .output()
-        {
-            tracing::info!("Killed processes on port {}: {:?}", port, output);
-        }
-    }
-    #[cfg(target_os = "windows")]
-    {
-        // On Windows, use netstat to find PID and taskkill to kill it
-        if let Ok(output) = std::process::Command::new("cmd")
-            .args(["/c", &format!("for /f \"tokens=5\" %a in ('netstat -aon ^| findstr :{} ^| findstr LISTENING') do taskkill /F /PID %a", port)])
-            .output()
-        {
-            tracing::info!("Killed processes on port {}: {:?}", port, output);
-        }
-    }
-
-    // Give the OS a moment to release the port
-    std::thread::sleep(std::time::Duration::from_millis(100));
-}
-
 fn open_system_browser(url: &str) {
     #[cfg(target_os = "macos")]
     {
@@ -458,8 +416,10 @@ fn open_system_browser(url: &str) {
     }
     #[cfg(target_os = "windows")]
     {
+        use std::os::windows::process::CommandExt;
         let _ = std::process::Command::new("cmd")
             .args(["/c", "start", "", url])
+            .creation_flags(crate::win32::CREATE_NO_WINDOW)
             .spawn();
     }
     #[cfg(target_os = "linux")]
@@ -473,6 +433,9 @@ pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
     let pkce = PKCECodes::new();
     let state = generate_state();
 
+    // The Codex client only accepts the registered localhost:1455 redirect URI
+    let listener = callback::bind("Codex", OAUTH_CALLBACK_PORT, PortPolicy::Fixed).await?;
+
     // Store PKCE verifier
     PENDING_OAUTH
         .write()
@@ -521,24 +484,6 @@ pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
     // Build router
     let app = Router::new().route("/auth/callback", get(callback_handler));
 
-    // Kill any existing process on the port
-    kill_process_on_port(OAUTH_CALLBACK_PORT);
-
-    // Try to bind to the port
-    let addr = format!("127.0.0.1:{}", OAUTH_CALLBACK_PORT);
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            return Err(anyhow::anyhow!(
-                "Failed to start OAuth callback server on port {}: {}",
-                OAUTH_CALLBACK_PORT,
-                e
-            ));
-        }
-    };
-
-    tracing::info!("OAuth callback server listening on {}", addr);
-
     // Return the auth URL - the caller should open it in the browser
     // We don't open the browser here to avoid Tauri shell permission issues
     tracing::info!("Please open this URL in your browser: {}", auth_url);
@@ -546,27 +491,20 @@ pub async fn start_oauth_with_callback() -> Result<OAuthResult> {
     // Try to open browser using system command directly
     open_system_browser(&auth_url);
 
-    // Spawn server with graceful shutdown
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-
-    tokio::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                shutdown_rx.await.ok();
-            })
-            .await
-            .ok();
-    });
+    // Serve callbacks until the flow finishes or the listener times out
+    let shutdown_tx = listener.serve(app);
 
     // Wait for result with timeout
-    let result = tokio::time::timeout(std::time::Duration::from_secs(300), result_rx).await;
+    let result = tokio::time::timeout(callback::CALLBACK_TIMEOUT, result_rx).await;
 
     // Shutdown server
     let _ = shutdown_tx.send(());
 
     match result {
         Ok(Ok(res)) => res,
-        Ok(Err(_)) => Err(anyhow::anyhow!("OAuth callback channel closed")),
+        Ok(Err(_)) => Err(anyhow::anyhow!(
+            "OAuth login was cancelled or replaced by a newer login"
+        )),
         Err(_) => Err(anyhow::anyhow!("OAuth flow timed out after 5 minutes")),
     }
 }
@@ -642,7 +580,7 @@ pub async fn finish_device_oauth(session_id: &str) -> Result<OAuthResult> {
 }
 
 async fn request_device_user_code() -> Result<DeviceUserCodeResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
     let response = client
         .post(CODEX_DEVICE_USER_CODE_URL)
         .header("Content-Type", "application/json")
@@ -672,7 +610,7 @@ async fn poll_device_token(
     user_code: &str,
     interval: Duration,
 ) -> Result<DeviceTokenResponse> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
     let deadline = tokio::time::Instant::now() + CODEX_DEVICE_TIMEOUT;
 
     loop {
@@ -844,7 +782,7 @@ pub async fn fetch_codex_quota(
     access_token: &str,
     account_id: Option<&str>,
 ) -> Result<CodexQuotaData> {
-    let client = reqwest::Client::new();
+    let client = crate::api::http_client::client();
 
     let mut request = client
         .get(CODEX_USAGE_URL)
@@ -0,0 +1,189 @@
+// Canonical auth file schema
+// Auth files come in several shapes: our own `AuthFile` (`provider` + nested `token`), the
+// CLIProxyAPI Gemini format (`type` + nested `token`, no enabled flag), Codex files with the
+// tokens at the root and older root-token files that only have an `access_token`. All of them
+// are normalized to one versioned schema: credentials stay where the providers read them, and
+// every file carries `schema_version`, `type`, `email` (when known) and consistent
+// `enabled`/`disabled` flags. Files are rewritten on startup, after a copy of the original is
+// kept in `.schema-backup` inside the auth directory.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Version written to migrated files
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Directory inside the auth dir holding the files as they were before migration
+const BACKUP_DIR: &str = ".schema-backup";
+
+fn schema_version(json: &Value) -> u64 {
+    json.get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+}
+
+/// Canonical form of an auth file, `None` when the JSON holds no access token
+pub fn canonicalize(json: &Value, filename: &str) -> Option<Value> {
+    let obj = json.as_object()?;
+    let has_access_token = obj.contains_key("access_token")
+        || obj
+            .get("token")
+            .and_then(|t| t.as_object())
+            .is_some_and(|t| t.contains_key("access_token"));
+    if !has_access_token {
+        return None;
+    }
+
+    let mut canonical = obj.clone();
+    // Provider from "type" or "provider", or from filenames like "antigravity-email.json"
+    let provider = obj
+        .get("type")
+        .or_else(|| obj.get("provider"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| {
+            filename
+                .split(['-', '_'])
+                .next()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    canonical.insert("type".to_string(), json!(provider));
+
+    if canonical.get("email").is_some_and(|v| v.is_null()) {
+        canonical.remove("email");
+    }
+
+    // Disabled wins over enabled, as when the proxy picks accounts
+    let disabled = obj
+        .get("disabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let enabled = !disabled && obj.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true);
+    canonical.insert("enabled".to_string(), json!(enabled));
+    canonical.insert("disabled".to_string(), json!(!enabled));
+
+    canonical.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+    Some(Value::Object(canonical))
+}
+
+/// Rewrite every auth file older than the current schema; returns how many were migrated
+pub fn migrate_auth_dir(auth_dir: &Path) -> Result<usize> {
+    if !auth_dir.exists() {
+        return Ok(0);
+    }
+
+    let backup_dir = auth_dir.join(BACKUP_DIR);
+    let stamp = chrono::Local::now().format("%Y%m%d%H%M%S").to_string();
+    let mut migrated = 0;
+    for entry in std::fs::read_dir(auth_dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().map(|e| e == "json") != Some(true) {
+            continue;
+        }
+        let Some(filename) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if filename == "config" {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(json) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+        if schema_version(&json) >= SCHEMA_VERSION {
+            continue;
+        }
+        let Some(canonical) = canonicalize(&json, filename) else {
+            continue;
+        };
+
+        std::fs::create_dir_all(&backup_dir)?;
+        std::fs::write(
+            backup_dir.join(format!("{}.{}.json", filename, stamp)),
+            &content,
+        )?;
+        std::fs::write(&path, serde_json::to_string_pretty(&canonical)?)?;
+        tracing::info!(
+            "Migrated auth file {} to schema version {}",
+            filename,
+            SCHEMA_VERSION
+        );
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// Migrate the shared auth dir and the dir of every configured tenant; returns how many files
+/// were migrated
+pub fn migrate_configured_auth_dirs() -> usize {
+    let shared_auth_dir = crate::config::resolve_shared_auth_dir();
+    let tenant_auth_dirs = crate::config::get_config()
+        .map(|config| config.tenants)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|tenant| crate::api::tenant::valid_name(&tenant.name))
+        .map(|tenant| crate::api::tenant::auth_dir(&shared_auth_dir, &tenant.name));
+
+    let mut total = 0;
+    for auth_dir in std::iter::once(shared_auth_dir.clone()).chain(tenant_auth_dirs) {
+        match migrate_auth_dir(&auth_dir) {
+            Ok(0) => {}
+            Ok(n) => {
+                tracing::info!(
+                    "Migrated {} auth file(s) in {:?} to the current schema",
+                    n,
+                    auth_dir
+                );
+                total += n;
+            }
+            Err(e) => tracing::error!("Failed to migrate auth files: {}", e),
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_every_legacy_shape() {
+        let auth_file = json!({
+            "provider": "claude",
+            "email": "a@example.com",
+            "token": {"access_token": "at", "refresh_token": "rt", "token_type": "Bearer"},
+            "project_id": null,
+            "enabled": false,
+            "prefix": null
+        });
+        let canonical = canonicalize(&auth_file, "claude_a@example.com").unwrap();
+        assert_eq!(canonical["type"], "claude");
+        assert_eq!(canonical["enabled"], false);
+        assert_eq!(canonical["disabled"], true);
+        assert_eq!(canonical["token"]["refresh_token"], "rt");
+        assert_eq!(canonical["schema_version"], SCHEMA_VERSION);
+
+        let gemini = json!({
+            "token": {"access_token": "at"},
+            "project_id": "p",
+            "email": "b@example.com",
+            "type": "gemini"
+        });
+        let canonical = canonicalize(&gemini, "gemini-b@example.com-all").unwrap();
+        assert_eq!(canonical["type"], "gemini");
+        assert_eq!(canonical["enabled"], true);
+
+        let root_token = json!({"access_token": "at", "email": null, "disabled": true});
+        let canonical = canonicalize(&root_token, "antigravity-c@example.com").unwrap();
+        assert_eq!(canonical["type"], "antigravity");
+        assert_eq!(canonical["enabled"], false);
+        assert!(canonical.get("email").is_none());
+
+        assert!(canonicalize(&json!({"port": 8317}), "settings").is_none());
+    }
+}
@@ -0,0 +1,213 @@
+// Fallback for auth files that cannot be written
+// Token refreshes rewrite the account's auth file. When the auth dir is read-only or missing
+// (e.g. a synced folder that went offline) the write fails, and the next request would read the
+// old token and refresh again. Failed writes are kept in memory instead and served to token
+// readers until a write succeeds again, and a persistent warning is shown through
+// `get_server_status` and the `auth-dir-warning` event. The file as it was on disk at the
+// failure is remembered: once it is edited or deleted by anything else (the UI, a sync
+// restore, another machine) the copy in memory is dropped rather than written over it.
+// Pending writes are retried every `RETRY_INTERVAL`.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often writes kept in memory are retried
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An auth file on disk, `None` when it cannot be read
+#[derive(Debug, Clone, PartialEq)]
+struct DiskCopy {
+    modified: Option<SystemTime>,
+    content: String,
+}
+
+fn disk_copy(path: &Path) -> Option<DiskCopy> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some(DiskCopy { modified, content })
+}
+
+/// Content that could not be written and the file as it was on disk at that time
+#[derive(Debug, Clone)]
+struct Pending {
+    content: String,
+    disk: Option<DiskCopy>,
+}
+
+impl Pending {
+    /// Whether the file was changed by something else since the write failed. A file that
+    /// could not be read at the time (missing dir) is taken as unchanged when it comes back.
+    fn is_stale(&self, current: &Option<DiskCopy>) -> bool {
+        self.disk.is_some() && *current != self.disk
+    }
+}
+
+/// Auth file contents that could not be written to disk, by path
+static PENDING: Lazy<RwLock<HashMap<PathBuf, Pending>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static WARNING: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// Current warning about the auth dir, `None` while every write succeeds
+pub fn warning() -> Option<String> {
+    WARNING.read().clone()
+}
+
+fn set_warning(warning: Option<String>) {
+    let changed = {
+        let mut current = WARNING.write();
+        let changed = *current != warning;
+        *current = warning.clone();
+        changed
+    };
+    if changed {
+        crate::api::events::emit(crate::api::events::AUTH_DIR_WARNING, warning);
+    }
+}
+
+fn clear_warning_if_done() {
+    if PENDING.read().is_empty() {
+        set_warning(None);
+    }
+}
+
+/// Drop the copy in memory of `path` if it is still `pending`
+fn discard(path: &Path, pending: &Pending) {
+    let mut map = PENDING.write();
+    if map.get(path).is_some_and(|p| p.content == pending.content) {
+        map.remove(path);
+    }
+}
+
+/// Read an auth file, preferring a newer copy that could not be written
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let pending = PENDING.read().get(path).cloned();
+    if let Some(pending) = pending {
+        if !pending.is_stale(&disk_copy(path)) {
+            return Ok(pending.content);
+        }
+        tracing::info!(
+            "Auth file {:?} changed on disk, dropping the copy kept in memory",
+            path
+        );
+        discard(path, &pending);
+        clear_warning_if_done();
+    }
+    std::fs::read_to_string(path)
+}
+
+/// Write an auth file; on failure the content is kept in memory and `false` is returned
+pub fn write(path: &Path, content: String) -> bool {
+    match std::fs::write(path, &content) {
+        Ok(()) => {
+            PENDING.write().remove(path);
+            flush();
+            true
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Could not write auth file {:?}, keeping it in memory: {}",
+                path,
+                e
+            );
+            let mut map = PENDING.write();
+            // A later failed write keeps the disk copy seen by the first one
+            let disk = match map.get(path) {
+                Some(previous) => previous.disk.clone(),
+                None => disk_copy(path),
+            };
+            map.insert(path.to_path_buf(), Pending { content, disk });
+            drop(map);
+            set_warning(Some(format!(
+                "Auth directory is not writable ({}); refreshed tokens are only kept in memory \
+                 until it is available again",
+                e
+            )));
+            false
+        }
+    }
+}
+
+/// Retry the writes kept in memory whose file is unchanged on disk, drop the others, and
+/// clear the warning once none are left
+fn flush() {
+    let pending: Vec<(PathBuf, Pending)> = PENDING
+        .read()
+        .iter()
+        .map(|(path, pending)| (path.clone(), pending.clone()))
+        .collect();
+    for (path, pending) in pending {
+        if pending.is_stale(&disk_copy(&path)) {
+            tracing::info!(
+                "Auth file {:?} changed on disk, dropping the copy kept in memory",
+                path
+            );
+            discard(&path, &pending);
+        } else if std::fs::write(&path, &pending.content).is_ok() {
+            // Keeps entries replaced by a newer failed write in the meantime
+            discard(&path, &pending);
+        }
+    }
+    clear_warning_if_done();
+}
+
+/// Retry the writes kept in memory every `RETRY_INTERVAL`
+pub async fn retry_pending() {
+    let mut interval = tokio::time::interval(RETRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        if !PENDING.read().is_empty() {
+            flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_failed_writes_from_memory() {
+        let dir = std::env::temp_dir().join(format!("oneproxy-token-store-{}", std::process::id()));
+        let path = dir.join("missing").join("codex-a.json");
+
+        assert!(!write(&path, "{\"access_token\":\"new\"}".to_string()));
+        assert_eq!(read_to_string(&path).unwrap(), "{\"access_token\":\"new\"}");
+        assert!(warning().is_some());
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        assert!(write(&path, "{\"access_token\":\"newer\"}".to_string()));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"access_token\":\"newer\"}"
+        );
+        assert!(warning().is_none());
+
+        // An edit made on disk after the failure wins over the copy in memory
+        let stale = Pending {
+            content: "{\"access_token\":\"refreshed\"}".to_string(),
+            disk: disk_copy(&path),
+        };
+        PENDING.write().insert(path.clone(), stale.clone());
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "{\"access_token\":\"refreshed\"}"
+        );
+        std::fs::write(&path, "{\"enabled\":false}").unwrap();
+        assert_eq!(read_to_string(&path).unwrap(), "{\"enabled\":false}");
+        assert!(PENDING.read().is_empty());
+
+        // ... and is not overwritten by a retry
+        PENDING.write().insert(path.clone(), stale);
+        std::fs::write(&path, "{\"enabled\":true}").unwrap();
+        flush();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"enabled\":true}"
+        );
+        assert!(PENDING.read().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
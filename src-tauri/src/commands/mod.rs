@@ -9,6 +9,8 @@ pub struct ServerStatus {
     pub running: bool,
     pub port: u16,
     pub host: String,
+    /// Set while auth files cannot be written and refreshed tokens only live in memory
+    pub auth_dir_warning: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,16 +20,6 @@ pub struct AuthSummary {
     pub by_provider: HashMap<String, i32>,
 }
 
-#[tauri::command]
-pub async fn get_config() -> Result<AppConfig, String> {
-    config::get_config().ok_or_else(|| "Config not initialized".to_string())
-}
-
-#[tauri::command]
-pub async fn save_config(config: AppConfig) -> Result<(), String> {
-    config::update_config(config).map_err(|e| e.to_string())
-}
-
 #[tauri::command]
 pub async fn get_auth_accounts() -> Result<Vec<AuthAccount>, String> {
     crate::auth::list_accounts()
@@ -83,9 +75,15 @@ pub async fn get_server_status() -> Result<ServerStatus, String> {
         } else {
             config.host
         },
+        auth_dir_warning: crate::auth::token_store::warning(),
     })
 }
 
+#[tauri::command]
+pub async fn get_warmup_status() -> Result<Option<crate::api::warmup::WarmupProgress>, String> {
+    Ok(crate::api::warmup::last_progress())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthAccount {
     pub id: String,
@@ -93,6 +91,15 @@ pub struct AuthAccount {
     pub email: Option<String>,
     pub enabled: bool,
     pub prefix: Option<String>,
+    /// Why the account was disabled automatically (e.g. banned by the provider)
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
+    /// User-defined labels used to group accounts for bulk operations
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Result of the probe run when the account was added
+    #[serde(default)]
+    pub validation: Option<crate::auth::onboarding::AccountValidation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -152,6 +159,16 @@ pub async fn save_api_key_account(
         .map_err(|e| e.to_string())
 }
 
+/// Re-run the onboarding probe of an account
+#[tauri::command]
+pub async fn validate_account(
+    account_id: String,
+) -> Result<crate::auth::onboarding::AccountValidation, String> {
+    crate::auth::onboarding::validate_account(&account_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn delete_account(account_id: String) -> Result<(), String> {
     crate::auth::delete_account(&account_id).map_err(|e| e.to_string())
@@ -162,6 +179,14 @@ pub async fn set_account_enabled(account_id: String, enabled: bool) -> Result<()
     crate::auth::set_account_enabled(&account_id, enabled).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn bulk_update_accounts(
+    filter: crate::auth::AccountFilter,
+    action: crate::auth::BulkAccountAction,
+) -> Result<crate::auth::BulkAccountResult, String> {
+    crate::auth::bulk_update_accounts(&filter, &action).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn set_gemini_project_id(account_id: String, project_id: String) -> Result<(), String> {
     crate::auth::set_gemini_project_id(&account_id, &project_id).map_err(|e| e.to_string())
@@ -228,6 +253,47 @@ pub async fn get_cached_quotas() -> Result<HashMap<String, crate::db::CachedQuot
     crate::db::get_all_quota_cache().map_err(|e| e.to_string())
 }
 
+/// Start (unix seconds) of a range such as "24h", "7d" or "30d" ending now (default 7d)
+fn range_start(range: Option<String>) -> Result<i64, String> {
+    let range = range.unwrap_or_else(|| "7d".to_string());
+    let range = range.trim();
+    let invalid = || format!("Invalid range '{}', expected e.g. 24h or 7d", range);
+    let (amount, unit_seconds) = match (range.strip_suffix('h'), range.strip_suffix('d')) {
+        (Some(hours), _) => (hours, 3600),
+        (_, Some(days)) => (days, 24 * 3600),
+        _ => return Err(invalid()),
+    };
+    let seconds = amount
+        .parse::<i64>()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .and_then(|amount| amount.checked_mul(unit_seconds))
+        .ok_or_else(invalid)?;
+    Ok(chrono::Utc::now().timestamp() - seconds)
+}
+
+/// Quota snapshots of an account over a range such as "24h", "7d" or "30d" (default 7d)
+#[tauri::command]
+pub async fn get_quota_history(
+    account_id: String,
+    range: Option<String>,
+) -> Result<Vec<crate::db::QuotaSnapshot>, String> {
+    let since = range_start(range)?;
+    crate::db::get_quota_history(&account_id, since).map_err(|e| e.to_string())
+}
+
+/// Requests and tokens per client API key over a range such as "24h" or "30d" (default 7d)
+#[tauri::command]
+pub async fn get_key_usage(range: Option<String>) -> Result<Vec<crate::db::KeyUsage>, String> {
+    let since = range_start(range)?;
+    crate::db::get_key_usage(since).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn invalidate_kiro_model_cache() -> Result<(), String> {
+    crate::api::kiro::invalidate_model_cache().map_err(|e| e.to_string())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodexRoutingStatusData {
     pub account_id: String,
@@ -257,6 +323,24 @@ pub async fn get_codex_routing_statuses() -> Result<HashMap<String, CodexRouting
         .collect())
 }
 
+#[tauri::command]
+pub async fn get_provider_status(
+) -> Result<Vec<crate::api::provider_health::ProviderStatus>, String> {
+    Ok(crate::api::provider_health::get_provider_status())
+}
+
+#[tauri::command]
+pub async fn run_smoke_test() -> Result<crate::api::smoke_test::SmokeTestReport, String> {
+    crate::api::smoke_test::run()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_request_stats() -> Result<Vec<crate::api::stats::ProviderStats>, String> {
+    Ok(crate::api::stats::get_request_stats())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsData {
     pub quota_refresh_interval: u32,
@@ -294,21 +378,369 @@ pub async fn get_settings() -> Result<SettingsData, String> {
 }
 
 #[tauri::command]
-pub async fn save_settings(settings: SettingsData) -> Result<(), String> {
-    let mut config = config::get_config().ok_or_else(|| "Config not initialized".to_string())?;
-    config.quota_refresh_interval = settings.quota_refresh_interval;
-    config.model_routing.mode = settings.model_routing_mode;
-    config.model_routing.provider_priorities = settings
-        .provider_priorities
-        .iter()
-        .map(|p| config::ProviderPriority {
-            provider: p.provider.clone(),
-            priority: p.priority,
-            enabled: p.enabled,
+pub async fn save_settings(
+    app: tauri::AppHandle,
+    settings: SettingsData,
+    etag: Option<String>,
+) -> Result<String, String> {
+    let etag = save_config(etag.as_deref(), |config| {
+        config.quota_refresh_interval = settings.quota_refresh_interval;
+        config.model_routing.mode = settings.model_routing_mode;
+        config.model_routing.provider_priorities = settings
+            .provider_priorities
+            .iter()
+            .map(|p| config::ProviderPriority {
+                provider: p.provider.clone(),
+                priority: p.priority,
+                enabled: p.enabled,
+            })
+            .collect();
+        config.routing.strategy = settings.account_routing_strategy;
+    })?;
+    crate::sync_routing_mode_menu(&app);
+    Ok(etag)
+}
+
+// ============ Config Section Commands ============
+// The webview only receives the slices it needs; API keys are redacted
+
+/// Redact a secret for display, keeping a short prefix and suffix
+fn redact_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    let prefix: String = chars[..3].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}****{}", prefix, suffix)
+}
+
+fn load_config() -> Result<AppConfig, String> {
+    config::get_config().ok_or_else(|| "Config not initialized".to_string())
+}
+
+/// Change and save the config; with an `etag` the change is refused when the config was
+/// edited elsewhere (e.g. through the management API) since the form was loaded
+/// Returns the new ETag, so a form saving in several steps can pass it on.
+fn save_config(etag: Option<&str>, change: impl FnOnce(&mut AppConfig)) -> Result<String, String> {
+    config::modify_config(etag, |config| {
+        change(config);
+        Ok(())
+    })
+    .map(|config| config::config_etag(&config))
+    .map_err(|e| e.to_string())
+}
+
+/// Version of the saved config, to pass to the save commands
+#[tauri::command]
+pub async fn get_config_etag() -> Result<String, String> {
+    Ok(config::config_etag(&load_config()?))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkSettings {
+    pub host: String,
+    pub port: u16,
+    pub ipv6_only: bool,
+    /// Redacted API keys (read-only, use the API key commands to change them)
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn get_network_settings() -> Result<NetworkSettings, String> {
+    let config = load_config()?;
+    Ok(NetworkSettings {
+        host: config.host,
+        port: config.port,
+        ipv6_only: config.ipv6_only,
+        api_keys: config.api_keys.iter().map(|k| redact_secret(k)).collect(),
+    })
+}
+
+#[tauri::command]
+pub async fn save_network_settings(
+    settings: NetworkSettings,
+    etag: Option<String>,
+) -> Result<String, String> {
+    save_config(etag.as_deref(), |config| {
+        config.host = settings.host;
+        config.port = settings.port;
+        config.ipv6_only = settings.ipv6_only;
+    })
+}
+
+#[tauri::command]
+pub async fn get_tailnet_info() -> Result<Option<crate::tailscale::TailnetInfo>, String> {
+    Ok(crate::tailscale::detect().await)
+}
+
+/// Bind the proxy to this machine's tailnet address, 127.0.0.1 stays served for the SSH tunnel
+/// and local clients; takes effect after a restart
+#[tauri::command]
+pub async fn bind_to_tailnet() -> Result<String, String> {
+    let info = crate::tailscale::detect()
+        .await
+        .ok_or_else(|| "No Tailscale address found on this machine".to_string())?;
+    let host = info
+        .ipv4
+        .or(info.ipv6)
+        .ok_or_else(|| "No Tailscale address found on this machine".to_string())?;
+    save_config(None, |config| config.host = host.clone())?;
+    Ok(host)
+}
+
+#[tauri::command]
+pub async fn get_ssh_tunnel_settings() -> Result<config::SshTunnelConfig, String> {
+    Ok(load_config()?.ssh_tunnel)
+}
+
+/// Save the tunnel settings and reconnect it when the server is running
+#[tauri::command]
+pub async fn save_ssh_tunnel_settings(
+    settings: config::SshTunnelConfig,
+    etag: Option<String>,
+) -> Result<String, String> {
+    let etag = save_config(etag.as_deref(), |config| config.ssh_tunnel = settings)?;
+    if crate::api::is_server_running() {
+        crate::tunnel::start(load_config()?.port);
+    }
+    Ok(etag)
+}
+
+#[tauri::command]
+pub async fn get_tunnel_status() -> Result<crate::tunnel::TunnelStatus, String> {
+    Ok(crate::tunnel::status())
+}
+
+/// Random proxy API key with the given prefix
+fn random_api_key(prefix: &str) -> String {
+    use rand::Rng;
+
+    let mut rng = rand::rng();
+    format!(
+        "{}{}",
+        prefix,
+        (0..24)
+            .map(|_| format!("{:02x}", rng.random::<u8>()))
+            .collect::<String>()
+    )
+}
+
+/// Generate a new proxy API key, replacing existing ones; returns the full key once
+#[tauri::command]
+pub async fn generate_api_key() -> Result<String, String> {
+    let key = random_api_key("sk-");
+
+    save_config(None, |config| config.api_keys = vec![key.clone()])?;
+    Ok(key)
+}
+
+/// Return the full primary API key (e.g. for copying to the clipboard)
+#[tauri::command]
+pub async fn reveal_api_key() -> Result<Option<String>, String> {
+    Ok(load_config()?.api_keys.into_iter().next())
+}
+
+#[tauri::command]
+pub async fn clear_api_keys() -> Result<(), String> {
+    save_config(None, |config| config.api_keys.clear())?;
+    Ok(())
+}
+
+/// Temporary API key as listed in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct TemporaryApiKeyInfo {
+    pub id: i64,
+    /// Redacted; the full key is only returned when it is created
+    pub key: String,
+    pub label: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub remaining_seconds: Option<i64>,
+    pub remaining_requests: Option<i64>,
+}
+
+/// Create a key that stops working after `ttl_hours` and/or `max_requests` requests; returns
+/// the full key once
+#[tauri::command]
+pub async fn create_temporary_api_key(
+    label: String,
+    ttl_hours: Option<u32>,
+    max_requests: Option<u32>,
+) -> Result<String, String> {
+    if load_config()?.api_keys.is_empty() {
+        return Err(
+            "Generate a permanent API key first, the proxy accepts any request without one"
+                .to_string(),
+        );
+    }
+    let ttl_hours = ttl_hours.filter(|hours| *hours > 0);
+    let max_requests = max_requests.filter(|max| *max > 0);
+    if ttl_hours.is_none() && max_requests.is_none() {
+        return Err("Set a lifetime in hours or a request limit".to_string());
+    }
+
+    let key = random_api_key("sk-tmp-");
+    let expires_at =
+        ttl_hours.map(|hours| chrono::Utc::now().timestamp() + i64::from(hours) * 3600);
+    let label = match label.trim() {
+        "" => "temporary".to_string(),
+        label => label.to_string(),
+    };
+    crate::db::create_temporary_api_key(&key, &label, expires_at, max_requests.map(i64::from))
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+#[tauri::command]
+pub async fn list_temporary_api_keys() -> Result<Vec<TemporaryApiKeyInfo>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let keys = crate::db::list_temporary_api_keys().map_err(|e| e.to_string())?;
+    Ok(keys
+        .into_iter()
+        .map(|key| TemporaryApiKeyInfo {
+            id: key.id,
+            key: redact_secret(&key.key),
+            label: key.label,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            remaining_seconds: key.expires_at.map(|at| (at - now).max(0)),
+            remaining_requests: key.max_requests.map(|max| (max - key.used_requests).max(0)),
         })
-        .collect();
-    config.routing.strategy = settings.account_routing_strategy;
-    config::update_config(config).map_err(|e| e.to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn revoke_temporary_api_key(id: i64) -> Result<(), String> {
+    crate::db::delete_temporary_api_key(id).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneralSettings {
+    pub debug: bool,
+    pub auth_dir: String,
+    pub request_retry: u32,
+}
+
+#[tauri::command]
+pub async fn get_general_settings() -> Result<GeneralSettings, String> {
+    let config = load_config()?;
+    Ok(GeneralSettings {
+        debug: config.debug,
+        auth_dir: config.auth_dir,
+        request_retry: config.request_retry,
+    })
+}
+
+#[tauri::command]
+pub async fn save_general_settings(
+    settings: GeneralSettings,
+    etag: Option<String>,
+) -> Result<String, String> {
+    save_config(etag.as_deref(), |config| {
+        config.debug = settings.debug;
+        config.auth_dir = settings.auth_dir;
+        config.request_retry = settings.request_retry;
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingSettings {
+    /// "provider" (require provider prefix) or "model" (aggregate by model name)
+    pub mode: String,
+    /// Account selection strategy: "round-robin" | "stick-until-exhausted"
+    pub account_strategy: String,
+    pub provider_priorities: Vec<ProviderPriorityData>,
+    pub thinking_variant_fallback: String,
+    #[serde(default)]
+    pub context_upgrade: bool,
+}
+
+#[tauri::command]
+pub async fn get_routing_settings() -> Result<RoutingSettings, String> {
+    let config = load_config()?;
+    Ok(RoutingSettings {
+        mode: config.model_routing.mode,
+        account_strategy: config.routing.strategy,
+        provider_priorities: config
+            .model_routing
+            .provider_priorities
+            .iter()
+            .map(|p| ProviderPriorityData {
+                provider: p.provider.clone(),
+                priority: p.priority,
+                enabled: p.enabled,
+            })
+            .collect(),
+        thinking_variant_fallback: config.model_routing.thinking_variant_fallback,
+        context_upgrade: config.model_routing.context_upgrade,
+    })
+}
+
+#[tauri::command]
+pub async fn save_routing_settings(
+    app: tauri::AppHandle,
+    settings: RoutingSettings,
+    etag: Option<String>,
+) -> Result<String, String> {
+    let etag = save_config(etag.as_deref(), |config| {
+        config.model_routing.mode = settings.mode;
+        config.routing.strategy = settings.account_strategy;
+        config.model_routing.provider_priorities = settings
+            .provider_priorities
+            .iter()
+            .map(|p| config::ProviderPriority {
+                provider: p.provider.clone(),
+                priority: p.priority,
+                enabled: p.enabled,
+            })
+            .collect();
+        config.model_routing.thinking_variant_fallback = settings.thinking_variant_fallback;
+        config.model_routing.context_upgrade = settings.context_upgrade;
+    })?;
+    crate::sync_routing_mode_menu(&app);
+    Ok(etag)
+}
+
+/// Switch between "provider" (explicit prefix) and "model" (aggregation) routing
+pub(crate) fn apply_routing_mode(app: &tauri::AppHandle, mode: &str) -> Result<(), String> {
+    let mode = mode.trim().to_lowercase();
+    if mode != "provider" && mode != "model" {
+        return Err(format!(
+            "Unknown routing mode: {} (expected \"provider\" or \"model\")",
+            mode
+        ));
+    }
+
+    save_config(None, |config| config.model_routing.mode = mode.clone())?;
+
+    crate::sync_routing_mode_menu(app);
+    crate::api::events::emit(crate::api::events::ROUTING_MODE_CHANGED, &mode);
+    tracing::info!("Routing mode switched to '{}'", mode);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_routing_mode(app: tauri::AppHandle, mode: String) -> Result<(), String> {
+    apply_routing_mode(&app, &mode)
+}
+
+/// Pause or resume routing from any entry point and keep the tray in sync
+pub(crate) fn apply_routing_paused(app: &tauri::AppHandle, paused: bool) {
+    crate::api::set_routing_paused(paused);
+    crate::sync_routing_paused_menu(app);
+}
+
+#[tauri::command]
+pub async fn get_routing_paused() -> Result<bool, String> {
+    Ok(crate::api::is_routing_paused())
+}
+
+#[tauri::command]
+pub async fn set_routing_paused(app: tauri::AppHandle, paused: bool) -> Result<(), String> {
+    apply_routing_paused(&app, paused);
+    Ok(())
 }
 
 // ============ Request Logs Commands ============
@@ -439,7 +871,11 @@ pub struct CustomProviderEntry {
     pub name: String,
     pub prefix: Option<String>,
     pub base_url: String,
+    /// Redacted when read; a redacted key sent back on save keeps the stored key
     pub api_keys: Vec<String>,
+    /// Id of the stored key behind each entry of `api_keys`, empty for keys added in the UI
+    #[serde(default)]
+    pub key_ids: Vec<String>,
     pub models: Vec<String>,
 }
 
@@ -463,7 +899,12 @@ pub async fn get_custom_providers() -> Result<CustomProvidersData, String> {
             api_keys: e
                 .api_key_entries
                 .iter()
-                .map(|k| k.api_key.clone())
+                .map(|k| redact_secret(&k.api_key))
+                .collect(),
+            key_ids: e
+                .api_key_entries
+                .iter()
+                .map(|k| crate::api::key_id(&k.api_key))
                 .collect(),
             models: e.models.clone(),
         })
@@ -479,7 +920,12 @@ pub async fn get_custom_providers() -> Result<CustomProvidersData, String> {
             api_keys: e
                 .api_key_entries
                 .iter()
-                .map(|k| k.api_key.clone())
+                .map(|k| redact_secret(&k.api_key))
+                .collect(),
+            key_ids: e
+                .api_key_entries
+                .iter()
+                .map(|k| crate::api::key_id(&k.api_key))
                 .collect(),
             models: e.models.clone(),
         })
@@ -491,8 +937,26 @@ pub async fn get_custom_providers() -> Result<CustomProvidersData, String> {
     })
 }
 
+/// Stored key a submitted custom provider key stands for: keys are listed redacted along
+/// with the id of the stored key, so an unchanged key comes back as the redacted form of the
+/// key with its id; anything else is a key typed in the UI
+fn restore_provider_key(key: &str, id: Option<&String>, stored_keys: &[String]) -> String {
+    id.filter(|id| !id.is_empty())
+        .and_then(|id| {
+            stored_keys
+                .iter()
+                .find(|stored| &crate::api::key_id(stored) == id)
+        })
+        .filter(|stored| redact_secret(stored) == key)
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
 #[tauri::command]
-pub async fn save_custom_providers(data: CustomProvidersData) -> Result<(), String> {
+pub async fn save_custom_providers(
+    data: CustomProvidersData,
+    etag: Option<String>,
+) -> Result<String, String> {
     // Reserved provider names that cannot be used
     const RESERVED_NAMES: &[&str] = &[
         "gemini",
@@ -548,49 +1012,62 @@ pub async fn save_custom_providers(data: CustomProvidersData) -> Result<(), Stri
         all_prefixes.push(prefix);
     }
 
-    let mut config = config::get_config().ok_or_else(|| "Config not initialized".to_string())?;
-
-    config.openai_compatibility = data
-        .openai_compatibility
-        .iter()
-        .map(|e| config::OpenAICompatEntry {
-            name: e.name.clone(),
-            prefix: e.prefix.clone(),
-            base_url: e.base_url.clone(),
-            api_key_entries: e
-                .api_keys
-                .iter()
-                .map(|k| config::ApiKeyEntry {
-                    api_key: k.clone(),
-                    prefix: None,
-                    base_url: None,
-                    proxy_url: None,
-                })
-                .collect(),
-            models: e.models.clone(),
-        })
-        .collect();
+    save_config(etag.as_deref(), |config| {
+        let stored_keys: Vec<String> = config
+            .openai_compatibility
+            .iter()
+            .flat_map(|e| &e.api_key_entries)
+            .chain(
+                config
+                    .claude_code_compatibility
+                    .iter()
+                    .flat_map(|e| &e.api_key_entries),
+            )
+            .map(|k| k.api_key.clone())
+            .collect();
 
-    config.claude_code_compatibility = data
-        .claude_code_compatibility
-        .iter()
-        .map(|e| config::ClaudeCodeCompatEntry {
-            name: e.name.clone(),
-            prefix: e.prefix.clone(),
-            base_url: e.base_url.clone(),
-            api_key_entries: e
-                .api_keys
-                .iter()
-                .map(|k| config::ApiKeyEntry {
-                    api_key: k.clone(),
-                    prefix: None,
-                    base_url: None,
-                    proxy_url: None,
-                })
-                .collect(),
-            models: e.models.clone(),
-        })
-        .collect();
+        config.openai_compatibility = data
+            .openai_compatibility
+            .iter()
+            .map(|e| config::OpenAICompatEntry {
+                name: e.name.clone(),
+                prefix: e.prefix.clone(),
+                base_url: e.base_url.clone(),
+                api_key_entries: e
+                    .api_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, k)| config::ApiKeyEntry {
+                        api_key: restore_provider_key(k, e.key_ids.get(i), &stored_keys),
+                        prefix: None,
+                        base_url: None,
+                        proxy_url: None,
+                    })
+                    .collect(),
+                models: e.models.clone(),
+            })
+            .collect();
 
-    config::update_config(config).map_err(|e| e.to_string())
+        config.claude_code_compatibility = data
+            .claude_code_compatibility
+            .iter()
+            .map(|e| config::ClaudeCodeCompatEntry {
+                name: e.name.clone(),
+                prefix: e.prefix.clone(),
+                base_url: e.base_url.clone(),
+                api_key_entries: e
+                    .api_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, k)| config::ApiKeyEntry {
+                        api_key: restore_provider_key(k, e.key_ids.get(i), &stored_keys),
+                        prefix: None,
+                        base_url: None,
+                        proxy_url: None,
+                    })
+                    .collect(),
+                models: e.models.clone(),
+            })
+            .collect();
+    })
 }
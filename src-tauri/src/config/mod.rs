@@ -4,6 +4,10 @@ use anyhow::Result;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
 
@@ -13,9 +17,15 @@ static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct AppConfig {
+    /// Bind address: IPv4 or IPv6 literal (brackets optional), "localhost", or empty for all IPv4 interfaces
     #[serde(default)]
     pub host: String,
 
+    /// Only accept IPv6 connections when bound to an IPv6 address
+    /// By default "::" also accepts IPv4 clients through v4-mapped addresses
+    #[serde(default)]
+    pub ipv6_only: bool,
+
     #[serde(default = "default_port")]
     pub port: u16,
 
@@ -69,6 +79,31 @@ pub struct AppConfig {
 
     #[serde(default)]
     pub model_routing: ModelRoutingConfig,
+
+    /// Cleanup applied to response text, keyed by provider ("antigravity", "kiro", ...)
+    #[serde(default)]
+    pub response_post_processors: BTreeMap<String, ResponsePostProcessConfig>,
+
+    /// Static headers added to upstream requests, keyed by provider ("codex", "claude", ...) or
+    /// custom provider name, e.g. `OpenAI-Organization` or gateway attribution headers
+    #[serde(default)]
+    pub upstream_headers: BTreeMap<String, BTreeMap<String, String>>,
+
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+
+    #[serde(default)]
+    pub response_translation: ResponseTranslationConfig,
+
+    #[serde(default)]
+    pub structured_output: StructuredOutputConfig,
+
+    #[serde(default)]
+    pub ssh_tunnel: SshTunnelConfig,
+
+    /// Teams sharing this instance, each with its own keys, accounts and logs
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
 }
 
 fn default_port() -> u16 {
@@ -150,6 +185,40 @@ pub struct ModelRoutingConfig {
     /// Higher priority providers are tried first
     #[serde(default = "default_provider_priorities")]
     pub provider_priorities: Vec<ProviderPriority>,
+
+    /// Model name aliases: alias -> canonical name
+    /// Models whose names map to the same canonical name are merged in aggregation mode
+    #[serde(default = "default_model_aliases")]
+    pub model_aliases: BTreeMap<String, String>,
+
+    /// Thinking variant fallback for Claude models in aggregation mode:
+    /// "requested-first" (default, try providers offering the requested variant before
+    /// substituting the "-thinking"/non-thinking counterpart), "priority" (follow provider
+    /// priority and substitute the counterpart where needed) or "off" (never substitute)
+    #[serde(default = "default_thinking_variant_fallback")]
+    pub thinking_variant_fallback: String,
+
+    /// Switch to a larger-context variant on the same provider when the estimated prompt
+    /// does not fit the requested model's context window
+    #[serde(default)]
+    pub context_upgrade: bool,
+
+    /// "Auto-economy" policy: small prompts without tools go to a cheap model
+    #[serde(default)]
+    pub economy: EconomyRoutingConfig,
+
+    /// Time windows with their own provider priorities, e.g. preferring a provider at night
+    #[serde(default)]
+    pub priority_schedule: Vec<PriorityWindow>,
+
+    /// Proxy API keys whose requests always race the top two providers and keep the first
+    /// successful answer; other clients opt in per request with the x-oneproxy-race header
+    #[serde(default)]
+    pub race_api_keys: Vec<String>,
+
+    /// Model that picks the best candidate of a fan-out request, empty to only return them all
+    #[serde(default)]
+    pub fan_out_judge_model: String,
 }
 
 impl Default for ModelRoutingConfig {
@@ -157,10 +226,64 @@ impl Default for ModelRoutingConfig {
         Self {
             mode: default_routing_mode(),
             provider_priorities: default_provider_priorities(),
+            model_aliases: default_model_aliases(),
+            thinking_variant_fallback: default_thinking_variant_fallback(),
+            context_upgrade: false,
+            economy: EconomyRoutingConfig::default(),
+            priority_schedule: Vec::new(),
+            race_api_keys: Vec::new(),
+            fan_out_judge_model: String::new(),
         }
     }
 }
 
+/// Provider priorities that apply while a cron-like schedule matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PriorityWindow {
+    /// "minute hour day-of-month month day-of-week", e.g. "* 22-23,0-5 * * mon-fri"
+    pub schedule: String,
+    /// Evaluate the schedule in UTC instead of local time
+    #[serde(default)]
+    pub utc: bool,
+    /// Priority per provider while the window is active
+    #[serde(default)]
+    pub priorities: BTreeMap<String, u32>,
+}
+
+/// Cheap-model routing for small requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EconomyRoutingConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// Model small requests are sent to, with or without a provider prefix
+    /// (e.g. "gemini/gemini-2.5-flash")
+    #[serde(default = "default_economy_model")]
+    pub model: String,
+    /// Estimated prompt tokens up to which a request counts as small
+    #[serde(default = "default_economy_max_prompt_tokens")]
+    pub max_prompt_tokens: i64,
+}
+
+impl Default for EconomyRoutingConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            model: default_economy_model(),
+            max_prompt_tokens: default_economy_max_prompt_tokens(),
+        }
+    }
+}
+
+fn default_economy_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+fn default_economy_max_prompt_tokens() -> i64 {
+    2_000
+}
+
 /// Provider priority configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -213,6 +336,27 @@ fn default_provider_priorities() -> Vec<ProviderPriority> {
     ]
 }
 
+fn default_model_aliases() -> BTreeMap<String, String> {
+    [
+        ("claude-sonnet-4.5", "claude-sonnet-4-5"),
+        ("claude-4.5-sonnet", "claude-sonnet-4-5"),
+        ("claude-4-5-sonnet", "claude-sonnet-4-5"),
+        ("claude-opus-4.5", "claude-opus-4-5"),
+        ("claude-4.5-opus", "claude-opus-4-5"),
+        ("claude-4-5-opus", "claude-opus-4-5"),
+        ("claude-haiku-4.5", "claude-haiku-4-5"),
+        ("claude-4.5-haiku", "claude-haiku-4-5"),
+        ("claude-4-5-haiku", "claude-haiku-4-5"),
+    ]
+    .into_iter()
+    .map(|(alias, canonical)| (alias.to_string(), canonical.to_string()))
+    .collect()
+}
+
+fn default_thinking_variant_fallback() -> String {
+    "requested-first".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct ApiKeyEntry {
@@ -251,6 +395,166 @@ pub struct ClaudeCodeCompatEntry {
     pub models: Vec<String>,
 }
 
+/// A tenant of a shared instance; requests with its keys only use its own accounts
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct TenantConfig {
+    /// Letters, digits, '-' and '_'; also the auth subdirectory (`<auth-dir>/tenants/<name>`)
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// Requests per UTC day across the tenant's keys, 0 for no limit
+    #[serde(default)]
+    pub daily_request_limit: u32,
+}
+
+/// SSH reverse tunnel that exposes the proxy on a remote host's port
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SshTunnelConfig {
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub user: String,
+    /// Private key file, empty to use the ssh agent / default keys
+    #[serde(default)]
+    pub key_path: String,
+    /// Port opened on the remote host
+    #[serde(default)]
+    pub remote_port: u16,
+    /// Remote address the port is bound to; "127.0.0.1" keeps it private to the remote host
+    #[serde(default = "default_remote_bind")]
+    pub remote_bind: String,
+}
+
+impl Default for SshTunnelConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            host: String::new(),
+            port: default_ssh_port(),
+            user: String::new(),
+            key_path: String::new(),
+            remote_port: 0,
+            remote_bind: default_remote_bind(),
+        }
+    }
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+fn default_remote_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+/// Backend of the `/v1/moderations` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModerationConfig {
+    /// "heuristic" (default, local keyword scoring) or "gemini" (Gemini safety ratings,
+    /// falls back to the heuristic when no Gemini account can answer)
+    #[serde(default = "default_moderation_backend")]
+    pub backend: String,
+    /// Gemini model used for safety scoring
+    #[serde(default = "default_moderation_model")]
+    pub model: String,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_moderation_backend(),
+            model: default_moderation_model(),
+        }
+    }
+}
+
+fn default_moderation_backend() -> String {
+    "heuristic".to_string()
+}
+
+fn default_moderation_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+/// Translation of final responses into the language of the client's API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResponseTranslationConfig {
+    /// Model the response text is translated with, routed like any chat request
+    #[serde(default = "default_translation_model")]
+    pub model: String,
+    /// Target language per proxy API key, e.g. "zh-CN" or "Japanese"; keys not listed get the
+    /// backend's output unchanged
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+}
+
+impl Default for ResponseTranslationConfig {
+    fn default() -> Self {
+        Self {
+            model: default_translation_model(),
+            languages: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_translation_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+/// What the proxy does with responses to requests carrying a JSON schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StructuredOutputMode {
+    /// Pass responses through unchecked
+    Off,
+    /// Check the output and report the result
+    #[default]
+    Validate,
+    /// Check the output and re-prompt once with the errors when it does not match
+    Repair,
+}
+
+/// Server-side checking of structured output (`response_format` with a JSON schema)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct StructuredOutputConfig {
+    /// Mode for API keys without an entry in `keys`
+    #[serde(default)]
+    pub mode: StructuredOutputMode,
+    /// Mode per proxy API key
+    #[serde(default)]
+    pub keys: BTreeMap<String, StructuredOutputMode>,
+}
+
+impl StructuredOutputConfig {
+    pub fn mode_for_key(&self, key: Option<&str>) -> StructuredOutputMode {
+        key.and_then(|key| self.keys.get(key))
+            .copied()
+            .unwrap_or(self.mode)
+    }
+}
+
+/// Response text cleanup for one provider
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResponsePostProcessConfig {
+    /// Remove chat-template control tokens such as "<|im_end|>" or "<end_of_turn>"
+    #[serde(default)]
+    pub trim_control_tokens: bool,
+    /// Regular expressions whose matches are removed from response text
+    #[serde(default)]
+    pub strip_patterns: Vec<String>,
+}
+
 pub async fn init_config(app: &AppHandle) -> Result<()> {
     let config_dir = app
         .path()
@@ -282,24 +586,226 @@ pub fn get_config() -> Option<AppConfig> {
     CONFIG.get().map(|c| c.read().clone())
 }
 
-pub fn update_config(config: AppConfig) -> Result<()> {
-    if let Some(lock) = CONFIG.get() {
-        *lock.write() = config.clone();
+/// Re-read config.yaml, e.g. after it was edited outside the app
+/// An invalid file is rejected and the current config stays in effect.
+pub fn reload_config() -> Result<AppConfig> {
+    let path = CONFIG_PATH
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Config not initialized"))?;
+    let content = std::fs::read_to_string(path)?;
+    let config: AppConfig = serde_yaml::from_str(&content)?;
+    validate_config(&config)?;
+
+    let lock = CONFIG
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Config not initialized"))?;
+    *lock.write() = config.clone();
+    tracing::info!("Config reloaded from {:?}", path);
+    Ok(config)
+}
+
+/// Parse the configured bind host into an IP address
+/// Accepts IPv4/IPv6 literals (IPv6 optionally in brackets) and "localhost"
+pub fn parse_bind_host(host: &str) -> Result<IpAddr> {
+    let trimmed = host.trim();
+    if trimmed.is_empty() {
+        return Ok(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    }
+    if trimmed.eq_ignore_ascii_case("localhost") {
+        return Ok(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    let unbracketed = trimmed
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(trimmed);
+    unbracketed.parse::<IpAddr>().map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid host '{}': expected an IPv4 or IPv6 address (e.g. 0.0.0.0, 127.0.0.1, ::, [::1])",
+            host
+        )
+    })
+}
+
+/// Resolve the socket address the API server binds to
+pub fn bind_addr(config: &AppConfig) -> Result<SocketAddr> {
+    Ok(SocketAddr::new(parse_bind_host(&config.host)?, config.port))
+}
+
+/// Validate a config before it is applied
+pub fn validate_config(config: &AppConfig) -> Result<()> {
+    parse_bind_host(&config.host)?;
+    for (provider, headers) in &config.upstream_headers {
+        for (name, value) in headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                || reqwest::header::HeaderValue::from_str(value).is_err()
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid upstream header '{}' for provider '{}'",
+                    name,
+                    provider
+                ));
+            }
+        }
+    }
+    for window in &config.model_routing.priority_schedule {
+        crate::api::priority_schedule::CronSchedule::parse(&window.schedule)
+            .map_err(|e| anyhow::anyhow!("Invalid priority schedule: {}", e))?;
     }
+    if !config.response_translation.languages.is_empty()
+        && config.response_translation.model.trim().is_empty()
+    {
+        return Err(anyhow::anyhow!(
+            "Response translation needs a model when languages are configured"
+        ));
+    }
+    for (field, value) in [
+        ("host", &config.ssh_tunnel.host),
+        ("user", &config.ssh_tunnel.user),
+    ] {
+        // ssh reads a destination starting with '-' as an option (e.g. -oProxyCommand=...)
+        let value = value.trim();
+        if value.starts_with('-') || value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(anyhow::anyhow!("Invalid SSH tunnel {} '{}'", field, value));
+        }
+    }
+    Ok(())
+}
 
+fn write_config_file(config: &AppConfig) -> Result<()> {
     if let Some(path) = CONFIG_PATH.get() {
-        let content = serde_yaml::to_string(&config)?;
+        let content = serde_yaml::to_string(config)?;
         std::fs::write(path, content)?;
     }
-
     Ok(())
 }
 
+/// Version tag of a config, used as the ETag for optimistic concurrency
+pub fn config_etag(config: &AppConfig) -> String {
+    let content = serde_json::to_vec(config).unwrap_or_default();
+    let digest = Sha256::digest(&content);
+    let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// Check an If-Match / If-None-Match header value against the current ETag ("*" matches any
+/// version)
+pub fn etag_matches(if_match: &str, etag: &str) -> bool {
+    if_match.split(',').map(|tag| tag.trim()).any(|tag| {
+        tag == "*" || tag.trim_start_matches("W/") == etag || format!("\"{}\"", tag) == etag
+    })
+}
+
+/// Apply a JSON merge patch (RFC 7396): objects merge recursively, null removes a key
+fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Some(patch_map) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    if let Some(target_map) = target.as_object_mut() {
+        for (key, value) in patch_map {
+            if value.is_null() {
+                target_map.remove(key);
+            } else {
+                apply_merge_patch(target_map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Error from a conditional config update
+#[derive(Debug)]
+pub enum ConfigPatchError {
+    /// The config changed since the client read it
+    VersionMismatch { current_etag: String },
+    /// The patched config is invalid or couldn't be saved
+    Invalid(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ConfigPatchError {
+    fn from(err: anyhow::Error) -> Self {
+        ConfigPatchError::Invalid(err)
+    }
+}
+
+impl std::fmt::Display for ConfigPatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigPatchError::VersionMismatch { .. } => {
+                write!(
+                    f,
+                    "The config was changed elsewhere, reload it and try again"
+                )
+            }
+            ConfigPatchError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Change the config and save it
+///
+/// If `if_match` is given it must match the current ETag, otherwise nothing is changed.
+/// The check and the update happen under the config write lock so concurrent editors
+/// (desktop forms, the management API) can't interleave or overwrite each other.
+pub fn modify_config(
+    if_match: Option<&str>,
+    change: impl FnOnce(&mut AppConfig) -> Result<()>,
+) -> std::result::Result<AppConfig, ConfigPatchError> {
+    let lock = CONFIG
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Config not initialized"))?;
+    let mut current = lock.write();
+
+    let etag = config_etag(&current);
+    if let Some(expected) = if_match {
+        if !etag_matches(expected, &etag) {
+            return Err(ConfigPatchError::VersionMismatch { current_etag: etag });
+        }
+    }
+
+    let mut updated = current.clone();
+    change(&mut updated)?;
+    validate_config(&updated)?;
+
+    write_config_file(&updated)?;
+    *current = updated.clone();
+    Ok(updated)
+}
+
+/// Apply a JSON merge patch to the config (keys in the same kebab-case form as the config file)
+///
+/// If `if_match` is given it must match the current ETag, otherwise nothing is changed.
+pub fn patch_config(
+    patch: &Value,
+    if_match: Option<&str>,
+) -> std::result::Result<AppConfig, ConfigPatchError> {
+    modify_config(if_match, |config| {
+        let mut value = serde_json::to_value(&*config)?;
+        apply_merge_patch(&mut value, patch);
+        *config = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Invalid config patch: {}", e))?;
+        Ok(())
+    })
+}
+
 pub fn get_config_path() -> Option<PathBuf> {
     CONFIG_PATH.get().cloned()
 }
 
+/// Auth dir of the tenant serving the current request, or the shared auth dir
 pub fn resolve_auth_dir() -> PathBuf {
+    let base = resolve_shared_auth_dir();
+    match crate::api::tenant::current() {
+        Some(tenant) => crate::api::tenant::auth_dir(&base, &tenant),
+        None => base,
+    }
+}
+
+/// The configured auth dir, regardless of tenant
+pub fn resolve_shared_auth_dir() -> PathBuf {
     let auth_dir = get_config()
         .map(|c| c.auth_dir)
         .unwrap_or_else(default_auth_dir);
@@ -329,3 +835,81 @@ pub fn resolve_auth_dir() -> PathBuf {
 
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn parses_ipv4_ipv6_and_bracketed_hosts() {
+        assert_eq!(
+            parse_bind_host("").unwrap(),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+        assert_eq!(
+            parse_bind_host("127.0.0.1").unwrap(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(
+            parse_bind_host("[::]").unwrap(),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        );
+        assert_eq!(
+            parse_bind_host("::1").unwrap(),
+            IpAddr::V6(Ipv6Addr::LOCALHOST)
+        );
+        assert!(parse_bind_host("example.com").is_err());
+        assert!(parse_bind_host("127.0.0.1:8417").is_err());
+    }
+
+    #[test]
+    fn rejects_ssh_tunnel_values_read_as_options() {
+        let mut config = AppConfig::default();
+        config.ssh_tunnel.host = "vps.example.com".to_string();
+        config.ssh_tunnel.user = "deploy".to_string();
+        assert!(validate_config(&config).is_ok());
+
+        config.ssh_tunnel.host = "-oProxyCommand=touch /tmp/pwned".to_string();
+        assert!(validate_config(&config).is_err());
+        config.ssh_tunnel.host = "vps.example.com".to_string();
+        config.ssh_tunnel.user = "deploy\n-oProxyCommand=id".to_string();
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn merge_patch_replaces_nested_fields_and_removes_nulls() {
+        let mut target = serde_json::json!({
+            "port": 8417,
+            "tls": {"enable": false, "cert": "a.pem"},
+            "api-keys": ["one"]
+        });
+        apply_merge_patch(
+            &mut target,
+            &serde_json::json!({"tls": {"enable": true, "cert": null}, "api-keys": ["two"]}),
+        );
+        assert_eq!(
+            target,
+            serde_json::json!({"port": 8417, "tls": {"enable": true}, "api-keys": ["two"]})
+        );
+    }
+
+    #[test]
+    fn etag_matching_accepts_wildcard_and_weak_tags() {
+        let etag = config_etag(&AppConfig::default());
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(etag_matches(&format!("W/{}", etag), &etag));
+        assert!(!etag_matches("\"0000\"", &etag));
+    }
+
+    #[test]
+    fn formats_ipv6_bind_addr_with_brackets() {
+        let config = AppConfig {
+            host: "::".to_string(),
+            port: 8417,
+            ..Default::default()
+        };
+        assert_eq!(bind_addr(&config).unwrap().to_string(), "[::]:8417");
+    }
+}
@@ -18,6 +18,28 @@ pub struct CachedQuota {
     pub last_updated: i64,
 }
 
+/// Quota of an account as fetched at one refresh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaSnapshot {
+    pub provider: String,
+    pub quota_data: String,
+    /// Unix seconds
+    pub timestamp: i64,
+}
+
+/// Snapshots kept per account; older ones are removed when a new one is saved
+const QUOTA_HISTORY_RETENTION_DAYS: i64 = 90;
+
+/// Upper bound on snapshots returned for a chart, longer ranges are thinned out evenly
+const QUOTA_HISTORY_MAX_POINTS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModels {
+    pub cache_key: String,
+    pub models_data: String,
+    pub last_updated: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestLogEntry {
     pub id: i64,
@@ -33,6 +55,50 @@ pub struct RequestLogEntry {
     pub duration_ms: i64,
     pub timestamp: i64,
     pub error_message: Option<String>,
+    /// Client-supplied tags from `X-OneProxy-Tag` or request `metadata`/`user`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Result of checking the output against the client's JSON schema, if it sent one
+    #[serde(default)]
+    pub schema_validation: Option<String>,
+    /// Tenant whose API key made the request, `None` for the shared keys
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+/// Requests and tokens of one client API key over a period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyUsage {
+    /// Name of the key, e.g. "api-key-1a2b3c4d", "temporary:ci" or "team-a:key-5e6f7a8b"
+    pub key_name: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Unix seconds of the start of the last hour the key was used in
+    pub last_used: i64,
+}
+
+/// Short-lived proxy API key, valid until a deadline and/or for a number of requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemporaryApiKey {
+    pub id: i64,
+    pub key: String,
+    pub label: String,
+    pub created_at: i64,
+    /// Unix seconds after which the key is rejected
+    pub expires_at: Option<i64>,
+    pub max_requests: Option<i64>,
+    pub used_requests: i64,
+}
+
+/// Result of presenting a temporary API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporaryKeyStatus {
+    Valid,
+    Expired,
+    Exhausted,
+    Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -42,6 +108,10 @@ pub struct LogFilter {
     pub protocol: Option<String>,
     pub search: Option<String>,
     pub account_id: Option<String>,
+    /// Only logs carrying this exact tag
+    pub tag: Option<String>,
+    /// Only logs of this tenant
+    pub tenant: Option<String>,
 }
 
 /// Initialize the SQLite database
@@ -62,6 +132,32 @@ pub fn init_db(app_data_dir: PathBuf) -> Result<()> {
         [],
     )?;
 
+    // Create quota_history table (one row per quota refresh)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            quota_data TEXT NOT NULL,
+            timestamp INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_history_account ON quota_history(account_id, timestamp)",
+        [],
+    )?;
+
+    // Create model_cache table (provider model lists persisted across restarts)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS model_cache (
+            cache_key TEXT PRIMARY KEY,
+            models_data TEXT NOT NULL,
+            last_updated INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
     // Create request_logs table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS request_logs (
@@ -84,6 +180,15 @@ pub fn init_db(app_data_dir: PathBuf) -> Result<()> {
 
     // Add provider column if it doesn't exist (migration for existing databases)
     let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN provider TEXT", []);
+    // Comma-separated request tags
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN tags TEXT", []);
+    // Structured output check result (valid, invalid, repaired, repair_failed)
+    let _ = conn.execute(
+        "ALTER TABLE request_logs ADD COLUMN schema_validation TEXT",
+        [],
+    );
+    // Tenant of the API key used (multi-tenant mode)
+    let _ = conn.execute("ALTER TABLE request_logs ADD COLUMN tenant TEXT", []);
 
     // Create index for faster queries
     conn.execute(
@@ -91,6 +196,34 @@ pub fn init_db(app_data_dir: PathBuf) -> Result<()> {
         [],
     )?;
 
+    // Create temporary_api_keys table (keys shared with a limited lifetime)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS temporary_api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key TEXT NOT NULL UNIQUE,
+            label TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER,
+            max_requests INTEGER,
+            used_requests INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Create key_usage table (hourly request and token counts per client API key name)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS key_usage (
+            key_name TEXT NOT NULL,
+            hour INTEGER NOT NULL,
+            requests INTEGER NOT NULL DEFAULT 0,
+            errors INTEGER NOT NULL DEFAULT 0,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (key_name, hour)
+        )",
+        [],
+    )?;
+
     tracing::info!("SQLite database initialized at {:?}", db_path);
 
     DB_CONNECTION
@@ -100,7 +233,7 @@ pub fn init_db(app_data_dir: PathBuf) -> Result<()> {
     Ok(())
 }
 
-/// Save quota data to cache
+/// Save quota data to cache, and as a snapshot in the quota history
 pub fn save_quota_cache(account_id: &str, provider: &str, quota_data: &str) -> Result<()> {
     let conn = DB_CONNECTION
         .get()
@@ -114,6 +247,15 @@ pub fn save_quota_cache(account_id: &str, provider: &str, quota_data: &str) -> R
          VALUES (?1, ?2, ?3, ?4)",
         rusqlite::params![account_id, provider, quota_data, now],
     )?;
+    conn.execute(
+        "INSERT INTO quota_history (account_id, provider, quota_data, timestamp)
+         VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![account_id, provider, quota_data, now],
+    )?;
+    conn.execute(
+        "DELETE FROM quota_history WHERE account_id = ?1 AND timestamp < ?2",
+        rusqlite::params![account_id, now - QUOTA_HISTORY_RETENTION_DAYS * 24 * 3600],
+    )?;
 
     tracing::debug!("Saved quota cache for account: {}", account_id);
     Ok(())
@@ -188,41 +330,179 @@ pub fn delete_quota_cache(account_id: &str) -> Result<()> {
         "DELETE FROM quota_cache WHERE account_id = ?1",
         [account_id],
     )?;
+    conn.execute(
+        "DELETE FROM quota_history WHERE account_id = ?1",
+        [account_id],
+    )?;
+
+    tracing::debug!(
+        "Deleted quota cache and history for account: {}",
+        account_id
+    );
+    Ok(())
+}
+
+/// Quota snapshots of an account taken since `since` (unix seconds), oldest first
+pub fn get_quota_history(account_id: &str, since: i64) -> Result<Vec<QuotaSnapshot>> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+
+    let mut stmt = conn.prepare(
+        "SELECT provider, quota_data, timestamp FROM quota_history
+         WHERE account_id = ?1 AND timestamp >= ?2 ORDER BY timestamp ASC",
+    )?;
+
+    let rows = stmt.query_map(rusqlite::params![account_id, since], |row| {
+        Ok(QuotaSnapshot {
+            provider: row.get(0)?,
+            quota_data: row.get(1)?,
+            timestamp: row.get(2)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+
+    // Keep every n-th snapshot and always the latest one
+    if result.len() > QUOTA_HISTORY_MAX_POINTS {
+        let step = result.len().div_ceil(QUOTA_HISTORY_MAX_POINTS);
+        let last = result.len() - 1;
+        result = result
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| index % step == 0 || *index == last)
+            .map(|(_, snapshot)| snapshot)
+            .collect();
+    }
+
+    Ok(result)
+}
+
+// ============ Model Cache Functions ============
+
+/// Save a provider model list to cache
+pub fn save_model_cache(cache_key: &str, models_data: &str) -> Result<()> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let now = chrono::Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO model_cache (cache_key, models_data, last_updated)
+         VALUES (?1, ?2, ?3)",
+        rusqlite::params![cache_key, models_data, now],
+    )?;
+
+    tracing::debug!("Saved model cache: {}", cache_key);
+    Ok(())
+}
+
+/// Get a cached provider model list
+pub fn get_model_cache(cache_key: &str) -> Result<Option<CachedModels>> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+
+    let result = conn.query_row(
+        "SELECT cache_key, models_data, last_updated FROM model_cache WHERE cache_key = ?1",
+        [cache_key],
+        |row| {
+            Ok(CachedModels {
+                cache_key: row.get(0)?,
+                models_data: row.get(1)?,
+                last_updated: row.get(2)?,
+            })
+        },
+    );
+
+    match result {
+        Ok(models) => Ok(Some(models)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Delete a cached provider model list
+pub fn delete_model_cache(cache_key: &str) -> Result<()> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    conn.execute("DELETE FROM model_cache WHERE cache_key = ?1", [cache_key])?;
 
-    tracing::debug!("Deleted quota cache for account: {}", account_id);
+    tracing::debug!("Deleted model cache: {}", cache_key);
     Ok(())
 }
 
 // ============ Request Logs Functions ============
 
+/// A request log entry to save; the timestamp is taken when it is saved
+#[derive(Debug, Clone, Default)]
+pub struct NewRequestLog<'a> {
+    pub status: i32,
+    pub method: &'a str,
+    pub model: Option<&'a str>,
+    pub protocol: Option<&'a str>,
+    pub provider: Option<&'a str>,
+    pub account_id: Option<&'a str>,
+    pub path: &'a str,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub duration_ms: i64,
+    pub error_message: Option<&'a str>,
+    pub tags: &'a [String],
+    pub schema_validation: Option<&'a str>,
+    pub tenant: Option<&'a str>,
+}
+
 /// Save a request log entry
-pub fn save_request_log(
-    status: i32,
-    method: &str,
-    model: Option<&str>,
-    protocol: Option<&str>,
-    provider: Option<&str>,
-    account_id: Option<&str>,
-    path: &str,
-    input_tokens: i32,
-    output_tokens: i32,
-    duration_ms: i64,
-    error_message: Option<&str>,
-) -> Result<()> {
+pub fn save_request_log(log: &NewRequestLog) -> Result<()> {
     let conn = DB_CONNECTION
         .get()
         .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
 
     let conn = conn.lock();
     let now = chrono::Utc::now().timestamp_millis();
+    let tags = (!log.tags.is_empty()).then(|| log.tags.join(","));
 
     conn.execute(
-        "INSERT INTO request_logs (status, method, model, protocol, provider, account_id, path, input_tokens, output_tokens, duration_ms, timestamp, error_message)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-        rusqlite::params![status, method, model, protocol, provider, account_id, path, input_tokens, output_tokens, duration_ms, now, error_message],
+        "INSERT INTO request_logs (status, method, model, protocol, provider, account_id, path, input_tokens, output_tokens, duration_ms, timestamp, error_message, tags, schema_validation, tenant)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        rusqlite::params![
+            log.status,
+            log.method,
+            log.model,
+            log.protocol,
+            log.provider,
+            log.account_id,
+            log.path,
+            log.input_tokens,
+            log.output_tokens,
+            log.duration_ms,
+            now,
+            log.error_message,
+            tags,
+            log.schema_validation,
+            log.tenant
+        ],
     )?;
 
-    tracing::debug!("Saved request log: {} {} -> {}", method, path, status);
+    tracing::debug!(
+        "Saved request log: {} {} -> {}",
+        log.method,
+        log.path,
+        log.status
+    );
     Ok(())
 }
 
@@ -240,7 +520,7 @@ pub fn get_request_logs(
     let filter = filter.unwrap_or_default();
 
     let mut sql = String::from(
-        "SELECT id, status, method, model, protocol, provider, account_id, path, input_tokens, output_tokens, duration_ms, timestamp, error_message
+        "SELECT id, status, method, model, protocol, provider, account_id, path, input_tokens, output_tokens, duration_ms, timestamp, error_message, tags, schema_validation, tenant
          FROM request_logs WHERE 1=1"
     );
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -259,6 +539,16 @@ pub fn get_request_logs(
         params.push(Box::new(account_id.clone()));
     }
 
+    if let Some(ref tag) = filter.tag {
+        sql.push_str(" AND instr(',' || tags || ',', ?) > 0");
+        params.push(Box::new(format!(",{},", tag)));
+    }
+
+    if let Some(ref tenant) = filter.tenant {
+        sql.push_str(" AND tenant = ?");
+        params.push(Box::new(tenant.clone()));
+    }
+
     if let Some(ref search) = filter.search {
         sql.push_str(" AND (path LIKE ? OR model LIKE ?)");
         let search_pattern = format!("%{}%", search);
@@ -289,6 +579,12 @@ pub fn get_request_logs(
             duration_ms: row.get(10)?,
             timestamp: row.get(11)?,
             error_message: row.get(12)?,
+            tags: row
+                .get::<_, Option<String>>(13)?
+                .map(|tags| tags.split(',').map(|tag| tag.to_string()).collect())
+                .unwrap_or_default(),
+            schema_validation: row.get(14)?,
+            tenant: row.get(15)?,
         })
     })?;
 
@@ -326,6 +622,16 @@ pub fn get_request_logs_count(filter: Option<LogFilter>) -> Result<i64> {
         params.push(Box::new(account_id.clone()));
     }
 
+    if let Some(ref tag) = filter.tag {
+        sql.push_str(" AND instr(',' || tags || ',', ?) > 0");
+        params.push(Box::new(format!(",{},", tag)));
+    }
+
+    if let Some(ref tenant) = filter.tenant {
+        sql.push_str(" AND tenant = ?");
+        params.push(Box::new(tenant.clone()));
+    }
+
     if let Some(ref search) = filter.search {
         sql.push_str(" AND (path LIKE ? OR model LIKE ?)");
         let search_pattern = format!("%{}%", search);
@@ -339,6 +645,21 @@ pub fn get_request_logs_count(filter: Option<LogFilter>) -> Result<i64> {
     Ok(count)
 }
 
+/// Number of requests a tenant made since `since` (Unix ms)
+pub fn count_tenant_requests(tenant: &str, since: i64) -> Result<i64> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM request_logs WHERE tenant = ?1 AND timestamp >= ?2",
+        rusqlite::params![tenant, since],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
 /// Clear all request logs
 pub fn clear_request_logs() -> Result<()> {
     let conn = DB_CONNECTION
@@ -351,3 +672,216 @@ pub fn clear_request_logs() -> Result<()> {
     tracing::info!("Cleared all request logs");
     Ok(())
 }
+
+// ============ Temporary API Key Functions ============
+
+/// Store a new temporary API key
+pub fn create_temporary_api_key(
+    key: &str,
+    label: &str,
+    expires_at: Option<i64>,
+    max_requests: Option<i64>,
+) -> Result<()> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let now = chrono::Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO temporary_api_keys (key, label, created_at, expires_at, max_requests)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![key, label, now, expires_at, max_requests],
+    )?;
+
+    tracing::info!("Created temporary API key '{}'", label);
+    Ok(())
+}
+
+/// Delete temporary keys that expired or used up their requests
+fn purge_temporary_api_keys(conn: &Connection, now: i64) -> Result<usize> {
+    let purged = conn.execute(
+        "DELETE FROM temporary_api_keys
+         WHERE (expires_at IS NOT NULL AND expires_at <= ?1)
+            OR (max_requests IS NOT NULL AND used_requests >= max_requests)",
+        [now],
+    )?;
+    if purged > 0 {
+        tracing::info!("Removed {} expired temporary API key(s)", purged);
+    }
+    Ok(purged)
+}
+
+/// List the temporary keys that are still valid
+pub fn list_temporary_api_keys() -> Result<Vec<TemporaryApiKey>> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    purge_temporary_api_keys(&conn, chrono::Utc::now().timestamp())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, key, label, created_at, expires_at, max_requests, used_requests
+         FROM temporary_api_keys ORDER BY created_at DESC",
+    )?;
+    let keys = stmt
+        .query_map([], |row| {
+            Ok(TemporaryApiKey {
+                id: row.get(0)?,
+                key: row.get(1)?,
+                label: row.get(2)?,
+                created_at: row.get(3)?,
+                expires_at: row.get(4)?,
+                max_requests: row.get(5)?,
+                used_requests: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(keys)
+}
+
+/// Whether any temporary key is still valid
+pub fn has_temporary_api_keys() -> Result<bool> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let now = chrono::Utc::now().timestamp();
+
+    let valid: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM temporary_api_keys
+         WHERE (expires_at IS NULL OR expires_at > ?1)
+           AND (max_requests IS NULL OR used_requests < max_requests)",
+        [now],
+        |row| row.get(0),
+    )?;
+    Ok(valid > 0)
+}
+
+/// Check a presented temporary key, counting the request against its budget if `count` is set,
+/// and return its status and label (`None` for unknown keys)
+/// Keys found expired or used up are removed.
+pub fn use_temporary_api_key(
+    key: &str,
+    count: bool,
+) -> Result<(TemporaryKeyStatus, Option<String>)> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let now = chrono::Utc::now().timestamp();
+
+    let result = conn.query_row(
+        "SELECT expires_at, max_requests, used_requests, label FROM temporary_api_keys WHERE key = ?1",
+        [key],
+        |row| {
+            Ok((
+                row.get::<_, Option<i64>>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        },
+    );
+    let (expires_at, max_requests, used_requests, label) = match result {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Ok((TemporaryKeyStatus::Unknown, None))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let status = if expires_at.is_some_and(|at| at <= now) {
+        TemporaryKeyStatus::Expired
+    } else if max_requests.is_some_and(|max| used_requests >= max) {
+        TemporaryKeyStatus::Exhausted
+    } else {
+        TemporaryKeyStatus::Valid
+    };
+    if status != TemporaryKeyStatus::Valid {
+        purge_temporary_api_keys(&conn, now)?;
+    } else if count {
+        conn.execute(
+            "UPDATE temporary_api_keys SET used_requests = used_requests + 1 WHERE key = ?1",
+            [key],
+        )?;
+    }
+    Ok((status, Some(label)))
+}
+
+/// Revoke a temporary key
+pub fn delete_temporary_api_key(id: i64) -> Result<()> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    conn.execute("DELETE FROM temporary_api_keys WHERE id = ?1", [id])?;
+
+    tracing::info!("Revoked temporary API key {}", id);
+    Ok(())
+}
+
+/// Count a request made with the API key named `key_name` in the current hour
+pub fn record_key_usage(
+    key_name: &str,
+    input_tokens: i64,
+    output_tokens: i64,
+    error: bool,
+) -> Result<()> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let now = chrono::Utc::now().timestamp();
+    let hour = now - now.rem_euclid(3600);
+    conn.execute(
+        "INSERT INTO key_usage (key_name, hour, requests, errors, input_tokens, output_tokens)
+         VALUES (?1, ?2, 1, ?3, ?4, ?5)
+         ON CONFLICT(key_name, hour) DO UPDATE SET
+            requests = requests + 1,
+            errors = errors + excluded.errors,
+            input_tokens = input_tokens + excluded.input_tokens,
+            output_tokens = output_tokens + excluded.output_tokens",
+        rusqlite::params![key_name, hour, error as i64, input_tokens, output_tokens],
+    )?;
+    Ok(())
+}
+
+/// Usage per API key name since `since` (unix seconds), busiest key first
+pub fn get_key_usage(since: i64) -> Result<Vec<KeyUsage>> {
+    let conn = DB_CONNECTION
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+
+    let conn = conn.lock();
+    let mut stmt = conn.prepare(
+        "SELECT key_name, SUM(requests), SUM(errors), SUM(input_tokens), SUM(output_tokens), MAX(hour)
+         FROM key_usage WHERE hour >= ?1
+         GROUP BY key_name
+         ORDER BY SUM(requests) DESC",
+    )?;
+    // Include the hour `since` falls in
+    let since = since - since.rem_euclid(3600);
+    let rows = stmt.query_map([since], |row| {
+        Ok(KeyUsage {
+            key_name: row.get(0)?,
+            requests: row.get(1)?,
+            errors: row.get(2)?,
+            input_tokens: row.get(3)?,
+            output_tokens: row.get(4)?,
+            last_used: row.get(5)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
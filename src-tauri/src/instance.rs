@@ -0,0 +1,209 @@
+// Single-instance enforcement
+// The running instance listens on a loopback port recorded in a lock file. A second launch
+// asks it to show its window and exits instead of starting a competing API server. On Windows
+// the channel is a named pipe per user SID instead, so no port is opened for it.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+#[cfg(not(target_os = "windows"))]
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const LOCK_FILE_NAME: &str = "instance.lock";
+const FOCUS_REQUEST: &str = "oneproxy:focus";
+const FOCUS_ACK: &str = "oneproxy:ok";
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceLock {
+    pid: u32,
+    port: u16,
+}
+
+fn lock_file_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("com.nick.oneproxy")
+        .join(LOCK_FILE_NAME)
+}
+
+/// Ask an already running instance to show its window
+/// Returns true if another instance acknowledged the request
+#[cfg(not(target_os = "windows"))]
+pub fn notify_running_instance() -> bool {
+    let Ok(content) = std::fs::read_to_string(lock_file_path()) else {
+        return false;
+    };
+    let Ok(lock) = serde_json::from_str::<InstanceLock>(&content) else {
+        return false;
+    };
+    if lock.pid == std::process::id() {
+        return false;
+    }
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, lock.port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, PING_TIMEOUT) else {
+        // Stale lock file left by a crashed instance
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(PING_TIMEOUT));
+    if writeln!(stream, "{}", FOCUS_REQUEST).is_err() {
+        return false;
+    }
+
+    // Only trust the answer of another one-proxy instance, not whatever reuses the port
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == FOCUS_ACK
+}
+
+/// Listen for focus requests from later launches and record this instance in the lock file
+#[cfg(not(target_os = "windows"))]
+pub fn start_listener(app: AppHandle) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let lock = InstanceLock {
+        pid: std::process::id(),
+        port: listener.local_addr()?.port(),
+    };
+
+    let path = lock_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string(&lock)?)?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(PING_TIMEOUT));
+            let mut request = String::new();
+            let mut reader = BufReader::new(&stream);
+            if reader.read_line(&mut request).is_err() || request.trim() != FOCUS_REQUEST {
+                continue;
+            }
+
+            tracing::info!("Another instance was launched, showing the existing window");
+            crate::show_main_window(&app);
+            let _ = writeln!(&stream, "{}", FOCUS_ACK);
+        }
+    });
+
+    tracing::info!("Single-instance listener on port {}", lock.port);
+    Ok(())
+}
+
+/// Control pipe of the current user; pipe names are machine-wide and user names are not
+/// unique (a local and a domain account can share one), so it is named after the user's SID
+#[cfg(target_os = "windows")]
+fn pipe_name() -> String {
+    let user = crate::win32::current_user_sid()
+        .unwrap_or_else(|| std::env::var("USERNAME").unwrap_or_default());
+    format!(r"\\.\pipe\com.nick.oneproxy-{}", user)
+}
+
+/// Ask an already running instance to show its window
+/// Returns true if another instance acknowledged the request
+#[cfg(target_os = "windows")]
+pub fn notify_running_instance() -> bool {
+    // All pipe instances are taken while the running instance serves another launch
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    let mut pipe = None;
+    for _ in 0..5 {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe_name())
+        {
+            Ok(file) => {
+                pipe = Some(file);
+                break;
+            }
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                std::thread::sleep(PING_TIMEOUT / 5)
+            }
+            Err(_) => return false,
+        }
+    }
+    let Some(mut pipe) = pipe else {
+        return false;
+    };
+    if writeln!(pipe, "{}", FOCUS_REQUEST).is_err() {
+        return false;
+    }
+
+    // Reads on a synchronous pipe handle can't time out, so wait for the reply on a thread
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reply = String::new();
+        let read = BufReader::new(pipe).read_line(&mut reply);
+        let _ = sender.send(read.map(|_| reply));
+    });
+    matches!(
+        receiver.recv_timeout(PING_TIMEOUT),
+        Ok(Ok(reply)) if reply.trim() == FOCUS_ACK
+    )
+}
+
+/// Listen for focus requests from later launches on the control pipe
+#[cfg(target_os = "windows")]
+pub fn start_listener(app: AppHandle) -> std::io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name();
+    let runtime = tauri::async_runtime::handle();
+    let _guard = runtime.inner().enter();
+    // Fails when another instance already owns the pipe
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&name)?;
+
+    let pipe = name.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let connected = server.connect().await;
+            // Open the next instance first so a later launch always finds the pipe
+            let next = match ServerOptions::new().create(&pipe) {
+                Ok(next) => next,
+                Err(e) => {
+                    tracing::warn!("Single-instance pipe closed: {}", e);
+                    return;
+                }
+            };
+            let client = std::mem::replace(&mut server, next);
+            if connected.is_err() {
+                continue;
+            }
+
+            let mut reader = tokio::io::BufReader::new(client);
+            let mut request = String::new();
+            let read = tokio::time::timeout(PING_TIMEOUT, reader.read_line(&mut request)).await;
+            if !matches!(read, Ok(Ok(_))) || request.trim() != FOCUS_REQUEST {
+                continue;
+            }
+            tracing::info!("Another instance was launched, showing the existing window");
+            crate::show_main_window(&app);
+            let _ = reader
+                .get_mut()
+                .write_all(format!("{}\n", FOCUS_ACK).as_bytes())
+                .await;
+        }
+    });
+
+    tracing::info!("Single-instance listener on pipe {}", name);
+    Ok(())
+}
+
+/// Remove the lock file if it belongs to this instance
+pub fn release() {
+    let path = lock_file_path();
+    let owned = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<InstanceLock>(&content).ok())
+        .map(|lock| lock.pid == std::process::id())
+        .unwrap_or(false);
+    if owned {
+        let _ = std::fs::remove_file(path);
+    }
+}
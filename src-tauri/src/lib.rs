@@ -5,10 +5,16 @@ pub mod auth;
 pub mod commands;
 pub mod config;
 pub mod db;
+pub mod instance;
 pub mod proxy;
+pub mod reload;
+pub mod tailscale;
+pub mod tunnel;
+#[cfg(target_os = "windows")]
+pub mod win32;
 
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Manager,
 };
@@ -25,12 +31,23 @@ pub fn run() {
         )
         .init();
 
+    // Hand over to an already running instance instead of fighting over the port
+    if instance::notify_running_instance() {
+        tracing::info!("One Proxy is already running, focused the existing window");
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
             let app_handle = app.handle().clone();
+            api::events::init(&app_handle);
+
+            if let Err(e) = instance::start_listener(app_handle.clone()) {
+                tracing::warn!("Failed to start single-instance listener: {}", e);
+            }
 
             // Initialize config and start server on startup
             let config_handle = app_handle.clone();
@@ -40,6 +57,10 @@ pub fn run() {
                 if let Err(e) = config::init_config(&config_handle).await {
                     tracing::error!("Failed to initialize config: {}", e);
                 }
+                sync_routing_mode_menu(&config_handle);
+
+                // Bring auth files to the current schema before anything reads them
+                auth::schema::migrate_configured_auth_dirs();
 
                 // Initialize SQLite database
                 if let Ok(data_dir) = config_handle.path().app_data_dir() {
@@ -48,6 +69,13 @@ pub fn run() {
                     }
                 }
 
+                // Token refreshes that could not be written are retried in the background
+                tauri::async_runtime::spawn(auth::token_store::retry_pending());
+
+                // `kill -HUP` re-reads config.yaml and the auth files
+                #[cfg(unix)]
+                tauri::async_runtime::spawn(reload::watch_sighup());
+
                 // Then start the API server
                 tracing::info!("Starting API server...");
                 if let Err(e) = crate::api::start_server(server_handle).await {
@@ -61,19 +89,20 @@ pub fn run() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
-            commands::get_config,
-            commands::save_config,
             commands::get_auth_accounts,
             commands::get_auth_summary,
             commands::start_server,
             commands::stop_server,
             commands::get_server_status,
+            commands::get_warmup_status,
             commands::start_oauth_login,
             commands::start_codex_device_login,
             commands::finish_codex_device_login,
             commands::save_api_key_account,
+            commands::validate_account,
             commands::delete_account,
             commands::set_account_enabled,
+            commands::bulk_update_accounts,
             commands::set_gemini_project_id,
             commands::fetch_antigravity_quota,
             commands::fetch_codex_quota,
@@ -84,9 +113,36 @@ pub fn run() {
             commands::export_accounts_to_file,
             commands::import_accounts_from_file,
             commands::get_cached_quotas,
+            commands::get_quota_history,
+            commands::get_key_usage,
+            commands::invalidate_kiro_model_cache,
             commands::get_codex_routing_statuses,
+            commands::get_provider_status,
+            commands::get_request_stats,
+            commands::run_smoke_test,
             commands::get_settings,
             commands::save_settings,
+            commands::get_config_etag,
+            commands::get_network_settings,
+            commands::save_network_settings,
+            commands::get_tailnet_info,
+            commands::bind_to_tailnet,
+            commands::get_ssh_tunnel_settings,
+            commands::save_ssh_tunnel_settings,
+            commands::get_tunnel_status,
+            commands::generate_api_key,
+            commands::reveal_api_key,
+            commands::clear_api_keys,
+            commands::create_temporary_api_key,
+            commands::list_temporary_api_keys,
+            commands::revoke_temporary_api_key,
+            commands::get_general_settings,
+            commands::save_general_settings,
+            commands::get_routing_settings,
+            commands::save_routing_settings,
+            commands::set_routing_mode,
+            commands::get_routing_paused,
+            commands::set_routing_paused,
             commands::get_request_logs,
             commands::get_request_logs_count,
             commands::clear_request_logs,
@@ -95,8 +151,47 @@ pub fn run() {
             commands::get_custom_providers,
             commands::save_custom_providers,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                instance::release();
+            }
+        });
+}
+
+/// Show, restore and focus the main window
+pub(crate) fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Tray check items of the routing mode submenu
+struct RoutingModeMenu {
+    provider: CheckMenuItem<tauri::Wry>,
+    model: CheckMenuItem<tauri::Wry>,
+}
+
+/// Reflect the configured routing mode in the tray submenu
+pub(crate) fn sync_routing_mode_menu(app: &tauri::AppHandle) {
+    if let Some(menu) = app.try_state::<RoutingModeMenu>() {
+        let aggregation = crate::api::model_router::is_aggregation_mode();
+        let _ = menu.provider.set_checked(!aggregation);
+        let _ = menu.model.set_checked(aggregation);
+    }
+}
+
+/// Tray check item of the routing pause switch
+struct RoutingPausedMenu(CheckMenuItem<tauri::Wry>);
+
+/// Reflect the routing pause switch in the tray
+pub(crate) fn sync_routing_paused_menu(app: &tauri::AppHandle) {
+    if let Some(menu) = app.try_state::<RoutingPausedMenu>() {
+        let _ = menu.0.set_checked(crate::api::is_routing_paused());
+    }
 }
 
 fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
@@ -107,6 +202,38 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     let start_item = MenuItem::with_id(app, "start", "Start Server", true, None::<&str>)?;
     let stop_item = MenuItem::with_id(app, "stop", "Stop Server", true, None::<&str>)?;
     let separator2 = MenuItem::with_id(app, "sep2", "─────────", false, None::<&str>)?;
+    let aggregation = crate::api::model_router::is_aggregation_mode();
+    let routing_provider_item = CheckMenuItem::with_id(
+        app,
+        "routing-provider",
+        "Provider Prefix Mode",
+        true,
+        !aggregation,
+        None::<&str>,
+    )?;
+    let routing_model_item = CheckMenuItem::with_id(
+        app,
+        "routing-model",
+        "Model Aggregation Mode",
+        true,
+        aggregation,
+        None::<&str>,
+    )?;
+    let routing_menu = Submenu::with_items(
+        app,
+        "Routing Mode",
+        true,
+        &[&routing_provider_item, &routing_model_item],
+    )?;
+    let pause_item = CheckMenuItem::with_id(
+        app,
+        "pause-routing",
+        "Pause Routing",
+        true,
+        crate::api::is_routing_paused(),
+        None::<&str>,
+    )?;
+    let separator3 = MenuItem::with_id(app, "sep3", "─────────", false, None::<&str>)?;
     let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
     // Create menu
@@ -119,21 +246,25 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
             &start_item,
             &stop_item,
             &separator2,
+            &routing_menu,
+            &pause_item,
+            &separator3,
             &quit_item,
         ],
     )?;
 
+    app.manage(RoutingModeMenu {
+        provider: routing_provider_item,
+        model: routing_model_item,
+    });
+    app.manage(RoutingPausedMenu(pause_item));
+
     // Build tray icon
     let _tray = TrayIconBuilder::new()
         .menu(&menu)
         .tooltip("CLI Proxy API")
         .on_menu_event(|app, event| match event.id.as_ref() {
-            "show" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
+            "show" => show_main_window(app),
             "hide" => {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.hide();
@@ -154,6 +285,20 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
                     }
                 });
             }
+            "routing-provider" | "routing-model" => {
+                let mode = if event.id.as_ref() == "routing-model" {
+                    "model"
+                } else {
+                    "provider"
+                };
+                if let Err(e) = commands::apply_routing_mode(app, mode) {
+                    tracing::error!("Failed to switch routing mode: {}", e);
+                    sync_routing_mode_menu(app);
+                }
+            }
+            "pause-routing" => {
+                commands::apply_routing_paused(app, !crate::api::is_routing_paused());
+            }
             "quit" => {
                 app.exit(0);
             }
@@ -167,11 +312,7 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
                 ..
             } = event
             {
-                let app = tray.app_handle();
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
+                show_main_window(tray.app_handle());
             }
         })
         .build(app)?;
@@ -0,0 +1,102 @@
+// Config reload without restart
+// `kill -HUP <pid>` and `POST /management/reload` re-read config.yaml and bring new auth
+// files to the current schema, for headless and containerized setups where config.yaml is
+// bind-mounted and edited outside the app. Auth files are read from disk per request, so
+// added or replaced accounts are picked up right away. A changed SSH tunnel is reconnected.
+// Settings of the listening socket (host, port, ipv6-only) only apply after the API server is
+// restarted; the result says so.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::api::events;
+use crate::config::AppConfig;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadResult {
+    /// Auth files migrated to the current schema during the reload
+    pub migrated_auth_files: usize,
+    /// Host, port or ipv6-only changed while the server is running
+    pub restart_required: bool,
+    /// The SSH tunnel settings changed and the tunnel was reconnected
+    pub tunnel_restarted: bool,
+}
+
+/// Whether the running server has to be restarted to apply a new config
+fn needs_restart(previous: &AppConfig, current: &AppConfig) -> bool {
+    previous.host != current.host
+        || previous.port != current.port
+        || previous.ipv6_only != current.ipv6_only
+}
+
+/// Re-read config.yaml and the auth dirs; the current config stays if the file is invalid
+pub fn reload() -> Result<ReloadResult> {
+    let previous = crate::config::get_config();
+    let config = crate::config::reload_config()?;
+
+    let running = crate::api::is_server_running();
+    let result = ReloadResult {
+        migrated_auth_files: crate::auth::schema::migrate_configured_auth_dirs(),
+        restart_required: running
+            && previous
+                .as_ref()
+                .is_some_and(|previous| needs_restart(previous, &config)),
+        tunnel_restarted: running
+            && previous
+                .as_ref()
+                .is_some_and(|previous| previous.ssh_tunnel != config.ssh_tunnel),
+    };
+    if result.tunnel_restarted {
+        // The server keeps listening on the port it was started with until it is restarted
+        let port = previous
+            .as_ref()
+            .map_or(config.port, |previous| previous.port);
+        crate::tunnel::start(port);
+    }
+    // Model lists depend on routing settings and on the accounts present
+    crate::api::models_cache::invalidate();
+    if let Some(app) = events::app_handle() {
+        crate::sync_routing_mode_menu(app);
+    }
+    if result.restart_required {
+        tracing::warn!("Host or port changed, restart the API server to apply them");
+    }
+    events::emit(events::CONFIG_RELOADED, result.clone());
+    Ok(result)
+}
+
+/// Reload on every SIGHUP
+#[cfg(unix)]
+pub async fn watch_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        tracing::info!("SIGHUP received, reloading config");
+        if let Err(e) = reload() {
+            tracing::error!("Config reload failed, keeping the current config: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_socket_settings_need_a_restart() {
+        let previous = AppConfig::default();
+        let mut current = previous.clone();
+        current.api_keys = vec!["sk-new".to_string()];
+        assert!(!needs_restart(&previous, &current));
+
+        current.port = previous.port + 1;
+        assert!(needs_restart(&previous, &current));
+    }
+}
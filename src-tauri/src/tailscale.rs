@@ -0,0 +1,142 @@
+// Tailscale / Headscale awareness
+// Finds this machine's tailnet address so the proxy can bind to it instead of 0.0.0.0, and
+// reports the MagicDNS name other devices on the tailnet should use to reach it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::{IpAddr, UdpSocket};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Tailscale's in-tailnet DNS/service address, routed through the tailnet interface
+const QUAD100: &str = "100.100.100.100:53";
+const CLI_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How this machine is reachable on the tailnet (payload of `get_tailnet_info`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TailnetInfo {
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>,
+    /// MagicDNS name without the trailing dot, e.g. "desktop.tail1234.ts.net"
+    pub dns_name: Option<String>,
+    /// Base URL clients should use, filled in from the proxy port and TLS setting
+    pub url: Option<String>,
+    /// Whether the configured host is a tailnet address
+    pub bound: bool,
+}
+
+/// Tailscale addresses live in 100.64.0.0/10 and fd7a:115c:a1e0::/48
+pub fn is_tailnet_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            octets[0] == 100 && (octets[1] & 0xc0) == 64
+        }
+        IpAddr::V6(v6) => {
+            let segments = v6.segments();
+            segments[0] == 0xfd7a && segments[1] == 0x115c && segments[2] == 0xa1e0
+        }
+    }
+}
+
+/// Parse `tailscale status --json`
+fn parse_status(status: &Value) -> Option<TailnetInfo> {
+    let this = status.get("Self")?;
+    let mut info = TailnetInfo::default();
+    for ip in this
+        .get("TailscaleIPs")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .filter_map(|s| s.parse::<IpAddr>().ok())
+    {
+        match ip {
+            IpAddr::V4(_) if info.ipv4.is_none() => info.ipv4 = Some(ip.to_string()),
+            IpAddr::V6(_) if info.ipv6.is_none() => info.ipv6 = Some(ip.to_string()),
+            _ => {}
+        }
+    }
+    info.dns_name = this
+        .get("DNSName")
+        .and_then(|v| v.as_str())
+        .map(|name| name.trim_end_matches('.').to_string())
+        .filter(|name| !name.is_empty());
+    (info.ipv4.is_some() || info.ipv6.is_some()).then_some(info)
+}
+
+async fn detect_with_cli() -> Option<TailnetInfo> {
+    let mut command = Command::new("tailscale");
+    command.args(["status", "--json"]).kill_on_drop(true);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(crate::win32::CREATE_NO_WINDOW);
+    let output = tokio::time::timeout(CLI_TIMEOUT, command.output())
+        .await
+        .ok()?
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_status(&serde_json::from_slice(&output.stdout).ok()?)
+}
+
+/// Source address the OS would use to reach 100.100.100.100; connecting a UDP socket sends
+/// nothing, it only selects the route. Used when the tailscale CLI is not installed
+/// (e.g. Headscale clients or sandboxed installs).
+fn detect_with_route() -> Option<TailnetInfo> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(QUAD100).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    is_tailnet_ip(&ip).then(|| TailnetInfo {
+        ipv4: Some(ip.to_string()),
+        ..TailnetInfo::default()
+    })
+}
+
+/// Detect the tailnet address of this machine, `None` when it is not on a tailnet
+pub async fn detect() -> Option<TailnetInfo> {
+    let mut info = match detect_with_cli().await {
+        Some(info) => info,
+        None => detect_with_route()?,
+    };
+
+    let config = crate::config::get_config().unwrap_or_default();
+    let scheme = if config.tls.enable { "https" } else { "http" };
+    let host = info.dns_name.clone().or_else(|| info.ipv4.clone());
+    info.url = host.map(|host| format!("{}://{}:{}", scheme, host, config.port));
+    info.bound = [&info.ipv4, &info.ipv6]
+        .into_iter()
+        .flatten()
+        .any(|ip| config.host.trim_matches(['[', ']']) == ip);
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn recognizes_tailnet_ranges() {
+        assert!(is_tailnet_ip(&"100.101.102.103".parse().unwrap()));
+        assert!(is_tailnet_ip(&"100.127.255.1".parse().unwrap()));
+        assert!(!is_tailnet_ip(&"100.128.0.1".parse().unwrap()));
+        assert!(!is_tailnet_ip(&"192.168.1.5".parse().unwrap()));
+        assert!(is_tailnet_ip(&"fd7a:115c:a1e0::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn parses_tailscale_status() {
+        let status = json!({
+            "Self": {
+                "DNSName": "desktop.tail1234.ts.net.",
+                "TailscaleIPs": ["100.64.0.7", "fd7a:115c:a1e0::7"]
+            }
+        });
+        let info = parse_status(&status).unwrap();
+        assert_eq!(info.ipv4.as_deref(), Some("100.64.0.7"));
+        assert_eq!(info.ipv6.as_deref(), Some("fd7a:115c:a1e0::7"));
+        assert_eq!(info.dns_name.as_deref(), Some("desktop.tail1234.ts.net"));
+        assert!(parse_status(&json!({"Self": {"TailscaleIPs": []}})).is_none());
+    }
+}
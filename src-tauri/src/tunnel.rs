@@ -0,0 +1,282 @@
+// SSH reverse tunnel for remote access
+// Runs the system `ssh` client with `-R` so a VPS can reach the proxy on this machine through
+// its own loopback port, without binding the proxy to 0.0.0.0. The tunnel follows the API
+// server lifecycle and is restarted with backoff whenever ssh exits.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+use crate::config::SshTunnelConfig;
+
+/// ssh gives no signal once forwarding is up; with ExitOnForwardFailure it exits quickly on
+/// failure, so a process still alive after this long is considered connected
+const CONNECT_GRACE: Duration = Duration::from_secs(5);
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelState {
+    Disabled,
+    Connecting,
+    Connected,
+    Reconnecting,
+}
+
+/// Tunnel status shown in the UI (payload of `get_tunnel_status`)
+#[derive(Debug, Clone, Serialize)]
+pub struct TunnelStatus {
+    pub state: TunnelState,
+    /// "user@host:remote-port", empty when disabled
+    pub remote: String,
+    pub local_port: u16,
+    /// Unix millis of the last state change
+    pub since: Option<i64>,
+    pub last_error: Option<String>,
+    pub restarts: u32,
+}
+
+impl Default for TunnelStatus {
+    fn default() -> Self {
+        Self {
+            state: TunnelState::Disabled,
+            remote: String::new(),
+            local_port: 0,
+            since: None,
+            last_error: None,
+            restarts: 0,
+        }
+    }
+}
+
+struct ActiveTunnel {
+    cancel: Arc<Notify>,
+}
+
+static STATUS: Lazy<Mutex<TunnelStatus>> = Lazy::new(|| Mutex::new(TunnelStatus::default()));
+static ACTIVE: Lazy<Mutex<Option<ActiveTunnel>>> = Lazy::new(|| Mutex::new(None));
+
+fn set_state(state: TunnelState, error: Option<String>) {
+    let mut status = STATUS.lock();
+    if status.state != state {
+        status.state = state;
+        status.since = Some(chrono::Utc::now().timestamp_millis());
+    }
+    if error.is_some() {
+        status.last_error = error;
+    }
+}
+
+/// Arguments for the ssh client, forwarding `remote-bind:remote-port` to the local proxy
+pub fn ssh_args(config: &SshTunnelConfig, local_port: u16) -> Vec<String> {
+    let mut args = vec![
+        "-N".to_string(),
+        "-T".to_string(),
+        "-o".to_string(),
+        "ExitOnForwardFailure=yes".to_string(),
+        "-o".to_string(),
+        "ServerAliveInterval=30".to_string(),
+        "-o".to_string(),
+        "ServerAliveCountMax=3".to_string(),
+        // Never prompt, the tunnel runs without a terminal
+        "-o".to_string(),
+        "BatchMode=yes".to_string(),
+        "-o".to_string(),
+        "StrictHostKeyChecking=accept-new".to_string(),
+        "-p".to_string(),
+        config.port.to_string(),
+    ];
+    let key_path = config.key_path.trim();
+    if !key_path.is_empty() {
+        args.push("-i".to_string());
+        args.push(expand_home(key_path));
+    }
+    args.push("-R".to_string());
+    args.push(format!(
+        "{}:{}:127.0.0.1:{}",
+        config.remote_bind, config.remote_port, local_port
+    ));
+    // Nothing after "--" is read as an option
+    args.push("--".to_string());
+    args.push(destination(config));
+    args
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn destination(config: &SshTunnelConfig) -> String {
+    if config.user.trim().is_empty() {
+        config.host.trim().to_string()
+    } else {
+        format!("{}@{}", config.user.trim(), config.host.trim())
+    }
+}
+
+/// Start the tunnel if it is enabled, replacing a running one
+pub fn start(local_port: u16) {
+    stop();
+    let config = crate::config::get_config().unwrap_or_default().ssh_tunnel;
+    if !config.enable {
+        return;
+    }
+    if config.host.trim().is_empty() || config.remote_port == 0 {
+        set_state(
+            TunnelState::Disabled,
+            Some("SSH tunnel needs a host and a remote port".to_string()),
+        );
+        return;
+    }
+
+    {
+        let mut status = STATUS.lock();
+        *status = TunnelStatus {
+            remote: format!("{}:{}", destination(&config), config.remote_port),
+            local_port,
+            ..TunnelStatus::default()
+        };
+    }
+    let cancel = Arc::new(Notify::new());
+    ACTIVE.lock().replace(ActiveTunnel {
+        cancel: cancel.clone(),
+    });
+    tokio::spawn(supervise(config, local_port, cancel));
+}
+
+/// Stop the tunnel if one is running
+pub fn stop() {
+    if let Some(active) = ACTIVE.lock().take() {
+        active.cancel.notify_one();
+    }
+    set_state(TunnelState::Disabled, None);
+}
+
+pub fn status() -> TunnelStatus {
+    STATUS.lock().clone()
+}
+
+async fn supervise(config: SshTunnelConfig, local_port: u16, cancel: Arc<Notify>) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        set_state(TunnelState::Connecting, None);
+        let mut command = Command::new("ssh");
+        command
+            .args(ssh_args(&config, local_port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(target_os = "windows")]
+        command.creation_flags(crate::win32::CREATE_NO_WINDOW);
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                tracing::error!("[Tunnel] Failed to start ssh: {}", e);
+                set_state(
+                    TunnelState::Disabled,
+                    Some(format!("Failed to start ssh: {}", e)),
+                );
+                return;
+            }
+        };
+
+        // Keep the last stderr line as the error to show
+        let last_line = Arc::new(Mutex::new(None::<String>));
+        if let Some(stderr) = child.stderr.take() {
+            let last_line = last_line.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::debug!("[Tunnel] ssh: {}", line);
+                    if !line.trim().is_empty() {
+                        last_line.lock().replace(line);
+                    }
+                }
+            });
+        }
+
+        let started = tokio::time::Instant::now();
+        let exited_early = tokio::select! {
+            _ = cancel.notified() => {
+                let _ = child.kill().await;
+                tracing::info!("[Tunnel] SSH tunnel stopped");
+                return;
+            }
+            exit = child.wait() => Some(exit),
+            _ = tokio::time::sleep(CONNECT_GRACE) => None,
+        };
+        let exit = match exited_early {
+            Some(exit) => exit,
+            None => {
+                tracing::info!("[Tunnel] SSH tunnel to {} is up", destination(&config));
+                set_state(TunnelState::Connected, None);
+                backoff = MIN_BACKOFF;
+                tokio::select! {
+                    _ = cancel.notified() => {
+                        let _ = child.kill().await;
+                        tracing::info!("[Tunnel] SSH tunnel stopped");
+                        return;
+                    }
+                    exit = child.wait() => exit,
+                }
+            }
+        };
+
+        let reason = last_line.lock().take().unwrap_or_else(|| match exit {
+            Ok(status) => format!("ssh exited with {}", status),
+            Err(e) => format!("ssh failed: {}", e),
+        });
+        tracing::warn!(
+            "[Tunnel] SSH tunnel closed after {:?}: {}",
+            started.elapsed(),
+            reason
+        );
+        set_state(TunnelState::Reconnecting, Some(reason));
+        STATUS.lock().restarts += 1;
+
+        tokio::select! {
+            _ = cancel.notified() => return,
+            _ = tokio::time::sleep(backoff) => {}
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_reverse_forward_arguments() {
+        let config = SshTunnelConfig {
+            enable: true,
+            host: "vps.example.com".to_string(),
+            port: 2222,
+            user: "deploy".to_string(),
+            key_path: String::new(),
+            remote_port: 18417,
+            remote_bind: "127.0.0.1".to_string(),
+        };
+        let args = ssh_args(&config, 8417);
+        assert!(args.windows(2).any(|w| w == ["-p", "2222"]));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["-R", "127.0.0.1:18417:127.0.0.1:8417"]));
+        assert!(!args.contains(&"-i".to_string()));
+        assert_eq!(
+            args[args.len() - 2..],
+            ["--".to_string(), "deploy@vps.example.com".to_string()]
+        );
+    }
+}
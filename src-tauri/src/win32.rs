@@ -0,0 +1,141 @@
+// Windows-native helpers
+// Port inspection uses the IP Helper API instead of piping `netstat` into `taskkill` through
+// `cmd`, which flashed a console window and depended on the output format of those tools.
+// Child processes the proxy starts (ssh, tailscale, the browser launcher) are created without
+// a console window. The single-instance pipe is named after the SID of the current user.
+
+use windows_sys::core::PWSTR;
+use windows_sys::Win32::Foundation::{
+    CloseHandle, LocalFree, ERROR_INSUFFICIENT_BUFFER, HANDLE, NO_ERROR,
+};
+use windows_sys::Win32::NetworkManagement::IpHelper::{
+    GetExtendedTcpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID, TCP_TABLE_OWNER_PID_LISTENER,
+};
+use windows_sys::Win32::Networking::WinSock::{AF_INET, AF_INET6};
+use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+use windows_sys::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+use windows_sys::Win32::System::Threading::{
+    GetCurrentProcess, OpenProcess, OpenProcessToken, TerminateProcess, PROCESS_TERMINATE,
+};
+
+pub use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+
+/// Raw listener table of one address family, `None` when the API fails
+fn listener_table(family: u16) -> Option<Vec<u8>> {
+    let mut size = 0u32;
+    let mut buffer: Vec<u8> = Vec::new();
+    // The table can grow between the size query and the read, so retry a few times
+    for _ in 0..4 {
+        let result = unsafe {
+            GetExtendedTcpTable(
+                buffer.as_mut_ptr().cast(),
+                &mut size,
+                0,
+                family as u32,
+                TCP_TABLE_OWNER_PID_LISTENER,
+                0,
+            )
+        };
+        match result {
+            NO_ERROR => return Some(buffer),
+            ERROR_INSUFFICIENT_BUFFER => buffer = vec![0; size as usize],
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Rows of a listener table: a `u32` entry count followed by the rows
+fn rows<T: Copy>(table: &[u8]) -> Vec<T> {
+    let Some(count) = table.get(..4) else {
+        return Vec::new();
+    };
+    let count = u32::from_ne_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    // Rows only hold u32 fields and byte arrays, so they start right after the count
+    let row_size = std::mem::size_of::<T>();
+    (0..count)
+        .map_while(|index| {
+            let start = 4 + index * row_size;
+            let bytes = table.get(start..start + row_size)?;
+            Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<T>()) })
+        })
+        .collect()
+}
+
+/// Local port of a table row, stored in network byte order in the low 16 bits
+fn row_port(port: u32) -> u16 {
+    u16::from_be(port as u16)
+}
+
+/// Processes listening on a TCP port, IPv4 and IPv6
+pub fn listening_pids(port: u16) -> Vec<u32> {
+    let mut pids: Vec<u32> = Vec::new();
+    if let Some(table) = listener_table(AF_INET) {
+        pids.extend(
+            rows::<MIB_TCPROW_OWNER_PID>(&table)
+                .into_iter()
+                .filter(|row| row_port(row.dwLocalPort) == port)
+                .map(|row| row.dwOwningPid),
+        );
+    }
+    if let Some(table) = listener_table(AF_INET6) {
+        pids.extend(
+            rows::<MIB_TCP6ROW_OWNER_PID>(&table)
+                .into_iter()
+                .filter(|row| row_port(row.dwLocalPort) == port)
+                .map(|row| row.dwOwningPid),
+        );
+    }
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+/// Terminate a process; returns false when it could not be opened or stopped
+pub fn kill_process(pid: u32) -> bool {
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let killed = TerminateProcess(handle, 1) != 0;
+        CloseHandle(handle);
+        killed
+    }
+}
+
+/// SID of the user running this process in its string form (`S-1-5-21-...`)
+pub fn current_user_sid() -> Option<String> {
+    unsafe {
+        let mut token: HANDLE = std::ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return None;
+        }
+        let mut size = 0u32;
+        GetTokenInformation(token, TokenUser, std::ptr::null_mut(), 0, &mut size);
+        // u64 words keep the TOKEN_USER at the start of the buffer aligned
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        let read = size > 0
+            && GetTokenInformation(
+                token,
+                TokenUser,
+                buffer.as_mut_ptr().cast(),
+                size,
+                &mut size,
+            ) != 0;
+        CloseHandle(token);
+        if !read {
+            return None;
+        }
+
+        let user = &*buffer.as_ptr().cast::<TOKEN_USER>();
+        let mut sid: PWSTR = std::ptr::null_mut();
+        if ConvertSidToStringSidW(user.User.Sid, &mut sid) == 0 {
+            return None;
+        }
+        let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        let text = String::from_utf16_lossy(std::slice::from_raw_parts(sid, len));
+        LocalFree(sid.cast());
+        Some(text)
+    }
+}
@@ -12,6 +12,7 @@ export interface ServerStatus {
   running: boolean;
   port: number;
   host: string;
+  auth_dir_warning?: string | null;
 }
 
 export interface AppConfig {
@@ -20,6 +20,7 @@ interface AuthAccount {
   email: string | null;
   enabled: boolean;
   prefix: string | null;
+  tags?: string[];
 }
 
 interface ModelQuota {
@@ -16,19 +16,16 @@ import {
   Save,
   RefreshCw,
   Trash2,
+  AlertTriangle,
+  PauseCircle,
 } from "lucide-react";
 
-interface AppConfig {
+interface NetworkSettings {
   host: string;
   port: number;
-  debug: boolean;
-  "auth-dir": string;
-  "api-keys": string[];
-  "proxy-url": string;
-  "request-retry": number;
-  routing: {
-    strategy: string;
-  };
+  ipv6_only: boolean;
+  // Redacted; use reveal_api_key for the full key
+  api_keys: string[];
 }
 
 interface ClaudeCodeConfig {
@@ -37,13 +34,56 @@ interface ClaudeCodeConfig {
   haiku_model: string;
 }
 
+interface TailnetInfo {
+  ipv4: string | null;
+  ipv6: string | null;
+  dns_name: string | null;
+  url: string | null;
+  bound: boolean;
+}
+
+interface ProviderStatus {
+  provider: string;
+  state: "healthy" | "degraded" | "down";
+  requests: number;
+  errors: number;
+  error_rate: number;
+  since: number | null;
+}
+
+interface TemporaryApiKey {
+  id: number;
+  // Redacted; the full key is only shown right after creation
+  key: string;
+  label: string;
+  created_at: number;
+  expires_at: number | null;
+  remaining_seconds: number | null;
+  remaining_requests: number | null;
+}
+
+function formatRemaining(key: TemporaryApiKey): string {
+  const parts: string[] = [];
+  if (key.remaining_seconds !== null) {
+    const hours = Math.floor(key.remaining_seconds / 3600);
+    const minutes = Math.floor((key.remaining_seconds % 3600) / 60);
+    parts.push(hours > 0 ? `${hours} 小时 ${minutes} 分` : `${minutes} 分钟`);
+  }
+  if (key.remaining_requests !== null) {
+    parts.push(`${key.remaining_requests} 次请求`);
+  }
+  return `剩余 ${parts.join(" / ")}`;
+}
+
 interface DashboardProps {
   serverStatus: ServerStatus;
   onStatusChange: () => void;
 }
 
 export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
-  const [config, setConfig] = useState<AppConfig | null>(null);
+  const [config, setConfig] = useState<NetworkSettings | null>(null);
+  // Version of the config the form was loaded from, so a save can't overwrite other edits
+  const [configEtag, setConfigEtag] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
   const [selectedProtocol, setSelectedProtocol] = useState<
     "openai" | "anthropic" | "gemini"
@@ -66,11 +106,19 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
   });
   const [claudeConfigSaving, setClaudeConfigSaving] = useState(false);
   const [claudeConfigSaved, setClaudeConfigSaved] = useState(false);
+  const [providerStatus, setProviderStatus] = useState<ProviderStatus[]>([]);
+  const [tailnet, setTailnet] = useState<TailnetInfo | null>(null);
+  const [routingPaused, setRoutingPaused] = useState(false);
+  const [tempKeys, setTempKeys] = useState<TemporaryApiKey[]>([]);
+  const [tempKeyLabel, setTempKeyLabel] = useState("");
+  const [tempKeyHours, setTempKeyHours] = useState("24");
+  const [tempKeyRequests, setTempKeyRequests] = useState("");
+  const [createdTempKey, setCreatedTempKey] = useState<string | null>(null);
 
   const baseUrl = `http://127.0.0.1:${config?.port ?? 8417}`;
-  const apiKey = config?.["api-keys"]?.[0] ?? "your-api-key";
-
-  const curlCommands = {
+  // The listed keys are redacted, the snippet shows a placeholder and the full key is only
+  // filled in when it is copied
+  const buildCurlCommands = (apiKey: string) => ({
     openai: `curl -X POST ${baseUrl}/v1/chat/completions \\
   -H "Content-Type: application/json" \\
   -H "Authorization: Bearer ${apiKey}" \\
@@ -93,11 +141,14 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
   -d '{
     "contents": [{"role": "user", "parts": [{"text": "Hello"}]}]
   }'`,
-  };
+  });
+  const curlCommands = buildCurlCommands("your-api-key");
 
   useEffect(() => {
     fetchConfig();
     fetchClaudeCodeConfig();
+    fetchTailnet();
+    fetchTempKeys();
   }, []);
 
   useEffect(() => {
@@ -106,6 +157,30 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
     }
   }, [serverStatus.running, config]);
 
+  useEffect(() => {
+    if (!serverStatus.running) {
+      setProviderStatus([]);
+      return;
+    }
+    const fetchProviderStatus = async () => {
+      try {
+        setProviderStatus(
+          await invoke<ProviderStatus[]>("get_provider_status"),
+        );
+        setRoutingPaused(await invoke<boolean>("get_routing_paused"));
+      } catch (error) {
+        console.error("Failed to fetch provider status:", error);
+      }
+    };
+    fetchProviderStatus();
+    const interval = setInterval(fetchProviderStatus, 15000);
+    return () => clearInterval(interval);
+  }, [serverStatus.running]);
+
+  const unhealthyProviders = providerStatus.filter(
+    (status) => status.state !== "healthy",
+  );
+
   // Auto-reset loading state when server status changes
   useEffect(() => {
     setServerLoading(null);
@@ -118,8 +193,9 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
       const headers: Record<string, string> = {
         "Content-Type": "application/json",
       };
-      if (config?.["api-keys"]?.[0]) {
-        headers["Authorization"] = `Bearer ${config["api-keys"][0]}`;
+      const key = await invoke<string | null>("reveal_api_key");
+      if (key) {
+        headers["Authorization"] = `Bearer ${key}`;
       }
       const response = await fetch(url, { headers });
       if (response.ok) {
@@ -148,11 +224,15 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
     }
   }
 
+  async function loadNetworkSettings() {
+    setConfigEtag(await invoke<string>("get_config_etag"));
+    setConfig(await invoke<NetworkSettings>("get_network_settings"));
+  }
+
   async function fetchConfig() {
     try {
       setLoading(true);
-      const result = await invoke<AppConfig>("get_config");
-      setConfig(result);
+      await loadNetworkSettings();
     } catch (error) {
       console.error("Failed to fetch config:", error);
     } finally {
@@ -188,9 +268,14 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
     }
   }
 
-  async function saveConfig(newConfig: AppConfig) {
+  async function saveConfig(newConfig: NetworkSettings) {
     try {
-      await invoke("save_config", { config: newConfig });
+      setConfigEtag(
+        await invoke<string>("save_network_settings", {
+          settings: newConfig,
+          etag: configEtag,
+        }),
+      );
       setConfig(newConfig);
     } catch (error) {
       console.error("Failed to save config:", error);
@@ -223,24 +308,84 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
   }
 
   async function handleGenerateApiKey() {
-    if (!config) return;
-    const newKey =
-      "sk-" +
-      Array.from(crypto.getRandomValues(new Uint8Array(24)))
-        .map((b) => b.toString(16).padStart(2, "0"))
-        .join("");
-    const newConfig = { ...config, "api-keys": [newKey] };
-    await saveConfig(newConfig);
+    try {
+      await invoke<string>("generate_api_key");
+      await loadNetworkSettings();
+    } catch (error) {
+      console.error("Failed to generate API key:", error);
+      alert(`保存失败: ${error}`);
+    }
   }
 
   async function handleCopyApiKey() {
-    if (!config || config["api-keys"].length === 0) return;
-    await navigator.clipboard.writeText(config["api-keys"][0]);
+    const key = await invoke<string | null>("reveal_api_key");
+    if (!key) return;
+    await navigator.clipboard.writeText(key);
   }
 
   async function handleClearApiKey() {
+    try {
+      await invoke("clear_api_keys");
+      await loadNetworkSettings();
+    } catch (error) {
+      console.error("Failed to clear API keys:", error);
+      alert(`保存失败: ${error}`);
+    }
+  }
+
+  async function fetchTempKeys() {
+    try {
+      setTempKeys(await invoke<TemporaryApiKey[]>("list_temporary_api_keys"));
+    } catch (error) {
+      console.error("Failed to load temporary API keys:", error);
+    }
+  }
+
+  async function handleCreateTempKey() {
+    try {
+      const key = await invoke<string>("create_temporary_api_key", {
+        label: tempKeyLabel,
+        ttlHours: parseInt(tempKeyHours) || null,
+        maxRequests: parseInt(tempKeyRequests) || null,
+      });
+      setCreatedTempKey(key);
+      setTempKeyLabel("");
+      await fetchTempKeys();
+    } catch (error) {
+      console.error("Failed to create temporary API key:", error);
+      alert(`创建临时密钥失败: ${error}`);
+    }
+  }
+
+  async function handleRevokeTempKey(id: number) {
+    try {
+      await invoke("revoke_temporary_api_key", { id });
+      await fetchTempKeys();
+    } catch (error) {
+      console.error("Failed to revoke temporary API key:", error);
+      alert(`撤销临时密钥失败: ${error}`);
+    }
+  }
+
+  async function fetchTailnet() {
+    try {
+      setTailnet(await invoke<TailnetInfo | null>("get_tailnet_info"));
+    } catch (error) {
+      console.error("Failed to detect Tailscale:", error);
+    }
+  }
+
+  async function handleBindTailnet() {
     if (!config) return;
-    await saveConfig({ ...config, "api-keys": [] });
+    try {
+      const host = await invoke<string>("bind_to_tailnet");
+      setConfig({ ...config, host });
+      setConfigEtag(await invoke<string>("get_config_etag"));
+      await fetchTailnet();
+    } catch (error) {
+      console.error("Failed to bind to tailnet:", error);
+      alert(`保存失败: ${error}`);
+    }
   }
 
   function isLanAccess() {
@@ -253,6 +398,16 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
     await saveConfig({ ...config, host: newHost });
   }
 
+  async function handleToggleRoutingPaused() {
+    try {
+      await invoke("set_routing_paused", { paused: !routingPaused });
+      setRoutingPaused(await invoke<boolean>("get_routing_paused"));
+    } catch (error) {
+      console.error("Failed to toggle routing pause:", error);
+      alert(`切换暂停状态失败: ${error}`);
+    }
+  }
+
   async function handlePortChange(port: number) {
     if (!config) return;
     await saveConfig({ ...config, port });
@@ -285,6 +440,41 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
         </div>
       </div>
 
+      {/* Routing paused banner */}
+      {serverStatus.running && routingPaused && (
+        <div className="flex items-center justify-between gap-3 p-4 rounded-2xl border border-rose-200 dark:border-rose-800/50 bg-rose-50 dark:bg-rose-900/20 text-rose-800 dark:text-rose-200">
+          <div className="flex items-center gap-3 text-sm">
+            <PauseCircle className="w-5 h-5 shrink-0" />
+            <span>
+              路由已暂停：所有 API 请求都会返回 503，不会消耗任何额度
+            </span>
+          </div>
+          <button
+            onClick={handleToggleRoutingPaused}
+            className="px-4 py-1.5 rounded-xl text-sm font-bold bg-rose-500 hover:bg-rose-600 text-white transition-colors"
+          >
+            恢复路由
+          </button>
+        </div>
+      )}
+
+      {/* Provider outage banner */}
+      {unhealthyProviders.length > 0 && (
+        <div className="flex items-start gap-3 p-4 rounded-2xl border border-amber-200 dark:border-amber-800/50 bg-amber-50 dark:bg-amber-900/20 text-amber-800 dark:text-amber-200">
+          <AlertTriangle className="w-5 h-5 mt-0.5 shrink-0" />
+          <div className="text-sm space-y-1">
+            {unhealthyProviders.map((status) => (
+              <p key={status.provider}>
+                <span className="font-semibold">{status.provider}</span>{" "}
+                {status.state === "down" ? "服务中断" : "服务降级"}：最近{" "}
+                {status.requests} 次请求中 {status.errors} 次失败（
+                {Math.round(status.error_rate * 100)}%），聚合路由将优先使用其他供应商
+              </p>
+            ))}
+          </div>
+        </div>
+      )}
+
       <div className="grid grid-cols-1 xl:grid-cols-2 gap-8">
         {/* Service Configuration Options */}
         <div className="space-y-8 flex flex-col">
@@ -321,32 +511,44 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
                 </div>
               </div>
 
-              <button
-                onClick={
-                  serverStatus.running ? handleStopServer : handleStartServer
-                }
-                disabled={serverLoading !== null}
-                className={`relative overflow-hidden px-6 py-2.5 rounded-xl font-bold flex items-center gap-2 transition-all duration-300 transform active:scale-95 disabled:scale-100 shadow-lg ${
-                  serverStatus.running
-                    ? "bg-rose-500 hover:bg-rose-600 text-white shadow-rose-500/25"
-                    : "bg-gray-900 dark:bg-white text-white dark:text-gray-900 hover:bg-gray-800 dark:hover:bg-gray-100"
-                } ${serverLoading ? "opacity-70 cursor-not-allowed" : "hover:-translate-y-0.5"}`}
-              >
-                {serverLoading ? (
-                  <Loader2 className="w-5 h-5 animate-spin" />
-                ) : serverStatus.running ? (
-                  <Square className="w-4 h-4 fill-current" />
-                ) : (
-                  <Play className="w-4 h-4 fill-current" />
+              <div className="flex items-center gap-3">
+                {serverStatus.running && !routingPaused && (
+                  <button
+                    onClick={handleToggleRoutingPaused}
+                    title="立即拒绝所有 API 请求，管理界面保持可用"
+                    className="px-4 py-2.5 rounded-xl font-bold flex items-center gap-2 border border-rose-300 dark:border-rose-700 text-rose-600 dark:text-rose-400 hover:bg-rose-50 dark:hover:bg-rose-900/20 transition-colors"
+                  >
+                    <PauseCircle className="w-4 h-4" />
+                    暂停路由
+                  </button>
                 )}
-                {serverLoading === "starting"
-                  ? "启动中"
-                  : serverLoading === "stopping"
-                    ? "停止中"
-                    : serverStatus.running
-                      ? "停止"
-                      : "启动"}
-              </button>
+                <button
+                  onClick={
+                    serverStatus.running ? handleStopServer : handleStartServer
+                  }
+                  disabled={serverLoading !== null}
+                  className={`relative overflow-hidden px-6 py-2.5 rounded-xl font-bold flex items-center gap-2 transition-all duration-300 transform active:scale-95 disabled:scale-100 shadow-lg ${
+                    serverStatus.running
+                      ? "bg-rose-500 hover:bg-rose-600 text-white shadow-rose-500/25"
+                      : "bg-gray-900 dark:bg-white text-white dark:text-gray-900 hover:bg-gray-800 dark:hover:bg-gray-100"
+                  } ${serverLoading ? "opacity-70 cursor-not-allowed" : "hover:-translate-y-0.5"}`}
+                >
+                  {serverLoading ? (
+                    <Loader2 className="w-5 h-5 animate-spin" />
+                  ) : serverStatus.running ? (
+                    <Square className="w-4 h-4 fill-current" />
+                  ) : (
+                    <Play className="w-4 h-4 fill-current" />
+                  )}
+                  {serverLoading === "starting"
+                    ? "启动中"
+                    : serverLoading === "stopping"
+                      ? "停止中"
+                      : serverStatus.running
+                        ? "停止"
+                        : "启动"}
+                </button>
+              </div>
             </div>
 
             <div className="grid grid-cols-1 sm:grid-cols-2 gap-5 relative z-10">
@@ -376,7 +578,11 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
                 </label>
                 <div className="flex items-center justify-between px-3 py-2.5 bg-white dark:bg-gray-900 border border-gray-300/60 dark:border-gray-600/60 rounded-xl shadow-sm">
                   <span className="text-xs font-medium text-gray-600 dark:text-gray-300 truncate mr-2">
-                    {isLanAccess() ? "0.0.0.0 (对外)" : "127.0.0.1 (本地)"}
+                    {isLanAccess()
+                      ? "0.0.0.0 (对外)"
+                      : tailnet?.bound
+                        ? `${config?.host} (Tailnet)`
+                        : "127.0.0.1 (本地)"}
                   </span>
                   <button
                     onClick={toggleLanAccess}
@@ -390,6 +596,34 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
               </div>
             </div>
 
+            {/* Tailnet */}
+            {tailnet && (
+              <div className="mt-4 p-4 rounded-2xl bg-gray-50/50 dark:bg-gray-800/40 border border-gray-200/50 dark:border-gray-700/50 relative z-10">
+                <div className="flex items-center justify-between gap-3">
+                  <div className="min-w-0">
+                    <p className="text-sm font-semibold text-gray-700 dark:text-gray-300">
+                      Tailscale 网络
+                    </p>
+                    <p className="text-xs text-gray-500 dark:text-gray-400 font-mono truncate">
+                      {tailnet.url ?? tailnet.ipv4 ?? tailnet.ipv6}
+                    </p>
+                  </div>
+                  {tailnet.bound ? (
+                    <span className="text-xs font-bold text-emerald-600 dark:text-emerald-400 flex-shrink-0">
+                      已绑定（重启生效）
+                    </span>
+                  ) : (
+                    <button
+                      onClick={handleBindTailnet}
+                      className="px-3 py-1.5 text-xs font-bold rounded-lg bg-gray-900 hover:bg-gray-800 dark:bg-white dark:hover:bg-gray-100 text-white dark:text-gray-900 flex-shrink-0"
+                    >
+                      绑定到 Tailnet IP
+                    </button>
+                  )}
+                </div>
+              </div>
+            )}
+
             {/* API Key Section */}
             <div className="mt-6 pt-6 border-t border-gray-200/50 dark:border-gray-700/50 relative z-10">
               <div className="flex items-center justify-between mb-3">
@@ -397,16 +631,16 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
                   <Key className="w-4 h-4" /> API 密钥
                 </label>
                 <span
-                  className={`text-[11px] px-2 py-0.5 rounded-full font-bold ${config?.["api-keys"]?.length ? "bg-emerald-100 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-400 border border-emerald-200 dark:border-emerald-800/50" : "bg-orange-100 text-orange-700 dark:bg-orange-900/30 dark:text-orange-400 border border-orange-200 dark:border-orange-800/50"}`}
+                  className={`text-[11px] px-2 py-0.5 rounded-full font-bold ${config?.api_keys?.length ? "bg-emerald-100 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-400 border border-emerald-200 dark:border-emerald-800/50" : "bg-orange-100 text-orange-700 dark:bg-orange-900/30 dark:text-orange-400 border border-orange-200 dark:border-orange-800/50"}`}
                 >
-                  {config?.["api-keys"]?.length ? "已设置" : "未设置（不安全）"}
+                  {config?.api_keys?.length ? "已设置" : "未设置（不安全）"}
                 </span>
               </div>
 
               <div className="flex h-11">
                 <input
                   type="text"
-                  value={config?.["api-keys"]?.[0] ?? ""}
+                  value={config?.api_keys?.[0] ?? ""}
                   readOnly
                   placeholder="未设置 API 密钥 - 任何人均可访问"
                   className="w-full px-4 rounded-l-xl border-y border-l border-gray-300/80 dark:border-gray-600/80 bg-gray-50/80 dark:bg-gray-800/80 text-gray-900 dark:text-gray-100 font-mono text-sm focus:outline-none placeholder-gray-400 dark:placeholder-gray-500"
@@ -427,13 +661,93 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
                 </button>
                 <button
                   onClick={handleClearApiKey}
-                  disabled={!config?.["api-keys"]?.length}
+                  disabled={!config?.api_keys?.length}
                   className="px-4 bg-gray-100 dark:bg-gray-700 hover:bg-red-100 dark:hover:bg-red-900/30 hover:text-red-600 dark:hover:text-red-400 border border-gray-300/80 dark:border-gray-600/80 rounded-r-xl transition-colors text-gray-600 dark:text-gray-300 outline-none disabled:opacity-40 disabled:cursor-not-allowed"
                   title="清空密钥"
                 >
                   <Trash2 className="w-4 h-4" />
                 </button>
               </div>
+
+              {/* Temporary keys */}
+              <div className="mt-4 space-y-2">
+                <p className="text-xs font-semibold text-gray-600 dark:text-gray-400">
+                  临时密钥（到期或用完请求次数后自动失效）
+                </p>
+                <div className="flex gap-2">
+                  <input
+                    type="text"
+                    value={tempKeyLabel}
+                    onChange={(e) => setTempKeyLabel(e.target.value)}
+                    placeholder="备注，如 CI"
+                    className="flex-1 min-w-0 px-3 py-2 rounded-xl border border-gray-300/80 dark:border-gray-600/80 bg-white dark:bg-gray-900 text-sm text-gray-900 dark:text-gray-100 outline-none"
+                  />
+                  <input
+                    type="number"
+                    min={0}
+                    value={tempKeyHours}
+                    onChange={(e) => setTempKeyHours(e.target.value)}
+                    placeholder="小时"
+                    title="有效小时数"
+                    className="w-20 px-3 py-2 rounded-xl border border-gray-300/80 dark:border-gray-600/80 bg-white dark:bg-gray-900 text-sm text-gray-900 dark:text-gray-100 outline-none"
+                  />
+                  <input
+                    type="number"
+                    min={0}
+                    value={tempKeyRequests}
+                    onChange={(e) => setTempKeyRequests(e.target.value)}
+                    placeholder="次数"
+                    title="最多请求次数"
+                    className="w-20 px-3 py-2 rounded-xl border border-gray-300/80 dark:border-gray-600/80 bg-white dark:bg-gray-900 text-sm text-gray-900 dark:text-gray-100 outline-none"
+                  />
+                  <button
+                    onClick={handleCreateTempKey}
+                    disabled={!config?.api_keys?.length}
+                    title={
+                      config?.api_keys?.length
+                        ? "创建临时密钥"
+                        : "请先生成永久密钥"
+                    }
+                    className="px-3 py-2 rounded-xl text-sm font-semibold bg-gray-100 dark:bg-gray-700 hover:bg-gray-200 dark:hover:bg-gray-600 text-gray-700 dark:text-gray-200 disabled:opacity-40 disabled:cursor-not-allowed"
+                  >
+                    创建
+                  </button>
+                </div>
+                {createdTempKey && (
+                  <div className="flex items-center justify-between gap-2 px-3 py-2 rounded-xl bg-emerald-50 dark:bg-emerald-900/20 border border-emerald-200 dark:border-emerald-800/50">
+                    <code className="text-xs font-mono text-emerald-800 dark:text-emerald-300 truncate">
+                      {createdTempKey}
+                    </code>
+                    <button
+                      onClick={() => navigator.clipboard.writeText(createdTempKey)}
+                      className="text-emerald-700 dark:text-emerald-400"
+                      title="复制（只显示这一次）"
+                    >
+                      <Copy className="w-4 h-4" />
+                    </button>
+                  </div>
+                )}
+                {tempKeys.map((key) => (
+                  <div
+                    key={key.id}
+                    className="flex items-center justify-between gap-2 px-3 py-2 rounded-xl bg-gray-50/80 dark:bg-gray-800/60 border border-gray-200/60 dark:border-gray-700/60 text-xs"
+                  >
+                    <span className="font-mono text-gray-700 dark:text-gray-300">
+                      {key.key}
+                    </span>
+                    <span className="text-gray-500 dark:text-gray-400 truncate">
+                      {key.label} · {formatRemaining(key)}
+                    </span>
+                    <button
+                      onClick={() => handleRevokeTempKey(key.id)}
+                      className="text-gray-400 hover:text-red-500"
+                      title="撤销"
+                    >
+                      <Trash2 className="w-3.5 h-3.5" />
+                    </button>
+                  </div>
+                ))}
+              </div>
             </div>
           </div>
 
@@ -617,9 +931,10 @@ export function Dashboard({ serverStatus, onStatusChange }: DashboardProps) {
                   <Terminal className="w-4 h-4" /> cURL 命令示例
                 </label>
                 <button
-                  onClick={() => {
+                  onClick={async () => {
+                    const key = await invoke<string | null>("reveal_api_key");
                     navigator.clipboard.writeText(
-                      curlCommands[selectedProtocol],
+                      buildCurlCommands(key ?? "your-api-key")[selectedProtocol],
                     );
                     setCopied(true);
                     setTimeout(() => setCopied(false), 2000);
@@ -7,6 +7,7 @@ import {
   Search,
   RefreshCw,
   Trash2,
+  Tag,
 } from "lucide-react";
 
 interface RequestLogEntry {
@@ -23,6 +24,7 @@ interface RequestLogEntry {
   duration_ms: number;
   timestamp: number;
   error_message: string | null;
+  tags?: string[];
 }
 
 interface LogFilter {
@@ -30,6 +32,7 @@ interface LogFilter {
   protocol: string | null;
   search: string | null;
   account_id: string | null;
+  tag: string | null;
 }
 
 type TabType = "all" | "errors" | "openai" | "gemini" | "anthropic";
@@ -47,6 +50,7 @@ export function RequestLogs() {
   const [loading, setLoading] = useState(true);
   const [paused, setPaused] = useState(false);
   const [search, setSearch] = useState("");
+  const [tagFilter, setTagFilter] = useState("");
   const [selectedTab, setSelectedTab] = useState<TabType>("all");
   const [totalCount, setTotalCount] = useState(0);
 
@@ -56,6 +60,7 @@ export function RequestLogs() {
       protocol: null,
       search: search.trim() || null,
       account_id: null,
+      tag: tagFilter.trim() || null,
     };
 
     if (selectedTab === "openai") {
@@ -67,7 +72,7 @@ export function RequestLogs() {
     }
 
     return filter;
-  }, [selectedTab, search]);
+  }, [selectedTab, search, tagFilter]);
 
   const fetchLogs = useCallback(async () => {
     try {
@@ -295,6 +300,20 @@ export function RequestLogs() {
               />
             </div>
 
+            {/* Tag filter */}
+            <div className="relative md:w-40">
+              <div className="absolute inset-y-0 left-0 pl-3 flex items-center pointer-events-none">
+                <Tag className="w-4 h-4 text-gray-400" />
+              </div>
+              <input
+                type="text"
+                value={tagFilter}
+                onChange={(e) => setTagFilter(e.target.value)}
+                placeholder="标签"
+                className="w-full pl-9 pr-4 py-2 bg-white dark:bg-gray-800 border border-gray-300 dark:border-gray-600 rounded-xl text-sm text-gray-900 dark:text-white placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-emerald-500/20 focus:border-emerald-500 transition-all"
+              />
+            </div>
+
             <div className="h-8 w-px bg-gray-200 dark:bg-gray-800 hidden md:block mx-1" />
 
             {/* Action Buttons */}
@@ -383,10 +402,24 @@ export function RequestLogs() {
                       </span>
                     </td>
                     <td
-                      className="px-4 py-3 text-gray-600 dark:text-gray-300 max-w-48 truncate"
+                      className="px-4 py-3 text-gray-600 dark:text-gray-300 max-w-48"
                       title={log.model || undefined}
                     >
-                      {log.model || "-"}
+                      <div className="truncate">{log.model || "-"}</div>
+                      {log.tags && log.tags.length > 0 && (
+                        <div className="flex flex-wrap gap-1 mt-1">
+                          {log.tags.map((tag) => (
+                            <button
+                              key={tag}
+                              onClick={() => setTagFilter(tag)}
+                              className="px-1.5 py-0.5 text-xs bg-emerald-50 dark:bg-emerald-900/30 text-emerald-700 dark:text-emerald-300 rounded"
+                              title="按标签筛选"
+                            >
+                              {tag}
+                            </button>
+                          ))}
+                        </div>
+                      )}
                     </td>
                     <td className="px-4 py-3 whitespace-nowrap text-gray-600 dark:text-gray-300">
                       {getProtocolLabel(log.protocol)}
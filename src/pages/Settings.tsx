@@ -16,25 +16,45 @@ interface SettingsData {
   account_routing_strategy: string;
 }
 
-interface AppConfig {
+interface GeneralSettings {
+  debug: boolean;
+  auth_dir: string;
+  request_retry: number;
+}
+
+interface SshTunnelSettings {
+  enable: boolean;
   host: string;
   port: number;
-  debug: boolean;
-  "auth-dir": string;
-  "api-keys": string[];
-  "proxy-url": string;
-  "request-retry": number;
-  routing: {
-    strategy: string;
-  };
-  [key: string]: unknown;
+  user: string;
+  "key-path": string;
+  "remote-port": number;
+  "remote-bind": string;
 }
 
+interface TunnelStatus {
+  state: "disabled" | "connecting" | "connected" | "reconnecting";
+  remote: string;
+  local_port: number;
+  since: number | null;
+  last_error: string | null;
+  restarts: number;
+}
+
+const TUNNEL_STATE_LABELS: Record<TunnelStatus["state"], string> = {
+  disabled: "未启用",
+  connecting: "连接中",
+  connected: "已连接",
+  reconnecting: "重连中",
+};
+
 interface CustomProviderEntry {
   name: string;
   prefix: string | null;
   base_url: string;
   api_keys: string[];
+  // Id of the stored key behind each entry of api_keys, "" for keys added here
+  key_ids: string[];
   models: string[];
 }
 
@@ -57,6 +77,7 @@ const EMPTY_PROVIDER: CustomProviderEntry = {
   prefix: null,
   base_url: "",
   api_keys: [""],
+  key_ids: [""],
   models: [],
 };
 
@@ -67,23 +88,58 @@ export function Settings() {
     provider_priorities: [],
     account_routing_strategy: "stick-until-exhausted",
   });
-  const [appConfig, setAppConfig] = useState<AppConfig | null>(null);
+  const [generalSettings, setGeneralSettings] = useState<GeneralSettings | null>(null);
   const [customProviders, setCustomProviders] = useState<CustomProvidersData>({
     openai_compatibility: [],
     claude_code_compatibility: [],
   });
+  const [sshTunnel, setSshTunnel] = useState<SshTunnelSettings | null>(null);
+  const [tunnelStatus, setTunnelStatus] = useState<TunnelStatus | null>(null);
   const [loading, setLoading] = useState(true);
   const [configLoading, setConfigLoading] = useState(true);
   const [saving, setSaving] = useState(false);
   const [saved, setSaved] = useState(false);
   const [activeTab, setActiveTab] = useState<"general" | "openai" | "claude">("general");
+  // Version of the config the forms were loaded from, so a save can't overwrite other edits
+  const [configEtag, setConfigEtag] = useState<string | null>(null);
 
   useEffect(() => {
+    loadEtag();
     loadSettings();
     loadCustomProviders();
     loadConfig();
+    loadSshTunnel();
+  }, []);
+
+  useEffect(() => {
+    const fetchTunnelStatus = async () => {
+      try {
+        setTunnelStatus(await invoke<TunnelStatus>("get_tunnel_status"));
+      } catch (error) {
+        console.error("Failed to fetch tunnel status:", error);
+      }
+    };
+    fetchTunnelStatus();
+    const interval = setInterval(fetchTunnelStatus, 5000);
+    return () => clearInterval(interval);
   }, []);
 
+  async function loadEtag() {
+    try {
+      setConfigEtag(await invoke<string>("get_config_etag"));
+    } catch (error) {
+      console.error("Failed to load config version:", error);
+    }
+  }
+
+  async function loadSshTunnel() {
+    try {
+      setSshTunnel(await invoke<SshTunnelSettings>("get_ssh_tunnel_settings"));
+    } catch (error) {
+      console.error("Failed to load SSH tunnel settings:", error);
+    }
+  }
+
   async function loadSettings() {
     try {
       const data = await invoke<SettingsData>("get_settings");
@@ -97,8 +153,8 @@ export function Settings() {
 
   async function loadConfig() {
     try {
-      const data = await invoke<AppConfig>("get_config");
-      setAppConfig(data);
+      const data = await invoke<GeneralSettings>("get_general_settings");
+      setGeneralSettings(data);
     } catch (error) {
       console.error("Failed to load config:", error);
     } finally {
@@ -114,6 +170,7 @@ export function Settings() {
         providers.map(p => ({
           ...p,
           api_keys: p.api_keys.length > 0 ? p.api_keys : [""],
+          key_ids: p.api_keys.length > 0 ? p.key_ids : [""],
         }));
       setCustomProviders({
         openai_compatibility: normalizeProviders(data.openai_compatibility),
@@ -127,32 +184,37 @@ export function Settings() {
   async function handleSave() {
     setSaving(true);
     setSaved(false);
+    // Each save returns the new version, the next one is checked against it
+    let etag = configEtag;
     try {
-      // Important: Save appConfig first, then settings
-      // This ensures model_routing settings are not overwritten by appConfig
-      if (appConfig) {
-        await invoke("save_config", { config: appConfig });
+      if (generalSettings) {
+        etag = await invoke<string>("save_general_settings", { settings: generalSettings, etag });
+      }
+      if (sshTunnel) {
+        etag = await invoke<string>("save_ssh_tunnel_settings", { settings: sshTunnel, etag });
       }
       // Filter out empty api_keys before saving
       const cleanProviders = (providers: CustomProviderEntry[]) =>
         providers.map(p => ({
           ...p,
           api_keys: p.api_keys.filter(k => k.trim() !== ""),
+          key_ids: p.key_ids.filter((_, i) => p.api_keys[i].trim() !== ""),
         })).filter(p => p.name.trim() !== "" && p.base_url.trim() !== "");
-      await invoke("save_custom_providers", {
+      etag = await invoke<string>("save_custom_providers", {
         data: {
           openai_compatibility: cleanProviders(customProviders.openai_compatibility),
           claude_code_compatibility: cleanProviders(customProviders.claude_code_compatibility),
         },
+        etag,
       });
-      // Save settings last to ensure model_routing is preserved
-      await invoke("save_settings", { settings });
+      etag = await invoke<string>("save_settings", { settings, etag });
       setSaved(true);
       setTimeout(() => setSaved(false), 2000);
     } catch (error) {
       console.error("Failed to save settings:", error);
       alert(`保存失败: ${error}`);
     } finally {
+      setConfigEtag(etag);
       setSaving(false);
     }
   }
@@ -198,6 +260,7 @@ export function Settings() {
     providers[providerIndex] = {
       ...providers[providerIndex],
       api_keys: [...providers[providerIndex].api_keys, ""],
+      key_ids: [...providers[providerIndex].key_ids, ""],
     };
     setCustomProviders({ ...customProviders, [key]: providers });
   }
@@ -215,7 +278,12 @@ export function Settings() {
     const key = type === "openai" ? "openai_compatibility" : "claude_code_compatibility";
     const providers = [...customProviders[key]];
     const apiKeys = providers[providerIndex].api_keys.filter((_, i) => i !== keyIndex);
-    providers[providerIndex] = { ...providers[providerIndex], api_keys: apiKeys.length > 0 ? apiKeys : [""] };
+    const keyIds = providers[providerIndex].key_ids.filter((_, i) => i !== keyIndex);
+    providers[providerIndex] = {
+      ...providers[providerIndex],
+      api_keys: apiKeys.length > 0 ? apiKeys : [""],
+      key_ids: apiKeys.length > 0 ? keyIds : [""],
+    };
     setCustomProviders({ ...customProviders, [key]: providers });
   }
 
@@ -401,19 +469,19 @@ export function Settings() {
               </p>
               <div className="flex items-center justify-between p-3 border border-gray-300 dark:border-gray-600 rounded-lg">
                 <span className="text-sm text-gray-600 dark:text-gray-300">
-                  {appConfig?.debug ? "已开启" : "已关闭"}
+                  {generalSettings?.debug ? "已开启" : "已关闭"}
                 </span>
                 <button
                   onClick={() =>
-                    setAppConfig((prev) =>
+                    setGeneralSettings((prev) =>
                       prev ? { ...prev, debug: !prev.debug } : prev
                     )
                   }
-                  className={`relative w-12 h-6 rounded-full transition-colors ${appConfig?.debug ? "bg-gray-800 dark:bg-gray-600" : "bg-gray-300 dark:bg-gray-600"
+                  className={`relative w-12 h-6 rounded-full transition-colors ${generalSettings?.debug ? "bg-gray-800 dark:bg-gray-600" : "bg-gray-300 dark:bg-gray-600"
                     }`}
                 >
                   <span
-                    className={`absolute top-1 w-4 h-4 bg-white rounded-full transition-transform ${appConfig?.debug ? "left-7" : "left-1"
+                    className={`absolute top-1 w-4 h-4 bg-white rounded-full transition-transform ${generalSettings?.debug ? "left-7" : "left-1"
                       }`}
                   />
                 </button>
@@ -429,10 +497,10 @@ export function Settings() {
               <div className="flex flex-col gap-2 md:flex-row md:items-center">
                 <input
                   type="text"
-                  value={appConfig?.["auth-dir"] ?? ""}
+                  value={generalSettings?.auth_dir ?? ""}
                   onChange={(e) =>
-                    setAppConfig((prev) =>
-                      prev ? { ...prev, "auth-dir": e.target.value } : prev
+                    setGeneralSettings((prev) =>
+                      prev ? { ...prev, auth_dir: e.target.value } : prev
                     )
                   }
                   placeholder="例如：/Users/you/.cli-proxy-api"
@@ -446,8 +514,8 @@ export function Settings() {
                         multiple: false,
                       });
                       if (typeof selected === "string") {
-                        setAppConfig((prev) =>
-                          prev ? { ...prev, "auth-dir": selected } : prev
+                        setGeneralSettings((prev) =>
+                          prev ? { ...prev, auth_dir: selected } : prev
                         );
                       }
                     } catch (error) {
@@ -633,6 +701,82 @@ export function Settings() {
                 </div>
               </div>
             )}
+
+            {/* SSH Reverse Tunnel */}
+            {sshTunnel && (
+              <div className="space-y-2">
+                <label className="block text-sm font-medium text-gray-700 dark:text-gray-300">
+                  SSH 反向隧道
+                </label>
+                <p className="text-xs text-gray-500 dark:text-gray-400">
+                  通过 ssh -R 将本机代理映射到远程服务器的端口，远程服务器即可访问代理，无需监听 0.0.0.0。需要系统已安装 ssh 并配置免密登录。
+                </p>
+                <div className="flex items-center justify-between p-3 border border-gray-300 dark:border-gray-600 rounded-lg">
+                  <span className="text-sm text-gray-600 dark:text-gray-300">
+                    {tunnelStatus ? TUNNEL_STATE_LABELS[tunnelStatus.state] : "-"}
+                    {tunnelStatus?.remote && ` · ${tunnelStatus.remote}`}
+                    {tunnelStatus && tunnelStatus.restarts > 0 && ` · 重连 ${tunnelStatus.restarts} 次`}
+                  </span>
+                  <button
+                    onClick={() => setSshTunnel({ ...sshTunnel, enable: !sshTunnel.enable })}
+                    className={`relative w-12 h-6 rounded-full transition-colors ${sshTunnel.enable ? "bg-gray-800 dark:bg-gray-600" : "bg-gray-300 dark:bg-gray-600"
+                      }`}
+                  >
+                    <span
+                      className={`absolute top-1 w-4 h-4 bg-white rounded-full transition-transform ${sshTunnel.enable ? "left-7" : "left-1"
+                        }`}
+                    />
+                  </button>
+                </div>
+                {tunnelStatus?.last_error && (
+                  <p className="text-xs text-red-500 dark:text-red-400">{tunnelStatus.last_error}</p>
+                )}
+                <div className="grid grid-cols-1 md:grid-cols-3 gap-2">
+                  <input
+                    type="text"
+                    value={sshTunnel.host}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, host: e.target.value })}
+                    placeholder="服务器地址，例如 vps.example.com"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                  <input
+                    type="text"
+                    value={sshTunnel.user}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, user: e.target.value })}
+                    placeholder="用户名"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                  <input
+                    type="number"
+                    value={sshTunnel.port}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, port: Number(e.target.value) })}
+                    placeholder="SSH 端口"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                  <input
+                    type="text"
+                    value={sshTunnel["key-path"]}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, "key-path": e.target.value })}
+                    placeholder="私钥路径（可选），例如 ~/.ssh/id_ed25519"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                  <input
+                    type="number"
+                    value={sshTunnel["remote-port"] || ""}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, "remote-port": Number(e.target.value) })}
+                    placeholder="远程端口"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                  <input
+                    type="text"
+                    value={sshTunnel["remote-bind"]}
+                    onChange={(e) => setSshTunnel({ ...sshTunnel, "remote-bind": e.target.value })}
+                    placeholder="远程监听地址"
+                    className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-800 dark:text-white text-sm"
+                  />
+                </div>
+              </div>
+            )}
           </>
         )}
 